@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
 
 use crate::operations::{self, AVAILABLE_DECODERS};
+use crate::symbols::Symbols;
 use crate::{operations::DecodingError, Instruction};
 
 impl Instruction {
@@ -20,6 +21,14 @@ impl Instruction {
         Ok(instrs)
     }
 
+    /// Decodes a program encoded by [`Instruction::encode_program`].
+    pub fn program_from_bytes(input: &[u8]) -> Result<(Vec<Instruction>, Symbols)> {
+        let (symbols, input) = Symbols::decode(input).context("Failed to read symbols")?;
+        let instrs = Instruction::from_bytes(input)?;
+
+        Ok((instrs, symbols))
+    }
+
     pub fn decode(input: &[u8]) -> Result<(Instruction, usize, &[u8])> {
         let (op, input) = operations::pump_one(input)?;
 
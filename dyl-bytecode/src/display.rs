@@ -20,6 +20,37 @@ impl Display for Instruction {
             Instruction::Neg(op) => op.fmt(f),
             Instruction::Mul(op) => op.fmt(f),
             Instruction::Pop(op) => op.fmt(f),
+            Instruction::Cmp(op) => op.fmt(f),
+            Instruction::PushFn(op) => op.fmt(f),
+            Instruction::CallIndirect(op) => op.fmt(f),
+            Instruction::MakeClosure(op) => op.fmt(f),
+            Instruction::MakeRecord(op) => op.fmt(f),
+            Instruction::GetField(op) => op.fmt(f),
+            Instruction::Panic(op) => op.fmt(f),
+            Instruction::Rem(op) => op.fmt(f),
+            Instruction::HostCall(op) => op.fmt(f),
+            Instruction::PrintI(op) => op.fmt(f),
+            Instruction::PrintB(op) => op.fmt(f),
+            Instruction::PrintS(op) => op.fmt(f),
+            Instruction::ReadI(op) => op.fmt(f),
+            Instruction::Alloc(op) => op.fmt(f),
+            Instruction::Load(op) => op.fmt(f),
+            Instruction::Store(op) => op.fmt(f),
+            Instruction::MakeList(op) => op.fmt(f),
+            Instruction::Cons(op) => op.fmt(f),
+            Instruction::Head(op) => op.fmt(f),
+            Instruction::Tail(op) => op.fmt(f),
+            Instruction::IsEmpty(op) => op.fmt(f),
+            Instruction::EmptyMap(op) => op.fmt(f),
+            Instruction::MapInsert(op) => op.fmt(f),
+            Instruction::MapGet(op) => op.fmt(f),
+            Instruction::MapRemove(op) => op.fmt(f),
+            Instruction::MapContains(op) => op.fmt(f),
+            Instruction::Assert(op) => op.fmt(f),
+            Instruction::AssertEq(op) => op.fmt(f),
+            Instruction::AssertEqB(op) => op.fmt(f),
+            Instruction::MemoLoad(op) => op.fmt(f),
+            Instruction::MemoStore(op) => op.fmt(f),
         }
     }
 }
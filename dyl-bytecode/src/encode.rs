@@ -1,4 +1,5 @@
 use crate::operations::Operation;
+use crate::symbols::Symbols;
 use crate::Instruction;
 
 impl Instruction {
@@ -17,6 +18,37 @@ impl Instruction {
             Instruction::Neg(op) => op.encode(encoder),
             Instruction::Mul(op) => op.encode(encoder),
             Instruction::Pop(op) => op.encode(encoder),
+            Instruction::Cmp(op) => op.encode(encoder),
+            Instruction::PushFn(op) => op.encode(encoder),
+            Instruction::CallIndirect(op) => op.encode(encoder),
+            Instruction::MakeClosure(op) => op.encode(encoder),
+            Instruction::MakeRecord(op) => op.encode(encoder),
+            Instruction::GetField(op) => op.encode(encoder),
+            Instruction::Panic(op) => op.encode(encoder),
+            Instruction::Rem(op) => op.encode(encoder),
+            Instruction::HostCall(op) => op.encode(encoder),
+            Instruction::PrintI(op) => op.encode(encoder),
+            Instruction::PrintB(op) => op.encode(encoder),
+            Instruction::PrintS(op) => op.encode(encoder),
+            Instruction::ReadI(op) => op.encode(encoder),
+            Instruction::Alloc(op) => op.encode(encoder),
+            Instruction::Load(op) => op.encode(encoder),
+            Instruction::Store(op) => op.encode(encoder),
+            Instruction::MakeList(op) => op.encode(encoder),
+            Instruction::Cons(op) => op.encode(encoder),
+            Instruction::Head(op) => op.encode(encoder),
+            Instruction::Tail(op) => op.encode(encoder),
+            Instruction::IsEmpty(op) => op.encode(encoder),
+            Instruction::EmptyMap(op) => op.encode(encoder),
+            Instruction::MapInsert(op) => op.encode(encoder),
+            Instruction::MapGet(op) => op.encode(encoder),
+            Instruction::MapRemove(op) => op.encode(encoder),
+            Instruction::MapContains(op) => op.encode(encoder),
+            Instruction::Assert(op) => op.encode(encoder),
+            Instruction::AssertEq(op) => op.encode(encoder),
+            Instruction::AssertEqB(op) => op.encode(encoder),
+            Instruction::MemoLoad(op) => op.encode(encoder),
+            Instruction::MemoStore(op) => op.encode(encoder),
         }
     }
 
@@ -29,6 +61,18 @@ impl Instruction {
 
         buff
     }
+
+    /// Encodes a whole program: its symbols, followed by its instructions.
+    pub fn encode_program<'a, I>(instructions: I, symbols: &Symbols) -> Vec<u8>
+    where
+        I: IntoIterator<Item = &'a Instruction>,
+    {
+        let mut buff = Vec::new();
+        symbols.encode(&mut buff);
+        instructions.into_iter().for_each(|i| i.encode(&mut buff));
+
+        buff
+    }
 }
 
 #[cfg(test)]
@@ -51,4 +95,20 @@ mod tests {
         ];
         assert_eq!(left, right);
     }
+
+    #[test]
+    fn encode_program_roundtrip() {
+        let instructions = [Instruction::push_i(42), Instruction::f_stop()];
+        let mut symbols = Symbols::new();
+        symbols.add_function(0, "main".to_owned());
+        symbols.add_host_function("read".to_owned());
+        symbols.set_main_params(1);
+
+        let encoded = Instruction::encode_program(&instructions, &symbols);
+
+        assert_eq!(
+            Instruction::program_from_bytes(&encoded).unwrap(),
+            (instructions.to_vec(), symbols)
+        );
+    }
 }
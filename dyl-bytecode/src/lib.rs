@@ -1,11 +1,15 @@
 use operations::{
-    AddI, Call, CondJmp, FStop, Goto, Mul, Neg, Pop, PopCopy, PushCopy, PushI, ResV, Ret,
+    AddI, Alloc, Assert, AssertEq, AssertEqB, Call, CallIndirect, Cmp, CondJmp, Cons, EmptyMap,
+    FStop, GetField, Goto, Head, HostCall, IsEmpty, Load, MakeClosure, MakeList, MakeRecord,
+    MapContains, MapGet, MapInsert, MapRemove, MemoLoad, MemoStore, Mul, Neg, Panic, Pop, PopCopy,
+    PrintB, PrintI, PrintS, PushCopy, PushFn, PushI, ReadI, Rem, ResV, Ret, Store, Tail,
 };
 
 pub mod decode;
 pub mod display;
 pub mod encode;
 pub mod operations;
+pub mod symbols;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Instruction {
@@ -111,6 +115,341 @@ pub enum Instruction {
     ///     pop()
     /// ```
     Pop(Pop),
+
+    /// Pops two integers from the stack, compares the first one pushed with
+    /// the other one, and pushes -1, 0 or 1 depending on whether it is
+    /// smaller, equal or greater.
+    ///
+    /// Unlike the sign of their difference, the comparison can't overflow.
+    ///
+    /// ```none
+    /// b = pop()
+    /// a = pop()
+    /// push(sign(a - b))
+    /// ```
+    Cmp(Cmp),
+    /// Pushes a reference to the function starting at a given address on the
+    /// stack.
+    ///
+    /// ```none
+    /// push(fn(ptr))
+    /// ```
+    PushFn(PushFn),
+
+    /// Pops a function reference or a closure from the stack, then calls it.
+    ///
+    /// The values captured by a closure are pushed after the arguments.
+    ///
+    /// ```none
+    /// f = pop()
+    /// for v in captures(f):
+    ///     push(v)
+    /// push(ip)
+    /// ip = addr(f)
+    /// ```
+    CallIndirect(CallIndirect),
+
+    /// Pops a given number of values from the stack and packs them, with the
+    /// address of the code to run, in a closure.
+    ///
+    /// ```none
+    /// captures = []
+    /// for i in range(n):
+    ///     captures.prepend(pop())
+    /// push(closure(ptr, captures))
+    /// ```
+    MakeClosure(MakeClosure),
+
+    /// Pops a given number of values from the stack and packs them in a
+    /// record, in the order they were pushed.
+    ///
+    /// ```none
+    /// fields = []
+    /// for i in range(n):
+    ///     fields.prepend(pop())
+    /// push(record(fields))
+    /// ```
+    MakeRecord(MakeRecord),
+
+    /// Pops a record from the stack and pushes the value of one of its
+    /// fields.
+    ///
+    /// ```none
+    /// r = pop()
+    /// push(r.fields[idx])
+    /// ```
+    GetField(GetField),
+
+    /// Pops the bytes of a UTF-8 message from the stack and stops the
+    /// program with an error carrying that message.
+    ///
+    /// ```none
+    /// message = []
+    /// for i in range(len):
+    ///     message.prepend(pop())
+    /// fail(message)
+    /// ```
+    Panic(Panic),
+
+    /// Pops two integers from the stack, computes the remainder of the
+    /// division of the first one pushed by the other one, and pushes the
+    /// result on the stack.
+    ///
+    /// ```none
+    /// b = pop()
+    /// a = pop()
+    /// push(a % b)
+    /// ```
+    Rem(Rem),
+
+    /// Pops the arguments of a function provided by the application running
+    /// the program, calls it and pushes its return value.
+    ///
+    /// ```none
+    /// args = []
+    /// for i in range(n):
+    ///     args.prepend(pop())
+    /// push(host_functions[function](args))
+    /// ```
+    HostCall(HostCall),
+
+    /// Pops an integer from the stack and writes it to the output of the
+    /// program.
+    ///
+    /// ```none
+    /// write(pop())
+    /// ```
+    PrintI(PrintI),
+
+    /// Pops a boolean from the stack and writes it to the output of the
+    /// program, as `true` or `false`.
+    ///
+    /// ```none
+    /// write(pop() != 0)
+    /// ```
+    PrintB(PrintB),
+
+    /// Pops the bytes of a UTF-8 string from the stack and writes it to the
+    /// output of the program.
+    ///
+    /// ```none
+    /// s = []
+    /// for i in range(len):
+    ///     s.prepend(pop())
+    /// write(s)
+    /// ```
+    PrintS(PrintS),
+
+    /// Reads an integer from the input of the program and pushes it on the
+    /// stack.
+    ///
+    /// ```none
+    /// push(read_int())
+    /// ```
+    ReadI(ReadI),
+
+    /// Pops a given number of values from the stack, stores them in a new
+    /// object on the heap and pushes a handle to that object.
+    ///
+    /// The object is freed by the garbage collector once no value of the
+    /// stack refers to it anymore, directly or through other objects.
+    ///
+    /// ```none
+    /// fields = []
+    /// for i in range(n):
+    ///     fields.prepend(pop())
+    /// push(heap.alloc(fields))
+    /// ```
+    Alloc(Alloc),
+
+    /// Pops a handle from the stack and pushes the value of one of the
+    /// fields of the object it refers to.
+    ///
+    /// ```none
+    /// h = pop()
+    /// push(heap[h].fields[idx])
+    /// ```
+    Load(Load),
+
+    /// Pops a value and a handle from the stack, and writes the value in one
+    /// of the fields of the object the handle refers to.
+    ///
+    /// ```none
+    /// v = pop()
+    /// h = pop()
+    /// heap[h].fields[idx] = v
+    /// ```
+    Store(Store),
+
+    /// Pops a given number of values from the stack and pushes a list of
+    /// them, in the order they were pushed.
+    ///
+    /// ```none
+    /// elements = []
+    /// for i in range(n):
+    ///     elements.prepend(pop())
+    /// push(list(elements))
+    /// ```
+    MakeList(MakeList),
+
+    /// Pops a list and a value from the stack, and pushes the list starting
+    /// with that value followed by the elements of the popped list.
+    ///
+    /// The popped list is shared, not copied.
+    ///
+    /// ```none
+    /// l = pop()
+    /// v = pop()
+    /// push(cons(v, l))
+    /// ```
+    Cons(Cons),
+
+    /// Pops a non-empty list from the stack and pushes its first element.
+    ///
+    /// ```none
+    /// l = pop()
+    /// push(l[0])
+    /// ```
+    Head(Head),
+
+    /// Pops a non-empty list from the stack and pushes the list of its
+    /// elements but the first one.
+    ///
+    /// ```none
+    /// l = pop()
+    /// push(l[1..])
+    /// ```
+    Tail(Tail),
+
+    /// Pops a list from the stack and pushes whether it is empty.
+    ///
+    /// ```none
+    /// l = pop()
+    /// push(len(l) == 0)
+    /// ```
+    IsEmpty(IsEmpty),
+
+    /// Pushes a map with no entries.
+    ///
+    /// Maps are ordered by key, which must be an integer.
+    ///
+    /// ```none
+    /// push({})
+    /// ```
+    EmptyMap(EmptyMap),
+
+    /// Pops a value, a key and a map from the stack, and pushes a copy of the
+    /// map where the key is associated with the value.
+    ///
+    /// The entries of the popped map are shared until one of the maps is
+    /// modified.
+    ///
+    /// ```none
+    /// v = pop()
+    /// k = pop()
+    /// m = pop()
+    /// push(m + {k: v})
+    /// ```
+    MapInsert(MapInsert),
+
+    /// Pops a key and a map from the stack, and pushes the value associated
+    /// with the key as an `Option`, that is a record holding the tag `0` if
+    /// there is no such value, or the tag `1` followed by the value.
+    ///
+    /// ```none
+    /// k = pop()
+    /// m = pop()
+    /// if k in m:
+    ///     push({1, m[k]})
+    /// else:
+    ///     push({0})
+    /// ```
+    MapGet(MapGet),
+
+    /// Pops a key and a map from the stack, and pushes a copy of the map
+    /// without that key.
+    ///
+    /// ```none
+    /// k = pop()
+    /// m = pop()
+    /// push(m - k)
+    /// ```
+    MapRemove(MapRemove),
+
+    /// Pops a key and a map from the stack, and pushes whether the map has a
+    /// value for that key.
+    ///
+    /// ```none
+    /// k = pop()
+    /// m = pop()
+    /// push(k in m)
+    /// ```
+    MapContains(MapContains),
+
+    /// Pops the bytes of a UTF-8 message and a condition from the stack, and
+    /// stops the program with an error carrying that message if the
+    /// condition is false.
+    ///
+    /// ```none
+    /// message = []
+    /// for i in range(len):
+    ///     message.prepend(pop())
+    /// if pop() == 0:
+    ///     fail(message)
+    /// ```
+    Assert(Assert),
+
+    /// Pops the bytes of a UTF-8 message and two integers from the stack,
+    /// and stops the program with an error carrying that message and both
+    /// integers if they are not equal.
+    ///
+    /// ```none
+    /// message = []
+    /// for i in range(len):
+    ///     message.prepend(pop())
+    /// b = pop()
+    /// a = pop()
+    /// if a != b:
+    ///     fail(message, a, b)
+    /// ```
+    AssertEq(AssertEq),
+
+    /// Same as [`AssertEq`], but for booleans, which are written as `true`
+    /// or `false` in the error.
+    ///
+    /// ```none
+    /// message = []
+    /// for i in range(len):
+    ///     message.prepend(pop())
+    /// b = pop()
+    /// a = pop()
+    /// if a != b:
+    ///     fail(message, a != 0, b != 0)
+    /// ```
+    AssertEqB(AssertEqB),
+
+    /// Looks up the arguments of the current function in its cache, which
+    /// is filled by [`MemoStore`]. The cache is identified by the address of
+    /// the function. The return address is expected to be on top of the
+    /// stack, right above the `params` arguments.
+    ///
+    /// ```none
+    /// args = stack[top - params..top]
+    /// if (function, args) in cache:
+    ///     push(cache[function, args])
+    ///     goto(hit_addr)
+    /// ```
+    MemoLoad(MemoLoad),
+
+    /// Caches the value on top of the stack as the result of the function at
+    /// address `function` for its arguments, which sit below the return
+    /// address.
+    ///
+    /// ```none
+    /// args = stack[top - params - 1..top - 1]
+    /// cache[function, args] = stack[top]
+    /// ```
+    MemoStore(MemoStore),
 }
 
 impl Instruction {
@@ -174,6 +513,139 @@ impl Instruction {
     pub fn pop(idx: u16) -> Instruction {
         Pop(idx).into()
     }
+
+    pub fn cmp() -> Instruction {
+        Cmp.into()
+    }
+
+    pub fn push_fn(ptr: u32) -> Instruction {
+        PushFn(ptr).into()
+    }
+
+    pub fn call_indirect() -> Instruction {
+        CallIndirect.into()
+    }
+
+    pub fn make_closure(ptr: u32, captures: u16) -> Instruction {
+        MakeClosure {
+            addr: ptr,
+            captures,
+        }
+        .into()
+    }
+
+    pub fn make_record(fields: u16) -> Instruction {
+        MakeRecord(fields).into()
+    }
+
+    pub fn get_field(idx: u16) -> Instruction {
+        GetField(idx).into()
+    }
+
+    pub fn panic(len: u16) -> Instruction {
+        Panic(len).into()
+    }
+
+    pub fn rem() -> Instruction {
+        Rem.into()
+    }
+
+    pub fn host_call(function: u16, args: u16) -> Instruction {
+        HostCall { function, args }.into()
+    }
+
+    pub fn print_i() -> Instruction {
+        PrintI.into()
+    }
+
+    pub fn print_b() -> Instruction {
+        PrintB.into()
+    }
+
+    pub fn print_s(len: u16) -> Instruction {
+        PrintS(len).into()
+    }
+
+    pub fn read_i() -> Instruction {
+        ReadI.into()
+    }
+
+    pub fn alloc(fields: u16) -> Instruction {
+        Alloc(fields).into()
+    }
+
+    pub fn load(idx: u16) -> Instruction {
+        Load(idx).into()
+    }
+
+    pub fn store(idx: u16) -> Instruction {
+        Store(idx).into()
+    }
+
+    pub fn make_list(elements: u16) -> Instruction {
+        MakeList(elements).into()
+    }
+
+    pub fn cons() -> Instruction {
+        Cons.into()
+    }
+
+    pub fn head() -> Instruction {
+        Head.into()
+    }
+
+    pub fn tail() -> Instruction {
+        Tail.into()
+    }
+
+    pub fn is_empty() -> Instruction {
+        IsEmpty.into()
+    }
+
+    pub fn empty_map() -> Instruction {
+        EmptyMap.into()
+    }
+
+    pub fn map_insert() -> Instruction {
+        MapInsert.into()
+    }
+
+    pub fn map_get() -> Instruction {
+        MapGet.into()
+    }
+
+    pub fn map_remove() -> Instruction {
+        MapRemove.into()
+    }
+
+    pub fn map_contains() -> Instruction {
+        MapContains.into()
+    }
+
+    pub fn assert(len: u16) -> Instruction {
+        Assert(len).into()
+    }
+
+    pub fn assert_eq(len: u16) -> Instruction {
+        AssertEq(len).into()
+    }
+
+    pub fn assert_eq_b(len: u16) -> Instruction {
+        AssertEqB(len).into()
+    }
+
+    pub fn memo_load(function: u32, params: u16, hit_addr: u32) -> Instruction {
+        MemoLoad {
+            function,
+            params,
+            hit_addr,
+        }
+        .into()
+    }
+
+    pub fn memo_store(function: u32, params: u16) -> Instruction {
+        MemoStore { function, params }.into()
+    }
 }
 
 macro_rules! impl_from_operation {
@@ -188,4 +660,10 @@ macro_rules! impl_from_operation {
     };
 }
 
-impl_from_operation! { PushI, AddI, FStop, PushCopy, Call, Ret, ResV, PopCopy, Goto, CondJmp, Neg, Mul, Pop }
+impl_from_operation! {
+    PushI, AddI, FStop, PushCopy, Call, Ret, ResV, PopCopy, Goto, CondJmp, Neg, Mul, Pop, Cmp, PushFn,
+    CallIndirect, MakeClosure, MakeRecord, GetField, Panic, Rem, HostCall, PrintI, PrintB, PrintS,
+    ReadI, Alloc, Load, Store, MakeList, Cons, Head, Tail, IsEmpty,
+    EmptyMap, MapInsert, MapGet, MapRemove, MapContains, Assert, AssertEq, AssertEqB,
+    MemoLoad, MemoStore,
+}
@@ -3,11 +3,11 @@ use std::{
     fmt::{Display, Formatter, Result as FResult},
 };
 
-use anyhow::{anyhow, ensure, Context, Result};
+use anyhow::{anyhow, Context, Result};
 
 use crate::Instruction;
 
-pub(crate) const AVAILABLE_DECODERS: [Decoder; 13] = [
+pub(crate) const AVAILABLE_DECODERS: [Decoder; 44] = [
     PushI::decode_and_wrap,
     AddI::decode_and_wrap,
     FStop::decode_and_wrap,
@@ -21,6 +21,37 @@ pub(crate) const AVAILABLE_DECODERS: [Decoder; 13] = [
     Neg::decode_and_wrap,
     Mul::decode_and_wrap,
     Pop::decode_and_wrap,
+    Cmp::decode_and_wrap,
+    PushFn::decode_and_wrap,
+    CallIndirect::decode_and_wrap,
+    MakeClosure::decode_and_wrap,
+    MakeRecord::decode_and_wrap,
+    GetField::decode_and_wrap,
+    Panic::decode_and_wrap,
+    Rem::decode_and_wrap,
+    HostCall::decode_and_wrap,
+    PrintI::decode_and_wrap,
+    PrintB::decode_and_wrap,
+    PrintS::decode_and_wrap,
+    ReadI::decode_and_wrap,
+    Alloc::decode_and_wrap,
+    Load::decode_and_wrap,
+    Store::decode_and_wrap,
+    MakeList::decode_and_wrap,
+    Cons::decode_and_wrap,
+    Head::decode_and_wrap,
+    Tail::decode_and_wrap,
+    IsEmpty::decode_and_wrap,
+    EmptyMap::decode_and_wrap,
+    MapInsert::decode_and_wrap,
+    MapGet::decode_and_wrap,
+    MapRemove::decode_and_wrap,
+    MapContains::decode_and_wrap,
+    Assert::decode_and_wrap,
+    AssertEq::decode_and_wrap,
+    AssertEqB::decode_and_wrap,
+    MemoLoad::decode_and_wrap,
+    MemoStore::decode_and_wrap,
 ];
 
 pub(crate) type Decoder = fn(&[u8]) -> Result<(Instruction, usize, &[u8])>;
@@ -38,14 +69,15 @@ pub(crate) trait Operation: Sized + Into<Instruction> {
             .map(|(op, tail)| (op.into(), Self::SIZE, tail))
     }
 
+    #[cfg(test)]
     fn decode_single_with_opcode(input: &[u8]) -> Result<Self> {
         let (opcode, tail) = pump_one(input).context("Failed to get opcode value")?;
-        ensure!(opcode == Self::ID as u8, "Incorrect opcode");
+        anyhow::ensure!(opcode == Self::ID as u8, "Incorrect opcode");
 
         let (instr, tail) =
             Self::decode(tail).context("Failed to decode instruction parameters")?;
 
-        ensure!(tail.is_empty(), "Some bytes have not been consumed");
+        anyhow::ensure!(tail.is_empty(), "Some bytes have not been consumed");
 
         Ok(instr)
     }
@@ -432,6 +464,821 @@ impl Display for Pop {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cmp;
+
+impl Operation for Cmp {
+    const ID: usize = next_id![Pop];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "cmp";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let instr = Cmp;
+
+        Ok((instr, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for Cmp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        write!(f, "cmp")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PushFn(pub u32);
+
+impl Operation for PushFn {
+    const ID: usize = next_id![Cmp];
+    const SIZE: usize = 5;
+    const DISPLAY_NAME: &'static str = "push_fn";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (addr, tail) = pump_four(input).context("Failed to get function address to push")?;
+        let instr = PushFn(addr);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_four(self.0));
+    }
+}
+
+impl Display for PushFn {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "push_fn {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CallIndirect;
+
+impl Operation for CallIndirect {
+    const ID: usize = next_id![PushFn];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "call_indirect";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let instr = CallIndirect;
+
+        Ok((instr, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for CallIndirect {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "call_indirect")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MakeClosure {
+    pub addr: u32,
+    pub captures: u16,
+}
+
+impl Operation for MakeClosure {
+    const ID: usize = next_id![CallIndirect];
+    const SIZE: usize = 7;
+    const DISPLAY_NAME: &'static str = "make_closure";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (addr, input) = pump_four(input).context("Failed to get closure code address")?;
+        let (captures, input) =
+            pump_two(input).context("Failed to get number of captured values")?;
+        let instr = MakeClosure { addr, captures };
+
+        Ok((instr, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_four(self.addr));
+        encoder.extend_from_slice(&dump_two(self.captures));
+    }
+}
+
+impl Display for MakeClosure {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "make_closure {} {}", self.addr, self.captures)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MakeRecord(pub u16);
+
+impl Operation for MakeRecord {
+    const ID: usize = next_id![MakeClosure];
+    const SIZE: usize = 3;
+    const DISPLAY_NAME: &'static str = "make_record";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (fields, tail) = pump_two(input).context("Failed to get number of record fields")?;
+        let instr = MakeRecord(fields);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.0));
+    }
+}
+
+impl Display for MakeRecord {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "make_record {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GetField(pub u16);
+
+impl Operation for GetField {
+    const ID: usize = next_id![MakeRecord];
+    const SIZE: usize = 3;
+    const DISPLAY_NAME: &'static str = "get_field";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (idx, tail) = pump_two(input).context("Failed to get field index")?;
+        let instr = GetField(idx);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.0));
+    }
+}
+
+impl Display for GetField {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "get_field {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Panic(pub u16);
+
+impl Operation for Panic {
+    const ID: usize = next_id![GetField];
+    const SIZE: usize = 3;
+    const DISPLAY_NAME: &'static str = "panic";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (len, tail) = pump_two(input).context("Failed to get message length")?;
+        let instr = Panic(len);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.0));
+    }
+}
+
+impl Display for Panic {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "panic {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rem;
+
+impl Operation for Rem {
+    const ID: usize = next_id![Panic];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "rem";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let instr = Rem;
+
+        Ok((instr, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for Rem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        write!(f, "rem")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HostCall {
+    pub function: u16,
+    pub args: u16,
+}
+
+impl Operation for HostCall {
+    const ID: usize = next_id![Rem];
+    const SIZE: usize = 5;
+    const DISPLAY_NAME: &'static str = "host_call";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (function, input) = pump_two(input).context("Failed to get host function index")?;
+        let (args, input) = pump_two(input).context("Failed to get number of arguments")?;
+        let instr = HostCall { function, args };
+
+        Ok((instr, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.function));
+        encoder.extend_from_slice(&dump_two(self.args));
+    }
+}
+
+impl Display for HostCall {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "host_call {} {}", self.function, self.args)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PrintI;
+
+impl Operation for PrintI {
+    const ID: usize = next_id![HostCall];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "print_i";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((PrintI, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for PrintI {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "print_i")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PrintB;
+
+impl Operation for PrintB {
+    const ID: usize = next_id![PrintI];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "print_b";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((PrintB, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for PrintB {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "print_b")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PrintS(pub u16);
+
+impl Operation for PrintS {
+    const ID: usize = next_id![PrintB];
+    const SIZE: usize = 3;
+    const DISPLAY_NAME: &'static str = "print_s";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (len, tail) = pump_two(input).context("Failed to get string length")?;
+        let instr = PrintS(len);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.0));
+    }
+}
+
+impl Display for PrintS {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "print_s {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ReadI;
+
+impl Operation for ReadI {
+    const ID: usize = next_id![PrintS];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "read_i";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((ReadI, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for ReadI {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "read_i")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Alloc(pub u16);
+
+impl Operation for Alloc {
+    const ID: usize = next_id![ReadI];
+    const SIZE: usize = 3;
+    const DISPLAY_NAME: &'static str = "alloc";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (fields, tail) = pump_two(input).context("Failed to get number of object fields")?;
+        let instr = Alloc(fields);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.0));
+    }
+}
+
+impl Display for Alloc {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "alloc {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Load(pub u16);
+
+impl Operation for Load {
+    const ID: usize = next_id![Alloc];
+    const SIZE: usize = 3;
+    const DISPLAY_NAME: &'static str = "load";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (idx, tail) = pump_two(input).context("Failed to get field index")?;
+        let instr = Load(idx);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.0));
+    }
+}
+
+impl Display for Load {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "load {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Store(pub u16);
+
+impl Operation for Store {
+    const ID: usize = next_id![Load];
+    const SIZE: usize = 3;
+    const DISPLAY_NAME: &'static str = "store";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (idx, tail) = pump_two(input).context("Failed to get field index")?;
+        let instr = Store(idx);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.0));
+    }
+}
+
+impl Display for Store {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "store {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MakeList(pub u16);
+
+impl Operation for MakeList {
+    const ID: usize = next_id![Store];
+    const SIZE: usize = 3;
+    const DISPLAY_NAME: &'static str = "make_list";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (elements, tail) = pump_two(input).context("Failed to get number of list elements")?;
+        let instr = MakeList(elements);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.0));
+    }
+}
+
+impl Display for MakeList {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "make_list {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cons;
+
+impl Operation for Cons {
+    const ID: usize = next_id![MakeList];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "cons";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((Cons, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for Cons {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "cons")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Head;
+
+impl Operation for Head {
+    const ID: usize = next_id![Cons];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "head";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((Head, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for Head {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "head")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tail;
+
+impl Operation for Tail {
+    const ID: usize = next_id![Head];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "tail";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((Tail, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for Tail {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "tail")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IsEmpty;
+
+impl Operation for IsEmpty {
+    const ID: usize = next_id![Tail];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "is_empty";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((IsEmpty, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for IsEmpty {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "is_empty")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EmptyMap;
+
+impl Operation for EmptyMap {
+    const ID: usize = next_id![IsEmpty];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "empty_map";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((EmptyMap, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for EmptyMap {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "empty_map")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MapInsert;
+
+impl Operation for MapInsert {
+    const ID: usize = next_id![EmptyMap];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "map_insert";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((MapInsert, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for MapInsert {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "map_insert")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MapGet;
+
+impl Operation for MapGet {
+    const ID: usize = next_id![MapInsert];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "map_get";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((MapGet, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for MapGet {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "map_get")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MapRemove;
+
+impl Operation for MapRemove {
+    const ID: usize = next_id![MapGet];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "map_remove";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((MapRemove, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for MapRemove {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "map_remove")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MapContains;
+
+impl Operation for MapContains {
+    const ID: usize = next_id![MapRemove];
+    const SIZE: usize = 1;
+    const DISPLAY_NAME: &'static str = "map_contains";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        Ok((MapContains, input))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+    }
+}
+
+impl Display for MapContains {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "map_contains")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Assert(pub u16);
+
+impl Operation for Assert {
+    const ID: usize = next_id![MapContains];
+    const SIZE: usize = 3;
+    const DISPLAY_NAME: &'static str = "assert";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (len, tail) = pump_two(input).context("Failed to get message length")?;
+        let instr = Assert(len);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.0));
+    }
+}
+
+impl Display for Assert {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "assert {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AssertEq(pub u16);
+
+impl Operation for AssertEq {
+    const ID: usize = next_id![Assert];
+    const SIZE: usize = 3;
+    const DISPLAY_NAME: &'static str = "assert_eq";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (len, tail) = pump_two(input).context("Failed to get message length")?;
+        let instr = AssertEq(len);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.0));
+    }
+}
+
+impl Display for AssertEq {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "assert_eq {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AssertEqB(pub u16);
+
+impl Operation for AssertEqB {
+    const ID: usize = next_id![AssertEq];
+    const SIZE: usize = 3;
+    const DISPLAY_NAME: &'static str = "assert_eq_b";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (len, tail) = pump_two(input).context("Failed to get message length")?;
+        let instr = AssertEqB(len);
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_two(self.0));
+    }
+}
+
+impl Display for AssertEqB {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "assert_eq_b {}", self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MemoLoad {
+    pub function: u32,
+    pub params: u16,
+    pub hit_addr: u32,
+}
+
+impl Operation for MemoLoad {
+    const ID: usize = next_id![AssertEqB];
+    const SIZE: usize = 11;
+    const DISPLAY_NAME: &'static str = "memo_load";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (function, tail) = pump_four(input).context("Failed to get function address")?;
+        let (params, tail) = pump_two(tail).context("Failed to get parameter count")?;
+        let (hit_addr, tail) = pump_four(tail).context("Failed to get cache hit address")?;
+        let instr = MemoLoad {
+            function,
+            params,
+            hit_addr,
+        };
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_four(self.function));
+        encoder.extend_from_slice(&dump_two(self.params));
+        encoder.extend_from_slice(&dump_four(self.hit_addr));
+    }
+}
+
+impl Display for MemoLoad {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(
+            f,
+            "memo_load {} {} {}",
+            self.function, self.params, self.hit_addr
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MemoStore {
+    pub function: u32,
+    pub params: u16,
+}
+
+impl Operation for MemoStore {
+    const ID: usize = next_id![MemoLoad];
+    const SIZE: usize = 7;
+    const DISPLAY_NAME: &'static str = "memo_store";
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8])> {
+        let (function, tail) = pump_four(input).context("Failed to get function address")?;
+        let (params, tail) = pump_two(tail).context("Failed to get parameter count")?;
+        let instr = MemoStore { function, params };
+
+        Ok((instr, tail))
+    }
+
+    fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.push(Self::ID as u8);
+        encoder.extend_from_slice(&dump_four(self.function));
+        encoder.extend_from_slice(&dump_two(self.params));
+    }
+}
+
+impl Display for MemoStore {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "memo_store {} {}", self.function, self.params)
+    }
+}
+
 pub(crate) fn pump_one(input: &[u8]) -> Result<(u8, &[u8])> {
     match input {
         [fst, rest @ ..] => Ok((*fst, rest)),
@@ -439,374 +1286,934 @@ pub(crate) fn pump_one(input: &[u8]) -> Result<(u8, &[u8])> {
             Err(anyhow!(DecodingError::UnexpectedEof)).context("Failed to get one byte from input")
         }
     }
-}
+}
+
+pub fn dump_one(input: u8) -> [u8; 1] {
+    [input]
+}
+
+pub(crate) fn pump_two(input: &[u8]) -> Result<(u16, &[u8])> {
+    match input {
+        [fst, snd, rest @ ..] => {
+            let val = u16::from_be_bytes([*fst, *snd]);
+            Ok((val, rest))
+        }
+        _ => {
+            Err(anyhow!(DecodingError::UnexpectedEof)).context("Failed to get two bytes from input")
+        }
+    }
+}
+
+pub(crate) fn dump_two(input: u16) -> [u8; 2] {
+    input.to_be_bytes()
+}
+
+pub(crate) fn pump_four(input: &[u8]) -> Result<(u32, &[u8])> {
+    match input {
+        [fst, snd, trd, fth, rest @ ..] => {
+            let val = u32::from_be_bytes([*fst, *snd, *trd, *fth]);
+            Ok((val, rest))
+        }
+        _ => Err(anyhow!(DecodingError::UnexpectedEof))
+            .context("Failed to get four bytes from input"),
+    }
+}
+
+pub(crate) fn dump_four(input: u32) -> [u8; 4] {
+    input.to_be_bytes()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodingError {
+    UnknownOpcode(u8),
+    UnexpectedEof,
+}
+
+impl Display for DecodingError {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        match self {
+            DecodingError::UnexpectedEof => write!(f, "Unexpected EOF"),
+            DecodingError::UnknownOpcode(id) => write!(f, "Unknown opcode: `{}`", id),
+        }
+    }
+}
+
+impl Error for DecodingError {}
+
+#[cfg(test)]
+mod id_tests {
+    use super::*;
+
+    macro_rules! assert_correct_id {
+        ($ty:ident) => {
+            assert_eq!(
+                AVAILABLE_DECODERS[$ty::ID] as usize,
+                $ty::decode_and_wrap as Decoder as usize
+            );
+        };
+    }
+
+    #[test]
+    fn decoder_id_matches_operation_id() {
+        assert_correct_id!(PushI);
+        assert_correct_id!(AddI);
+        assert_correct_id!(FStop);
+        assert_correct_id!(PushCopy);
+        assert_correct_id!(Call);
+        assert_correct_id!(Ret);
+        assert_correct_id!(ResV);
+        assert_correct_id!(PopCopy);
+        assert_correct_id!(Goto);
+        assert_correct_id!(CondJmp);
+        assert_correct_id!(Neg);
+        assert_correct_id!(Mul);
+        assert_correct_id!(Pop);
+        assert_correct_id!(Cmp);
+        assert_correct_id!(PushFn);
+        assert_correct_id!(CallIndirect);
+        assert_correct_id!(MakeClosure);
+        assert_correct_id!(MakeRecord);
+        assert_correct_id!(GetField);
+        assert_correct_id!(Panic);
+        assert_correct_id!(Rem);
+        assert_correct_id!(HostCall);
+        assert_correct_id!(PrintI);
+        assert_correct_id!(PrintB);
+        assert_correct_id!(PrintS);
+        assert_correct_id!(ReadI);
+        assert_correct_id!(Alloc);
+        assert_correct_id!(Load);
+        assert_correct_id!(Store);
+        assert_correct_id!(MakeList);
+        assert_correct_id!(Cons);
+        assert_correct_id!(Head);
+        assert_correct_id!(Tail);
+        assert_correct_id!(IsEmpty);
+        assert_correct_id!(EmptyMap);
+        assert_correct_id!(MapInsert);
+        assert_correct_id!(MapGet);
+        assert_correct_id!(MapRemove);
+        assert_correct_id!(MapContains);
+        assert_correct_id!(Assert);
+        assert_correct_id!(AssertEq);
+        assert_correct_id!(AssertEqB);
+        assert_correct_id!(MemoLoad);
+        assert_correct_id!(MemoStore);
+    }
+}
+
+#[cfg(test)]
+macro_rules! test_encoding {
+    ( $( $instr:expr => $bytecode:expr )* $(,)? ) => {
+        #[test]
+        fn encoding() {
+            $(
+                assert_eq!(encode($instr), $bytecode);
+
+                let mut generated_bytecode = Vec::new();
+                Instruction::from($instr).encode(&mut generated_bytecode);
+
+                assert_eq!(generated_bytecode, $bytecode);
+            )*
+        }
+    };
+}
+
+#[cfg(test)]
+macro_rules! test_symmetry {
+    ($operation:ident, $instr:expr , $bytecode:expr $(,)? ) => {
+        #[test]
+        fn symmetry() {
+            assert_eq!(
+                $operation::decode_single_with_opcode(&encode($instr)).unwrap(),
+                $instr
+            );
+            assert_eq!(
+                encode($operation::decode_single_with_opcode(&$bytecode).unwrap()),
+                $bytecode
+            );
+        }
+    };
+}
+
+#[cfg(test)]
+macro_rules! test_display {
+    ( $( $instr:expr => $display:literal ),* $(,)? ) => {
+        #[test]
+        fn display() {
+            $(
+                assert_eq!($instr.to_string(), $display);
+                assert_eq!(Instruction::from($instr).to_string(), $display);
+            )*
+        }
+    };
+}
+
+#[cfg(test)]
+fn encode(instr: impl Operation) -> Vec<u8> {
+    let mut tmp = Vec::new();
+    instr.encode(&mut tmp);
+    tmp
+}
+
+#[cfg(test)]
+mod push_i {
+    use super::*;
+
+    test_encoding! {
+        PushI(42) => [0, 0, 0, 0, 42],
+    }
+
+    test_symmetry! {
+        PushI, PushI(42), [0, 0, 0, 0, 42],
+    }
+
+    test_display! {
+        PushI(42) => "push_i 42",
+        PushI(101) => "push_i 101",
+    }
+}
+
+#[cfg(test)]
+mod add_i {
+    use super::*;
+
+    test_encoding! {
+        AddI => [1],
+    }
+
+    test_symmetry! {
+        AddI, AddI, [1],
+    }
+
+    test_display! {
+        AddI => "add_i",
+    }
+}
+
+#[cfg(test)]
+mod f_stop {
+    use super::*;
+
+    test_encoding! {
+        FStop => [2],
+    }
+
+    test_symmetry! {
+        FStop, FStop, [2],
+    }
+
+    test_display! {
+        FStop => "f_stop",
+    }
+}
+
+#[cfg(test)]
+mod push_copy {
+    use super::*;
+
+    test_encoding! {
+        PushCopy(300) => [3, 1, 44]
+    }
+
+    test_symmetry! {
+        PushCopy, PushCopy(300), [3, 1, 44],
+    }
+
+    test_display! {
+        PushCopy(3) => "push_copy 3",
+        PushCopy(11) => "push_copy 11",
+    }
+}
+
+#[cfg(test)]
+mod call {
+    use super::*;
+
+    test_encoding! {
+        Call(247) => [4, 0, 0, 0, 247],
+    }
+
+    test_symmetry! {
+        Call, Call(247), [4, 0, 0, 0, 247],
+    }
+
+    test_display! {
+        Call(1010) => "call 1010",
+        Call(12) => "call 12",
+    }
+}
+
+#[cfg(test)]
+mod ret {
+    use super::*;
+
+    test_encoding! {
+        Ret { shrink_offset: 2, ip_offset: 4 } => [5, 0, 2, 0, 4],
+    }
+
+    test_symmetry! {
+        Ret,
+        Ret { shrink_offset: 2, ip_offset: 4 },
+        [5, 0, 2, 0, 4],
+    }
+
+    test_display! {
+        Ret { shrink_offset: 100, ip_offset: 34 } => "ret 100 34",
+        Ret { shrink_offset: 10, ip_offset: 4 } => "ret 10 4",
+    }
+}
+
+#[cfg(test)]
+mod res_v {
+    use super::*;
+
+    test_encoding! {
+        ResV(22) => [6, 0, 22],
+    }
+
+    test_symmetry! {
+        ResV, ResV(101), [6, 0, 101],
+    }
+
+    test_display! {
+        ResV(10) => "res_v 10"
+    }
+}
+
+#[cfg(test)]
+mod pop_cpy {
+    use super::*;
+
+    test_encoding! {
+        PopCopy(32) => [7, 0, 32],
+    }
+
+    test_symmetry! {
+        PopCopy, PopCopy(13), [7, 0, 32],
+    }
+
+    test_display! {
+        PopCopy(12) => "pop_copy 12",
+        PopCopy(2) => "pop_copy 2",
+    }
+}
+
+#[cfg(test)]
+mod goto {
+    use super::*;
+
+    test_encoding! {
+        Goto(444) => [8, 0, 0, 1, 188],
+    }
+
+    test_symmetry! {
+        Goto, Goto(10), [8, 0, 0, 0, 10],
+    }
+
+    test_display! {
+        Goto(1337) => "goto 1337",
+        Goto(2503) => "goto 2503",
+    }
+}
+
+#[cfg(test)]
+mod cond_jmp {
+    use super::*;
+
+    test_encoding! {
+        CondJmp { negative_addr: 101, null_addr: 69, positive_addr: 13 }
+        =>
+        [9, 0, 0, 0, 101, 0, 0, 0, 69, 0, 0, 0, 13],
+    }
+
+    test_symmetry! {
+        CondJmp,
+        CondJmp { negative_addr: 101, null_addr: 69, positive_addr: 42 },
+        [9, 0, 0, 0, 101, 0, 0, 0, 69, 0, 0, 0, 42],
+    }
+
+    test_display! {
+        CondJmp { negative_addr: 1221, null_addr: 92, positive_addr: 218 } => "cond_jmp 1221 92 218",
+    }
+}
+
+#[cfg(test)]
+mod neg {
+    use super::*;
+
+    test_encoding! {
+        Neg => [10],
+    }
+
+    test_symmetry! {
+        Neg, Neg, [10],
+    }
+
+    test_display! {
+        Neg => "neg",
+    }
+}
+
+#[cfg(test)]
+mod mul {
+    use super::*;
+
+    test_encoding! {
+        Mul => [11],
+    }
+
+    test_symmetry! {
+        Mul, Mul, [11],
+    }
+
+    test_display! {
+        Mul => "mul",
+    }
+}
+
+#[cfg(test)]
+mod pop {
+    use super::*;
+
+    test_encoding! {
+        Pop(10) => [12, 0, 10],
+    }
+
+    test_symmetry! {
+        Pop, Pop(32), [12, 0, 32],
+    }
+
+    test_display! {
+        Pop(111) => "pop 111",
+    }
+}
+
+#[cfg(test)]
+mod cmp {
+    use super::*;
+
+    test_encoding! {
+        Cmp => [13],
+    }
+
+    test_symmetry! {
+        Cmp, Cmp, [13],
+    }
+
+    test_display! {
+        Cmp => "cmp",
+    }
+}
+
+#[cfg(test)]
+mod push_fn {
+    use super::*;
+
+    test_encoding! {
+        PushFn(444) => [14, 0, 0, 1, 188],
+    }
+
+    test_symmetry! {
+        PushFn, PushFn(10), [14, 0, 0, 0, 10],
+    }
+
+    test_display! {
+        PushFn(42) => "push_fn 42",
+    }
+}
+
+#[cfg(test)]
+mod call_indirect {
+    use super::*;
+
+    test_encoding! {
+        CallIndirect => [15],
+    }
+
+    test_symmetry! {
+        CallIndirect, CallIndirect, [15],
+    }
+
+    test_display! {
+        CallIndirect => "call_indirect",
+    }
+}
+
+#[cfg(test)]
+mod make_closure {
+    use super::*;
+
+    test_encoding! {
+        MakeClosure { addr: 300, captures: 2 } => [16, 0, 0, 1, 44, 0, 2],
+    }
+
+    test_symmetry! {
+        MakeClosure, MakeClosure { addr: 10, captures: 1 }, [16, 0, 0, 0, 10, 0, 1],
+    }
+
+    test_display! {
+        MakeClosure { addr: 42, captures: 3 } => "make_closure 42 3",
+    }
+}
+
+#[cfg(test)]
+mod make_record {
+    use super::*;
+
+    test_encoding! {
+        MakeRecord(300) => [17, 1, 44],
+    }
+
+    test_symmetry! {
+        MakeRecord, MakeRecord(2), [17, 0, 2],
+    }
+
+    test_display! {
+        MakeRecord(3) => "make_record 3",
+    }
+}
+
+#[cfg(test)]
+mod get_field {
+    use super::*;
+
+    test_encoding! {
+        GetField(1) => [18, 0, 1],
+    }
+
+    test_symmetry! {
+        GetField, GetField(4), [18, 0, 4],
+    }
+
+    test_display! {
+        GetField(0) => "get_field 0",
+    }
+}
+
+#[cfg(test)]
+mod panic {
+    use super::*;
+
+    test_encoding! {
+        Panic(3) => [19, 0, 3],
+    }
+
+    test_symmetry! {
+        Panic, Panic(257), [19, 1, 1],
+    }
+
+    test_display! {
+        Panic(5) => "panic 5",
+    }
+}
+
+#[cfg(test)]
+mod rem {
+    use super::*;
+
+    test_encoding! {
+        Rem => [20],
+    }
+
+    test_symmetry! {
+        Rem, Rem, [20],
+    }
 
-pub fn dump_one(input: u8) -> [u8; 1] {
-    [input]
+    test_display! {
+        Rem => "rem",
+    }
 }
 
-fn pump_two(input: &[u8]) -> Result<(u16, &[u8])> {
-    match input {
-        [fst, snd, rest @ ..] => {
-            let val = u16::from_be_bytes([*fst, *snd]);
-            Ok((val, rest))
-        }
-        _ => {
-            Err(anyhow!(DecodingError::UnexpectedEof)).context("Failed to get two bytes from input")
-        }
+#[cfg(test)]
+mod host_call {
+    use super::*;
+
+    test_encoding! {
+        HostCall { function: 2, args: 300 } => [21, 0, 2, 1, 44],
     }
-}
 
-fn dump_two(input: u16) -> [u8; 2] {
-    input.to_be_bytes()
-}
+    test_symmetry! {
+        HostCall, HostCall { function: 1, args: 3 }, [21, 0, 1, 0, 3],
+    }
 
-fn pump_four(input: &[u8]) -> Result<(u32, &[u8])> {
-    match input {
-        [fst, snd, trd, fth, rest @ ..] => {
-            let val = u32::from_be_bytes([*fst, *snd, *trd, *fth]);
-            Ok((val, rest))
-        }
-        _ => Err(anyhow!(DecodingError::UnexpectedEof))
-            .context("Failed to get four bytes from input"),
+    test_display! {
+        HostCall { function: 0, args: 2 } => "host_call 0 2",
     }
 }
 
-fn dump_four(input: u32) -> [u8; 4] {
-    input.to_be_bytes()
+#[cfg(test)]
+mod print_i {
+    use super::*;
+
+    test_encoding! {
+        PrintI => [22],
+    }
+
+    test_symmetry! {
+        PrintI, PrintI, [22],
+    }
+
+    test_display! {
+        PrintI => "print_i",
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum DecodingError {
-    UnknownOpcode(u8),
-    UnexpectedEof,
+#[cfg(test)]
+mod print_b {
+    use super::*;
+
+    test_encoding! {
+        PrintB => [23],
+    }
+
+    test_symmetry! {
+        PrintB, PrintB, [23],
+    }
+
+    test_display! {
+        PrintB => "print_b",
+    }
 }
 
-impl Display for DecodingError {
-    fn fmt(&self, f: &mut Formatter) -> FResult {
-        match self {
-            DecodingError::UnexpectedEof => write!(f, "Unexpected EOF"),
-            DecodingError::UnknownOpcode(id) => write!(f, "Unknown opcode: `{}`", id),
-        }
+#[cfg(test)]
+mod print_s {
+    use super::*;
+
+    test_encoding! {
+        PrintS(3) => [24, 0, 3],
+    }
+
+    test_symmetry! {
+        PrintS, PrintS(258), [24, 1, 2],
+    }
+
+    test_display! {
+        PrintS(5) => "print_s 5",
     }
 }
 
-impl Error for DecodingError {}
+#[cfg(test)]
+mod read_i {
+    use super::*;
+
+    test_encoding! {
+        ReadI => [25],
+    }
+
+    test_symmetry! {
+        ReadI, ReadI, [25],
+    }
+
+    test_display! {
+        ReadI => "read_i",
+    }
+}
 
 #[cfg(test)]
-mod id_tests {
+mod alloc {
     use super::*;
 
-    macro_rules! assert_correct_id {
-        ($ty:ident) => {
-            assert_eq!(
-                AVAILABLE_DECODERS[$ty::ID] as usize,
-                $ty::decode_and_wrap as usize
-            );
-        };
+    test_encoding! {
+        Alloc(300) => [26, 1, 44],
     }
 
-    #[test]
-    fn decoder_id_matches_operation_id() {
-        assert_correct_id!(PushI);
-        assert_correct_id!(AddI);
-        assert_correct_id!(FStop);
-        assert_correct_id!(PushCopy);
-        assert_correct_id!(Call);
-        assert_correct_id!(Ret);
-        assert_correct_id!(ResV);
-        assert_correct_id!(PopCopy);
-        assert_correct_id!(Goto);
-        assert_correct_id!(CondJmp);
-        assert_correct_id!(Neg);
-        assert_correct_id!(Mul);
-        assert_correct_id!(Pop);
+    test_symmetry! {
+        Alloc, Alloc(2), [26, 0, 2],
+    }
+
+    test_display! {
+        Alloc(3) => "alloc 3",
     }
 }
 
 #[cfg(test)]
-macro_rules! test_encoding {
-    ( $( $instr:expr => $bytecode:expr )* $(,)? ) => {
-        #[test]
-        fn encoding() {
-            $(
-                assert_eq!(encode($instr), $bytecode);
+mod load {
+    use super::*;
 
-                let mut generated_bytecode = Vec::new();
-                Instruction::from($instr).encode(&mut generated_bytecode);
+    test_encoding! {
+        Load(300) => [27, 1, 44],
+    }
 
-                assert_eq!(generated_bytecode, $bytecode);
-            )*
-        }
-    };
+    test_symmetry! {
+        Load, Load(2), [27, 0, 2],
+    }
+
+    test_display! {
+        Load(3) => "load 3",
+    }
 }
 
 #[cfg(test)]
-macro_rules! test_symmetry {
-    ($operation:ident, $instr:expr , $bytecode:expr $(,)? ) => {
-        #[test]
-        fn symmetry() {
-            assert_eq!(
-                $operation::decode_single_with_opcode(&encode($instr)).unwrap(),
-                $instr
-            );
-            assert_eq!(
-                encode($operation::decode_single_with_opcode(&$bytecode).unwrap()),
-                $bytecode
-            );
-        }
-    };
+mod store {
+    use super::*;
+
+    test_encoding! {
+        Store(300) => [28, 1, 44],
+    }
+
+    test_symmetry! {
+        Store, Store(2), [28, 0, 2],
+    }
+
+    test_display! {
+        Store(3) => "store 3",
+    }
 }
 
 #[cfg(test)]
-macro_rules! test_display {
-    ( $( $instr:expr => $display:literal ),* $(,)? ) => {
-        #[test]
-        fn display() {
-            $(
-                assert_eq!($instr.to_string(), $display);
-                assert_eq!(Instruction::from($instr).to_string(), $display);
-            )*
-        }
-    };
+mod make_list {
+    use super::*;
+
+    test_encoding! {
+        MakeList(300) => [29, 1, 44],
+    }
+
+    test_symmetry! {
+        MakeList, MakeList(2), [29, 0, 2],
+    }
+
+    test_display! {
+        MakeList(3) => "make_list 3",
+    }
 }
 
 #[cfg(test)]
-fn encode(instr: impl Operation) -> Vec<u8> {
-    let mut tmp = Vec::new();
-    instr.encode(&mut tmp);
-    tmp
+mod cons {
+    use super::*;
+
+    test_encoding! {
+        Cons => [30],
+    }
+
+    test_symmetry! {
+        Cons, Cons, [30],
+    }
+
+    test_display! {
+        Cons => "cons",
+    }
 }
 
 #[cfg(test)]
-mod push_i {
+mod head {
     use super::*;
 
     test_encoding! {
-        PushI(42) => [0, 0, 0, 0, 42],
+        Head => [31],
     }
 
     test_symmetry! {
-        PushI, PushI(42), [0, 0, 0, 0, 42],
+        Head, Head, [31],
     }
 
     test_display! {
-        PushI(42) => "push_i 42",
-        PushI(101) => "push_i 101",
+        Head => "head",
     }
 }
 
 #[cfg(test)]
-mod add_i {
+mod tail {
     use super::*;
 
     test_encoding! {
-        AddI => [1],
+        Tail => [32],
     }
 
     test_symmetry! {
-        AddI, AddI, [1],
+        Tail, Tail, [32],
     }
 
     test_display! {
-        AddI => "add_i",
+        Tail => "tail",
     }
 }
 
 #[cfg(test)]
-mod f_stop {
+mod is_empty {
     use super::*;
 
     test_encoding! {
-        FStop => [2],
+        IsEmpty => [33],
     }
 
     test_symmetry! {
-        FStop, FStop, [2],
+        IsEmpty, IsEmpty, [33],
     }
 
     test_display! {
-        FStop => "f_stop",
+        IsEmpty => "is_empty",
     }
 }
 
 #[cfg(test)]
-mod push_copy {
+mod empty_map {
     use super::*;
 
     test_encoding! {
-        PushCopy(300) => [3, 1, 44]
+        EmptyMap => [34],
     }
 
     test_symmetry! {
-        PushCopy, PushCopy(300), [3, 1, 44],
+        EmptyMap, EmptyMap, [34],
     }
 
     test_display! {
-        PushCopy(3) => "push_copy 3",
-        PushCopy(11) => "push_copy 11",
+        EmptyMap => "empty_map",
     }
 }
 
 #[cfg(test)]
-mod call {
+mod map_insert {
     use super::*;
 
     test_encoding! {
-        Call(247) => [4, 0, 0, 0, 247],
+        MapInsert => [35],
     }
 
     test_symmetry! {
-        Call, Call(247), [4, 0, 0, 0, 247],
+        MapInsert, MapInsert, [35],
     }
 
     test_display! {
-        Call(1010) => "call 1010",
-        Call(12) => "call 12",
+        MapInsert => "map_insert",
     }
 }
 
 #[cfg(test)]
-mod ret {
+mod map_get {
     use super::*;
 
     test_encoding! {
-        Ret { shrink_offset: 2, ip_offset: 4 } => [5, 0, 2, 0, 4],
+        MapGet => [36],
     }
 
     test_symmetry! {
-        Ret,
-        Ret { shrink_offset: 2, ip_offset: 4 },
-        [5, 0, 2, 0, 4],
+        MapGet, MapGet, [36],
     }
 
     test_display! {
-        Ret { shrink_offset: 100, ip_offset: 34 } => "ret 100 34",
-        Ret { shrink_offset: 10, ip_offset: 4 } => "ret 10 4",
+        MapGet => "map_get",
     }
 }
 
 #[cfg(test)]
-mod res_v {
+mod map_remove {
     use super::*;
 
     test_encoding! {
-        ResV(22) => [6, 0, 22],
+        MapRemove => [37],
     }
 
     test_symmetry! {
-        ResV, ResV(101), [6, 0, 101],
+        MapRemove, MapRemove, [37],
     }
 
     test_display! {
-        ResV(10) => "res_v 10"
+        MapRemove => "map_remove",
     }
 }
 
 #[cfg(test)]
-mod pop_cpy {
+mod map_contains {
     use super::*;
 
     test_encoding! {
-        PopCopy(32) => [7, 0, 32],
+        MapContains => [38],
     }
 
     test_symmetry! {
-        PopCopy, PopCopy(13), [7, 0, 32],
+        MapContains, MapContains, [38],
     }
 
     test_display! {
-        PopCopy(12) => "pop_copy 12",
-        PopCopy(2) => "pop_copy 2",
+        MapContains => "map_contains",
     }
 }
 
 #[cfg(test)]
-mod goto {
+mod assert {
     use super::*;
 
     test_encoding! {
-        Goto(444) => [8, 0, 0, 1, 188],
+        Assert(3) => [39, 0, 3],
     }
 
     test_symmetry! {
-        Goto, Goto(10), [8, 0, 0, 0, 10],
+        Assert, Assert(257), [39, 1, 1],
     }
 
     test_display! {
-        Goto(1337) => "goto 1337",
-        Goto(2503) => "goto 2503",
+        Assert(5) => "assert 5",
     }
 }
 
 #[cfg(test)]
-mod cond_jmp {
+mod assert_eq {
     use super::*;
 
     test_encoding! {
-        CondJmp { negative_addr: 101, null_addr: 69, positive_addr: 13 }
-        =>
-        [9, 0, 0, 0, 101, 0, 0, 0, 69, 0, 0, 0, 13],
+        AssertEq(3) => [40, 0, 3],
     }
 
     test_symmetry! {
-        CondJmp,
-        CondJmp { negative_addr: 101, null_addr: 69, positive_addr: 42 },
-        [9, 0, 0, 0, 101, 0, 0, 0, 69, 0, 0, 0, 42],
+        AssertEq, AssertEq(257), [40, 1, 1],
     }
 
     test_display! {
-        CondJmp { negative_addr: 1221, null_addr: 92, positive_addr: 218 } => "cond_jmp 1221 92 218",
+        AssertEq(5) => "assert_eq 5",
     }
 }
 
 #[cfg(test)]
-mod neg {
+mod assert_eq_b {
     use super::*;
 
     test_encoding! {
-        Neg => [10],
+        AssertEqB(3) => [41, 0, 3],
     }
 
     test_symmetry! {
-        Neg, Neg, [10],
+        AssertEqB, AssertEqB(257), [41, 1, 1],
     }
 
     test_display! {
-        Neg => "neg",
+        AssertEqB(5) => "assert_eq_b 5",
     }
 }
 
 #[cfg(test)]
-mod mul {
+mod memo_load {
     use super::*;
 
     test_encoding! {
-        Mul => [11],
+        MemoLoad { function: 8, params: 2, hit_addr: 444 } => [42, 0, 0, 0, 8, 0, 2, 0, 0, 1, 188],
     }
 
     test_symmetry! {
-        Mul, Mul, [11],
+        MemoLoad,
+        MemoLoad { function: 3, params: 1, hit_addr: 10 },
+        [42, 0, 0, 0, 3, 0, 1, 0, 0, 0, 10],
     }
 
     test_display! {
-        Mul => "mul",
+        MemoLoad { function: 7, params: 3, hit_addr: 42 } => "memo_load 7 3 42",
     }
 }
 
 #[cfg(test)]
-mod pop {
+mod memo_store {
     use super::*;
 
     test_encoding! {
-        Pop(10) => [12, 0, 10],
+        MemoStore { function: 8, params: 3 } => [43, 0, 0, 0, 8, 0, 3],
     }
 
     test_symmetry! {
-        Pop, Pop(32), [12, 0, 32],
+        MemoStore, MemoStore { function: 300, params: 257 }, [43, 0, 0, 1, 44, 1, 1],
     }
 
     test_display! {
-        Pop(111) => "pop 111",
+        MemoStore { function: 7, params: 5 } => "memo_store 7 5",
     }
 }
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+
+use crate::operations::{dump_four, dump_two, pump_four, pump_two, DecodingError};
+
+/// The name of the function that starts at each address of the bytecode,
+/// the name of the host functions it calls and the number of arguments of
+/// its `main` function.
+///
+/// Function names are not needed to run a program, but allow to describe the
+/// code being run, for instance when a program panics. Host functions are
+/// called by index, and their names tell which function of the application
+/// running the program each index refers to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Symbols {
+    functions: BTreeMap<u32, String>,
+    host_functions: Vec<String>,
+    main_params: u16,
+}
+
+impl Symbols {
+    pub fn new() -> Symbols {
+        Symbols::default()
+    }
+
+    pub fn add_function(&mut self, addr: u32, name: String) {
+        self.functions.insert(addr, name);
+    }
+
+    /// Returns the name of the function that contains the instruction at
+    /// `addr`, assuming that functions are laid out contiguously.
+    pub fn function_at(&self, addr: u32) -> Option<&str> {
+        self.functions
+            .range(..=addr)
+            .next_back()
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// Returns the address of the function named `name`.
+    pub fn function_addr(&self, name: &str) -> Option<u32> {
+        self.functions
+            .iter()
+            .find(|(_, function)| *function == name)
+            .map(|(addr, _)| *addr)
+    }
+
+    /// Declares the next host function, whose index is the number of host
+    /// functions declared before it.
+    pub fn add_host_function(&mut self, name: String) -> u16 {
+        self.host_functions.push(name);
+        (self.host_functions.len() - 1) as u16
+    }
+
+    /// Returns the names of the host functions, in index order.
+    pub fn host_functions(&self) -> &[String] {
+        self.host_functions.as_slice()
+    }
+
+    pub fn set_main_params(&mut self, main_params: u16) {
+        self.main_params = main_params;
+    }
+
+    /// Returns the number of integers that must be pushed on the stack before
+    /// running the program, one per parameter of `main`.
+    pub fn main_params(&self) -> u16 {
+        self.main_params
+    }
+
+    /// Appends the symbols to `encoder`, so that they can be stored along
+    /// with the bytecode.
+    ///
+    /// ```none
+    /// main_params: u16
+    /// functions: u32, then for each function: addr: u32, name: string
+    /// host_functions: u16, then for each host function: name: string
+    /// ```
+    ///
+    /// Strings are stored as their length (`u32`), followed by their UTF-8
+    /// bytes.
+    pub fn encode(&self, encoder: &mut Vec<u8>) {
+        encoder.extend_from_slice(&dump_two(self.main_params));
+
+        encoder.extend_from_slice(&dump_four(self.functions.len() as u32));
+        self.functions.iter().for_each(|(addr, name)| {
+            encoder.extend_from_slice(&dump_four(*addr));
+            encode_string(name, encoder);
+        });
+
+        encoder.extend_from_slice(&dump_two(self.host_functions.len() as u16));
+        self.host_functions
+            .iter()
+            .for_each(|name| encode_string(name, encoder));
+    }
+
+    /// Reads symbols written by [`Symbols::encode`], returning the rest of
+    /// the input.
+    pub fn decode(input: &[u8]) -> Result<(Symbols, &[u8])> {
+        let mut symbols = Symbols::new();
+
+        let (main_params, mut input) =
+            pump_two(input).context("Failed to get the number of parameters of `main`")?;
+        symbols.set_main_params(main_params);
+
+        let (functions, tail) = pump_four(input).context("Failed to get number of functions")?;
+        input = tail;
+        for _ in 0..functions {
+            let (addr, tail) = pump_four(input).context("Failed to get function address")?;
+            let (name, tail) = decode_string(tail).context("Failed to get function name")?;
+            symbols.add_function(addr, name);
+            input = tail;
+        }
+
+        let (host_functions, tail) =
+            pump_two(input).context("Failed to get number of host functions")?;
+        input = tail;
+        for _ in 0..host_functions {
+            let (name, tail) = decode_string(input).context("Failed to get host function name")?;
+            symbols.add_host_function(name);
+            input = tail;
+        }
+
+        Ok((symbols, input))
+    }
+}
+
+fn encode_string(s: &str, encoder: &mut Vec<u8>) {
+    encoder.extend_from_slice(&dump_four(s.len() as u32));
+    encoder.extend_from_slice(s.as_bytes());
+}
+
+fn decode_string(input: &[u8]) -> Result<(String, &[u8])> {
+    let (len, input) = pump_four(input).context("Failed to get string length")?;
+    let len = len as usize;
+
+    let bytes = input.get(..len).ok_or(DecodingError::UnexpectedEof)?;
+    let s = String::from_utf8(bytes.to_vec()).context("Invalid UTF-8 string")?;
+
+    Ok((s, &input[len..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn function_at() {
+        let mut symbols = Symbols::new();
+        symbols.add_function(0, "main".to_owned());
+        symbols.add_function(10, "f".to_owned());
+
+        assert_eq!(symbols.function_at(3), Some("main"));
+        assert_eq!(symbols.function_at(10), Some("f"));
+        assert_eq!(symbols.function_at(42), Some("f"));
+    }
+
+    #[test]
+    fn function_addr() {
+        let mut symbols = Symbols::new();
+        symbols.add_function(0, "main".to_owned());
+        symbols.add_function(10, "f".to_owned());
+
+        assert_eq!(symbols.function_addr("f"), Some(10));
+        assert_eq!(symbols.function_addr("g"), None);
+    }
+
+    #[test]
+    fn no_function_before_the_first_one() {
+        let mut symbols = Symbols::new();
+        symbols.add_function(5, "main".to_owned());
+
+        assert_eq!(symbols.function_at(2), None);
+    }
+
+    #[test]
+    fn host_functions_are_indexed_in_order() {
+        let mut symbols = Symbols::new();
+
+        assert_eq!(symbols.add_host_function("read".to_owned()), 0);
+        assert_eq!(symbols.add_host_function("write".to_owned()), 1);
+        assert_eq!(symbols.host_functions(), ["read", "write"]);
+    }
+
+    #[test]
+    fn encoding_roundtrip() {
+        let mut symbols = Symbols::new();
+        symbols.add_function(0, "main".to_owned());
+        symbols.add_function(10, "f".to_owned());
+        symbols.add_host_function("read".to_owned());
+        symbols.set_main_params(2);
+
+        let mut encoded = Vec::new();
+        symbols.encode(&mut encoded);
+        encoded.push(42);
+
+        let (decoded, tail) = Symbols::decode(&encoded).unwrap();
+        assert_eq!(decoded, symbols);
+        assert_eq!(tail, [42]);
+    }
+
+    #[test]
+    fn truncated_symbols() {
+        let mut symbols = Symbols::new();
+        symbols.add_function(0, "main".to_owned());
+
+        let mut encoded = Vec::new();
+        symbols.encode(&mut encoded);
+        encoded.pop();
+
+        assert!(Symbols::decode(&encoded).is_err());
+    }
+}
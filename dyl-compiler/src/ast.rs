@@ -1,264 +1,2799 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::ty::Ty;
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct Program {
     functions: Vec<Function>,
+    traits: Vec<Trait>,
+    impls: Vec<Impl>,
+    records: Vec<Record>,
+    externs: Vec<ExternFunction>,
+    /// The file each function is declared in, when the program is made of
+    /// multiple files.
+    files: HashMap<String, String>,
 }
 
 impl Program {
+    #[cfg(test)]
     pub(crate) fn new(functions: Vec<Function>) -> Program {
-        Program { functions }
+        Program::with_traits(functions, Vec::new(), Vec::new())
+    }
+
+    /// The methods of `impls` must be part of `functions`, named after
+    /// [`method_name`].
+    pub(crate) fn with_traits(
+        functions: Vec<Function>,
+        traits: Vec<Trait>,
+        impls: Vec<Impl>,
+    ) -> Program {
+        Program {
+            functions,
+            traits,
+            impls,
+            records: Vec::new(),
+            externs: Vec::new(),
+            files: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn with_records(self, records: Vec<Record>) -> Program {
+        Program { records, ..self }
+    }
+
+    pub(crate) fn with_externs(self, externs: Vec<ExternFunction>) -> Program {
+        Program { externs, ..self }
+    }
+
+    /// Sets the file each function is declared in, indexed by function name.
+    pub(crate) fn with_files(self, files: HashMap<String, String>) -> Program {
+        Program { files, ..self }
+    }
+
+    /// Declares the traits that overload the arithmetic operators, such as
+    /// `trait Add { fn add(self, other: Self) -> Self; }`, and implements them
+    /// for `int`. Traits that the program already declares are left as is.
+    pub(crate) fn with_operator_traits(self) -> Program {
+        self.with_operator_trait::<Addition>()
+            .with_operator_trait::<Subtraction>()
+            .with_operator_trait::<Multiplication>()
+            .with_operator_trait::<Remainder>()
+    }
+
+    fn with_operator_trait<O: Operator>(mut self) -> Program {
+        if self.traits.iter().any(|t| t.name() == O::TRAIT) {
+            return self;
+        }
+
+        let params = |ty: Ty| {
+            vec![
+                Param::new("self".to_owned(), ty.clone()),
+                Param::new("other".to_owned(), ty),
+            ]
+        };
+
+        let self_ty = Ty::Param("Self".to_owned());
+        let signature =
+            MethodSignature::new(O::METHOD.to_owned(), params(self_ty.clone()), self_ty);
+        self.traits
+            .push(Trait::new(O::TRAIT.to_owned(), vec![signature]));
+
+        let body = O::expr(
+            ExprKind::ident("self".to_owned()),
+            ExprKind::ident("other".to_owned()),
+        );
+        self.functions.push(Function::new(
+            method_name(Some(O::TRAIT), &Ty::Int, O::METHOD),
+            Vec::new(),
+            params(Ty::Int),
+            Some(Ty::Int),
+            body,
+        ));
+        self.impls.push(Impl::new(
+            O::TRAIT.to_owned(),
+            Ty::Int,
+            vec![O::METHOD.to_owned()],
+        ));
+
+        self
+    }
+
+    /// Turns the calls to the functions implemented by the VM, such as
+    /// `head(l)`, into [`Builtin`]s. Just like the prelude, builtins are
+    /// shadowed by the functions of the program and by local variables.
+    pub(crate) fn with_builtins(self) -> Program {
+        let defined = self
+            .functions
+            .iter()
+            .map(Function::name)
+            .chain(self.externs.iter().map(ExternFunction::name))
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+
+        let mut resolve = |call: Call, site: &CallSite| {
+            let builtin = match call.callee() {
+                ExprKind::Ident(callee) => {
+                    let name = callee.name();
+                    let is_shadowed = site.bound().iter().chain(&defined).any(|n| n == name);
+
+                    BuiltinFunction::named(name).filter(|_| !is_shadowed)
+                }
+                _ => None,
+            };
+
+            match builtin {
+                Some(function) => ExprKind::builtin(function, call.args().to_vec()),
+                None => ExprKind::Call(call),
+            }
+        };
+
+        let functions = self
+            .functions
+            .iter()
+            .map(|f| {
+                let params = || f.params().iter().map(|p| p.name().to_owned());
+                let body = f
+                    .body()
+                    .map_calls_with(&mut resolve, &mut CallSite::new(params().collect()));
+
+                f.with_name_and_body(f.name().to_owned(), body)
+                    .map_clauses(|condition| {
+                        let bound = params().chain(std::iter::once(RESULT.to_owned()));
+                        condition.map_calls_with(&mut resolve, &mut CallSite::new(bound.collect()))
+                    })
+            })
+            .collect();
+
+        self.with_functions(functions)
+    }
+
+    /// Returns a program with the same declarations, but different
+    /// functions.
+    pub(crate) fn with_functions(&self, functions: Vec<Function>) -> Program {
+        Program::with_traits(functions, self.traits.clone(), self.impls.clone())
+            .with_records(self.records.clone())
+            .with_externs(self.externs.clone())
+            .with_files(self.files.clone())
+    }
+
+    /// Returns the same program, with the contracts of the functions
+    /// removed.
+    pub(crate) fn without_contracts(&self) -> Program {
+        let functions = self
+            .functions
+            .iter()
+            .map(|f| f.clone().with_clauses(Vec::new()))
+            .collect();
+
+        self.with_functions(functions)
     }
 
     pub(crate) fn functions(&self) -> &[Function] {
         self.functions.as_slice()
     }
+
+    pub(crate) fn traits(&self) -> &[Trait] {
+        self.traits.as_slice()
+    }
+
+    pub(crate) fn impls(&self) -> &[Impl] {
+        self.impls.as_slice()
+    }
+
+    pub(crate) fn records(&self) -> &[Record] {
+        self.records.as_slice()
+    }
+
+    pub(crate) fn externs(&self) -> &[ExternFunction] {
+        self.externs.as_slice()
+    }
+
+    /// Returns the file in which `function` is declared, if known.
+    pub(crate) fn file_of(&self, function: &str) -> Option<&str> {
+        self.files.get(function).map(String::as_str)
+    }
 }
 
+/// A top-level declaration of a source file.
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct Function {
+pub(crate) enum Item {
+    Function(Function),
+    Trait(Trait),
+    /// An `impl` block, along with its methods named after [`method_name`].
+    Impl(Impl, Vec<Function>),
+    Record(Record),
+    Extern(ExternFunction),
+    /// A `mod name;` declaration, whose content is in another file.
+    Mod(String),
+    /// A `use path::to::function;` declaration.
+    Use(Vec<String>),
+    /// A `macro_rules!` declaration, which is expanded in the file that
+    /// declares it.
+    Macro(Macro),
+}
+
+/// Returns the name of the function that implements `method` for `ty`,
+/// either in an implementation of `trait_` or directly in an `impl` block.
+///
+/// It is not a valid identifier, so that it can't collide with functions
+/// declared by the user.
+pub(crate) fn method_name(trait_: Option<&str>, ty: &Ty, method: &str) -> String {
+    match trait_ {
+        Some(trait_) => format!("<{} as {}>::{}", ty, trait_, method),
+        None => format!("{}::{}", ty, method),
+    }
+}
+
+/// A record type, such as `struct Point { x: int, y: int }`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Record {
     name: String,
-    body: ExprKind,
+    fields: Vec<Param>,
 }
 
-impl Function {
-    pub(crate) fn new(name: String, body: ExprKind) -> Function {
-        Function { name, body }
+impl Record {
+    pub(crate) fn new(name: String, fields: Vec<Param>) -> Record {
+        Record { name, fields }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Returns the fields of the record, in declaration order.
+    pub(crate) fn fields(&self) -> &[Param] {
+        self.fields.as_slice()
+    }
+
+    /// Returns the position of a field and its type.
+    pub(crate) fn field(&self, name: &str) -> Option<(usize, &Ty)> {
+        self.fields
+            .iter()
+            .enumerate()
+            .find_map(|(idx, field)| (field.name() == name).then_some((idx, field.ty())))
+    }
+}
+
+/// A macro declared with `macro_rules!`, such as
+/// `macro_rules! square { ($x:expr) => { $x * $x }; }`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Macro {
+    name: String,
+    rules: Vec<MacroRule>,
+}
+
+impl Macro {
+    pub(crate) fn new(name: String, rules: Vec<MacroRule>) -> Macro {
+        Macro { name, rules }
     }
 
     pub(crate) fn name(&self) -> &str {
         self.name.as_str()
     }
 
+    /// Returns the rules of the macro, in declaration order.
+    pub(crate) fn rules(&self) -> &[MacroRule] {
+        self.rules.as_slice()
+    }
+}
+
+/// A rule of a macro, which expands to its body when the macro is invoked
+/// with one expression per parameter.
+///
+/// The parameters are the names of the metavariables, without their `$`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MacroRule {
+    params: Vec<String>,
+    body: ExprKind,
+}
+
+impl MacroRule {
+    pub(crate) fn new(params: Vec<String>, body: ExprKind) -> MacroRule {
+        MacroRule { params, body }
+    }
+
+    pub(crate) fn params(&self) -> &[String] {
+        self.params.as_slice()
+    }
+
     pub(crate) fn body(&self) -> &ExprKind {
         &self.body
     }
 }
 
+/// A function provided by the application that runs the program, such as
+/// `extern fn sqrt(x: int) -> int;`.
+///
+/// The application knows it by its host name, which is the name it is
+/// declared with, regardless of the module that declares it.
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) enum ExprKind {
-    Addition(Addition),
-    Subtraction(Subtraction),
-    Multiplication(Multiplication),
-    Integer(Integer),
-    If(If),
-    Bindings(Bindings),
-    Ident(Ident),
-    Bool(Bool),
+pub(crate) struct ExternFunction {
+    name: String,
+    host_name: String,
+    params: Vec<Param>,
+    ret_ty: Ty,
+    public: bool,
 }
 
-impl ExprKind {
-    pub(crate) fn addition(lhs: ExprKind, rhs: ExprKind) -> ExprKind {
-        ExprKind::Addition(Addition::new(lhs, rhs))
+impl ExternFunction {
+    pub(crate) fn new(name: String, params: Vec<Param>, ret_ty: Ty) -> ExternFunction {
+        ExternFunction {
+            host_name: name.clone(),
+            name,
+            params,
+            ret_ty,
+            public: false,
+        }
     }
 
-    pub(crate) fn subtraction(lhs: ExprKind, rhs: ExprKind) -> ExprKind {
-        ExprKind::Subtraction(Subtraction::new(lhs, rhs))
+    /// Marks the function as `pub`, so that other modules can use it.
+    pub(crate) fn public(self) -> ExternFunction {
+        ExternFunction {
+            public: true,
+            ..self
+        }
     }
 
-    pub(crate) fn multiplication(lhs: ExprKind, rhs: ExprKind) -> ExprKind {
-        ExprKind::Multiplication(Multiplication::new(lhs, rhs))
+    pub(crate) fn is_public(&self) -> bool {
+        self.public
     }
 
-    pub(crate) fn integer(value: i32) -> ExprKind {
-        ExprKind::Integer(Integer::new(value))
+    /// Returns the same function, with a different name in the program.
+    pub(crate) fn with_name(&self, name: String) -> ExternFunction {
+        ExternFunction {
+            name,
+            ..self.clone()
+        }
     }
 
-    pub(crate) fn if_(
-        condition: ExprKind,
-        consequent: ExprKind,
-        alternative: ExprKind,
-    ) -> ExprKind {
-        ExprKind::If(If::new(condition, consequent, alternative))
+    /// Returns the same function, with a different signature.
+    pub(crate) fn with_signature(&self, params: Vec<Param>, ret_ty: Ty) -> ExternFunction {
+        ExternFunction {
+            params,
+            ret_ty,
+            ..self.clone()
+        }
     }
 
-    pub(crate) fn bindings(bs: Vec<Binding>, next: ExprKind) -> ExprKind {
-        ExprKind::Bindings(Bindings::from_vec(bs, next))
+    pub(crate) fn name(&self) -> &str {
+        self.name.as_str()
     }
 
-    pub(crate) fn ident(name: String) -> ExprKind {
-        ExprKind::Ident(Ident::new(name))
+    pub(crate) fn host_name(&self) -> &str {
+        self.host_name.as_str()
     }
 
-    pub(crate) fn bool_(bool_: bool) -> ExprKind {
-        ExprKind::Bool(Bool::new(bool_))
+    pub(crate) fn params(&self) -> &[Param] {
+        self.params.as_slice()
     }
-}
 
-#[cfg(test)]
-impl ExprKind {
-    pub(crate) fn single_binding(
-        name: String,
-        value: ExprKind,
-        inner_expression: ExprKind,
-    ) -> ExprKind {
-        ExprKind::Bindings(Bindings::single(name, value, inner_expression))
+    pub(crate) fn ret_ty(&self) -> &Ty {
+        &self.ret_ty
+    }
+
+    pub(crate) fn ty(&self) -> Ty {
+        let params = self.params.iter().map(|p| p.ty().clone()).collect();
+        Ty::Function(params, Box::new(self.ret_ty.clone()))
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct Addition(Box<(ExprKind, ExprKind)>);
+pub(crate) struct Function {
+    name: String,
+    generics: Vec<String>,
+    bounds: Vec<Bound>,
+    params: Vec<Param>,
+    ret_ty: Option<Ty>,
+    body: ExprKind,
+    clauses: Vec<Clause>,
+    public: bool,
+    const_: bool,
+    attributes: Vec<Attribute>,
+}
 
-impl Addition {
-    pub(crate) fn new(lhs: ExprKind, rhs: ExprKind) -> Addition {
-        Addition(Box::new((lhs, rhs)))
+impl Function {
+    pub(crate) fn new(
+        name: String,
+        generics: Vec<String>,
+        params: Vec<Param>,
+        ret_ty: Option<Ty>,
+        body: ExprKind,
+    ) -> Function {
+        Function {
+            name,
+            generics,
+            bounds: Vec::new(),
+            params,
+            ret_ty,
+            body,
+            clauses: Vec::new(),
+            public: false,
+            const_: false,
+            attributes: Vec::new(),
+        }
     }
 
-    pub(crate) fn left(&self) -> &ExprKind {
-        &self.inner().0
+    pub(crate) fn with_bounds(self, bounds: Vec<Bound>) -> Function {
+        Function { bounds, ..self }
     }
 
-    pub(crate) fn right(&self) -> &ExprKind {
-        &self.inner().1
+    /// Sets the `requires` and `ensures` clauses of the function.
+    pub(crate) fn with_clauses(self, clauses: Vec<Clause>) -> Function {
+        Function { clauses, ..self }
     }
 
-    fn inner(&self) -> &(ExprKind, ExprKind) {
-        &self.0
+    /// Returns the same function, with the condition of each clause replaced
+    /// by `f`.
+    pub(crate) fn map_clauses(&self, mut f: impl FnMut(&ExprKind) -> ExprKind) -> Function {
+        let clauses = self
+            .clauses
+            .iter()
+            .map(|clause| clause.with_condition(f(clause.condition())))
+            .collect();
+
+        Function {
+            clauses,
+            ..self.clone()
+        }
     }
-}
 
-#[derive(Clone, Debug, PartialEq)]
-pub(crate) struct Subtraction(Box<(ExprKind, ExprKind)>);
+    /// Marks the function as `pub`, so that other modules can use it.
+    pub(crate) fn public(self) -> Function {
+        Function {
+            public: true,
+            ..self
+        }
+    }
 
-impl Subtraction {
-    pub(crate) fn new(lhs: ExprKind, rhs: ExprKind) -> Subtraction {
-        Subtraction(Box::new((lhs, rhs)))
+    pub(crate) fn is_public(&self) -> bool {
+        self.public
     }
 
-    pub(crate) fn left(&self) -> &ExprKind {
-        &self.inner().0
+    /// Marks the function as `const`, so that the calls whose arguments are
+    /// constants are evaluated at compile time.
+    pub(crate) fn const_(self) -> Function {
+        Function {
+            const_: true,
+            ..self
+        }
     }
 
-    pub(crate) fn right(&self) -> &ExprKind {
-        &self.inner().1
+    pub(crate) fn is_const(&self) -> bool {
+        self.const_
     }
 
-    fn inner(&self) -> &(ExprKind, ExprKind) {
-        &self.0
+    /// Sets the attributes of the function, such as `#[memo]`.
+    pub(crate) fn with_attributes(self, attributes: Vec<Attribute>) -> Function {
+        Function { attributes, ..self }
     }
-}
 
-#[derive(Clone, Debug, PartialEq)]
-pub(crate) struct Multiplication(Box<(ExprKind, ExprKind)>);
+    pub(crate) fn attributes(&self) -> &[Attribute] {
+        self.attributes.as_slice()
+    }
 
-impl Multiplication {
-    pub(crate) fn new(lhs: ExprKind, rhs: ExprKind) -> Multiplication {
-        Multiplication(Box::new((lhs, rhs)))
+    /// Marks the function as `#[memo]`, so that its results are cached by
+    /// the VM. The attribute is placed at the start of the file.
+    #[cfg(test)]
+    pub(crate) fn memo(mut self) -> Function {
+        let span = Span::new(None, 1, 1);
+        self.attributes
+            .push(Attribute::new(MEMO.to_owned(), Vec::new(), span));
+        self
     }
 
-    pub(crate) fn left(&self) -> &ExprKind {
-        &self.inner().0
+    pub(crate) fn is_memo(&self) -> bool {
+        self.attributes.iter().any(|a| a.name() == MEMO)
     }
 
-    pub(crate) fn right(&self) -> &ExprKind {
-        &self.inner().1
+    /// Returns the same function, with a different name and body.
+    pub(crate) fn with_name_and_body(&self, name: String, body: ExprKind) -> Function {
+        Function {
+            name,
+            body,
+            ..self.clone()
+        }
     }
 
-    fn inner(&self) -> &(ExprKind, ExprKind) {
-        &self.0
+    /// Returns the same function, with a different signature and body.
+    pub(crate) fn with_signature_and_body(
+        &self,
+        generics: Vec<String>,
+        params: Vec<Param>,
+        ret_ty: Option<Ty>,
+        body: ExprKind,
+    ) -> Function {
+        Function {
+            generics,
+            params,
+            ret_ty,
+            body,
+            ..self.clone()
+        }
     }
-}
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub(crate) struct Integer(i32);
+    pub(crate) fn name(&self) -> &str {
+        self.name.as_str()
+    }
 
-impl Integer {
-    pub(crate) fn new(value: i32) -> Integer {
-        Integer(value)
+    /// Returns the names of the type parameters, in declaration order.
+    pub(crate) fn generics(&self) -> &[String] {
+        self.generics.as_slice()
     }
 
-    pub(crate) fn value(&self) -> i32 {
-        self.0
+    /// Returns the traits that the type parameters must implement.
+    pub(crate) fn bounds(&self) -> &[Bound] {
+        self.bounds.as_slice()
+    }
+
+    pub(crate) fn params(&self) -> &[Param] {
+        self.params.as_slice()
+    }
+
+    /// Returns the annotated return type, if any.
+    pub(crate) fn ret_ty(&self) -> Option<&Ty> {
+        self.ret_ty.as_ref()
+    }
+
+    pub(crate) fn body(&self) -> &ExprKind {
+        &self.body
+    }
+
+    /// Returns the `requires` and `ensures` clauses, in declaration order.
+    pub(crate) fn clauses(&self) -> &[Clause] {
+        self.clauses.as_slice()
+    }
+
+    /// Returns the conditions that must hold when the function is called.
+    pub(crate) fn requires(&self) -> impl Iterator<Item = &Clause> {
+        self.clauses
+            .iter()
+            .filter(|clause| clause.kind() == ClauseKind::Requires)
+    }
+
+    /// Returns the conditions that must hold when the function returns.
+    pub(crate) fn ensures(&self) -> impl Iterator<Item = &Clause> {
+        self.clauses
+            .iter()
+            .filter(|clause| clause.kind() == ClauseKind::Ensures)
     }
 }
 
+/// The name under which the condition of an `ensures` clause refers to the
+/// value returned by the function.
+pub(crate) const RESULT: &str = "result";
+
+/// A condition of a function contract, such as `requires x <= b` or
+/// `ensures result >= 0`.
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct If(Box<(ExprKind, ExprKind, ExprKind)>);
+pub(crate) struct Clause {
+    kind: ClauseKind,
+    condition: ExprKind,
+    /// The condition as written in the source, for error messages.
+    source: String,
+}
 
-impl If {
-    pub(crate) fn new(condition: ExprKind, consequent: ExprKind, alternative: ExprKind) -> If {
-        If(Box::new((condition, consequent, alternative)))
+impl Clause {
+    pub(crate) fn new(kind: ClauseKind, condition: ExprKind, source: String) -> Clause {
+        Clause {
+            kind,
+            condition,
+            source,
+        }
+    }
+
+    pub(crate) fn kind(&self) -> ClauseKind {
+        self.kind
     }
 
     pub(crate) fn condition(&self) -> &ExprKind {
-        &self.inner().0
+        &self.condition
     }
 
-    pub(crate) fn consequent(&self) -> &ExprKind {
-        &self.inner().1
+    /// Returns the same clause, with a different condition.
+    pub(crate) fn with_condition(&self, condition: ExprKind) -> Clause {
+        Clause {
+            condition,
+            ..self.clone()
+        }
     }
 
-    pub(crate) fn alternative(&self) -> &ExprKind {
-        &self.inner().2
+    /// Returns the message of the panic caused by a violation of the clause
+    /// by `function`.
+    pub(crate) fn violation_message(&self, function: &str) -> String {
+        format!(
+            "Contract violation: `{}` {} `{}`",
+            function,
+            self.kind.keyword(),
+            self.source
+        )
     }
+}
 
-    fn inner(&self) -> &(ExprKind, ExprKind, ExprKind) {
-        &self.0
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum ClauseKind {
+    /// A precondition, checked when the function is called.
+    Requires,
+    /// A postcondition, checked when the function returns.
+    Ensures,
+}
+
+impl ClauseKind {
+    pub(crate) fn keyword(self) -> &'static str {
+        match self {
+            ClauseKind::Requires => "requires",
+            ClauseKind::Ensures => "ensures",
+        }
     }
 }
 
+/// The attribute of the functions whose results are cached by the VM.
+pub(crate) const MEMO: &str = "memo";
+
+/// The attributes that functions may have, along with the maximum number of
+/// arguments that each of them takes.
+const KNOWN_ATTRIBUTES: [(&str, usize); 4] = [("entry", 0), ("inline", 1), (MEMO, 0), ("test", 0)];
+
+/// An attribute of a function, such as `#[test]` or `#[inline(always)]`.
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct Bindings(Vec<Binding>, Box<ExprKind>);
+pub(crate) struct Attribute {
+    name: String,
+    args: Vec<String>,
+    span: Span,
+}
 
-impl Bindings {
-    pub(crate) fn from_vec(bs: Vec<Binding>, next: ExprKind) -> Bindings {
-        Bindings(bs, Box::new(next))
+impl Attribute {
+    pub(crate) fn new(name: String, args: Vec<String>, span: Span) -> Attribute {
+        Attribute { name, args, span }
     }
 
-    pub(crate) fn defines(&self) -> &[Binding] {
-        self.0.as_slice()
+    pub(crate) fn name(&self) -> &str {
+        self.name.as_str()
     }
 
-    pub(crate) fn ending_expression(&self) -> &ExprKind {
-        &self.1
+    pub(crate) fn args(&self) -> &[String] {
+        self.args.as_slice()
     }
-}
 
-#[cfg(test)]
-impl Bindings {
-    pub(crate) fn single(name: String, value: ExprKind, next: ExprKind) -> Bindings {
-        let binding = Binding::new(name, value);
-        Bindings(vec![binding], Box::new(next))
+    pub(crate) fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Checks that the attribute is a known one, with a valid number of
+    /// arguments.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let max_args = KNOWN_ATTRIBUTES
+            .iter()
+            .find(|(name, _)| *name == self.name)
+            .map(|(_, max_args)| *max_args)
+            .ok_or_else(|| format!("Unknown attribute `{}`", self.name))?;
+
+        if self.args().len() > max_args {
+            let expected = match max_args {
+                0 => "no arguments".to_owned(),
+                1 => "at most 1 argument".to_owned(),
+                n => format!("at most {} arguments", n),
+            };
+
+            return Err(format!(
+                "Attribute `{}` takes {}, found {}",
+                self.name,
+                expected,
+                self.args().len()
+            ));
+        }
+
+        Ok(())
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct Binding(String, ExprKind);
+pub(crate) struct Param(String, Ty);
 
-impl Binding {
-    pub(crate) fn new(name: String, value: ExprKind) -> Binding {
-        Binding(name, value)
+impl Param {
+    pub(crate) fn new(name: String, ty: Ty) -> Param {
+        Param(name, ty)
     }
 
     pub(crate) fn name(&self) -> &str {
         self.0.as_str()
     }
 
-    pub(crate) fn value(&self) -> &ExprKind {
+    pub(crate) fn ty(&self) -> &Ty {
         &self.1
     }
 }
 
+/// A trait that a type parameter must implement, such as `T: Show`.
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct Ident(String);
+pub(crate) struct Bound(String, String);
 
-impl Ident {
-    pub(crate) fn new(name: String) -> Ident {
-        Ident(name)
+impl Bound {
+    pub(crate) fn new(param: String, trait_: String) -> Bound {
+        Bound(param, trait_)
     }
 
-    pub(crate) fn name(&self) -> &str {
+    pub(crate) fn param(&self) -> &str {
         self.0.as_str()
     }
+
+    pub(crate) fn trait_(&self) -> &str {
+        self.1.as_str()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct Bool(bool);
+pub(crate) struct Trait {
+    name: String,
+    methods: Vec<MethodSignature>,
+}
 
-impl Bool {
-    pub(crate) fn new(bool_: bool) -> Bool {
-        Bool(bool_)
+impl Trait {
+    pub(crate) fn new(name: String, methods: Vec<MethodSignature>) -> Trait {
+        Trait { name, methods }
     }
 
-    pub(crate) fn value(&self) -> bool {
-        self.0
+    pub(crate) fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub(crate) fn methods(&self) -> &[MethodSignature] {
+        self.methods.as_slice()
+    }
+
+    pub(crate) fn method(&self, name: &str) -> Option<&MethodSignature> {
+        self.methods.iter().find(|m| m.name() == name)
+    }
+}
+
+/// A method declared in a trait. Its signature may refer to the implementing
+/// type as `Self`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MethodSignature {
+    name: String,
+    params: Vec<Param>,
+    ret_ty: Ty,
+}
+
+impl MethodSignature {
+    pub(crate) fn new(name: String, params: Vec<Param>, ret_ty: Ty) -> MethodSignature {
+        MethodSignature {
+            name,
+            params,
+            ret_ty,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub(crate) fn params(&self) -> &[Param] {
+        self.params.as_slice()
+    }
+
+    pub(crate) fn ret_ty(&self) -> &Ty {
+        &self.ret_ty
+    }
+
+    /// Returns the type of the method when implemented for `ty`.
+    pub(crate) fn ty_for(&self, ty: &Ty) -> Ty {
+        let self_ty = [("Self".to_owned(), ty.clone())].into_iter().collect();
+
+        let params = self
+            .params
+            .iter()
+            .map(|p| p.ty().substitute(&self_ty))
+            .collect();
+        let ret_ty = self.ret_ty.substitute(&self_ty);
+
+        Ty::Function(params, Box::new(ret_ty))
+    }
+}
+
+/// An `impl Trait for Type` or `impl Type` block. Its methods are stored
+/// among the functions of the program.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Impl {
+    trait_: Option<String>,
+    ty: Ty,
+    methods: Vec<String>,
+}
+
+impl Impl {
+    pub(crate) fn new(trait_: String, ty: Ty, methods: Vec<String>) -> Impl {
+        Impl {
+            trait_: Some(trait_),
+            ty,
+            methods,
+        }
+    }
+
+    /// Creates an implementation that is not tied to any trait.
+    pub(crate) fn inherent(ty: Ty, methods: Vec<String>) -> Impl {
+        Impl {
+            trait_: None,
+            ty,
+            methods,
+        }
+    }
+
+    /// Returns the implemented trait, if any.
+    pub(crate) fn trait_(&self) -> Option<&str> {
+        self.trait_.as_deref()
+    }
+
+    pub(crate) fn ty(&self) -> &Ty {
+        &self.ty
+    }
+
+    /// Returns the names of the implemented methods, as written in the block.
+    pub(crate) fn methods(&self) -> &[String] {
+        self.methods.as_slice()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum ExprKind {
+    Addition(Addition),
+    Subtraction(Subtraction),
+    Multiplication(Multiplication),
+    Remainder(Remainder),
+    Integer(Integer),
+    If(If),
+    Bindings(Bindings),
+    Ident(Ident),
+    Bool(Bool),
+    Unit(Unit),
+    For(For),
+    Break(Break),
+    Continue(Continue),
+    Call(Call),
+    MethodCall(MethodCall),
+    Lambda(Lambda),
+    RecordLiteral(RecordLiteral),
+    FieldAccess(FieldAccess),
+    Variant(Variant),
+    Match(Match),
+    Try(Try),
+    Panic(Panic),
+    Comparison(Comparison),
+    Print(Print),
+    ReadInt(ReadInt),
+    List(ListLiteral),
+    Builtin(Builtin),
+    Assert(Assert),
+}
+
+impl ExprKind {
+    pub(crate) fn addition(lhs: ExprKind, rhs: ExprKind) -> ExprKind {
+        ExprKind::Addition(Addition::new(lhs, rhs))
+    }
+
+    pub(crate) fn subtraction(lhs: ExprKind, rhs: ExprKind) -> ExprKind {
+        ExprKind::Subtraction(Subtraction::new(lhs, rhs))
+    }
+
+    pub(crate) fn multiplication(lhs: ExprKind, rhs: ExprKind) -> ExprKind {
+        ExprKind::Multiplication(Multiplication::new(lhs, rhs))
+    }
+
+    pub(crate) fn remainder(lhs: ExprKind, rhs: ExprKind) -> ExprKind {
+        ExprKind::Remainder(Remainder::new(lhs, rhs))
+    }
+
+    pub(crate) fn integer(value: i32) -> ExprKind {
+        ExprKind::Integer(Integer::new(value))
+    }
+
+    pub(crate) fn if_(
+        condition: ExprKind,
+        consequent: ExprKind,
+        alternative: ExprKind,
+    ) -> ExprKind {
+        ExprKind::If(If::new(condition, consequent, alternative))
+    }
+
+    pub(crate) fn statements(stmts: Vec<Statement>, next: ExprKind) -> ExprKind {
+        ExprKind::Bindings(Bindings::from_statements(stmts, next))
+    }
+
+    pub(crate) fn ident(name: String) -> ExprKind {
+        ExprKind::Ident(Ident::new(name))
+    }
+
+    pub(crate) fn bool_(bool_: bool) -> ExprKind {
+        ExprKind::Bool(Bool::new(bool_))
+    }
+
+    pub(crate) fn unit() -> ExprKind {
+        ExprKind::Unit(Unit)
+    }
+
+    pub(crate) fn for_(variable: String, range: Range, body: ExprKind) -> ExprKind {
+        ExprKind::For(For::new(variable, range, body))
+    }
+
+    pub(crate) fn break_() -> ExprKind {
+        ExprKind::Break(Break)
+    }
+
+    pub(crate) fn continue_() -> ExprKind {
+        ExprKind::Continue(Continue)
+    }
+
+    pub(crate) fn call(callee: ExprKind, args: Vec<ExprKind>) -> ExprKind {
+        ExprKind::Call(Call::new(callee, args))
+    }
+
+    pub(crate) fn method_call(receiver: ExprKind, method: String, args: Vec<ExprKind>) -> ExprKind {
+        ExprKind::MethodCall(MethodCall::new(receiver, method, args))
+    }
+
+    pub(crate) fn lambda(params: Vec<Param>, body: ExprKind) -> ExprKind {
+        ExprKind::Lambda(Lambda::new(params, body))
+    }
+
+    pub(crate) fn record_literal(name: String, fields: Vec<(String, ExprKind)>) -> ExprKind {
+        ExprKind::RecordLiteral(RecordLiteral::new(name, fields))
+    }
+
+    pub(crate) fn field_access(record: ExprKind, field: String) -> ExprKind {
+        ExprKind::FieldAccess(FieldAccess::new(record, field))
+    }
+
+    pub(crate) fn variant(constructor: Constructor, payload: Option<ExprKind>) -> ExprKind {
+        ExprKind::Variant(Variant::new(constructor, payload))
+    }
+
+    pub(crate) fn match_(scrutinee: ExprKind, arms: Vec<Arm>) -> ExprKind {
+        ExprKind::Match(Match::new(scrutinee, arms))
+    }
+
+    pub(crate) fn try_(operand: ExprKind) -> ExprKind {
+        ExprKind::Try(Try::new(operand))
+    }
+
+    pub(crate) fn panic(message: String) -> ExprKind {
+        ExprKind::Panic(Panic::new(message))
+    }
+
+    pub(crate) fn comparison(
+        operator: ComparisonOperator,
+        lhs: ExprKind,
+        rhs: ExprKind,
+    ) -> ExprKind {
+        ExprKind::Comparison(Comparison::new(operator, lhs, rhs))
+    }
+
+    pub(crate) fn print(arg: PrintArg, newline: bool) -> ExprKind {
+        ExprKind::Print(Print::new(arg, newline))
+    }
+
+    pub(crate) fn read_int() -> ExprKind {
+        ExprKind::ReadInt(ReadInt)
+    }
+
+    pub(crate) fn list(elements: Vec<ExprKind>) -> ExprKind {
+        ExprKind::List(ListLiteral::new(elements))
+    }
+
+    pub(crate) fn builtin(function: BuiltinFunction, args: Vec<ExprKind>) -> ExprKind {
+        ExprKind::Builtin(Builtin::new(function, args))
+    }
+
+    pub(crate) fn assert(kind: AssertKind, span: Span) -> ExprKind {
+        ExprKind::Assert(Assert::new(kind, span))
+    }
+
+    /// Returns the expression with the parameters of every lambda replaced by
+    /// `f`. Lambdas are visited before the lambdas of their body.
+    pub(crate) fn map_lambda_params(&self, f: &mut impl FnMut(&[Param]) -> Vec<Param>) -> ExprKind {
+        self.map_annotations(f, &mut str::to_owned)
+    }
+
+    /// Same as `map_lambda_params`, except that the names of the record
+    /// literals are also replaced, by `records`.
+    pub(crate) fn map_annotations(
+        &self,
+        f: &mut impl FnMut(&[Param]) -> Vec<Param>,
+        records: &mut impl FnMut(&str) -> String,
+    ) -> ExprKind {
+        match self {
+            ExprKind::Addition(e) => {
+                let (left, right) = (
+                    e.left().map_annotations(f, records),
+                    e.right().map_annotations(f, records),
+                );
+                ExprKind::Addition(Addition::new(left, right))
+            }
+
+            ExprKind::Subtraction(e) => {
+                let (left, right) = (
+                    e.left().map_annotations(f, records),
+                    e.right().map_annotations(f, records),
+                );
+                ExprKind::Subtraction(Subtraction::new(left, right))
+            }
+
+            ExprKind::Multiplication(e) => {
+                let (left, right) = (
+                    e.left().map_annotations(f, records),
+                    e.right().map_annotations(f, records),
+                );
+                ExprKind::Multiplication(Multiplication::new(left, right))
+            }
+
+            ExprKind::Remainder(e) => {
+                let (left, right) = (
+                    e.left().map_annotations(f, records),
+                    e.right().map_annotations(f, records),
+                );
+                ExprKind::Remainder(Remainder::new(left, right))
+            }
+
+            ExprKind::If(e) => {
+                let condition = e.condition().map_annotations(f, records);
+                let consequent = e.consequent().map_annotations(f, records);
+                let alternative = e.alternative().map_annotations(f, records);
+
+                ExprKind::If(If::new(condition, consequent, alternative))
+            }
+
+            ExprKind::Bindings(e) => {
+                let statements = e
+                    .statements()
+                    .iter()
+                    .map(|stmt| match stmt {
+                        Statement::Binding(b) => Statement::Binding(Binding::new(
+                            b.name().to_owned(),
+                            b.value().map_annotations(f, records),
+                        )),
+                        Statement::Expr(e) => Statement::Expr(e.map_annotations(f, records)),
+                    })
+                    .collect();
+                let ending = e.ending_expression().map_annotations(f, records);
+
+                ExprKind::Bindings(Bindings::from_statements(statements, ending))
+            }
+
+            ExprKind::For(e) => {
+                let start = e.range().start().map_annotations(f, records);
+                let end = e.range().end().map_annotations(f, records);
+                let range = Range::new(start, end, e.range().is_inclusive());
+                let body = e.body().map_annotations(f, records);
+
+                ExprKind::For(For::new(e.variable().to_owned(), range, body))
+            }
+
+            ExprKind::Call(e) => {
+                let callee = e.callee().map_annotations(f, records);
+                let args = e
+                    .args()
+                    .iter()
+                    .map(|arg| arg.map_annotations(f, records))
+                    .collect();
+
+                ExprKind::Call(e.with_callee_and_args(callee, args))
+            }
+
+            ExprKind::MethodCall(e) => {
+                let receiver = e.receiver().map_annotations(f, records);
+                let args = e
+                    .args()
+                    .iter()
+                    .map(|arg| arg.map_annotations(f, records))
+                    .collect();
+
+                ExprKind::MethodCall(MethodCall::new(receiver, e.method().to_owned(), args))
+            }
+
+            ExprKind::Lambda(e) => {
+                let params = f(e.params());
+                let body = e.body().map_annotations(f, records);
+
+                ExprKind::Lambda(Lambda::new(params, body))
+            }
+
+            ExprKind::RecordLiteral(e) => {
+                let fields = e
+                    .fields()
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.map_annotations(f, records)))
+                    .collect();
+
+                ExprKind::RecordLiteral(RecordLiteral::new(records(e.name()), fields))
+            }
+
+            ExprKind::FieldAccess(e) => {
+                let record = e.record().map_annotations(f, records);
+                ExprKind::FieldAccess(FieldAccess(Box::new(record), e.1.clone(), e.2))
+            }
+
+            ExprKind::Variant(e) => {
+                let payload = e
+                    .payload()
+                    .map(|payload| payload.map_annotations(f, records));
+                ExprKind::variant(e.constructor(), payload)
+            }
+
+            ExprKind::Match(e) => {
+                let scrutinee = e.scrutinee().map_annotations(f, records);
+                let arms = e
+                    .arms()
+                    .iter()
+                    .map(|arm| {
+                        Arm::new(
+                            arm.pattern().clone(),
+                            arm.body().map_annotations(f, records),
+                        )
+                    })
+                    .collect();
+
+                ExprKind::match_(scrutinee, arms)
+            }
+
+            ExprKind::Try(e) => ExprKind::try_(e.operand().map_annotations(f, records)),
+
+            ExprKind::Comparison(e) => {
+                let (left, right) = (
+                    e.left().map_annotations(f, records),
+                    e.right().map_annotations(f, records),
+                );
+                ExprKind::comparison(e.operator(), left, right)
+            }
+
+            ExprKind::Print(e) => ExprKind::Print(e.map_arg(|arg| arg.map_annotations(f, records))),
+
+            ExprKind::Assert(e) => {
+                ExprKind::Assert(e.map_operands(|operand| operand.map_annotations(f, records)))
+            }
+
+            ExprKind::List(e) => {
+                let elements = e.elements().iter().map(|e| e.map_annotations(f, records));
+                ExprKind::list(elements.collect())
+            }
+
+            ExprKind::Builtin(e) => {
+                let args = e.args().iter().map(|arg| arg.map_annotations(f, records));
+                ExprKind::builtin(e.function(), args.collect())
+            }
+
+            ExprKind::Integer(_)
+            | ExprKind::Ident(_)
+            | ExprKind::Bool(_)
+            | ExprKind::Unit(_)
+            | ExprKind::Break(_)
+            | ExprKind::Continue(_)
+            | ExprKind::Panic(_)
+            | ExprKind::ReadInt(_) => self.clone(),
+        }
+    }
+
+    /// Returns the expression with the identifiers that are not bound by a
+    /// local variable replaced by `f`. Identifiers for which `f` returns
+    /// `None` are left as is.
+    pub(crate) fn rename_free_idents(
+        &self,
+        f: &mut impl FnMut(&str) -> Option<String>,
+    ) -> ExprKind {
+        self.replace_free_idents(&mut |name| f(name).map(ExprKind::ident))
+    }
+
+    /// Returns the expression with the identifiers that are not bound by a
+    /// local variable replaced by the expression returned by `f`.
+    /// Identifiers for which `f` returns `None` are left as is.
+    pub(crate) fn replace_free_idents(
+        &self,
+        f: &mut impl FnMut(&str) -> Option<ExprKind>,
+    ) -> ExprKind {
+        self.replace_free_idents_with(f, &mut Vec::new())
+    }
+
+    fn replace_free_idents_with(
+        &self,
+        f: &mut impl FnMut(&str) -> Option<ExprKind>,
+        bound: &mut Vec<String>,
+    ) -> ExprKind {
+        let mut rename =
+            |e: &ExprKind, bound: &mut Vec<String>| e.replace_free_idents_with(f, bound);
+
+        match self {
+            ExprKind::Addition(e) => {
+                let (left, right) = (rename(e.left(), bound), rename(e.right(), bound));
+                ExprKind::Addition(Addition::new(left, right))
+            }
+
+            ExprKind::Subtraction(e) => {
+                let (left, right) = (rename(e.left(), bound), rename(e.right(), bound));
+                ExprKind::Subtraction(Subtraction::new(left, right))
+            }
+
+            ExprKind::Multiplication(e) => {
+                let (left, right) = (rename(e.left(), bound), rename(e.right(), bound));
+                ExprKind::Multiplication(Multiplication::new(left, right))
+            }
+
+            ExprKind::Remainder(e) => {
+                let (left, right) = (rename(e.left(), bound), rename(e.right(), bound));
+                ExprKind::Remainder(Remainder::new(left, right))
+            }
+
+            ExprKind::If(e) => {
+                let condition = rename(e.condition(), bound);
+                let consequent = rename(e.consequent(), bound);
+                let alternative = rename(e.alternative(), bound);
+
+                ExprKind::If(If::new(condition, consequent, alternative))
+            }
+
+            ExprKind::Bindings(e) => {
+                let scope = bound.len();
+
+                let statements = e
+                    .statements()
+                    .iter()
+                    .map(|stmt| match stmt {
+                        Statement::Binding(b) => {
+                            let value = rename(b.value(), bound);
+                            bound.push(b.name().to_owned());
+                            Statement::Binding(Binding::new(b.name().to_owned(), value))
+                        }
+                        Statement::Expr(e) => Statement::Expr(rename(e, bound)),
+                    })
+                    .collect();
+                let ending = rename(e.ending_expression(), bound);
+
+                bound.truncate(scope);
+
+                ExprKind::Bindings(Bindings::from_statements(statements, ending))
+            }
+
+            ExprKind::Ident(e) if !bound.iter().any(|b| b == e.name()) => match f(e.name()) {
+                Some(replacement) => replacement,
+                None => self.clone(),
+            },
+
+            ExprKind::For(e) => {
+                let start = rename(e.range().start(), bound);
+                let end = rename(e.range().end(), bound);
+                let range = Range::new(start, end, e.range().is_inclusive());
+
+                bound.push(e.variable().to_owned());
+                let body = rename(e.body(), bound);
+                bound.pop();
+
+                ExprKind::For(For::new(e.variable().to_owned(), range, body))
+            }
+
+            ExprKind::Call(e) => {
+                let callee = rename(e.callee(), bound);
+                let args = e.args().iter().map(|arg| rename(arg, bound)).collect();
+
+                ExprKind::Call(e.with_callee_and_args(callee, args))
+            }
+
+            ExprKind::MethodCall(e) => {
+                let receiver = rename(e.receiver(), bound);
+                let args = e.args().iter().map(|arg| rename(arg, bound)).collect();
+
+                ExprKind::MethodCall(MethodCall::new(receiver, e.method().to_owned(), args))
+            }
+
+            ExprKind::Lambda(e) => {
+                let scope = bound.len();
+
+                bound.extend(e.params().iter().map(|p| p.name().to_owned()));
+                let body = rename(e.body(), bound);
+                bound.truncate(scope);
+
+                ExprKind::Lambda(Lambda::new(e.params().to_vec(), body))
+            }
+
+            ExprKind::RecordLiteral(e) => {
+                let fields = e
+                    .fields()
+                    .iter()
+                    .map(|(name, value)| (name.clone(), rename(value, bound)))
+                    .collect();
+
+                ExprKind::RecordLiteral(RecordLiteral::new(e.name().to_owned(), fields))
+            }
+
+            ExprKind::FieldAccess(e) => {
+                let record = rename(e.record(), bound);
+                ExprKind::FieldAccess(FieldAccess(Box::new(record), e.1.clone(), e.2))
+            }
+
+            ExprKind::Variant(e) => {
+                let payload = e.payload().map(|payload| rename(payload, bound));
+                ExprKind::variant(e.constructor(), payload)
+            }
+
+            ExprKind::Match(e) => {
+                let scrutinee = rename(e.scrutinee(), bound);
+                let arms = e
+                    .arms()
+                    .iter()
+                    .map(|arm| {
+                        let scope = bound.len();
+
+                        bound.extend(arm.pattern().binding().map(str::to_owned));
+                        let body = rename(arm.body(), bound);
+                        bound.truncate(scope);
+
+                        Arm::new(arm.pattern().clone(), body)
+                    })
+                    .collect();
+
+                ExprKind::match_(scrutinee, arms)
+            }
+
+            ExprKind::Try(e) => ExprKind::try_(rename(e.operand(), bound)),
+
+            ExprKind::Comparison(e) => {
+                let (left, right) = (rename(e.left(), bound), rename(e.right(), bound));
+                ExprKind::comparison(e.operator(), left, right)
+            }
+
+            ExprKind::Print(e) => ExprKind::Print(e.map_arg(|arg| rename(arg, bound))),
+
+            ExprKind::Assert(e) => {
+                ExprKind::Assert(e.map_operands(|operand| rename(operand, bound)))
+            }
+
+            ExprKind::List(e) => {
+                let elements = e.elements().iter().map(|e| rename(e, bound));
+                ExprKind::list(elements.collect())
+            }
+
+            ExprKind::Builtin(e) => {
+                let args = e.args().iter().map(|arg| rename(arg, bound));
+                ExprKind::builtin(e.function(), args.collect())
+            }
+
+            ExprKind::Integer(_)
+            | ExprKind::Ident(_)
+            | ExprKind::Bool(_)
+            | ExprKind::Unit(_)
+            | ExprKind::Break(_)
+            | ExprKind::Continue(_)
+            | ExprKind::Panic(_)
+            | ExprKind::ReadInt(_) => self.clone(),
+        }
+    }
+
+    /// Returns the expression with the variables it declares renamed by `f`,
+    /// along with the identifiers that refer to them. Identifiers that refer
+    /// to variables declared outside of the expression are left as is.
+    pub(crate) fn rename_bound_idents(&self, f: &mut impl FnMut(&str) -> String) -> ExprKind {
+        self.rename_bound_idents_with(f, &mut Vec::new())
+    }
+
+    fn rename_bound_idents_with(
+        &self,
+        f: &mut impl FnMut(&str) -> String,
+        scope: &mut Vec<(String, String)>,
+    ) -> ExprKind {
+        let resolve = |name: &str, scope: &[(String, String)]| {
+            scope
+                .iter()
+                .rev()
+                .find(|(original, _)| original == name)
+                .map_or_else(|| name.to_owned(), |(_, renamed)| renamed.clone())
+        };
+
+        match self {
+            ExprKind::Addition(e) => {
+                let (left, right) = (
+                    e.left().rename_bound_idents_with(f, scope),
+                    e.right().rename_bound_idents_with(f, scope),
+                );
+                ExprKind::Addition(Addition::new(left, right))
+            }
+
+            ExprKind::Subtraction(e) => {
+                let (left, right) = (
+                    e.left().rename_bound_idents_with(f, scope),
+                    e.right().rename_bound_idents_with(f, scope),
+                );
+                ExprKind::Subtraction(Subtraction::new(left, right))
+            }
+
+            ExprKind::Multiplication(e) => {
+                let (left, right) = (
+                    e.left().rename_bound_idents_with(f, scope),
+                    e.right().rename_bound_idents_with(f, scope),
+                );
+                ExprKind::Multiplication(Multiplication::new(left, right))
+            }
+
+            ExprKind::Remainder(e) => {
+                let (left, right) = (
+                    e.left().rename_bound_idents_with(f, scope),
+                    e.right().rename_bound_idents_with(f, scope),
+                );
+                ExprKind::Remainder(Remainder::new(left, right))
+            }
+
+            ExprKind::If(e) => {
+                let condition = e.condition().rename_bound_idents_with(f, scope);
+                let consequent = e.consequent().rename_bound_idents_with(f, scope);
+                let alternative = e.alternative().rename_bound_idents_with(f, scope);
+
+                ExprKind::If(If::new(condition, consequent, alternative))
+            }
+
+            ExprKind::Bindings(e) => {
+                let depth = scope.len();
+
+                let statements = e
+                    .statements()
+                    .iter()
+                    .map(|stmt| match stmt {
+                        Statement::Binding(b) => {
+                            let value = b.value().rename_bound_idents_with(f, scope);
+                            let name = f(b.name());
+                            scope.push((b.name().to_owned(), name.clone()));
+                            Statement::Binding(Binding::new(name, value))
+                        }
+                        Statement::Expr(e) => Statement::Expr(e.rename_bound_idents_with(f, scope)),
+                    })
+                    .collect();
+                let ending = e.ending_expression().rename_bound_idents_with(f, scope);
+
+                scope.truncate(depth);
+
+                ExprKind::Bindings(Bindings::from_statements(statements, ending))
+            }
+
+            ExprKind::Ident(e) => ExprKind::ident(resolve(e.name(), scope)),
+
+            ExprKind::For(e) => {
+                let start = e.range().start().rename_bound_idents_with(f, scope);
+                let end = e.range().end().rename_bound_idents_with(f, scope);
+                let range = Range::new(start, end, e.range().is_inclusive());
+
+                let variable = f(e.variable());
+                scope.push((e.variable().to_owned(), variable.clone()));
+                let body = e.body().rename_bound_idents_with(f, scope);
+                scope.pop();
+
+                ExprKind::For(For::new(variable, range, body))
+            }
+
+            ExprKind::Call(e) => {
+                let callee = e.callee().rename_bound_idents_with(f, scope);
+                let args = e
+                    .args()
+                    .iter()
+                    .map(|arg| arg.rename_bound_idents_with(f, scope))
+                    .collect();
+
+                ExprKind::Call(e.with_callee_and_args(callee, args))
+            }
+
+            ExprKind::MethodCall(e) => {
+                let receiver = e.receiver().rename_bound_idents_with(f, scope);
+                let args = e
+                    .args()
+                    .iter()
+                    .map(|arg| arg.rename_bound_idents_with(f, scope))
+                    .collect();
+
+                ExprKind::MethodCall(MethodCall::new(receiver, e.method().to_owned(), args))
+            }
+
+            ExprKind::Lambda(e) => {
+                let depth = scope.len();
+
+                let params = e
+                    .params()
+                    .iter()
+                    .map(|p| {
+                        let name = f(p.name());
+                        scope.push((p.name().to_owned(), name.clone()));
+                        Param::new(name, p.ty().clone())
+                    })
+                    .collect();
+                let body = e.body().rename_bound_idents_with(f, scope);
+                scope.truncate(depth);
+
+                ExprKind::Lambda(Lambda::new(params, body))
+            }
+
+            ExprKind::RecordLiteral(e) => {
+                let fields = e
+                    .fields()
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.rename_bound_idents_with(f, scope)))
+                    .collect();
+
+                ExprKind::RecordLiteral(RecordLiteral::new(e.name().to_owned(), fields))
+            }
+
+            ExprKind::FieldAccess(e) => {
+                let record = e.record().rename_bound_idents_with(f, scope);
+                ExprKind::FieldAccess(FieldAccess(Box::new(record), e.1.clone(), e.2))
+            }
+
+            ExprKind::Variant(e) => {
+                let payload = e
+                    .payload()
+                    .map(|payload| payload.rename_bound_idents_with(f, scope));
+                ExprKind::variant(e.constructor(), payload)
+            }
+
+            ExprKind::Match(e) => {
+                let scrutinee = e.scrutinee().rename_bound_idents_with(f, scope);
+                let arms = e
+                    .arms()
+                    .iter()
+                    .map(|arm| {
+                        let depth = scope.len();
+
+                        let binding = arm.pattern().binding().map(|binding| {
+                            let name = f(binding);
+                            scope.push((binding.to_owned(), name.clone()));
+                            name
+                        });
+                        let pattern = Pattern::new(arm.pattern().constructor(), binding);
+                        let body = arm.body().rename_bound_idents_with(f, scope);
+                        scope.truncate(depth);
+
+                        Arm::new(pattern, body)
+                    })
+                    .collect();
+
+                ExprKind::match_(scrutinee, arms)
+            }
+
+            ExprKind::Try(e) => ExprKind::try_(e.operand().rename_bound_idents_with(f, scope)),
+
+            ExprKind::Comparison(e) => {
+                let (left, right) = (
+                    e.left().rename_bound_idents_with(f, scope),
+                    e.right().rename_bound_idents_with(f, scope),
+                );
+                ExprKind::comparison(e.operator(), left, right)
+            }
+
+            ExprKind::Print(e) => {
+                ExprKind::Print(e.map_arg(|arg| arg.rename_bound_idents_with(f, scope)))
+            }
+
+            ExprKind::Assert(e) => ExprKind::Assert(
+                e.map_operands(|operand| operand.rename_bound_idents_with(f, scope)),
+            ),
+
+            ExprKind::List(e) => {
+                let elements = e
+                    .elements()
+                    .iter()
+                    .map(|e| e.rename_bound_idents_with(f, scope));
+                ExprKind::list(elements.collect())
+            }
+
+            ExprKind::Builtin(e) => {
+                let args = e
+                    .args()
+                    .iter()
+                    .map(|arg| arg.rename_bound_idents_with(f, scope));
+                ExprKind::builtin(e.function(), args.collect())
+            }
+
+            ExprKind::Integer(_)
+            | ExprKind::Bool(_)
+            | ExprKind::Unit(_)
+            | ExprKind::Break(_)
+            | ExprKind::Continue(_)
+            | ExprKind::Panic(_)
+            | ExprKind::ReadInt(_) => self.clone(),
+        }
+    }
+
+    /// Returns the expression with every call replaced by `f`. The arguments
+    /// of a call are visited before the call itself.
+    pub(crate) fn map_calls(&self, f: &mut impl FnMut(Call) -> ExprKind) -> ExprKind {
+        self.map_calls_with(&mut |call, _| f(call), &mut CallSite::default())
+    }
+
+    /// Same as `map_calls`, except that `f` is also given the site of the
+    /// call, which starts at `site`.
+    pub(crate) fn map_calls_with(
+        &self,
+        f: &mut impl FnMut(Call, &CallSite) -> ExprKind,
+        site: &mut CallSite,
+    ) -> ExprKind {
+        match self {
+            ExprKind::Addition(e) => {
+                let (left, right) = (
+                    e.left().map_calls_with(f, site),
+                    e.right().map_calls_with(f, site),
+                );
+                ExprKind::Addition(Addition::new(left, right))
+            }
+
+            ExprKind::Subtraction(e) => {
+                let (left, right) = (
+                    e.left().map_calls_with(f, site),
+                    e.right().map_calls_with(f, site),
+                );
+                ExprKind::Subtraction(Subtraction::new(left, right))
+            }
+
+            ExprKind::Multiplication(e) => {
+                let (left, right) = (
+                    e.left().map_calls_with(f, site),
+                    e.right().map_calls_with(f, site),
+                );
+                ExprKind::Multiplication(Multiplication::new(left, right))
+            }
+
+            ExprKind::Remainder(e) => {
+                let (left, right) = (
+                    e.left().map_calls_with(f, site),
+                    e.right().map_calls_with(f, site),
+                );
+                ExprKind::Remainder(Remainder::new(left, right))
+            }
+
+            ExprKind::If(e) => {
+                let condition = e.condition().map_calls_with(f, site);
+
+                site.branches += 1;
+                let consequent = e.consequent().map_calls_with(f, site);
+                let alternative = e.alternative().map_calls_with(f, site);
+                site.branches -= 1;
+
+                ExprKind::If(If::new(condition, consequent, alternative))
+            }
+
+            ExprKind::Bindings(e) => {
+                let scope = site.bound.len();
+
+                let statements = e
+                    .statements()
+                    .iter()
+                    .map(|stmt| match stmt {
+                        Statement::Binding(b) => {
+                            let value = b.value().map_calls_with(f, site);
+                            site.bound.push(b.name().to_owned());
+                            Statement::Binding(Binding::new(b.name().to_owned(), value))
+                        }
+                        Statement::Expr(e) => Statement::Expr(e.map_calls_with(f, site)),
+                    })
+                    .collect();
+                let ending = e.ending_expression().map_calls_with(f, site);
+
+                site.bound.truncate(scope);
+
+                ExprKind::Bindings(Bindings::from_statements(statements, ending))
+            }
+
+            ExprKind::For(e) => {
+                let start = e.range().start().map_calls_with(f, site);
+                let end = e.range().end().map_calls_with(f, site);
+                let range = Range::new(start, end, e.range().is_inclusive());
+
+                // The body may run zero times.
+                site.branches += 1;
+                site.bound.push(e.variable().to_owned());
+                let body = e.body().map_calls_with(f, site);
+                site.bound.pop();
+                site.branches -= 1;
+
+                ExprKind::For(For::new(e.variable().to_owned(), range, body))
+            }
+
+            ExprKind::Call(e) => {
+                let callee = e.callee().map_calls_with(f, site);
+                let args = e
+                    .args()
+                    .iter()
+                    .map(|arg| arg.map_calls_with(f, site))
+                    .collect();
+
+                f(e.with_callee_and_args(callee, args), site)
+            }
+
+            ExprKind::MethodCall(e) => {
+                let receiver = e.receiver().map_calls_with(f, site);
+                let args = e
+                    .args()
+                    .iter()
+                    .map(|arg| arg.map_calls_with(f, site))
+                    .collect();
+
+                ExprKind::MethodCall(MethodCall::new(receiver, e.method().to_owned(), args))
+            }
+
+            ExprKind::Lambda(e) => {
+                let scope = site.bound.len();
+                let may_have_returned = site.may_have_returned;
+
+                // A `?` in the body returns from the lambda only.
+                site.branches += 1;
+                site.bound
+                    .extend(e.params().iter().map(|p| p.name().to_owned()));
+                let body = e.body().map_calls_with(f, site);
+                site.bound.truncate(scope);
+                site.branches -= 1;
+                site.may_have_returned = may_have_returned;
+
+                ExprKind::Lambda(Lambda::new(e.params().to_vec(), body))
+            }
+
+            ExprKind::RecordLiteral(e) => {
+                let fields = e
+                    .fields()
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.map_calls_with(f, site)))
+                    .collect();
+
+                ExprKind::RecordLiteral(RecordLiteral::new(e.name().to_owned(), fields))
+            }
+
+            ExprKind::FieldAccess(e) => {
+                let record = e.record().map_calls_with(f, site);
+                ExprKind::FieldAccess(FieldAccess(Box::new(record), e.1.clone(), e.2))
+            }
+
+            ExprKind::Variant(e) => {
+                let payload = e.payload().map(|payload| payload.map_calls_with(f, site));
+                ExprKind::variant(e.constructor(), payload)
+            }
+
+            ExprKind::Match(e) => {
+                let scrutinee = e.scrutinee().map_calls_with(f, site);
+
+                site.branches += 1;
+                let arms = e
+                    .arms()
+                    .iter()
+                    .map(|arm| {
+                        let scope = site.bound.len();
+
+                        site.bound
+                            .extend(arm.pattern().binding().map(str::to_owned));
+                        let body = arm.body().map_calls_with(f, site);
+                        site.bound.truncate(scope);
+
+                        Arm::new(arm.pattern().clone(), body)
+                    })
+                    .collect();
+                site.branches -= 1;
+
+                ExprKind::match_(scrutinee, arms)
+            }
+
+            ExprKind::Try(e) => {
+                let operand = e.operand().map_calls_with(f, site);
+
+                // The function returns early if the operand is an error.
+                site.may_have_returned = true;
+
+                ExprKind::try_(operand)
+            }
+
+            ExprKind::Comparison(e) => {
+                let (left, right) = (
+                    e.left().map_calls_with(f, site),
+                    e.right().map_calls_with(f, site),
+                );
+                ExprKind::comparison(e.operator(), left, right)
+            }
+
+            ExprKind::Print(e) => ExprKind::Print(e.map_arg(|arg| arg.map_calls_with(f, site))),
+
+            ExprKind::Assert(e) => {
+                ExprKind::Assert(e.map_operands(|operand| operand.map_calls_with(f, site)))
+            }
+
+            ExprKind::List(e) => {
+                let elements = e.elements().iter().map(|e| e.map_calls_with(f, site));
+                ExprKind::list(elements.collect())
+            }
+
+            ExprKind::Builtin(e) => {
+                let args = e.args().iter().map(|arg| arg.map_calls_with(f, site));
+                ExprKind::builtin(e.function(), args.collect())
+            }
+
+            ExprKind::Integer(_)
+            | ExprKind::Ident(_)
+            | ExprKind::Bool(_)
+            | ExprKind::Unit(_)
+            | ExprKind::Break(_)
+            | ExprKind::Continue(_)
+            | ExprKind::Panic(_)
+            | ExprKind::ReadInt(_) => self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl ExprKind {
+    pub(crate) fn call_at(callee: ExprKind, args: Vec<ExprKind>, span: Span) -> ExprKind {
+        ExprKind::Call(Call::new(callee, args).with_span(span))
+    }
+
+    pub(crate) fn bindings(bs: Vec<Binding>, next: ExprKind) -> ExprKind {
+        ExprKind::Bindings(Bindings::from_vec(bs, next))
+    }
+
+    pub(crate) fn single_binding(
+        name: String,
+        value: ExprKind,
+        inner_expression: ExprKind,
+    ) -> ExprKind {
+        ExprKind::Bindings(Bindings::single(name, value, inner_expression))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Addition(Box<(ExprKind, ExprKind)>);
+
+impl Addition {
+    pub(crate) fn new(lhs: ExprKind, rhs: ExprKind) -> Addition {
+        Addition(Box::new((lhs, rhs)))
+    }
+
+    pub(crate) fn left(&self) -> &ExprKind {
+        &self.inner().0
+    }
+
+    pub(crate) fn right(&self) -> &ExprKind {
+        &self.inner().1
+    }
+
+    fn inner(&self) -> &(ExprKind, ExprKind) {
+        &self.0
+    }
+}
+
+/// A comparison between two integers, such as `a <= b`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Comparison(ComparisonOperator, Box<(ExprKind, ExprKind)>);
+
+impl Comparison {
+    pub(crate) fn new(operator: ComparisonOperator, lhs: ExprKind, rhs: ExprKind) -> Comparison {
+        Comparison(operator, Box::new((lhs, rhs)))
+    }
+
+    pub(crate) fn operator(&self) -> ComparisonOperator {
+        self.0
+    }
+
+    pub(crate) fn left(&self) -> &ExprKind {
+        &self.1 .0
+    }
+
+    pub(crate) fn right(&self) -> &ExprKind {
+        &self.1 .1
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum ComparisonOperator {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl ComparisonOperator {
+    /// Returns whether the comparison holds for two integers whose ordering
+    /// is `ordering`.
+    pub(crate) fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            ComparisonOperator::Lt => ordering == Ordering::Less,
+            ComparisonOperator::Le => ordering != Ordering::Greater,
+            ComparisonOperator::Gt => ordering == Ordering::Greater,
+            ComparisonOperator::Ge => ordering != Ordering::Less,
+            ComparisonOperator::Eq => ordering == Ordering::Equal,
+            ComparisonOperator::Ne => ordering != Ordering::Equal,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Subtraction(Box<(ExprKind, ExprKind)>);
+
+impl Subtraction {
+    pub(crate) fn new(lhs: ExprKind, rhs: ExprKind) -> Subtraction {
+        Subtraction(Box::new((lhs, rhs)))
+    }
+
+    pub(crate) fn left(&self) -> &ExprKind {
+        &self.inner().0
+    }
+
+    pub(crate) fn right(&self) -> &ExprKind {
+        &self.inner().1
+    }
+
+    fn inner(&self) -> &(ExprKind, ExprKind) {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Multiplication(Box<(ExprKind, ExprKind)>);
+
+impl Multiplication {
+    pub(crate) fn new(lhs: ExprKind, rhs: ExprKind) -> Multiplication {
+        Multiplication(Box::new((lhs, rhs)))
+    }
+
+    pub(crate) fn left(&self) -> &ExprKind {
+        &self.inner().0
+    }
+
+    pub(crate) fn right(&self) -> &ExprKind {
+        &self.inner().1
+    }
+
+    fn inner(&self) -> &(ExprKind, ExprKind) {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Remainder(Box<(ExprKind, ExprKind)>);
+
+impl Remainder {
+    pub(crate) fn new(lhs: ExprKind, rhs: ExprKind) -> Remainder {
+        Remainder(Box::new((lhs, rhs)))
+    }
+
+    pub(crate) fn left(&self) -> &ExprKind {
+        &self.inner().0
+    }
+
+    pub(crate) fn right(&self) -> &ExprKind {
+        &self.inner().1
+    }
+
+    fn inner(&self) -> &(ExprKind, ExprKind) {
+        &self.0
+    }
+}
+
+/// An arithmetic operator. Types other than `int` support it by
+/// implementing the corresponding trait.
+pub(crate) trait Operator {
+    const TRAIT: &'static str;
+    const METHOD: &'static str;
+
+    fn operands(&self) -> (&ExprKind, &ExprKind);
+
+    /// Creates an expression that applies the operator to `lhs` and `rhs`.
+    fn expr(lhs: ExprKind, rhs: ExprKind) -> ExprKind;
+}
+
+impl Operator for Addition {
+    const TRAIT: &'static str = "Add";
+    const METHOD: &'static str = "add";
+
+    fn operands(&self) -> (&ExprKind, &ExprKind) {
+        (self.left(), self.right())
+    }
+
+    fn expr(lhs: ExprKind, rhs: ExprKind) -> ExprKind {
+        ExprKind::addition(lhs, rhs)
+    }
+}
+
+impl Operator for Subtraction {
+    const TRAIT: &'static str = "Sub";
+    const METHOD: &'static str = "sub";
+
+    fn operands(&self) -> (&ExprKind, &ExprKind) {
+        (self.left(), self.right())
+    }
+
+    fn expr(lhs: ExprKind, rhs: ExprKind) -> ExprKind {
+        ExprKind::subtraction(lhs, rhs)
+    }
+}
+
+impl Operator for Multiplication {
+    const TRAIT: &'static str = "Mul";
+    const METHOD: &'static str = "mul";
+
+    fn operands(&self) -> (&ExprKind, &ExprKind) {
+        (self.left(), self.right())
+    }
+
+    fn expr(lhs: ExprKind, rhs: ExprKind) -> ExprKind {
+        ExprKind::multiplication(lhs, rhs)
+    }
+}
+
+impl Operator for Remainder {
+    const TRAIT: &'static str = "Rem";
+    const METHOD: &'static str = "rem";
+
+    fn operands(&self) -> (&ExprKind, &ExprKind) {
+        (self.left(), self.right())
+    }
+
+    fn expr(lhs: ExprKind, rhs: ExprKind) -> ExprKind {
+        ExprKind::remainder(lhs, rhs)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Integer(i32);
+
+impl Integer {
+    pub(crate) fn new(value: i32) -> Integer {
+        Integer(value)
+    }
+
+    pub(crate) fn value(&self) -> i32 {
+        self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct If(Box<(ExprKind, ExprKind, ExprKind)>);
+
+impl If {
+    pub(crate) fn new(condition: ExprKind, consequent: ExprKind, alternative: ExprKind) -> If {
+        If(Box::new((condition, consequent, alternative)))
+    }
+
+    pub(crate) fn condition(&self) -> &ExprKind {
+        &self.inner().0
+    }
+
+    pub(crate) fn consequent(&self) -> &ExprKind {
+        &self.inner().1
+    }
+
+    pub(crate) fn alternative(&self) -> &ExprKind {
+        &self.inner().2
+    }
+
+    fn inner(&self) -> &(ExprKind, ExprKind, ExprKind) {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Bindings(Vec<Statement>, Box<ExprKind>);
+
+impl Bindings {
+    pub(crate) fn from_statements(stmts: Vec<Statement>, next: ExprKind) -> Bindings {
+        Bindings(stmts, Box::new(next))
+    }
+
+    pub(crate) fn statements(&self) -> &[Statement] {
+        self.0.as_slice()
+    }
+
+    pub(crate) fn defines(&self) -> impl Iterator<Item = &Binding> {
+        self.0.iter().filter_map(|stmt| match stmt {
+            Statement::Binding(b) => Some(b),
+            Statement::Expr(_) => None,
+        })
+    }
+
+    pub(crate) fn ending_expression(&self) -> &ExprKind {
+        &self.1
+    }
+}
+
+#[cfg(test)]
+impl Bindings {
+    pub(crate) fn from_vec(bs: Vec<Binding>, next: ExprKind) -> Bindings {
+        let stmts = bs.into_iter().map(Statement::Binding).collect();
+        Bindings::from_statements(stmts, next)
+    }
+
+    pub(crate) fn single(name: String, value: ExprKind, next: ExprKind) -> Bindings {
+        let binding = Binding::new(name, value);
+        Bindings::from_vec(vec![binding], next)
+    }
+}
+
+/// Something that is evaluated before the ending expression of a block.
+///
+/// Expression statements are evaluated for their effects only, their value
+/// is discarded.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Statement {
+    Binding(Binding),
+    Expr(ExprKind),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Binding(String, ExprKind);
+
+impl Binding {
+    pub(crate) fn new(name: String, value: ExprKind) -> Binding {
+        Binding(name, value)
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub(crate) fn value(&self) -> &ExprKind {
+        &self.1
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Ident(String);
+
+impl Ident {
+    pub(crate) fn new(name: String) -> Ident {
+        Ident(name)
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Bool(bool);
+
+impl Bool {
+    pub(crate) fn new(bool_: bool) -> Bool {
+        Bool(bool_)
+    }
+
+    pub(crate) fn value(&self) -> bool {
+        self.0
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Unit;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct For(String, Box<(Range, ExprKind)>);
+
+impl For {
+    pub(crate) fn new(variable: String, range: Range, body: ExprKind) -> For {
+        For(variable, Box::new((range, body)))
+    }
+
+    pub(crate) fn variable(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub(crate) fn range(&self) -> &Range {
+        &self.inner().0
+    }
+
+    pub(crate) fn body(&self) -> &ExprKind {
+        &self.inner().1
+    }
+
+    fn inner(&self) -> &(Range, ExprKind) {
+        &self.1
+    }
+}
+
+/// A range of integers, such as `0..n` or `0..=n`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Range {
+    start: ExprKind,
+    end: ExprKind,
+    inclusive: bool,
+}
+
+impl Range {
+    pub(crate) fn new(start: ExprKind, end: ExprKind, inclusive: bool) -> Range {
+        Range {
+            start,
+            end,
+            inclusive,
+        }
+    }
+
+    pub(crate) fn start(&self) -> &ExprKind {
+        &self.start
+    }
+
+    pub(crate) fn end(&self) -> &ExprKind {
+        &self.end
+    }
+
+    pub(crate) fn is_inclusive(&self) -> bool {
+        self.inclusive
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Break;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Continue;
+
+/// A call to a function. Calls written in the source know where they are,
+/// while those introduced by the compiler don't.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Call(Box<ExprKind>, Vec<ExprKind>, Option<Span>);
+
+impl Call {
+    pub(crate) fn new(callee: ExprKind, args: Vec<ExprKind>) -> Call {
+        Call(Box::new(callee), args, None)
+    }
+
+    pub(crate) fn with_span(self, span: Span) -> Call {
+        Call(self.0, self.1, Some(span))
+    }
+
+    /// Returns a call at the same location, with a different callee and
+    /// arguments.
+    pub(crate) fn with_callee_and_args(&self, callee: ExprKind, args: Vec<ExprKind>) -> Call {
+        Call(Box::new(callee), args, self.2.clone())
+    }
+
+    pub(crate) fn callee(&self) -> &ExprKind {
+        &self.0
+    }
+
+    pub(crate) fn args(&self) -> &[ExprKind] {
+        self.1.as_slice()
+    }
+
+    pub(crate) fn span(&self) -> Option<&Span> {
+        self.2.as_ref()
+    }
+}
+
+/// Where a call is in the expression given to [`ExprKind::map_calls_with`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CallSite {
+    bound: Vec<String>,
+    branches: usize,
+    may_have_returned: bool,
+}
+
+impl CallSite {
+    /// Returns the site at the start of an expression, where `bound` are the
+    /// local variables in scope.
+    pub(crate) fn new(bound: Vec<String>) -> CallSite {
+        CallSite {
+            bound,
+            ..CallSite::default()
+        }
+    }
+
+    /// The local variables in scope at the call.
+    pub(crate) fn bound(&self) -> &[String] {
+        self.bound.as_slice()
+    }
+
+    /// Tells whether the call runs every time the whole expression does: it
+    /// is not in a branch, a loop or a lambda, and no `?` before it may have
+    /// returned.
+    pub(crate) fn is_unconditional(&self) -> bool {
+        self.branches == 0 && !self.may_have_returned
+    }
+}
+
+/// A call such as `x.show()`, where the method is either implemented directly
+/// for the type of the receiver, or provided by a trait that it implements.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MethodCall(Box<ExprKind>, String, Vec<ExprKind>);
+
+impl MethodCall {
+    pub(crate) fn new(receiver: ExprKind, method: String, args: Vec<ExprKind>) -> MethodCall {
+        MethodCall(Box::new(receiver), method, args)
+    }
+
+    pub(crate) fn receiver(&self) -> &ExprKind {
+        &self.0
+    }
+
+    pub(crate) fn method(&self) -> &str {
+        self.1.as_str()
+    }
+
+    pub(crate) fn args(&self) -> &[ExprKind] {
+        self.2.as_slice()
+    }
+}
+
+/// An anonymous function, which may capture the local variables it refers to.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Lambda(Vec<Param>, Box<ExprKind>);
+
+impl Lambda {
+    pub(crate) fn new(params: Vec<Param>, body: ExprKind) -> Lambda {
+        Lambda(params, Box::new(body))
+    }
+
+    pub(crate) fn params(&self) -> &[Param] {
+        self.0.as_slice()
+    }
+
+    pub(crate) fn body(&self) -> &ExprKind {
+        &self.1
+    }
+}
+
+/// The creation of a record, such as `Point { x: 1, y: 2 }`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RecordLiteral(String, Vec<(String, ExprKind)>);
+
+impl RecordLiteral {
+    pub(crate) fn new(name: String, fields: Vec<(String, ExprKind)>) -> RecordLiteral {
+        RecordLiteral(name, fields)
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Returns the value of each field, in the order they are written.
+    pub(crate) fn fields(&self) -> &[(String, ExprKind)] {
+        self.1.as_slice()
+    }
+}
+
+/// A read of a record field, such as `p.x`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FieldAccess(Box<ExprKind>, String, Option<u16>);
+
+impl FieldAccess {
+    pub(crate) fn new(record: ExprKind, field: String) -> FieldAccess {
+        FieldAccess(Box::new(record), field, None)
+    }
+
+    /// Sets the position of the field in the record. It depends on the type
+    /// of the record, which is only known after type checking.
+    pub(crate) fn with_index(self, idx: u16) -> FieldAccess {
+        FieldAccess(self.0, self.1, Some(idx))
+    }
+
+    pub(crate) fn record(&self) -> &ExprKind {
+        &self.0
+    }
+
+    pub(crate) fn field(&self) -> &str {
+        self.1.as_str()
+    }
+
+    pub(crate) fn index(&self) -> Option<u16> {
+        self.2
+    }
+}
+
+/// A constructor of a built-in type, such as `Some`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Constructor {
+    Some,
+    None,
+    Ok,
+    Err,
+}
+
+impl Constructor {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Constructor::Some => "Some",
+            Constructor::None => "None",
+            Constructor::Ok => "Ok",
+            Constructor::Err => "Err",
+        }
+    }
+
+    /// Returns the value that identifies the constructor at runtime.
+    ///
+    /// The constructors that stand for a missing value or a failure are
+    /// tagged with `0`, which is what `?` tests.
+    pub(crate) fn tag(self) -> i32 {
+        match self {
+            Constructor::Some | Constructor::Ok => 1,
+            Constructor::None | Constructor::Err => 0,
+        }
+    }
+
+    /// Returns the type of the values built by the constructor from a payload
+    /// of type `payload`. The types that the payload says nothing about are
+    /// created by `unknown`.
+    pub(crate) fn ty(self, payload: Ty, mut unknown: impl FnMut() -> Ty) -> Ty {
+        match self {
+            Constructor::Some => Ty::Option(Box::new(payload)),
+            Constructor::None => Ty::Option(Box::new(unknown())),
+            Constructor::Ok => Ty::Result(Box::new(payload), Box::new(unknown())),
+            Constructor::Err => Ty::Result(Box::new(unknown()), Box::new(payload)),
+        }
+    }
+
+    /// Returns the type of the payload of the values of type `ty` built by the
+    /// constructor, or `None` if the constructor does not build such values.
+    /// Constructors without a payload have a `()` payload.
+    pub(crate) fn payload_ty(self, ty: &Ty) -> Option<Ty> {
+        match (self, ty) {
+            (_, Ty::Err) => Some(Ty::Err),
+            (_, Ty::Never) => Some(Ty::Never),
+            (Constructor::Some, Ty::Option(inner)) => Some(inner.as_ref().clone()),
+            (Constructor::None, Ty::Option(_)) => Some(Ty::Unit),
+            (Constructor::Ok, Ty::Result(ok, _)) => Some(ok.as_ref().clone()),
+            (Constructor::Err, Ty::Result(_, err)) => Some(err.as_ref().clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns every constructor of `ty`, sorted by tag.
+    pub(crate) fn of(ty: &Ty) -> &'static [Constructor] {
+        match ty {
+            Ty::Option(_) => &[Constructor::None, Constructor::Some],
+            Ty::Result(..) => &[Constructor::Err, Constructor::Ok],
+            _ => &[],
+        }
+    }
+}
+
+/// The creation of a value of a built-in type, such as `Some(1)`, `None` or
+/// `Err(2)`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Variant(Constructor, Option<Box<ExprKind>>);
+
+impl Variant {
+    pub(crate) fn new(constructor: Constructor, payload: Option<ExprKind>) -> Variant {
+        Variant(constructor, payload.map(Box::new))
+    }
+
+    pub(crate) fn constructor(&self) -> Constructor {
+        self.0
+    }
+
+    pub(crate) fn payload(&self) -> Option<&ExprKind> {
+        self.1.as_deref()
+    }
+}
+
+/// A `match` expression, which evaluates the arm whose pattern matches the
+/// value of the scrutinee.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Match(Box<ExprKind>, Vec<Arm>);
+
+impl Match {
+    pub(crate) fn new(scrutinee: ExprKind, arms: Vec<Arm>) -> Match {
+        Match(Box::new(scrutinee), arms)
+    }
+
+    pub(crate) fn scrutinee(&self) -> &ExprKind {
+        &self.0
+    }
+
+    pub(crate) fn arms(&self) -> &[Arm] {
+        self.1.as_slice()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Arm(Pattern, ExprKind);
+
+impl Arm {
+    pub(crate) fn new(pattern: Pattern, body: ExprKind) -> Arm {
+        Arm(pattern, body)
+    }
+
+    pub(crate) fn pattern(&self) -> &Pattern {
+        &self.0
+    }
+
+    pub(crate) fn body(&self) -> &ExprKind {
+        &self.1
+    }
+}
+
+/// A pattern such as `Some(x)`, which binds the payload of the value to `x`,
+/// or `None`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Pattern(Constructor, Option<String>);
+
+impl Pattern {
+    pub(crate) fn new(constructor: Constructor, binding: Option<String>) -> Pattern {
+        Pattern(constructor, binding)
+    }
+
+    pub(crate) fn constructor(&self) -> Constructor {
+        self.0
+    }
+
+    pub(crate) fn binding(&self) -> Option<&str> {
+        self.1.as_deref()
+    }
+}
+
+/// The `?` operator, which unwraps a `Some` or an `Ok` and returns early from
+/// the enclosing function on `None` or `Err`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Try(Box<ExprKind>);
+
+impl Try {
+    pub(crate) fn new(operand: ExprKind) -> Try {
+        Try(Box::new(operand))
+    }
+
+    pub(crate) fn operand(&self) -> &ExprKind {
+        &self.0
+    }
+}
+
+/// A call to `panic`, which stops the program with a message.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Panic(String);
+
+impl Panic {
+    pub(crate) fn new(message: String) -> Panic {
+        Panic(message)
+    }
+
+    pub(crate) fn message(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A location in the source code, which is displayed as `file:line:column`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Span {
+    file: Option<String>,
+    line: u32,
+    column: usize,
+}
+
+impl Span {
+    pub(crate) fn new(file: Option<String>, line: u32, column: usize) -> Span {
+        Span { file, line, column }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A call to `assert` or `assert_eq`, which stops the program when the
+/// assertion doesn't hold. The message names the location of the assertion.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Assert(Box<AssertKind>, Span);
+
+impl Assert {
+    pub(crate) fn new(kind: AssertKind, span: Span) -> Assert {
+        Assert(Box::new(kind), span)
+    }
+
+    pub(crate) fn kind(&self) -> &AssertKind {
+        &self.0
+    }
+
+    pub(crate) fn span(&self) -> &Span {
+        &self.1
+    }
+
+    /// Returns the message of the panic caused by a failed assertion, which
+    /// the values of the operands of `assert_eq` are appended to.
+    pub(crate) fn message(&self) -> String {
+        format!("Assertion failed at {}", self.span())
+    }
+
+    /// Returns the operands of the assertion, in evaluation order.
+    pub(crate) fn operands(&self) -> Vec<&ExprKind> {
+        match self.kind() {
+            AssertKind::True(condition) => vec![condition],
+            AssertKind::Eq(left, right) | AssertKind::EqBool(left, right) => vec![left, right],
+        }
+    }
+
+    /// Returns the same assertion, with its operands replaced by `f`.
+    pub(crate) fn map_operands(&self, mut f: impl FnMut(&ExprKind) -> ExprKind) -> Assert {
+        let kind = match self.kind() {
+            AssertKind::True(condition) => AssertKind::True(f(condition)),
+            AssertKind::Eq(left, right) => {
+                let left = f(left);
+                AssertKind::Eq(left, f(right))
+            }
+            AssertKind::EqBool(left, right) => {
+                let left = f(left);
+                AssertKind::EqBool(left, f(right))
+            }
+        };
+
+        Assert::new(kind, self.span().clone())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum AssertKind {
+    /// `assert(condition)`, which holds when the condition is `true`.
+    True(ExprKind),
+    /// `assert_eq(left, right)`, which holds when both operands are equal.
+    /// They are integers, or booleans until monomorphization tells them
+    /// apart.
+    Eq(ExprKind, ExprKind),
+    /// `assert_eq(left, right)` on operands known to be booleans, which are
+    /// shown as `true` or `false` when the assertion fails.
+    EqBool(ExprKind, ExprKind),
+}
+
+/// A call to `read_int`, which reads an integer from the input of the
+/// program.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ReadInt;
+
+/// A call to `print` or `println`, which writes its argument to the output of
+/// the program.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Print {
+    arg: PrintArg,
+    newline: bool,
+}
+
+impl Print {
+    pub(crate) fn new(arg: PrintArg, newline: bool) -> Print {
+        Print { arg, newline }
+    }
+
+    pub(crate) fn arg(&self) -> &PrintArg {
+        &self.arg
+    }
+
+    /// Returns whether a newline is written after the argument, as done by
+    /// `println`.
+    pub(crate) fn newline(&self) -> bool {
+        self.newline
+    }
+
+    /// Returns the same call, with the printed expression (if any) replaced
+    /// by the result of `f`.
+    pub(crate) fn map_arg(&self, f: impl FnOnce(&ExprKind) -> ExprKind) -> Print {
+        let arg = match &self.arg {
+            PrintArg::Nothing => PrintArg::Nothing,
+            PrintArg::Str(s) => PrintArg::Str(s.clone()),
+            PrintArg::Value(e) => PrintArg::Value(Box::new(f(e))),
+            PrintArg::Bool(e) => PrintArg::Bool(Box::new(f(e))),
+        };
+
+        Print::new(arg, self.newline)
+    }
+}
+
+/// What is printed by a `print` or `println` call.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum PrintArg {
+    /// Nothing, as in `println()`.
+    Nothing,
+    /// A string literal, printed as is.
+    Str(String),
+    /// An integer, or a boolean until monomorphization tells them apart.
+    Value(Box<ExprKind>),
+    /// A value known to be a boolean, printed as `true` or `false`.
+    Bool(Box<ExprKind>),
+}
+
+impl PrintArg {
+    /// Returns the printed expression, if the argument is not a literal.
+    pub(crate) fn expr(&self) -> Option<&ExprKind> {
+        match self {
+            PrintArg::Value(e) | PrintArg::Bool(e) => Some(e),
+            PrintArg::Nothing | PrintArg::Str(_) => None,
+        }
+    }
+}
+
+/// A list literal, such as `[1, 2, 3]`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ListLiteral(Vec<ExprKind>);
+
+impl ListLiteral {
+    pub(crate) fn new(elements: Vec<ExprKind>) -> ListLiteral {
+        ListLiteral(elements)
+    }
+
+    pub(crate) fn elements(&self) -> &[ExprKind] {
+        self.0.as_slice()
+    }
+}
+
+/// A call to a function that is implemented by the VM.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Builtin(BuiltinFunction, Vec<ExprKind>);
+
+impl Builtin {
+    pub(crate) fn new(function: BuiltinFunction, args: Vec<ExprKind>) -> Builtin {
+        Builtin(function, args)
+    }
+
+    pub(crate) fn function(&self) -> BuiltinFunction {
+        self.0
+    }
+
+    pub(crate) fn args(&self) -> &[ExprKind] {
+        self.1.as_slice()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum BuiltinFunction {
+    Cons,
+    Head,
+    Tail,
+    IsEmpty,
+    EmptyMap,
+    Insert,
+    Get,
+    Remove,
+    Contains,
+}
+
+impl BuiltinFunction {
+    pub(crate) const ALL: [BuiltinFunction; 9] = [
+        BuiltinFunction::Cons,
+        BuiltinFunction::Head,
+        BuiltinFunction::Tail,
+        BuiltinFunction::IsEmpty,
+        BuiltinFunction::EmptyMap,
+        BuiltinFunction::Insert,
+        BuiltinFunction::Get,
+        BuiltinFunction::Remove,
+        BuiltinFunction::Contains,
+    ];
+
+    pub(crate) fn named(name: &str) -> Option<BuiltinFunction> {
+        BuiltinFunction::ALL
+            .into_iter()
+            .find(|function| function.name() == name)
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            BuiltinFunction::Cons => "cons",
+            BuiltinFunction::Head => "head",
+            BuiltinFunction::Tail => "tail",
+            BuiltinFunction::IsEmpty => "is_empty",
+            BuiltinFunction::EmptyMap => "empty_map",
+            BuiltinFunction::Insert => "insert",
+            BuiltinFunction::Get => "get",
+            BuiltinFunction::Remove => "remove",
+            BuiltinFunction::Contains => "contains",
+        }
+    }
+
+    /// Returns the type of the function. Its type parameters are instantiated
+    /// at each call.
+    pub(crate) fn signature(self) -> Ty {
+        let t = || Ty::Param("T".to_owned());
+        let list = || Ty::List(Box::new(t()));
+        let k = || Ty::Param("K".to_owned());
+        let v = || Ty::Param("V".to_owned());
+        let map = || Ty::Map(Box::new(k()), Box::new(v()));
+
+        let (params, ret) = match self {
+            BuiltinFunction::Cons => (vec![t(), list()], list()),
+            BuiltinFunction::Head => (vec![list()], t()),
+            BuiltinFunction::Tail => (vec![list()], list()),
+            BuiltinFunction::IsEmpty => (vec![list()], Ty::Bool),
+            BuiltinFunction::EmptyMap => (Vec::new(), map()),
+            BuiltinFunction::Insert => (vec![map(), k(), v()], map()),
+            BuiltinFunction::Get => (vec![map(), k()], Ty::Option(Box::new(v()))),
+            BuiltinFunction::Remove => (vec![map(), k()], map()),
+            BuiltinFunction::Contains => (vec![map(), k()], Ty::Bool),
+        };
+
+        Ty::Function(params, Box::new(ret))
     }
 }
@@ -0,0 +1,61 @@
+use crate::{
+    ast::Program,
+    context::{PassResult, TypingContext},
+};
+
+/// Checks that the attributes of each function are known ones, with a valid
+/// number of arguments.
+pub(crate) fn check_attributes(
+    ast: &Program,
+    ctxt: TypingContext,
+) -> PassResult<TypingContext, ()> {
+    let rslt = check_program(ast, &ctxt);
+    ctxt.wrap_result(rslt)
+}
+
+fn check_program(ast: &Program, ctxt: &TypingContext) -> Result<(), ()> {
+    ast.functions()
+        .iter()
+        .flat_map(|f| f.attributes())
+        .map(|attribute| {
+            attribute
+                .validate()
+                .map_err(|e| ctxt.errs().add(format!("{}: {}", attribute.span(), e)))
+        })
+        .fold(Ok(()), Result::and)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::check;
+
+    use super::*;
+
+    #[test]
+    fn known_attributes_are_accepted() {
+        let errs = check(
+            "#[test] #[inline(always)] fn f() {}
+             impl int { #[inline] fn double(self) -> int { self + self } }",
+            check_program,
+        );
+
+        assert_eq!(errs, "");
+    }
+
+    #[test]
+    fn invalid_attributes_are_reported() {
+        let errs = check(
+            "#[test(1)]
+             #[unknown] fn f() {}
+             impl int { #[inline(always, 2)] fn double(self) -> int { self + self } }",
+            check_program,
+        );
+
+        assert_eq!(
+            errs,
+            "1:1: Attribute `test` takes no arguments, found 1\n\
+             2:14: Unknown attribute `unknown`\n\
+             3:25: Attribute `inline` takes at most 1 argument, found 2\n"
+        );
+    }
+}
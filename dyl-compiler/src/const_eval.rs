@@ -0,0 +1,681 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{
+    ast::{AssertKind, Call, CallSite, Clause, ExprKind, Function, Program, Statement, RESULT},
+    context::{PassResult, TypingContext},
+    features::{Feature, FeatureFinder},
+    ty::Ty,
+};
+
+/// The maximum number of expressions that can be evaluated by a call to a
+/// `const fn`. Reaching it most likely means that the call never returns.
+const STEP_LIMIT: u32 = 1_000_000;
+
+/// The maximum number of nested calls while evaluating a `const fn`.
+const MAX_CALL_DEPTH: usize = 256;
+
+/// Replaces the calls to `const fn`s whose arguments are integer or boolean
+/// literals by the value they return.
+///
+/// A call whose evaluation fails is an error when it runs every time its
+/// function does. Otherwise, it may never run, so it is left to the runtime.
+///
+/// The body of each `const fn` is checked first: it may only use integers,
+/// booleans, local variables, loops and calls to other `const fn`s, so that
+/// it can be evaluated without running the program.
+pub(crate) fn evaluate_consts(
+    ast: &Program,
+    ctxt: TypingContext,
+) -> PassResult<TypingContext, Program> {
+    let rslt = fold_program(ast, &ctxt);
+    ctxt.wrap_result(rslt)
+}
+
+fn fold_program(ast: &Program, ctxt: &TypingContext) -> Result<Program, ()> {
+    let mut failed = false;
+
+    let const_fns = ast
+        .functions()
+        .iter()
+        .filter(|f| f.is_const())
+        .map(|f| (f.name(), f))
+        .collect::<HashMap<_, _>>();
+
+    let finder = FeatureFinder::new(ast);
+    ast.functions()
+        .iter()
+        .filter(|f| f.is_const())
+        .for_each(|f| {
+            if let Err(e) = check_const_fn(f, &const_fns, &finder) {
+                ctxt.errs().add(e);
+                failed = true;
+            }
+        });
+
+    if failed {
+        return Err(());
+    }
+
+    let functions = ast
+        .functions()
+        .iter()
+        .map(|f| {
+            let mut fold = |call, site: &CallSite| match fold_call(call, &const_fns) {
+                Ok(expr) => expr,
+                Err((call, e)) => {
+                    if site.is_unconditional() {
+                        ctxt.errs().add(e);
+                        failed = true;
+                    }
+
+                    ExprKind::Call(call)
+                }
+            };
+            let body = f.body().map_calls_with(&mut fold, &mut CallSite::default());
+
+            f.with_name_and_body(f.name().to_owned(), body)
+        })
+        .collect();
+
+    match failed {
+        true => Err(()),
+        false => Ok(ast.with_functions(functions)),
+    }
+}
+
+/// Evaluates a call to a `const fn` whose arguments are constants. Other
+/// calls are returned as is.
+fn fold_call(call: Call, const_fns: &HashMap<&str, &Function>) -> Result<ExprKind, (Call, String)> {
+    let function = match call.callee() {
+        ExprKind::Ident(ident) => const_fns.get(ident.name()),
+        _ => None,
+    };
+
+    let args = call
+        .args()
+        .iter()
+        .map(Value::from_literal)
+        .collect::<Option<Vec<_>>>();
+
+    let (function, args) = match (function, args) {
+        (Some(function), Some(args)) => (function, args),
+        _ => return Ok(ExprKind::Call(call)),
+    };
+
+    let mut evaluator = Evaluator::new(const_fns);
+
+    let e = match evaluator.call(function, args.clone()) {
+        Ok(value) => return Ok(value.into_literal()),
+        Err(Interrupt::Failed(e)) => e,
+        Err(Interrupt::TooLong) => format!("Evaluation did not finish after {} steps", STEP_LIMIT),
+        Err(Interrupt::TooDeep) => format!("Reached the maximum call depth of {}", MAX_CALL_DEPTH),
+        Err(Interrupt::Break | Interrupt::Continue) => {
+            unreachable!("`break` or `continue` escaped a call")
+        }
+    };
+
+    let args = args.iter().map(Value::to_string).collect::<Vec<_>>();
+    let e = format!(
+        "Failed to evaluate `{}({})` at compile time: {}",
+        function.name(),
+        args.join(", "),
+        e
+    );
+    let e = match call.span() {
+        Some(span) => format!("{}: {}", span, e),
+        None => e,
+    };
+
+    Err((call, e))
+}
+
+fn check_const_fn(
+    function: &Function,
+    const_fns: &HashMap<&str, &Function>,
+    finder: &FeatureFinder,
+) -> Result<(), String> {
+    let is_constant = |ty: &Ty| matches!(ty, Ty::Int | Ty::Bool);
+
+    let signature_is_constant = function.params().iter().all(|p| is_constant(p.ty()))
+        && function.ret_ty().is_some_and(is_constant);
+
+    if !signature_is_constant {
+        return Err(format!(
+            "`const fn` `{}` can only take and return integers and booleans",
+            function.name()
+        ));
+    }
+
+    let not_const = finder.features(function).into_iter().find(
+        |feature| !matches!(feature, Feature::Call(callee) if const_fns.contains_key(callee)),
+    );
+
+    match not_const {
+        None => Ok(()),
+        Some(Feature::Call(callee) | Feature::ExternCall(callee)) => Err(format!(
+            "`const fn` `{}` calls `{}`, which is not a `const fn`",
+            function.name(),
+            callee
+        )),
+        Some(feature) => Err(format!(
+            "`const fn` `{}` can't use {}",
+            function.name(),
+            feature.description()
+        )),
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Value {
+    Integer(i32),
+    Bool(bool),
+    Unit,
+}
+
+impl Value {
+    fn from_literal(expr: &ExprKind) -> Option<Value> {
+        match expr {
+            ExprKind::Integer(i) => Some(Value::Integer(i.value())),
+            ExprKind::Bool(b) => Some(Value::Bool(b.value())),
+            _ => None,
+        }
+    }
+
+    fn into_literal(self) -> ExprKind {
+        match self {
+            Value::Integer(i) => ExprKind::integer(i),
+            Value::Bool(b) => ExprKind::bool_(b),
+            // The signature of `const fn`s is checked beforehand.
+            Value::Unit => unreachable!("`const fn` returned `()`"),
+        }
+    }
+
+    fn as_integer(self) -> Result<i32, Interrupt> {
+        match self {
+            Value::Integer(i) => Ok(i),
+            // The program is well-typed, so this can't happen.
+            _ => Err(Interrupt::Failed(format!(
+                "Expected an integer, found `{}`",
+                self
+            ))),
+        }
+    }
+
+    fn as_bool(self) -> Result<bool, Interrupt> {
+        match self {
+            Value::Bool(b) => Ok(b),
+            _ => Err(Interrupt::Failed(format!(
+                "Expected a boolean, found `{}`",
+                self
+            ))),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Value::Integer(i) => i.fmt(f),
+            Value::Bool(b) => b.fmt(f),
+            Value::Unit => "()".fmt(f),
+        }
+    }
+}
+
+/// What stops the evaluation of an expression before it produces a value.
+enum Interrupt {
+    Break,
+    Continue,
+    Failed(String),
+    /// The evaluation reached [`STEP_LIMIT`].
+    TooLong,
+    /// The evaluation reached [`MAX_CALL_DEPTH`].
+    TooDeep,
+}
+
+/// Evaluates the body of `const fn`s, counting the expressions it evaluates.
+struct Evaluator<'a> {
+    functions: &'a HashMap<&'a str, &'a Function>,
+    variables: Vec<(String, Value)>,
+    steps: u32,
+    depth: usize,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(functions: &'a HashMap<&'a str, &'a Function>) -> Evaluator<'a> {
+        Evaluator {
+            functions,
+            variables: Vec::new(),
+            steps: 0,
+            depth: 0,
+        }
+    }
+
+    fn call(&mut self, function: &Function, args: Vec<Value>) -> Result<Value, Interrupt> {
+        if self.depth == MAX_CALL_DEPTH {
+            return Err(Interrupt::TooDeep);
+        }
+
+        let params = function.params().iter().map(|p| p.name().to_owned());
+        let caller_variables = std::mem::replace(&mut self.variables, params.zip(args).collect());
+        self.depth += 1;
+
+        let rslt = self
+            .check_clauses(function, function.requires())
+            .and_then(|()| self.eval(function.body()))
+            .and_then(|value| {
+                self.variables.push((RESULT.to_owned(), value));
+                self.check_clauses(function, function.ensures())?;
+
+                Ok(value)
+            });
+
+        self.depth -= 1;
+        self.variables = caller_variables;
+
+        match rslt {
+            // Loops are checked by the type checker.
+            Err(Interrupt::Break | Interrupt::Continue) => Err(Interrupt::Failed(
+                "`break` or `continue` outside of a loop".to_owned(),
+            )),
+            rslt => rslt,
+        }
+    }
+
+    /// Evaluates the given clauses of `function`, stopping at the first one
+    /// that doesn't hold.
+    fn check_clauses<'c>(
+        &mut self,
+        function: &Function,
+        mut clauses: impl Iterator<Item = &'c Clause>,
+    ) -> Result<(), Interrupt> {
+        clauses.try_for_each(|clause| match self.eval(clause.condition())?.as_bool()? {
+            true => Ok(()),
+            false => Err(Interrupt::Failed(clause.violation_message(function.name()))),
+        })
+    }
+
+    fn eval(&mut self, expr: &ExprKind) -> Result<Value, Interrupt> {
+        self.steps += 1;
+        if self.steps > STEP_LIMIT {
+            return Err(Interrupt::TooLong);
+        }
+
+        match expr {
+            ExprKind::Integer(i) => Ok(Value::Integer(i.value())),
+            ExprKind::Bool(b) => Ok(Value::Bool(b.value())),
+            ExprKind::Unit(_) => Ok(Value::Unit),
+
+            ExprKind::Addition(e) => self.arithmetic(e.left(), e.right(), "+", i32::checked_add),
+            ExprKind::Subtraction(e) => self.arithmetic(e.left(), e.right(), "-", i32::checked_sub),
+            ExprKind::Multiplication(e) => {
+                self.arithmetic(e.left(), e.right(), "*", i32::checked_mul)
+            }
+            ExprKind::Remainder(e) => self.arithmetic(e.left(), e.right(), "%", i32::checked_rem),
+
+            ExprKind::Comparison(e) => {
+                let left = self.eval(e.left())?.as_integer()?;
+                let right = self.eval(e.right())?.as_integer()?;
+
+                Ok(Value::Bool(e.operator().holds(left.cmp(&right))))
+            }
+
+            ExprKind::If(e) => match self.eval(e.condition())?.as_bool()? {
+                true => self.eval(e.consequent()),
+                false => self.eval(e.alternative()),
+            },
+
+            ExprKind::Bindings(e) => {
+                let scope = self.variables.len();
+                let rslt = self.eval_bindings(e.statements(), e.ending_expression());
+                self.variables.truncate(scope);
+
+                rslt
+            }
+
+            ExprKind::Ident(ident) => self
+                .variables
+                .iter()
+                .rev()
+                .find(|(name, _)| name == ident.name())
+                .map(|(_, value)| *value)
+                .ok_or_else(|| Interrupt::Failed(format!("Unknown variable `{}`", ident.name()))),
+
+            ExprKind::For(e) => {
+                let start = self.eval(e.range().start())?.as_integer()?;
+                let end = self.eval(e.range().end())?.as_integer()?;
+
+                let mut run = |i| {
+                    self.variables
+                        .push((e.variable().to_owned(), Value::Integer(i)));
+                    let rslt = self.eval(e.body());
+                    self.variables.pop();
+
+                    match rslt {
+                        Ok(_) | Err(Interrupt::Continue) => Ok(true),
+                        Err(Interrupt::Break) => Ok(false),
+                        Err(e) => Err(e),
+                    }
+                };
+
+                if e.range().is_inclusive() {
+                    for i in start..=end {
+                        if !run(i)? {
+                            break;
+                        }
+                    }
+                } else {
+                    for i in start..end {
+                        if !run(i)? {
+                            break;
+                        }
+                    }
+                }
+
+                Ok(Value::Unit)
+            }
+
+            ExprKind::Break(_) => Err(Interrupt::Break),
+            ExprKind::Continue(_) => Err(Interrupt::Continue),
+
+            ExprKind::Call(e) => {
+                let function = match e.callee() {
+                    ExprKind::Ident(ident) => self.functions.get(ident.name()).copied(),
+                    _ => None,
+                };
+                let function = function.ok_or_else(|| {
+                    Interrupt::Failed("Only `const fn`s can be called".to_owned())
+                })?;
+
+                let args = e
+                    .args()
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                self.call(function, args)
+            }
+
+            ExprKind::Panic(e) => Err(Interrupt::Failed(format!(
+                "Panicked with message `{}`",
+                e.message()
+            ))),
+
+            ExprKind::Assert(assert) => match assert.kind() {
+                AssertKind::True(condition) => match self.eval(condition)?.as_bool()? {
+                    true => Ok(Value::Unit),
+                    false => Err(Interrupt::Failed(assert.message())),
+                },
+                // Both operands have the same type, so comparing the values
+                // compares integers or booleans.
+                AssertKind::Eq(left, right) | AssertKind::EqBool(left, right) => {
+                    let left = self.eval(left)?;
+                    let right = self.eval(right)?;
+
+                    if left == right {
+                        Ok(Value::Unit)
+                    } else {
+                        Err(Interrupt::Failed(format!(
+                            "{}: left is `{}`, right is `{}`",
+                            assert.message(),
+                            left,
+                            right
+                        )))
+                    }
+                }
+            },
+
+            _ => Err(Interrupt::Failed(
+                "Expression can't be evaluated at compile time".to_owned(),
+            )),
+        }
+    }
+
+    fn eval_bindings(
+        &mut self,
+        statements: &[Statement],
+        ending: &ExprKind,
+    ) -> Result<Value, Interrupt> {
+        for stmt in statements {
+            match stmt {
+                Statement::Binding(b) => {
+                    let value = self.eval(b.value())?;
+                    self.variables.push((b.name().to_owned(), value));
+                }
+
+                Statement::Expr(e) => {
+                    self.eval(e)?;
+                }
+            }
+        }
+
+        self.eval(ending)
+    }
+
+    fn arithmetic(
+        &mut self,
+        left: &ExprKind,
+        right: &ExprKind,
+        symbol: &str,
+        operation: fn(i32, i32) -> Option<i32>,
+    ) -> Result<Value, Interrupt> {
+        let left = self.eval(left)?.as_integer()?;
+        let right = self.eval(right)?.as_integer()?;
+
+        operation(left, right).map(Value::Integer).ok_or_else(|| {
+            Interrupt::Failed(format!(
+                "Attempt to compute `{} {} {}`",
+                left, symbol, right
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::Span;
+
+    use super::*;
+
+    fn folded(input: &str) -> Result<Program, String> {
+        let (_, ast) = crate::parser::parse_input(input).unwrap();
+        let ctxt = TypingContext::new();
+
+        fold_program(&ast, &ctxt).map_err(|()| ctxt.errs().to_string())
+    }
+
+    fn main_body(ast: &Program) -> &ExprKind {
+        ast.functions()
+            .iter()
+            .find(|f| f.name() == "main")
+            .unwrap()
+            .body()
+    }
+
+    #[test]
+    fn calls_with_constant_arguments_are_evaluated() {
+        let ast = folded(
+            "const fn fib(n: int) -> int { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+             fn main() -> int { fib(10) }",
+        )
+        .unwrap();
+
+        assert_eq!(main_body(&ast), &ExprKind::integer(55));
+    }
+
+    #[test]
+    fn loops_and_variables() {
+        let ast = folded(
+            "const fn twice(n: int) -> int {
+                 for i in 0..=n { if i == 3 { break } else { continue } }
+                 let total = n + n;
+                 total
+             }
+             fn main() -> int { twice(100) }",
+        )
+        .unwrap();
+
+        assert_eq!(main_body(&ast), &ExprKind::integer(200));
+    }
+
+    #[test]
+    fn remainders() {
+        let ast = folded(
+            "const fn gcd(a: int, b: int) -> int { if b == 0 { a } else { gcd(b, a % b) } }
+             fn main() -> int { gcd(100000000, 1) }",
+        )
+        .unwrap();
+
+        assert_eq!(main_body(&ast), &ExprKind::integer(1));
+
+        let errs = folded(
+            "const fn rem(n: int) -> int { n % 0 }
+             fn main() -> int { rem(7) }",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errs,
+            "2:36: Failed to evaluate `rem(7)` at compile time: Attempt to compute `7 % 0`\n"
+        );
+    }
+
+    #[test]
+    fn other_calls_are_kept() {
+        let ast = folded(
+            "const fn double(n: int) -> int { n * 2 }
+             fn main(a: int) -> int { double(a) }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            main_body(&ast),
+            &ExprKind::call_at(
+                ExprKind::ident("double".to_owned()),
+                vec![ExprKind::ident("a".to_owned())],
+                Span::new(None, 2, 45)
+            )
+        );
+    }
+
+    #[test]
+    fn const_fns_only_call_const_fns() {
+        let errs = folded(
+            "fn one() -> int { 1 }
+             const fn f() -> int { one() }
+             const fn g(n: int) -> int { println(n); n }",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errs,
+            "`const fn` `f` calls `one`, which is not a `const fn`\n\
+             `const fn` `g` can't use `print`\n"
+        );
+    }
+
+    #[test]
+    fn evaluation_is_limited() {
+        let errs = folded(
+            "const fn forever(n: int) -> int { forever(n + 1) }
+             const fn slow(n: int) -> int {
+                 for i in 0..n { }
+                 n
+             }
+             fn main() -> int { forever(0) + slow(10000000) }",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errs,
+            "6:40: Failed to evaluate `forever(0)` at compile time: \
+             Reached the maximum call depth of 256\n\
+             6:50: Failed to evaluate `slow(10000000)` at compile time: \
+             Evaluation did not finish after 1000000 steps\n"
+        );
+    }
+
+    #[test]
+    fn calls_that_may_not_run_are_left_to_the_runtime() {
+        let ast = folded(
+            "const fn forever(n: int) -> int { forever(n + 1) }
+             fn main(a: int) -> int {
+                 for i in 0..a { forever(1); }
+                 let f = |x: int| forever(2);
+                 if a > 0 { forever(3) } else { f(a) }
+             }",
+        );
+
+        assert!(ast.is_ok());
+    }
+
+    #[test]
+    fn panics_are_reported() {
+        let errs = folded(
+            "const fn check(n: int) -> int { if n < 0 { panic(\"negative\") } else { n } }
+             fn main() -> int { check(0 - 1) + check(1) }",
+        );
+
+        // `0 - 1` is not a literal, so only `check(1)` is evaluated.
+        assert!(errs.is_ok());
+
+        let errs = folded(
+            "const fn check(n: int) -> int { if n > 0 { panic(\"positive\") } else { n } }
+             fn main() -> int { check(1) }",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errs,
+            "2:38: Failed to evaluate `check(1)` at compile time: \
+             Panicked with message `positive`\n"
+        );
+    }
+
+    #[test]
+    fn failed_assertions_are_reported() {
+        let errs = folded(
+            "const fn positive(n: int) -> int { assert(n > 0); n }
+             fn main() -> int { positive(0) }",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errs,
+            "2:41: Failed to evaluate `positive(0)` at compile time: Assertion failed at 1:36\n"
+        );
+    }
+
+    #[test]
+    fn failed_boolean_equality_assertions_show_booleans() {
+        let errs = folded(
+            "const fn check(b: bool) -> int { assert_eq(b, true); 0 }
+             fn main() -> int { check(false) }",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errs,
+            "2:38: Failed to evaluate `check(false)` at compile time: \
+             Assertion failed at 1:34: left is `false`, right is `true`\n"
+        );
+    }
+
+    #[test]
+    fn contract_violations_are_reported() {
+        let errs = folded(
+            "const fn half(n: int) -> int requires n >= 0 ensures result > 0 { n - 1 }
+             fn main() -> int { half(4) + half(0) }",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errs,
+            "2:47: Failed to evaluate `half(0)` at compile time: \
+             Contract violation: `half` ensures `result > 0`\n"
+        );
+    }
+}
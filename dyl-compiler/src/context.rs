@@ -1,15 +1,19 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{hash_map::Entry, HashMap, HashSet},
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
 };
 
 use anyhow::Error as AnyError;
 
-use dyl_bytecode::Instruction as ResolvedInstruction;
+use dyl_bytecode::{symbols::Symbols, Instruction as ResolvedInstruction};
 
-use crate::{instruction::Instruction, ty::Ty};
+use crate::{
+    ast::{Bound, ExprKind, Record, Trait},
+    instruction::Instruction,
+    ty::Ty,
+};
 
 pub(crate) fn resolve_labels(
     instructions: &[Instruction],
@@ -21,6 +25,7 @@ pub(crate) fn resolve_labels(
 #[derive(Clone, Debug, Default, PartialEq)]
 pub(crate) struct ParsingContext {
     errs: ErrorContext,
+    file: Option<String>,
 }
 
 impl ParsingContext {
@@ -28,10 +33,27 @@ impl ParsingContext {
         ParsingContext::default()
     }
 
+    /// Creates a context whose errors name the file being parsed.
+    pub(crate) fn for_file(file: String) -> ParsingContext {
+        ParsingContext {
+            file: Some(file),
+            ..ParsingContext::default()
+        }
+    }
+
     pub(crate) fn errors(&self) -> &ErrorContext {
         &self.errs
     }
 
+    pub(crate) fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Moves the errors of `other` to this context.
+    pub(crate) fn merge(&self, other: ParsingContext) {
+        self.errs.0.borrow_mut().extend(other.errs.0.into_inner());
+    }
+
     pub(crate) fn into_typing_context(self) -> TypingContext {
         let errs = self.errs;
 
@@ -49,7 +71,14 @@ impl ParsingContext {
 }
 
 #[derive(Clone, Debug, Default, PartialEq)]
-pub(crate) struct TypingContext(Vec<(String, Ty)>, ErrorContext);
+pub(crate) struct TypingContext(
+    Vec<(String, Ty)>,
+    ErrorContext,
+    HashMap<String, Ty>,
+    TraitContext,
+    HashMap<String, Record>,
+    Option<Ty>,
+);
 
 impl TypingContext {
     #[cfg(test)]
@@ -65,7 +94,50 @@ impl TypingContext {
         self.0
             .iter()
             .rev()
-            .find_map(|(binding, ty)| binding.eq(name).then(|| ty))
+            .find_map(|(binding, ty)| binding.eq(name).then_some(ty))
+    }
+
+    pub(crate) fn add_function(&mut self, name: String, ty: Ty) {
+        self.2.insert(name, ty);
+    }
+
+    pub(crate) fn resolve_function(&self, name: &str) -> Option<&Ty> {
+        self.2.get(name)
+    }
+
+    /// Resolves a name to the type of either a local binding or, when no
+    /// binding shadows it, a top-level function.
+    pub(crate) fn resolve_value(&self, name: &str) -> Option<&Ty> {
+        self.resolve_binding(name)
+            .or_else(|| self.resolve_function(name))
+    }
+
+    pub(crate) fn traits(&self) -> &TraitContext {
+        &self.3
+    }
+
+    pub(crate) fn traits_mut(&mut self) -> &mut TraitContext {
+        &mut self.3
+    }
+
+    pub(crate) fn add_record(&mut self, record: Record) {
+        self.4.insert(record.name().to_owned(), record);
+    }
+
+    pub(crate) fn resolve_record(&self, name: &str) -> Option<&Record> {
+        self.4.get(name)
+    }
+
+    /// Returns the return type of the function being checked, or `None`
+    /// inside a lambda.
+    pub(crate) fn return_ty(&self) -> Option<&Ty> {
+        self.5.as_ref()
+    }
+
+    /// Sets the return type of the function being checked, returning the
+    /// previous one.
+    pub(crate) fn set_return_ty(&mut self, ty: Option<Ty>) -> Option<Ty> {
+        std::mem::replace(&mut self.5, ty)
     }
 
     pub(crate) fn new_subcontext(&self) -> usize {
@@ -96,14 +168,87 @@ impl TypingContext {
     }
 }
 
+/// The traits of the program and the types that implement them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct TraitContext {
+    traits: HashMap<String, Trait>,
+    impls: Vec<(String, Ty)>,
+    function_bounds: HashMap<String, Vec<Bound>>,
+    /// The bounds of the function being checked.
+    bounds: Vec<Bound>,
+}
+
+impl TraitContext {
+    pub(crate) fn add_trait(&mut self, trait_: Trait) {
+        self.traits.insert(trait_.name().to_owned(), trait_);
+    }
+
+    pub(crate) fn resolve_trait(&self, name: &str) -> Option<&Trait> {
+        self.traits.get(name)
+    }
+
+    /// Returns the traits that declare a method named `method`.
+    pub(crate) fn traits_with_method<'a>(
+        &'a self,
+        method: &'a str,
+    ) -> impl Iterator<Item = &'a Trait> + 'a {
+        self.traits
+            .values()
+            .filter(move |trait_| trait_.method(method).is_some())
+    }
+
+    pub(crate) fn add_impl(&mut self, trait_: String, ty: Ty) {
+        self.impls.push((trait_, ty));
+    }
+
+    /// Returns whether `ty` implements `trait_`. Type parameters implement
+    /// the traits they are bound to in the function being checked.
+    pub(crate) fn implements(&self, ty: &Ty, trait_: &str) -> bool {
+        match ty {
+            Ty::Err => true,
+            Ty::Param(param) => self
+                .bounds
+                .iter()
+                .any(|b| b.param() == param && b.trait_() == trait_),
+            _ => self
+                .impls
+                .iter()
+                .any(|(t, implementor)| t == trait_ && implementor == ty),
+        }
+    }
+
+    pub(crate) fn add_function_bounds(&mut self, function: String, bounds: Vec<Bound>) {
+        self.function_bounds.insert(function, bounds);
+    }
+
+    pub(crate) fn function_bounds(&self, function: &str) -> &[Bound] {
+        self.function_bounds
+            .get(function)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn set_bounds(&mut self, bounds: Vec<Bound>) {
+        self.bounds = bounds;
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq)]
 pub(crate) struct LoweringContext {
     labels: LabelContext,
     stack: StackContext,
+    loops: LoopContext,
+    /// The `ensures` clauses of the function being lowered, along with the
+    /// message of the panic caused by their violation.
+    postconditions: Vec<(ExprKind, String)>,
+    functions: HashSet<String>,
+    host_functions: Vec<String>,
+    main_params: u16,
     errs: ErrorContext,
 }
 
 impl LoweringContext {
+    #[cfg(test)]
     pub(crate) fn labels(&self) -> &LabelContext {
         &self.labels
     }
@@ -120,6 +265,49 @@ impl LoweringContext {
         &mut self.stack
     }
 
+    pub(crate) fn loops(&self) -> &LoopContext {
+        &self.loops
+    }
+
+    pub(crate) fn loops_mut(&mut self) -> &mut LoopContext {
+        &mut self.loops
+    }
+
+    pub(crate) fn postconditions(&self) -> &[(ExprKind, String)] {
+        &self.postconditions
+    }
+
+    pub(crate) fn postconditions_mut(&mut self) -> &mut Vec<(ExprKind, String)> {
+        &mut self.postconditions
+    }
+
+    pub(crate) fn add_function(&mut self, name: String) {
+        self.functions.insert(name);
+    }
+
+    /// Returns whether `name` refers to a top-level function, ie: it is the
+    /// name of a function and is not shadowed by a variable.
+    pub(crate) fn is_function(&self, name: &str) -> bool {
+        self.functions.contains(name) && self.stack.resolve(name).is_none()
+    }
+
+    /// Returns the index of a host function, declaring it if needed.
+    pub(crate) fn host_function(&mut self, name: &str) -> u16 {
+        let idx = match self.host_functions.iter().position(|f| f == name) {
+            Some(idx) => idx,
+            None => {
+                self.host_functions.push(name.to_owned());
+                self.host_functions.len() - 1
+            }
+        };
+
+        idx as u16
+    }
+
+    pub(crate) fn set_main_params(&mut self, main_params: u16) {
+        self.main_params = main_params;
+    }
+
     pub(crate) fn errors(&self) -> &ErrorContext {
         &self.errs
     }
@@ -131,8 +319,20 @@ impl LoweringContext {
     }
 
     pub(crate) fn into_label_resolution_context(self) -> LabelResolutionContext {
-        let LoweringContext { errs, labels, .. } = self;
-        LabelResolutionContext { labels, errs }
+        let LoweringContext {
+            errs,
+            labels,
+            host_functions,
+            main_params,
+            ..
+        } = self;
+
+        LabelResolutionContext {
+            labels,
+            host_functions,
+            main_params,
+            errs,
+        }
     }
 
     #[cfg(test)]
@@ -144,6 +344,8 @@ impl LoweringContext {
 #[derive(Clone, Default, Debug, PartialEq)]
 pub(crate) struct LabelResolutionContext {
     labels: LabelContext,
+    host_functions: Vec<String>,
+    main_params: u16,
     errs: ErrorContext,
 }
 
@@ -152,12 +354,26 @@ impl LabelResolutionContext {
         &self.labels
     }
 
-    pub(crate) fn labels_mut(&mut self) -> &mut LabelContext {
-        &mut self.labels
-    }
+    /// Returns the address at which each function starts, so that the VM can
+    /// tell in which function a panic happened, the name of the host
+    /// functions, in index order, and the number of arguments of `main`.
+    pub(crate) fn symbols(&self) -> Symbols {
+        let mut symbols = self
+            .labels
+            .1
+            .iter()
+            .fold(Symbols::new(), |mut symbols, (name, addr)| {
+                symbols.add_function(*addr, name.clone());
+                symbols
+            });
 
-    pub(crate) fn errs(&self) -> &ErrorContext {
-        &self.errs
+        self.host_functions.iter().for_each(|name| {
+            symbols.add_host_function(name.clone());
+        });
+
+        symbols.set_main_params(self.main_params);
+
+        symbols
     }
 }
 
@@ -171,8 +387,14 @@ impl LabelContext {
         tmp as u32
     }
 
-    pub(crate) fn new_named(&mut self, name: String, pos: u32) {
-        self.1.insert(name, pos);
+    pub(crate) fn new_named(&mut self, name: String, pos: u32) -> Result<(), LabelDefinitionError> {
+        match self.1.entry(name) {
+            Entry::Vacant(entry) => {
+                entry.insert(pos);
+                Ok(())
+            }
+            Entry::Occupied(entry) => Err(LabelDefinitionError::AlreadyDefined(*entry.get())),
+        }
     }
 
     pub(crate) fn set_position(
@@ -201,7 +423,7 @@ impl LabelContext {
         self.1
             .get(label)
             .ok_or(LabelResolutionError::UnknownLabel)
-            .map(Clone::clone)
+            .copied()
     }
 
     #[cfg(test)]
@@ -249,7 +471,7 @@ impl StackContext {
             .iter()
             .rev()
             .enumerate()
-            .find_map(|(depth, var_name)| var_name.eq(name).then(|| depth as u16))
+            .find_map(|(depth, var_name)| var_name.eq(name).then_some(depth as u16))
     }
 
     pub(crate) fn new_subcontext(&self) -> usize {
@@ -260,12 +482,16 @@ impl StackContext {
         self.0.truncate(new_top);
     }
 
+    pub(crate) fn depth(&self) -> usize {
+        self.0.len()
+    }
+
     pub(crate) fn pop_top_anonymous(&mut self) -> Result<(), AnonymousPoppingError> {
         self.0
             .pop()
             .ok_or(AnonymousPoppingError::EmptyStack)?
             .is_empty()
-            .then(|| ())
+            .then_some(())
             .ok_or(AnonymousPoppingError::NotAnonymous)
     }
 
@@ -279,11 +505,6 @@ impl StackContext {
         self.0.push(name)
     }
 
-    #[cfg(test)]
-    pub(crate) fn depth(&self) -> usize {
-        self.0.len()
-    }
-
     #[cfg(test)]
     pub(crate) fn top(&self) -> Option<&str> {
         self.0.last().map(AsRef::as_ref)
@@ -295,6 +516,34 @@ impl StackContext {
     }
 }
 
+/// The loops that enclose the code being lowered, innermost last.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct LoopContext(Vec<LoopFrame>);
+
+impl LoopContext {
+    pub(crate) fn enter(&mut self, frame: LoopFrame) {
+        self.0.push(frame);
+    }
+
+    pub(crate) fn exit(&mut self) {
+        self.0.pop();
+    }
+
+    pub(crate) fn innermost(&self) -> Option<&LoopFrame> {
+        self.0.last()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct LoopFrame {
+    /// Label of the first instruction following the loop.
+    pub(crate) break_label: u32,
+    /// Label of the instructions that start the next iteration.
+    pub(crate) continue_label: u32,
+    /// Stack depth at the beginning of each iteration.
+    pub(crate) depth: usize,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub(crate) enum AnonymousNamingError {
     NoTopVariable,
@@ -308,11 +557,26 @@ pub(crate) enum AnonymousPoppingError {
 }
 
 #[derive(Clone, Debug, Default, PartialEq)]
-pub(crate) struct ErrorContext(RefCell<Vec<CompilationError>>);
+pub(crate) struct ErrorContext(RefCell<Vec<CompilationError>>, RefCell<Option<String>>);
 
 impl ErrorContext {
+    /// Adds an error, prefixed with the current file if any.
     pub(crate) fn add(&self, e: impl Into<CompilationError>) {
-        self.0.borrow_mut().push(e.into());
+        let e = match self.1.borrow().as_ref() {
+            Some(file) => CompilationError(format!("{}: {}", file, e.into().0)),
+            None => e.into(),
+        };
+
+        self.0.borrow_mut().push(e);
+    }
+
+    /// Sets the file that the next errors are about.
+    pub(crate) fn set_file(&self, file: Option<String>) {
+        *self.1.borrow_mut() = file;
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
     }
 
     fn emit_possible_errors<T>(&self, rslt: Result<T, ()>) -> Result<T, CompilerPassError> {
@@ -437,6 +701,17 @@ mod labels {
         );
     }
 
+    #[test]
+    fn named_label_when_already_defined() {
+        let mut ctxt = LabelContext::new();
+        ctxt.new_named("f".to_owned(), 12).unwrap();
+
+        assert_eq!(
+            ctxt.new_named("f".to_owned(), 20),
+            Err(LabelDefinitionError::AlreadyDefined(12))
+        );
+    }
+
     #[test]
     fn resolve_anonymous_defined() {
         let mut ctxt = LabelContext::new();
@@ -456,6 +731,23 @@ mod labels {
             Err(LabelResolutionError::UnknownLabelPosition)
         );
     }
+
+    #[test]
+    fn named_labels_are_symbols() {
+        let mut labels = LabelContext::new();
+        labels.new_named("main".to_owned(), 0).unwrap();
+        labels.new_named("f".to_owned(), 12).unwrap();
+        let _ = labels.new_anonymous();
+
+        let ctxt = LabelResolutionContext {
+            labels,
+            ..LabelResolutionContext::default()
+        };
+        let symbols = ctxt.symbols();
+
+        assert_eq!(symbols.function_at(3), Some("main"));
+        assert_eq!(symbols.function_at(14), Some("f"));
+    }
 }
 
 #[cfg(test)]
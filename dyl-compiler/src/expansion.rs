@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::{Call, ExprKind, Item, Macro},
+    context::ParsingContext,
+};
+
+/// The maximum number of nested expansions. Reaching it most likely means
+/// that a macro expands to an invocation of itself.
+const RECURSION_LIMIT: usize = 64;
+
+/// Expands the invocations of the macros declared in a source file, and
+/// removes the declarations of the macros.
+///
+/// Expansions are hygienic: the variables that a macro declares are renamed,
+/// so that they can't capture or shadow the variables of the caller.
+pub(crate) fn expand_macros(
+    items: Vec<Item>,
+    file: &str,
+    ctxt: &ParsingContext,
+) -> Result<Vec<Item>, ()> {
+    let mut expander = Expander {
+        macros: HashMap::new(),
+        file,
+        ctxt,
+        fresh_names: 0,
+        failed: false,
+    };
+
+    items.iter().for_each(|item| {
+        if let Item::Macro(macro_) = item {
+            expander.declare(macro_);
+        }
+    });
+
+    let items = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Function(f) => {
+                let body = expander.expand(f.body(), 0);
+                let f = f
+                    .with_name_and_body(f.name().to_owned(), body)
+                    .map_clauses(|condition| expander.expand(condition, 0));
+
+                Some(Item::Function(f))
+            }
+
+            Item::Impl(impl_, methods) => {
+                let methods = methods
+                    .iter()
+                    .map(|m| {
+                        let body = expander.expand(m.body(), 0);
+                        m.with_name_and_body(m.name().to_owned(), body)
+                            .map_clauses(|condition| expander.expand(condition, 0))
+                    })
+                    .collect();
+
+                Some(Item::Impl(impl_.clone(), methods))
+            }
+
+            Item::Macro(_) => None,
+
+            Item::Trait(_) | Item::Record(_) | Item::Extern(_) | Item::Mod(_) | Item::Use(_) => {
+                Some(item.clone())
+            }
+        })
+        .collect();
+
+    if expander.failed {
+        Err(())
+    } else {
+        Ok(items)
+    }
+}
+
+struct Expander<'a> {
+    macros: HashMap<&'a str, &'a Macro>,
+    file: &'a str,
+    ctxt: &'a ParsingContext,
+    fresh_names: usize,
+    failed: bool,
+}
+
+impl<'a> Expander<'a> {
+    fn declare(&mut self, macro_: &'a Macro) {
+        if self.macros.insert(macro_.name(), macro_).is_some() {
+            self.error(format!(
+                "Macro `{}` is declared multiple times",
+                macro_.name()
+            ));
+        }
+
+        macro_.rules().iter().for_each(|rule| {
+            let mut unknown = Vec::new();
+            rule.body().replace_free_idents(&mut |name| {
+                let metavariable = name.strip_prefix('$')?;
+                if !rule.params().iter().any(|param| param == metavariable) {
+                    unknown.push(name.to_owned());
+                }
+                None
+            });
+
+            unknown.into_iter().for_each(|name| {
+                self.error(format!(
+                    "Unknown metavariable `{}` in macro `{}!`",
+                    name,
+                    macro_.name()
+                ))
+            });
+        });
+    }
+
+    fn expand(&mut self, expr: &ExprKind, depth: usize) -> ExprKind {
+        expr.map_calls(&mut |call| self.expand_call(call, depth))
+    }
+
+    /// Expands a call whose callee is a macro, such as `square!(x)`. The
+    /// arguments are expected to be expanded already.
+    fn expand_call(&mut self, call: Call, depth: usize) -> ExprKind {
+        let name = match call.callee() {
+            ExprKind::Ident(callee) => callee.name().strip_suffix('!'),
+            _ => None,
+        };
+
+        // After an error, expanding further would only report the same
+        // error again, possibly a huge number of times.
+        let name = match name {
+            Some(name) if !self.failed => name,
+            _ => return ExprKind::Call(call),
+        };
+
+        let macro_ = match self.macros.get(name) {
+            Some(macro_) => *macro_,
+            None => {
+                self.error(format!("Unknown macro `{}!`", name));
+                return ExprKind::Call(call);
+            }
+        };
+
+        if depth == RECURSION_LIMIT {
+            self.error(format!(
+                "Recursion limit reached while expanding `{}!`",
+                name
+            ));
+            return ExprKind::Call(call);
+        }
+
+        let rule = match macro_
+            .rules()
+            .iter()
+            .find(|rule| rule.params().len() == call.args().len())
+        {
+            Some(rule) => rule,
+            None => {
+                self.error(format!(
+                    "No rule of macro `{}!` takes {} arguments",
+                    name,
+                    call.args().len()
+                ));
+                return ExprKind::Call(call);
+            }
+        };
+
+        // The variables of the macro are renamed before substituting the
+        // arguments, so that the variables of the arguments are left as is.
+        let fresh_names = &mut self.fresh_names;
+        let body = rule.body().rename_bound_idents(&mut |name| {
+            *fresh_names += 1;
+            format!("{}#{}", name, fresh_names)
+        });
+
+        let args = rule
+            .params()
+            .iter()
+            .map(|param| format!("${}", param))
+            .zip(call.args())
+            .collect::<HashMap<_, _>>();
+        let body = body.replace_free_idents(&mut |name| args.get(name).map(|&arg| arg.clone()));
+
+        self.expand(&body, depth + 1)
+    }
+
+    fn error(&mut self, message: String) {
+        self.ctxt
+            .errors()
+            .add(format!("{}: {}", self.file, message));
+        self.failed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{ast::Program, parser};
+
+    fn expanded(input: &str) -> Result<Program, String> {
+        let ctxt = ParsingContext::new();
+        let items = parser::parse_items(input, &ctxt).unwrap();
+
+        expand_macros(items, "main.dyl", &ctxt)
+            .map(parser::program_from_items)
+            .map_err(|()| ctxt.errors().to_string())
+    }
+
+    fn main_body(input: &str) -> ExprKind {
+        let ast = expanded(input).unwrap();
+        let main = ast.functions().iter().find(|f| f.name() == "main");
+
+        main.unwrap().body().clone()
+    }
+
+    #[test]
+    fn arguments_are_substituted() {
+        let body = main_body(
+            "macro_rules! square { ($x:expr) => { $x * $x }; }
+             fn main() -> int { square!(1 + 2) }",
+        );
+
+        let sum = || ExprKind::addition(ExprKind::integer(1), ExprKind::integer(2));
+        assert_eq!(body, ExprKind::multiplication(sum(), sum()));
+    }
+
+    #[test]
+    fn rules_are_chosen_by_arity() {
+        let body = main_body(
+            "macro_rules! add {
+                 () => { 0 };
+                 ($a:expr) => { $a };
+                 ($a:expr, $b:expr) => { $a + add!($b) };
+             }
+             fn main() -> int { add!() + add!(1, 2) }",
+        );
+
+        let sum = ExprKind::addition(ExprKind::integer(1), ExprKind::integer(2));
+        assert_eq!(body, ExprKind::addition(ExprKind::integer(0), sum));
+    }
+
+    #[test]
+    fn macro_variables_do_not_capture_arguments() {
+        let body = main_body(
+            "macro_rules! double { ($e:expr) => { let x = $e; x + x }; }
+             fn main() -> int { let x = 1; double!(x + 1) }",
+        );
+
+        let ident = |name: &str| ExprKind::ident(name.to_owned());
+        let renamed = ExprKind::single_binding(
+            "x#1".to_owned(),
+            ExprKind::addition(ident("x"), ExprKind::integer(1)),
+            ExprKind::addition(ident("x#1"), ident("x#1")),
+        );
+
+        assert_eq!(
+            body,
+            ExprKind::single_binding("x".to_owned(), ExprKind::integer(1), renamed)
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let errors = |input| expanded(input).unwrap_err();
+
+        assert_eq!(
+            errors("fn main() -> int { square!(1) }"),
+            "main.dyl: Unknown macro `square!`\n"
+        );
+        assert_eq!(
+            errors(
+                "macro_rules! one { () => { 1 }; }
+                 fn main() -> int { one!(1) }"
+            ),
+            "main.dyl: No rule of macro `one!` takes 1 arguments\n"
+        );
+        assert_eq!(
+            errors("macro_rules! bad { ($x:expr) => { $y }; }"),
+            "main.dyl: Unknown metavariable `$y` in macro `bad!`\n"
+        );
+        assert_eq!(
+            errors(
+                "macro_rules! one { () => { 1 }; }
+                 macro_rules! one { () => { 1 }; }"
+            ),
+            "main.dyl: Macro `one` is declared multiple times\n"
+        );
+        assert_eq!(
+            errors(
+                "macro_rules! forever { () => { forever!() }; }
+                 fn main() -> int { forever!() }"
+            ),
+            "main.dyl: Recursion limit reached while expanding `forever!`\n"
+        );
+    }
+}
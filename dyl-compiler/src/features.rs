@@ -0,0 +1,288 @@
+use std::collections::HashSet;
+
+use crate::ast::{ExprKind, Function, Program, Statement};
+
+/// Something that a function uses besides integer and boolean arithmetic,
+/// local variables and control flow.
+///
+/// The passes that restrict what some functions can do, such as `const fn`s
+/// or `#[memo]` functions, decide which features they accept.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Feature<'a> {
+    /// A direct call to a function of the program.
+    Call(&'a str),
+    /// A call to an extern function.
+    ExternCall(&'a str),
+    /// A call through a local variable or any other expression.
+    IndirectCall,
+    MethodCall,
+    Lambda,
+    Record,
+    OptionOrResult,
+    Collection,
+    Print,
+    ReadInt,
+}
+
+impl Feature<'_> {
+    /// Describes the feature, as in "can't use {}".
+    pub(crate) fn description(&self) -> String {
+        match self {
+            Feature::Call(name) => format!("the function `{}`", name),
+            Feature::ExternCall(name) => format!("the extern function `{}`", name),
+            Feature::IndirectCall => "indirect calls".to_owned(),
+            Feature::MethodCall => "method calls".to_owned(),
+            Feature::Lambda => "lambdas".to_owned(),
+            Feature::Record => "records".to_owned(),
+            Feature::OptionOrResult => "options and results".to_owned(),
+            Feature::Collection => "lists and maps".to_owned(),
+            Feature::Print => "`print`".to_owned(),
+            Feature::ReadInt => "`read_int`".to_owned(),
+        }
+    }
+}
+
+/// Lists the features used by the functions of a program.
+pub(crate) struct FeatureFinder<'a> {
+    functions: HashSet<&'a str>,
+    externs: HashSet<&'a str>,
+}
+
+impl<'a> FeatureFinder<'a> {
+    pub(crate) fn new(ast: &'a Program) -> FeatureFinder<'a> {
+        let functions = ast.functions().iter().map(Function::name).collect();
+        let externs = ast.externs().iter().map(|e| e.name()).collect();
+
+        FeatureFinder { functions, externs }
+    }
+
+    /// Returns the features used by the body and the clauses of `function`,
+    /// in the order in which they appear.
+    pub(crate) fn features(&self, function: &'a Function) -> Vec<Feature<'a>> {
+        let mut features = Vec::new();
+        let mut bound = function
+            .params()
+            .iter()
+            .map(|p| p.name())
+            .collect::<Vec<_>>();
+
+        self.visit_expr(function.body(), &mut bound, &mut features);
+        function
+            .clauses()
+            .iter()
+            .for_each(|clause| self.visit_expr(clause.condition(), &mut bound, &mut features));
+
+        features
+    }
+
+    fn visit_expr(
+        &self,
+        expr: &'a ExprKind,
+        bound: &mut Vec<&'a str>,
+        features: &mut Vec<Feature<'a>>,
+    ) {
+        match expr {
+            ExprKind::Addition(e) => {
+                self.visit_expr(e.left(), bound, features);
+                self.visit_expr(e.right(), bound, features);
+            }
+            ExprKind::Subtraction(e) => {
+                self.visit_expr(e.left(), bound, features);
+                self.visit_expr(e.right(), bound, features);
+            }
+            ExprKind::Multiplication(e) => {
+                self.visit_expr(e.left(), bound, features);
+                self.visit_expr(e.right(), bound, features);
+            }
+            ExprKind::Remainder(e) => {
+                self.visit_expr(e.left(), bound, features);
+                self.visit_expr(e.right(), bound, features);
+            }
+            ExprKind::Comparison(e) => {
+                self.visit_expr(e.left(), bound, features);
+                self.visit_expr(e.right(), bound, features);
+            }
+            ExprKind::If(e) => {
+                self.visit_expr(e.condition(), bound, features);
+                self.visit_expr(e.consequent(), bound, features);
+                self.visit_expr(e.alternative(), bound, features);
+            }
+            ExprKind::Bindings(e) => {
+                let scope = bound.len();
+
+                e.statements().iter().for_each(|stmt| match stmt {
+                    Statement::Binding(b) => {
+                        self.visit_expr(b.value(), bound, features);
+                        bound.push(b.name());
+                    }
+                    Statement::Expr(e) => self.visit_expr(e, bound, features),
+                });
+                self.visit_expr(e.ending_expression(), bound, features);
+
+                bound.truncate(scope);
+            }
+            ExprKind::For(e) => {
+                self.visit_expr(e.range().start(), bound, features);
+                self.visit_expr(e.range().end(), bound, features);
+
+                bound.push(e.variable());
+                self.visit_expr(e.body(), bound, features);
+                bound.pop();
+            }
+            ExprKind::Call(e) => {
+                let feature = match e.callee() {
+                    // Local variables shadow functions.
+                    ExprKind::Ident(callee) if bound.contains(&callee.name()) => {
+                        Feature::IndirectCall
+                    }
+                    ExprKind::Ident(callee) if self.functions.contains(callee.name()) => {
+                        Feature::Call(callee.name())
+                    }
+                    ExprKind::Ident(callee) if self.externs.contains(callee.name()) => {
+                        Feature::ExternCall(callee.name())
+                    }
+                    _ => Feature::IndirectCall,
+                };
+                features.push(feature);
+
+                if !matches!(e.callee(), ExprKind::Ident(_)) {
+                    self.visit_expr(e.callee(), bound, features);
+                }
+                e.args()
+                    .iter()
+                    .for_each(|arg| self.visit_expr(arg, bound, features));
+            }
+            ExprKind::MethodCall(e) => {
+                features.push(Feature::MethodCall);
+
+                self.visit_expr(e.receiver(), bound, features);
+                e.args()
+                    .iter()
+                    .for_each(|arg| self.visit_expr(arg, bound, features));
+            }
+            ExprKind::Lambda(e) => {
+                features.push(Feature::Lambda);
+
+                let scope = bound.len();
+                bound.extend(e.params().iter().map(|p| p.name()));
+                self.visit_expr(e.body(), bound, features);
+                bound.truncate(scope);
+            }
+            ExprKind::RecordLiteral(e) => {
+                features.push(Feature::Record);
+
+                e.fields()
+                    .iter()
+                    .for_each(|(_, value)| self.visit_expr(value, bound, features));
+            }
+            ExprKind::FieldAccess(e) => {
+                features.push(Feature::Record);
+
+                self.visit_expr(e.record(), bound, features);
+            }
+            ExprKind::Variant(e) => {
+                features.push(Feature::OptionOrResult);
+
+                if let Some(payload) = e.payload() {
+                    self.visit_expr(payload, bound, features);
+                }
+            }
+            ExprKind::Match(e) => {
+                features.push(Feature::OptionOrResult);
+
+                self.visit_expr(e.scrutinee(), bound, features);
+                e.arms().iter().for_each(|arm| {
+                    let scope = bound.len();
+                    bound.extend(arm.pattern().binding());
+                    self.visit_expr(arm.body(), bound, features);
+                    bound.truncate(scope);
+                });
+            }
+            ExprKind::Try(e) => {
+                features.push(Feature::OptionOrResult);
+
+                self.visit_expr(e.operand(), bound, features);
+            }
+            ExprKind::List(e) => {
+                features.push(Feature::Collection);
+
+                e.elements()
+                    .iter()
+                    .for_each(|element| self.visit_expr(element, bound, features));
+            }
+            ExprKind::Builtin(e) => {
+                features.push(Feature::Collection);
+
+                e.args()
+                    .iter()
+                    .for_each(|arg| self.visit_expr(arg, bound, features));
+            }
+            ExprKind::Print(e) => {
+                features.push(Feature::Print);
+
+                if let Some(arg) = e.arg().expr() {
+                    self.visit_expr(arg, bound, features);
+                }
+            }
+            ExprKind::ReadInt(_) => features.push(Feature::ReadInt),
+            ExprKind::Assert(e) => e
+                .operands()
+                .into_iter()
+                .for_each(|operand| self.visit_expr(operand, bound, features)),
+            ExprKind::Integer(_)
+            | ExprKind::Bool(_)
+            | ExprKind::Unit(_)
+            | ExprKind::Ident(_)
+            | ExprKind::Break(_)
+            | ExprKind::Continue(_)
+            | ExprKind::Panic(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(input: &str) -> Vec<String> {
+        let (_, ast) = crate::parser::parse_input(input).unwrap();
+        let finder = FeatureFinder::new(&ast);
+        let main = ast.functions().iter().find(|f| f.name() == "main").unwrap();
+
+        finder
+            .features(main)
+            .iter()
+            .map(Feature::description)
+            .collect()
+    }
+
+    #[test]
+    fn features_are_listed_in_order() {
+        let features = features(
+            "extern fn now() -> int;
+             fn one() -> int { 1 }
+             fn main() -> int { let f = |x: int| x + now(); println(f(one())); 0 }",
+        );
+
+        assert_eq!(
+            features,
+            [
+                "lambdas",
+                "the extern function `now`",
+                "`print`",
+                "indirect calls",
+                "the function `one`",
+            ]
+        );
+    }
+
+    #[test]
+    fn local_variables_shadow_functions() {
+        let features = features(
+            "fn one() -> int { 1 }
+             fn main() -> int { let one = |x: int| x; one(2) }",
+        );
+
+        assert_eq!(features, ["lambdas", "indirect calls"]);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::ast::{ClauseKind, ExprKind, Function, Lambda, Param, Statement, RESULT};
+
+/// The variables a lambda or a function refers to without defining them.
+///
+/// Variables are listed in order of first appearance. They may refer either
+/// to local variables of the enclosing function or to top-level functions.
+/// Telling them apart is up to the caller.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct FreeVariables {
+    used: Vec<String>,
+    methods: Vec<String>,
+}
+
+impl FreeVariables {
+    pub(crate) fn of_lambda(lambda: &Lambda) -> FreeVariables {
+        FreeVariables::of_body(lambda.params(), lambda.body())
+    }
+
+    /// Returns the free variables of the body of a function and of its
+    /// clauses, in which `result` is bound when they are `ensures` clauses.
+    pub(crate) fn of_function(function: &Function) -> FreeVariables {
+        let mut free = FreeVariables::of_body(function.params(), function.body());
+
+        function.clauses().iter().for_each(|clause| {
+            let mut bound = function
+                .params()
+                .iter()
+                .map(|p| p.name().to_owned())
+                .collect::<Vec<_>>();
+            if clause.kind() == ClauseKind::Ensures {
+                bound.push(RESULT.to_owned());
+            }
+
+            free.visit_expr(clause.condition(), &mut bound);
+        });
+
+        free
+    }
+
+    fn of_body(params: &[Param], body: &ExprKind) -> FreeVariables {
+        let mut free = FreeVariables::default();
+        let mut bound = params.iter().map(|p| p.name().to_owned()).collect();
+
+        free.visit_expr(body, &mut bound);
+
+        free
+    }
+
+    pub(crate) fn used(&self) -> &[String] {
+        self.used.as_slice()
+    }
+
+    /// Returns the names of the methods that are called.
+    pub(crate) fn methods(&self) -> &[String] {
+        self.methods.as_slice()
+    }
+
+    fn use_(&mut self, name: &str, bound: &[String]) {
+        let is_free = !bound.iter().any(|b| b == name);
+        let is_known = self.used.iter().any(|u| u == name);
+
+        if is_free && !is_known {
+            self.used.push(name.to_owned());
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &ExprKind, bound: &mut Vec<String>) {
+        match expr {
+            ExprKind::Addition(e) => {
+                self.visit_expr(e.left(), bound);
+                self.visit_expr(e.right(), bound);
+            }
+            ExprKind::Subtraction(e) => {
+                self.visit_expr(e.left(), bound);
+                self.visit_expr(e.right(), bound);
+            }
+            ExprKind::Multiplication(e) => {
+                self.visit_expr(e.left(), bound);
+                self.visit_expr(e.right(), bound);
+            }
+            ExprKind::Remainder(e) => {
+                self.visit_expr(e.left(), bound);
+                self.visit_expr(e.right(), bound);
+            }
+            ExprKind::If(e) => {
+                self.visit_expr(e.condition(), bound);
+                self.visit_expr(e.consequent(), bound);
+                self.visit_expr(e.alternative(), bound);
+            }
+            ExprKind::Bindings(e) => {
+                let scope = bound.len();
+
+                e.statements()
+                    .iter()
+                    .for_each(|stmt| self.visit_statement(stmt, bound));
+                self.visit_expr(e.ending_expression(), bound);
+
+                bound.truncate(scope);
+            }
+            ExprKind::Ident(e) => self.use_(e.name(), bound),
+            ExprKind::For(e) => {
+                self.visit_expr(e.range().start(), bound);
+                self.visit_expr(e.range().end(), bound);
+
+                bound.push(e.variable().to_owned());
+                self.visit_expr(e.body(), bound);
+                bound.pop();
+            }
+            ExprKind::Call(e) => {
+                self.visit_expr(e.callee(), bound);
+                e.args().iter().for_each(|arg| self.visit_expr(arg, bound));
+            }
+            ExprKind::MethodCall(e) => {
+                if !self.methods.iter().any(|m| m == e.method()) {
+                    self.methods.push(e.method().to_owned());
+                }
+
+                self.visit_expr(e.receiver(), bound);
+                e.args().iter().for_each(|arg| self.visit_expr(arg, bound));
+            }
+            ExprKind::Lambda(e) => {
+                let scope = bound.len();
+
+                bound.extend(e.params().iter().map(|p| p.name().to_owned()));
+                self.visit_expr(e.body(), bound);
+
+                bound.truncate(scope);
+            }
+            ExprKind::RecordLiteral(e) => e
+                .fields()
+                .iter()
+                .for_each(|(_, value)| self.visit_expr(value, bound)),
+            ExprKind::FieldAccess(e) => self.visit_expr(e.record(), bound),
+            ExprKind::Variant(e) => {
+                if let Some(payload) = e.payload() {
+                    self.visit_expr(payload, bound);
+                }
+            }
+            ExprKind::Match(e) => {
+                self.visit_expr(e.scrutinee(), bound);
+
+                e.arms().iter().for_each(|arm| {
+                    let scope = bound.len();
+
+                    bound.extend(arm.pattern().binding().map(str::to_owned));
+                    self.visit_expr(arm.body(), bound);
+
+                    bound.truncate(scope);
+                });
+            }
+            ExprKind::Try(e) => self.visit_expr(e.operand(), bound),
+            ExprKind::Comparison(e) => {
+                self.visit_expr(e.left(), bound);
+                self.visit_expr(e.right(), bound);
+            }
+            ExprKind::List(e) => e
+                .elements()
+                .iter()
+                .for_each(|element| self.visit_expr(element, bound)),
+            ExprKind::Builtin(e) => e.args().iter().for_each(|arg| self.visit_expr(arg, bound)),
+            ExprKind::Assert(e) => e
+                .operands()
+                .into_iter()
+                .for_each(|operand| self.visit_expr(operand, bound)),
+            ExprKind::Print(e) => {
+                if let Some(arg) = e.arg().expr() {
+                    self.visit_expr(arg, bound);
+                }
+            }
+            ExprKind::Integer(_)
+            | ExprKind::Bool(_)
+            | ExprKind::Unit(_)
+            | ExprKind::Break(_)
+            | ExprKind::Continue(_)
+            | ExprKind::Panic(_)
+            | ExprKind::ReadInt(_) => {}
+        }
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement, bound: &mut Vec<String>) {
+        match stmt {
+            Statement::Binding(b) => {
+                self.visit_expr(b.value(), bound);
+                bound.push(b.name().to_owned());
+            }
+            Statement::Expr(e) => self.visit_expr(e, bound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::Binding, ty::Ty};
+
+    use super::*;
+
+    fn lambda(params: &[&str], body: ExprKind) -> Lambda {
+        let params = params
+            .iter()
+            .map(|p| Param::new(p.to_string(), Ty::Int))
+            .collect();
+
+        Lambda::new(params, body)
+    }
+
+    fn ident(name: &str) -> ExprKind {
+        ExprKind::ident(name.to_owned())
+    }
+
+    #[test]
+    fn params_are_not_free() {
+        let l = lambda(&["x"], ExprKind::multiplication(ident("x"), ident("k")));
+
+        assert_eq!(FreeVariables::of_lambda(&l).used(), ["k"]);
+    }
+
+    #[test]
+    fn local_bindings_are_not_free() {
+        let body = ExprKind::statements(
+            vec![Statement::Binding(Binding::new("a".to_owned(), ident("b")))],
+            ExprKind::addition(ident("a"), ident("b")),
+        );
+        let l = lambda(&[], body);
+
+        assert_eq!(FreeVariables::of_lambda(&l).used(), ["b"]);
+    }
+
+    #[test]
+    fn nested_lambdas_propagate_free_variables() {
+        let inner = ExprKind::lambda(
+            vec![Param::new("y".to_owned(), Ty::Int)],
+            ExprKind::addition(ident("x"), ExprKind::addition(ident("y"), ident("z"))),
+        );
+        let l = lambda(&["x"], inner);
+
+        assert_eq!(FreeVariables::of_lambda(&l).used(), ["z"]);
+    }
+}
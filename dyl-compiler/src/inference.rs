@@ -0,0 +1,1137 @@
+//! Hindley-Milner type inference.
+//!
+//! This pass runs before the type checker. It infers the type of every
+//! parameter and return type that has not been annotated, and writes them in
+//! the AST. Functions are processed callees first, so that a function which
+//! works for any type can be used at different types by its callers: the
+//! remaining type variables of its signature become type parameters, which
+//! are then handled by the monomorphization pass.
+//!
+//! Method calls can only be inferred once the type of their receiver is
+//! known, either because it is a concrete type or a type parameter bound to
+//! a trait.
+//!
+//! Only the errors that the type checker can't see are reported there:
+//! infinite types, and types that nothing constrains. Mismatches are left to
+//! the type checker, which reports them on the annotated AST.
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::{
+        self, AssertKind, Bound, ClauseKind, Constructor, ExprKind, Function, Impl, Operator,
+        Param, Program, Record, Statement, Trait, RESULT,
+    },
+    context::{PassResult, TypingContext},
+    free_vars::FreeVariables,
+    ty::{Substitution, SubstitutionError, Ty},
+};
+
+pub(crate) fn infer_types(
+    ast: &Program,
+    ctxt: TypingContext,
+) -> PassResult<TypingContext, Program> {
+    let (program, errors) = infer_program(ast);
+
+    errors.iter().for_each(|(function, error)| {
+        ctxt.errs()
+            .set_file(ast.file_of(function).map(str::to_owned));
+        ctxt.errs().add(error.as_str());
+    });
+    ctxt.errs().set_file(None);
+
+    ctxt.wrap_result(Ok(program))
+}
+
+/// Annotates the program, and returns the errors found in each function.
+fn infer_program(ast: &Program) -> (Program, Vec<(String, String)>) {
+    let mut inferer = Inferer::new(ast);
+
+    components(ast)
+        .into_iter()
+        .for_each(|component| inferer.infer_component(&component));
+
+    let functions = ast
+        .functions()
+        .iter()
+        .map(|function| inferer.annotate(function))
+        .collect();
+
+    (ast.with_functions(functions), inferer.errors)
+}
+
+/// The inferred annotations of a function.
+#[derive(Clone, Debug, PartialEq)]
+struct Annotations {
+    generics: Vec<String>,
+    params: Vec<Ty>,
+    ret_ty: Ty,
+    /// The parameter types of each lambda, in order of appearance.
+    lambdas: Vec<Vec<Ty>>,
+}
+
+/// A possibly generic function signature.
+#[derive(Clone, Debug, PartialEq)]
+struct Scheme {
+    generics: Vec<String>,
+    ty: Ty,
+}
+
+#[derive(Default)]
+struct Inferer {
+    substitution: Substitution,
+    next_var: u32,
+    locals: Vec<(String, Ty)>,
+    schemes: HashMap<String, Scheme>,
+    lambdas: Vec<Vec<Ty>>,
+    annotations: HashMap<String, Annotations>,
+    traits: Vec<Trait>,
+    impls: Vec<Impl>,
+    /// The signature that the trait requires for each method implementation.
+    methods: HashMap<String, Ty>,
+    /// The bounds of the function being inferred.
+    bounds: Vec<Bound>,
+    records: Vec<Record>,
+    /// The return type of the function being inferred, which `?` returns
+    /// from. It is `None` inside lambdas.
+    ret: Option<Ty>,
+    /// The name of the function being inferred.
+    function: String,
+    /// The errors found so far, along with the function they are in.
+    errors: Vec<(String, String)>,
+}
+
+impl Inferer {
+    fn new(ast: &Program) -> Inferer {
+        let mut inferer = Inferer {
+            traits: ast.traits().to_vec(),
+            impls: ast.impls().to_vec(),
+            records: ast.records().to_vec(),
+            ..Inferer::default()
+        };
+
+        ast.impls().iter().for_each(|impl_| {
+            let trait_ = ast
+                .traits()
+                .iter()
+                .find(|t| Some(t.name()) == impl_.trait_());
+
+            impl_.methods().iter().for_each(|name| {
+                if let Some(method) = trait_.and_then(|t| t.method(name)) {
+                    let function = ast::method_name(impl_.trait_(), impl_.ty(), name);
+                    inferer.methods.insert(function, method.ty_for(impl_.ty()));
+                }
+            });
+        });
+
+        // Fully annotated functions can be called from anywhere with their
+        // declared signature.
+        ast.functions()
+            .iter()
+            .filter(|f| is_fully_annotated(f))
+            .for_each(|f| {
+                let params = f.params().iter().map(|p| p.ty().clone()).collect();
+                let ret = f.ret_ty().cloned().unwrap_or(Ty::Err);
+
+                let scheme = Scheme {
+                    generics: f.generics().to_vec(),
+                    ty: Ty::Function(params, Box::new(ret)),
+                };
+                inferer.schemes.insert(f.name().to_owned(), scheme);
+            });
+
+        ast.externs().iter().for_each(|e| {
+            let scheme = Scheme {
+                generics: Vec::new(),
+                ty: e.ty(),
+            };
+            inferer.schemes.insert(e.name().to_owned(), scheme);
+        });
+
+        inferer
+    }
+
+    fn fresh_var(&mut self) -> Ty {
+        let var = self.next_var;
+        self.next_var += 1;
+        Ty::Var(var)
+    }
+
+    fn annotation_or_fresh(&mut self, annotation: Option<&Ty>) -> Ty {
+        match annotation {
+            Some(Ty::Unannotated) | None => self.fresh_var(),
+            Some(ty) => ty.clone(),
+        }
+    }
+
+    fn unify(&mut self, lhs: &Ty, rhs: &Ty) {
+        // Mismatches are reported by the type checker, which can't see
+        // infinite types.
+        if let Err(e @ SubstitutionError::Infinite(..)) = self.substitution.unify(lhs, rhs) {
+            let function = self.function.clone();
+            self.report(&function, format!("{} in `{}`", e, function));
+        }
+    }
+
+    fn report(&mut self, function: &str, error: String) {
+        self.errors.push((function.to_owned(), error));
+    }
+
+    /// Infers the types of a group of mutually recursive functions.
+    fn infer_component(&mut self, component: &[&Function]) {
+        // Functions of the same component can't be used at different types by
+        // each other.
+        let signatures = component
+            .iter()
+            .map(|f| {
+                let params = f
+                    .params()
+                    .iter()
+                    .map(|p| self.annotation_or_fresh(Some(p.ty())))
+                    .collect::<Vec<_>>();
+                let ret = self.annotation_or_fresh(f.ret_ty());
+
+                if let Some(expected) = self.methods.get(f.name()).cloned() {
+                    let found = Ty::Function(params.clone(), Box::new(ret.clone()));
+                    self.unify(&found, &expected);
+                }
+
+                if !is_fully_annotated(f) {
+                    let scheme = Scheme {
+                        generics: Vec::new(),
+                        ty: Ty::Function(params.clone(), Box::new(ret.clone())),
+                    };
+                    self.schemes.insert(f.name().to_owned(), scheme);
+                }
+
+                (params, ret)
+            })
+            .collect::<Vec<_>>();
+
+        let lambdas = component
+            .iter()
+            .zip(&signatures)
+            .map(|(f, (params, ret))| {
+                self.locals = f
+                    .params()
+                    .iter()
+                    .zip(params)
+                    .map(|(p, ty)| (p.name().to_owned(), ty.clone()))
+                    .collect();
+                self.bounds = f.bounds().to_vec();
+                self.ret = Some(ret.clone());
+                self.function = f.name().to_owned();
+
+                let body_ty = self.infer(f.body());
+                self.unify(ret, &body_ty);
+
+                f.clauses().iter().for_each(|clause| {
+                    let scope = self.locals.len();
+                    if clause.kind() == ClauseKind::Ensures {
+                        self.locals.push((RESULT.to_owned(), ret.clone()));
+                    }
+
+                    let condition = self.infer(clause.condition());
+                    self.unify(&condition, &Ty::Bool);
+                    self.locals.truncate(scope);
+                });
+
+                std::mem::take(&mut self.lambdas)
+            })
+            .collect::<Vec<_>>();
+
+        component
+            .iter()
+            .zip(signatures)
+            .zip(lambdas)
+            .for_each(|((f, (params, ret)), lambdas)| self.generalize(f, params, ret, lambdas));
+    }
+
+    /// Turns the type variables of the parameters of a function into type
+    /// parameters, and stores its annotations.
+    fn generalize(&mut self, function: &Function, params: Vec<Ty>, ret: Ty, lambdas: Vec<Vec<Ty>>) {
+        let params = params
+            .iter()
+            .map(|p| self.substitution.apply(p))
+            .collect::<Vec<_>>();
+        let ret = self.substitution.apply(&ret);
+
+        let mut generics = function.generics().to_vec();
+        let mut generalization = Substitution::default();
+
+        params.iter().for_each(|param| {
+            param.for_each_var(&mut |var| {
+                if generalization.apply(&Ty::Var(var)) != Ty::Var(var) {
+                    return;
+                }
+
+                let name = (0..)
+                    .map(|idx| format!("T{}", idx))
+                    .find(|name| !generics.contains(name))
+                    .unwrap();
+
+                generalization
+                    .unify(&Ty::Var(var), &Ty::Param(name.clone()))
+                    .unwrap();
+                generics.push(name);
+            })
+        });
+
+        // Anything that is still unknown is reported when annotating the
+        // function.
+        let finalize = |ty: &Ty| generalization.apply(ty);
+
+        let params = params.iter().map(finalize).collect::<Vec<_>>();
+        let ret = match finalize(&ret) {
+            // Nothing constrains the return type of functions that never
+            // return, such as those that always panic.
+            Ty::Var(_) => Ty::Never,
+            ret => ret,
+        };
+
+        let lambdas = lambdas
+            .iter()
+            .map(|params| {
+                params
+                    .iter()
+                    .map(|p| finalize(&self.substitution.apply(p)))
+                    .collect()
+            })
+            .collect();
+
+        if !is_fully_annotated(function) {
+            let scheme = Scheme {
+                generics: generics.clone(),
+                ty: Ty::Function(params.clone(), Box::new(ret.clone())),
+            };
+            self.schemes.insert(function.name().to_owned(), scheme);
+        }
+
+        let annotations = Annotations {
+            generics,
+            params,
+            ret_ty: ret,
+            lambdas,
+        };
+        self.annotations
+            .insert(function.name().to_owned(), annotations);
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Ty {
+        let substitution = scheme
+            .generics
+            .iter()
+            .map(|generic| (generic.clone(), self.fresh_var()))
+            .collect();
+
+        scheme.ty.substitute(&substitution)
+    }
+
+    fn infer(&mut self, expr: &ExprKind) -> Ty {
+        match expr {
+            ExprKind::Addition(e) => self.infer_operator(e),
+            ExprKind::Subtraction(e) => self.infer_operator(e),
+            ExprKind::Multiplication(e) => self.infer_operator(e),
+            ExprKind::Remainder(e) => self.infer_operator(e),
+
+            ExprKind::Comparison(e) => {
+                let left = self.infer(e.left());
+                self.unify(&left, &Ty::Int);
+                let right = self.infer(e.right());
+                self.unify(&right, &Ty::Int);
+
+                Ty::Bool
+            }
+
+            ExprKind::Integer(_) | ExprKind::ReadInt(_) => Ty::Int,
+            ExprKind::Bool(_) => Ty::Bool,
+            ExprKind::Unit(_) | ExprKind::Break(_) | ExprKind::Continue(_) => Ty::Unit,
+
+            // A panic can stand for a value of any type.
+            ExprKind::Panic(_) => self.fresh_var(),
+
+            ExprKind::List(e) => {
+                let element = self.fresh_var();
+                e.elements().iter().for_each(|e| {
+                    let ty = self.infer(e);
+                    self.unify(&element, &ty);
+                });
+
+                Ty::List(Box::new(element))
+            }
+
+            ExprKind::Builtin(e) => {
+                let signature = e.function().signature();
+                let mut generics = Vec::new();
+                signature.for_each_param(&mut |name| generics.push(name.to_owned()));
+                let signature = self.instantiate(&Scheme {
+                    generics,
+                    ty: signature,
+                });
+
+                let args = e.args().iter().map(|arg| self.infer(arg)).collect();
+                let ret = self.fresh_var();
+
+                self.unify(&signature, &Ty::Function(args, Box::new(ret.clone())));
+
+                ret
+            }
+
+            ExprKind::Assert(e) => {
+                match e.kind() {
+                    AssertKind::True(condition) => {
+                        let condition = self.infer(condition);
+                        self.unify(&condition, &Ty::Bool);
+                    }
+                    // Whether the operands are integers or booleans is left
+                    // to the type checker.
+                    AssertKind::Eq(left, right) | AssertKind::EqBool(left, right) => {
+                        let left = self.infer(left);
+                        let right = self.infer(right);
+                        self.unify(&left, &right);
+                    }
+                }
+
+                Ty::Unit
+            }
+
+            // Whether the printed value is an integer or a boolean is checked
+            // by the type checker.
+            ExprKind::Print(e) => {
+                if let Some(arg) = e.arg().expr() {
+                    self.infer(arg);
+                }
+
+                Ty::Unit
+            }
+
+            ExprKind::If(e) => {
+                let condition = self.infer(e.condition());
+                self.unify(&condition, &Ty::Bool);
+
+                let consequent = self.infer(e.consequent());
+                let alternative = self.infer(e.alternative());
+                self.unify(&consequent, &alternative);
+
+                consequent
+            }
+
+            ExprKind::Bindings(e) => {
+                let scope = self.locals.len();
+
+                e.statements().iter().for_each(|stmt| match stmt {
+                    Statement::Binding(b) => {
+                        let ty = self.infer(b.value());
+                        self.locals.push((b.name().to_owned(), ty));
+                    }
+
+                    Statement::Expr(e) => {
+                        self.infer(e);
+                    }
+                });
+
+                let ty = self.infer(e.ending_expression());
+                self.locals.truncate(scope);
+
+                ty
+            }
+
+            ExprKind::Ident(e) => match self.resolve_local(e.name()) {
+                Some(ty) => ty,
+                None => match self.schemes.get(e.name()).cloned() {
+                    Some(scheme) => self.instantiate(&scheme),
+                    None => Ty::Err,
+                },
+            },
+
+            ExprKind::For(e) => {
+                let start = self.infer(e.range().start());
+                let end = self.infer(e.range().end());
+                self.unify(&start, &Ty::Int);
+                self.unify(&end, &Ty::Int);
+
+                self.locals.push((e.variable().to_owned(), Ty::Int));
+                let body = self.infer(e.body());
+                self.unify(&body, &Ty::Unit);
+                self.locals.pop();
+
+                Ty::Unit
+            }
+
+            ExprKind::Call(e) => {
+                let callee = self.infer(e.callee());
+                let args = e.args().iter().map(|arg| self.infer(arg)).collect();
+                let ret = self.fresh_var();
+
+                self.unify(&callee, &Ty::Function(args, Box::new(ret.clone())));
+
+                ret
+            }
+
+            ExprKind::MethodCall(e) => {
+                let receiver = self.infer(e.receiver());
+                let args = e.args().iter().map(|arg| self.infer(arg));
+                let params = std::iter::once(receiver.clone()).chain(args).collect();
+                let ret = self.fresh_var();
+
+                let receiver = self.substitution.apply(&receiver);
+                if let Some(signature) = self.method_signature(&receiver, e.method()) {
+                    self.unify(&signature, &Ty::Function(params, Box::new(ret.clone())));
+                }
+
+                ret
+            }
+
+            ExprKind::Lambda(e) => {
+                let params = e
+                    .params()
+                    .iter()
+                    .map(|p| self.annotation_or_fresh(Some(p.ty())))
+                    .collect::<Vec<_>>();
+
+                // Lambdas are numbered before their body is visited.
+                let idx = self.lambdas.len();
+                self.lambdas.push(Vec::new());
+
+                let scope = self.locals.len();
+                self.locals.extend(
+                    e.params()
+                        .iter()
+                        .zip(&params)
+                        .map(|(p, ty)| (p.name().to_owned(), ty.clone())),
+                );
+                let outer_ret = self.ret.take();
+                let body = self.infer(e.body());
+                self.ret = outer_ret;
+                self.locals.truncate(scope);
+
+                self.lambdas[idx] = params.clone();
+
+                Ty::Function(params, Box::new(body))
+            }
+
+            ExprKind::RecordLiteral(e) => {
+                let record = self.resolve_record(e.name()).cloned();
+
+                e.fields().iter().for_each(|(name, value)| {
+                    let ty = self.infer(value);
+                    if let Some((_, field_ty)) = record.as_ref().and_then(|r| r.field(name)) {
+                        self.unify(&ty, field_ty);
+                    }
+                });
+
+                match record {
+                    Some(_) => Ty::Record(e.name().to_owned()),
+                    None => Ty::Err,
+                }
+            }
+
+            ExprKind::FieldAccess(e) => {
+                let record = self.infer(e.record());
+
+                // Just like in OCaml, a record of unknown type is assumed to
+                // be the only record that has the field.
+                if let Ty::Var(_) = self.substitution.apply(&record) {
+                    let mut candidates =
+                        self.records.iter().filter(|r| r.field(e.field()).is_some());
+
+                    if let (Some(candidate), None) = (candidates.next(), candidates.next()) {
+                        let ty = Ty::Record(candidate.name().to_owned());
+                        self.unify(&record, &ty);
+                    }
+                }
+
+                match self.substitution.apply(&record) {
+                    Ty::Record(name) => self
+                        .resolve_record(&name)
+                        .and_then(|r| r.field(e.field()))
+                        .map(|(_, ty)| ty.clone())
+                        .unwrap_or(Ty::Err),
+                    _ => self.fresh_var(),
+                }
+            }
+
+            ExprKind::Variant(e) => {
+                let payload = match e.payload() {
+                    Some(payload) => self.infer(payload),
+                    None => Ty::Unit,
+                };
+
+                e.constructor().ty(payload, || self.fresh_var())
+            }
+
+            ExprKind::Match(e) => {
+                let scrutinee = self.infer(e.scrutinee());
+                let ty = self.fresh_var();
+
+                e.arms().iter().for_each(|arm| {
+                    let constructor = arm.pattern().constructor();
+                    let payload = self.fresh_var();
+                    let expected = constructor.ty(payload.clone(), || self.fresh_var());
+                    self.unify(&scrutinee, &expected);
+
+                    let scope = self.locals.len();
+                    if let Some(name) = arm.pattern().binding() {
+                        self.locals.push((name.to_owned(), payload));
+                    }
+                    let body = self.infer(arm.body());
+                    self.locals.truncate(scope);
+
+                    self.unify(&ty, &body);
+                });
+
+                ty
+            }
+
+            ExprKind::Try(e) => {
+                let operand = self.infer(e.operand());
+                let payload = self.fresh_var();
+
+                // When nothing is known about the operand, the return type of
+                // the function tells whether it is an `Option` or a `Result`.
+                let ret = self.ret.as_ref().map(|ret| self.substitution.apply(ret));
+                let is_result = match self.substitution.apply(&operand) {
+                    Ty::Result(..) => true,
+                    Ty::Var(_) => matches!(ret, Some(Ty::Result(..))),
+                    _ => false,
+                };
+
+                let (success, failure, error) = if is_result {
+                    (Constructor::Ok, Constructor::Err, self.fresh_var())
+                } else {
+                    (Constructor::Some, Constructor::None, Ty::Unit)
+                };
+
+                let expected = success.ty(payload.clone(), || error.clone());
+                self.unify(&operand, &expected);
+
+                if let Some(ret) = ret {
+                    let returned = failure.ty(error, || self.fresh_var());
+                    self.unify(&ret, &returned);
+                }
+
+                payload
+            }
+        }
+    }
+
+    /// Operands of a type that implements the trait of the operator are
+    /// passed to its method. Anything else is considered as an integer.
+    fn infer_operator<O: Operator>(&mut self, operator: &O) -> Ty {
+        let (left, right) = operator.operands();
+        let left = self.infer(left);
+        let right = self.infer(right);
+
+        let receiver = self.substitution.apply(&left);
+        let trait_ = self.traits.iter().find(|t| t.name() == O::TRAIT);
+
+        match trait_.filter(|t| self.implements(&receiver, t)) {
+            Some(trait_) => {
+                let signature = trait_.method(O::METHOD).unwrap().ty_for(&receiver);
+                let ret = self.fresh_var();
+                let call = Ty::Function(vec![left, right], Box::new(ret.clone()));
+                self.unify(&signature, &call);
+
+                ret
+            }
+
+            None => {
+                self.unify(&left, &Ty::Int);
+                self.unify(&right, &Ty::Int);
+
+                Ty::Int
+            }
+        }
+    }
+
+    fn resolve_record(&self, name: &str) -> Option<&Record> {
+        self.records.iter().find(|r| r.name() == name)
+    }
+
+    fn implements(&self, ty: &Ty, trait_: &Trait) -> bool {
+        match ty {
+            Ty::Param(param) => self
+                .bounds
+                .iter()
+                .any(|b| b.param() == param && b.trait_() == trait_.name()),
+            Ty::Var(_) | Ty::Err => false,
+            _ => self
+                .impls
+                .iter()
+                .any(|impl_| impl_.trait_() == Some(trait_.name()) && impl_.ty() == ty),
+        }
+    }
+
+    /// Returns the type of `method` for `receiver`, if it can be found.
+    fn method_signature(&mut self, receiver: &Ty, method: &str) -> Option<Ty> {
+        if !matches!(receiver, Ty::Var(_) | Ty::Err) {
+            let inherent = ast::method_name(None, receiver, method);
+
+            if let Some(scheme) = self.schemes.get(&inherent).cloned() {
+                return Some(self.instantiate(&scheme));
+            }
+        }
+
+        let mut candidates = self
+            .traits
+            .iter()
+            .filter(|trait_| trait_.method(method).is_some() && self.implements(receiver, trait_));
+
+        match (candidates.next(), candidates.next()) {
+            (Some(trait_), None) => Some(trait_.method(method)?.ty_for(receiver)),
+            _ => None,
+        }
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<Ty> {
+        self.locals
+            .iter()
+            .rev()
+            .find_map(|(local, ty)| (local == name).then(|| ty.clone()))
+    }
+
+    /// Writes the inferred annotations in a function, reporting the types
+    /// that could not be inferred.
+    fn annotate(&mut self, function: &Function) -> Function {
+        let annotations = match self.annotations.get(function.name()) {
+            Some(annotations) => annotations.clone(),
+            None => return function.clone(),
+        };
+
+        let params = function
+            .params()
+            .iter()
+            .zip(&annotations.params)
+            .map(|(param, ty)| Param::new(param.name().to_owned(), ty.clone()))
+            .collect();
+
+        // Lambdas are visited in the body first, then in the clauses.
+        let mut lambdas = annotations.lambdas.iter();
+        let mut unknown = Vec::new();
+        let body = annotate_lambdas(function.body(), &mut lambdas, &mut unknown);
+        let function = function
+            .with_signature_and_body(
+                annotations.generics.clone(),
+                params,
+                Some(annotations.ret_ty.clone()),
+                body,
+            )
+            .map_clauses(|condition| annotate_lambdas(condition, &mut lambdas, &mut unknown));
+
+        // An infinite type leaves unknown types behind, which would only
+        // repeat the error.
+        if self.errors.iter().any(|(f, _)| f == function.name()) {
+            return function;
+        }
+
+        unknown.iter().for_each(|param| {
+            let error = format!(
+                "Type of parameter `{}` of a lambda in `{}` can't be inferred",
+                param,
+                function.name()
+            );
+            self.report(function.name(), error);
+        });
+
+        if function.ret_ty().is_some_and(has_vars) {
+            let error = format!("Return type of `{}` can't be inferred", function.name());
+            self.report(function.name(), error);
+        }
+
+        function
+    }
+}
+
+fn is_fully_annotated(function: &Function) -> bool {
+    function.ret_ty().is_some() && function.params().iter().all(|p| p.ty() != &Ty::Unannotated)
+}
+
+fn has_vars(ty: &Ty) -> bool {
+    let mut has_vars = false;
+    ty.for_each_var(&mut |_| has_vars = true);
+    has_vars
+}
+
+/// Sets the parameter types of the lambdas of `expr`, in the order they were
+/// visited during inference. The parameters whose type is still unknown are
+/// added to `unknown`.
+fn annotate_lambdas<'a>(
+    expr: &ExprKind,
+    lambdas: &mut impl Iterator<Item = &'a Vec<Ty>>,
+    unknown: &mut Vec<String>,
+) -> ExprKind {
+    expr.map_lambda_params(&mut |params| {
+        let tys = lambdas.next().cloned().unwrap_or_default();
+
+        params
+            .iter()
+            .zip(tys.into_iter().map(Some).chain(std::iter::repeat(None)))
+            .map(|(param, ty)| match (param.ty(), ty) {
+                (Ty::Unannotated, Some(ty)) => {
+                    if has_vars(&ty) {
+                        unknown.push(param.name().to_owned());
+                    }
+
+                    Param::new(param.name().to_owned(), ty)
+                }
+                _ => param.clone(),
+            })
+            .collect()
+    })
+}
+
+/// Splits the functions of a program in groups of mutually recursive
+/// functions, callees first.
+fn components(ast: &Program) -> Vec<Vec<&Function>> {
+    let functions = ast.functions();
+    let indices = functions
+        .iter()
+        .enumerate()
+        .map(|(idx, f)| (f.name(), idx))
+        .collect::<HashMap<_, _>>();
+
+    // A method call may refer to any implementation of that method.
+    let mut implementations = HashMap::<&str, Vec<usize>>::new();
+    ast.impls().iter().for_each(|impl_| {
+        impl_.methods().iter().for_each(|method| {
+            let name = ast::method_name(impl_.trait_(), impl_.ty(), method);
+            if let Some(idx) = indices.get(name.as_str()) {
+                implementations.entry(method).or_default().push(*idx);
+            }
+        })
+    });
+
+    let callees = functions
+        .iter()
+        .map(|f| {
+            let free = FreeVariables::of_function(f);
+
+            let functions = free
+                .used()
+                .iter()
+                .filter_map(|name| indices.get(name.as_str()).copied());
+            let methods = free
+                .methods()
+                .iter()
+                .flat_map(|method| implementations.get(method.as_str()))
+                .flatten()
+                .copied();
+
+            functions.chain(methods).collect()
+        })
+        .collect::<Vec<Vec<usize>>>();
+
+    let mut tarjan = Tarjan::new(callees);
+    (0..functions.len()).for_each(|idx| tarjan.visit(idx));
+
+    tarjan
+        .components
+        .into_iter()
+        .map(|component| component.into_iter().map(|idx| &functions[idx]).collect())
+        .collect()
+}
+
+/// Tarjan's strongly connected components algorithm. Components are found
+/// in reverse topological order, which is exactly what we need.
+struct Tarjan {
+    edges: Vec<Vec<usize>>,
+    index: Vec<Option<usize>>,
+    low_link: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next_index: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan {
+    fn new(edges: Vec<Vec<usize>>) -> Tarjan {
+        let len = edges.len();
+
+        Tarjan {
+            edges,
+            index: vec![None; len],
+            low_link: vec![0; len],
+            on_stack: vec![false; len],
+            stack: Vec::new(),
+            next_index: 0,
+            components: Vec::new(),
+        }
+    }
+
+    fn visit(&mut self, node: usize) {
+        if self.index[node].is_some() {
+            return;
+        }
+
+        self.index[node] = Some(self.next_index);
+        self.low_link[node] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        for succ in self.edges[node].clone() {
+            match self.index[succ] {
+                None => {
+                    self.visit(succ);
+                    self.low_link[node] = self.low_link[node].min(self.low_link[succ]);
+                }
+                Some(succ_index) if self.on_stack[succ] => {
+                    self.low_link[node] = self.low_link[node].min(succ_index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.low_link[node]) == self.index[node] {
+            let mut component = Vec::new();
+
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                component.push(member);
+
+                if member == node {
+                    break;
+                }
+            }
+
+            // Keep the declaration order within a component.
+            component.sort_unstable();
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer(input: &str) -> Program {
+        let (_, ast) = crate::parser::parse_input(input).unwrap();
+        infer_program(&ast).0
+    }
+
+    fn check(input: &str) -> String {
+        let (_, ast) = crate::parser::parse_input(input).unwrap();
+        let (_, errors) = infer_program(&ast);
+
+        errors
+            .iter()
+            .map(|(_, error)| format!("{}\n", error))
+            .collect()
+    }
+
+    fn function<'a>(ast: &'a Program, name: &str) -> &'a Function {
+        ast.functions().iter().find(|f| f.name() == name).unwrap()
+    }
+
+    fn param_tys(function: &Function) -> Vec<Ty> {
+        function.params().iter().map(|p| p.ty().clone()).collect()
+    }
+
+    fn t(idx: usize) -> Ty {
+        Ty::Param(format!("T{}", idx))
+    }
+
+    #[test]
+    fn unify_binds_variables() {
+        let mut substitution = Substitution::default();
+
+        substitution
+            .unify(
+                &Ty::Function(vec![Ty::Var(0)], Box::new(Ty::Var(1))),
+                &Ty::Function(vec![Ty::Int], Box::new(Ty::Var(0))),
+            )
+            .unwrap();
+
+        assert_eq!(substitution.apply(&Ty::Var(1)), Ty::Int);
+    }
+
+    #[test]
+    fn unify_occurs_check() {
+        let mut substitution = Substitution::default();
+        let recursive = Ty::Function(vec![Ty::Var(0)], Box::new(Ty::Int));
+
+        assert!(substitution.unify(&Ty::Var(0), &recursive).is_err());
+    }
+
+    #[test]
+    fn unify_mismatch() {
+        let mut substitution = Substitution::default();
+
+        substitution.unify(&Ty::Var(0), &Ty::Int).unwrap();
+        assert!(substitution.unify(&Ty::Var(0), &Ty::Bool).is_err());
+    }
+
+    #[test]
+    fn params_and_return_type() {
+        let ast = infer("fn add(a, b) { a + b } fn main() { add(1, 2) }");
+        let add = function(&ast, "add");
+
+        assert_eq!(param_tys(add), [Ty::Int, Ty::Int]);
+        assert_eq!(add.ret_ty(), Some(&Ty::Int));
+        assert!(add.generics().is_empty());
+    }
+
+    #[test]
+    fn types_flow_from_callees() {
+        let ast = infer("fn not(b) { if b { false } else { true } } fn f(x) { not(x) }");
+
+        assert_eq!(param_tys(function(&ast, "f")), [Ty::Bool]);
+        assert_eq!(function(&ast, "f").ret_ty(), Some(&Ty::Bool));
+    }
+
+    #[test]
+    fn mutually_recursive_functions() {
+        let ast =
+            infer("fn f(n, b) { if b { g(n, false) } else { n } } fn g(n, b) { f(n + 1, b) }");
+
+        assert_eq!(param_tys(function(&ast, "f")), [Ty::Int, Ty::Bool]);
+        assert_eq!(param_tys(function(&ast, "g")), [Ty::Int, Ty::Bool]);
+    }
+
+    #[test]
+    fn unconstrained_params_are_generalized() {
+        let ast = infer("fn id(x) { x } fn main() { if id(true) { id(1) } else { 2 } }");
+        let id = function(&ast, "id");
+
+        assert_eq!(id.generics(), ["T0"]);
+        assert_eq!(param_tys(id), [t(0)]);
+        assert_eq!(id.ret_ty(), Some(&t(0)));
+    }
+
+    #[test]
+    fn generalization_skips_declared_generics() {
+        let ast = infer("fn pick<T0>(a: T0, b) -> T0 { a }");
+        let pick = function(&ast, "pick");
+
+        assert_eq!(pick.generics(), ["T0", "T1"]);
+        assert_eq!(param_tys(pick), [t(0), t(1)]);
+    }
+
+    #[test]
+    fn higher_order_functions() {
+        let ast = infer("fn apply(f, x) { f(x) + 1 }");
+
+        assert_eq!(
+            param_tys(function(&ast, "apply")),
+            [Ty::Function(vec![t(0)], Box::new(Ty::Int)), t(0)]
+        );
+    }
+
+    #[test]
+    fn lambda_params() {
+        let ast = infer("fn main() { let f = |x| x + 1; f(2) }");
+
+        let lambda = match function(&ast, "main").body() {
+            ExprKind::Bindings(b) => match &b.statements()[0] {
+                Statement::Binding(b) => b.value().clone(),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            lambda,
+            ExprKind::lambda(
+                vec![Param::new("x".to_owned(), Ty::Int)],
+                ExprKind::addition(ExprKind::ident("x".to_owned()), ExprKind::integer(1)),
+            )
+        );
+    }
+
+    #[test]
+    fn ill_typed_functions_are_left_to_the_checker() {
+        let ast = infer("fn f(x) { if x { x + 1 } else { 0 } }");
+
+        assert_eq!(function(&ast, "f").ret_ty(), Some(&Ty::Int));
+        assert_eq!(param_tys(function(&ast, "f")), [Ty::Bool]);
+    }
+
+    #[test]
+    fn method_calls() {
+        let ast = infer(
+            "trait Score { fn score(self, bonus: int) -> bool; }
+             impl Score for int { fn score(self, bonus) { true } }
+             fn f(b) { if 1.score(b) { 1 } else { 2 } }",
+        );
+
+        assert_eq!(param_tys(function(&ast, "f")), [Ty::Int]);
+        assert_eq!(function(&ast, "f").ret_ty(), Some(&Ty::Int));
+
+        let method = function(&ast, "<int as Score>::score");
+        assert_eq!(param_tys(method), [Ty::Int, Ty::Int]);
+        assert!(method.generics().is_empty());
+    }
+
+    #[test]
+    fn inherent_method_calls() {
+        let ast = infer(
+            "fn f(x: bool) { x.twice() }
+             impl bool { fn twice(self) { 2 } }",
+        );
+
+        assert_eq!(function(&ast, "f").ret_ty(), Some(&Ty::Int));
+    }
+
+    #[test]
+    fn field_access_on_unknown_record() {
+        let ast = infer("struct P { x: int } fn f(p) { p.x }");
+        let f = function(&ast, "f");
+
+        assert_eq!(param_tys(f), [Ty::Record("P".to_owned())]);
+        assert_eq!(f.ret_ty(), Some(&Ty::Int));
+    }
+
+    #[test]
+    fn overloaded_operators() {
+        let ast = infer(
+            "struct P { x: int }
+             impl Add for P { fn add(self, other: P) -> P { self } }
+             fn f(p: P, q) { p + q }",
+        );
+        let f = function(&ast, "f");
+
+        assert_eq!(param_tys(f)[1], Ty::Record("P".to_owned()));
+        assert_eq!(f.ret_ty(), Some(&Ty::Record("P".to_owned())));
+    }
+
+    #[test]
+    fn options() {
+        let ast =
+            infer("fn f(a, b) { let x = a?; match b { Some(y) => Some(x + y), None => None } }");
+        let f = function(&ast, "f");
+        let option_int = Ty::Option(Box::new(Ty::Int));
+
+        assert_eq!(param_tys(f), [option_int.clone(), option_int.clone()]);
+        assert_eq!(f.ret_ty(), Some(&option_int));
+    }
+
+    #[test]
+    fn functions_that_never_return() {
+        let ast = infer("fn fail(x) { panic(\"no\") } fn forever(n) { forever(n) }");
+
+        assert_eq!(function(&ast, "fail").ret_ty(), Some(&Ty::Never));
+        assert_eq!(function(&ast, "forever").ret_ty(), Some(&Ty::Never));
+    }
+
+    #[test]
+    fn infinite_types() {
+        let errs = check("fn main() -> int { let f = |x| x; let g = f(f); g }");
+
+        assert_eq!(errs, "Infinite type `?0 = fn(?0) -> ?0` in `main`\n");
+    }
+
+    #[test]
+    fn unknown_types() {
+        let errs = check(
+            "fn none() { None }
+             fn main() -> int { let f = |x| 1; 0 }",
+        );
+
+        assert_eq!(
+            errs,
+            "Return type of `none` can't be inferred\n\
+             Type of parameter `x` of a lambda in `main` can't be inferred\n"
+        );
+    }
+}
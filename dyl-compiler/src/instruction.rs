@@ -1,9 +1,12 @@
 use dyl_bytecode::operations as resolved_operations;
 use dyl_bytecode::Instruction as ResolvedInstruction;
 
-use crate::context::{LabelResolutionContext, Resolvable};
+use crate::{
+    ast::BuiltinFunction,
+    context::{LabelResolutionContext, Resolvable},
+};
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Instruction {
     PushI(PushI),
     AddI(AddI),
@@ -14,8 +17,38 @@ pub(crate) enum Instruction {
     Goto(Goto),
     PopCopy(PopCopy),
     Pop(Pop),
+    Cmp(Cmp),
     PushCopy(PushCopy),
     Ret(Ret),
+    ResV(ResV),
+    Call(Call),
+    PushFn(PushFn),
+    CallIndirect(CallIndirect),
+    MakeClosure(MakeClosure),
+    MakeRecord(MakeRecord),
+    GetField(GetField),
+    Panic(Panic),
+    Rem(Rem),
+    HostCall(HostCall),
+    PrintI(PrintI),
+    PrintB(PrintB),
+    PrintS(PrintS),
+    ReadI(ReadI),
+    MakeList(MakeList),
+    Cons(Cons),
+    Head(Head),
+    Tail(Tail),
+    IsEmpty(IsEmpty),
+    EmptyMap(EmptyMap),
+    MapInsert(MapInsert),
+    MapGet(MapGet),
+    MapRemove(MapRemove),
+    MapContains(MapContains),
+    Assert(Assert),
+    AssertEq(AssertEq),
+    AssertEqB(AssertEqB),
+    MemoLoad(MemoLoad),
+    MemoStore(MemoStore),
 }
 
 macro_rules! map_instruction {
@@ -30,8 +63,38 @@ macro_rules! map_instruction {
             Instruction::Mul($name) => $do,
             Instruction::PopCopy($name) => $do,
             Instruction::Pop($name) => $do,
+            Instruction::Cmp($name) => $do,
             Instruction::PushCopy($name) => $do,
             Instruction::Ret($name) => $do,
+            Instruction::ResV($name) => $do,
+            Instruction::Call($name) => $do,
+            Instruction::PushFn($name) => $do,
+            Instruction::CallIndirect($name) => $do,
+            Instruction::MakeClosure($name) => $do,
+            Instruction::MakeRecord($name) => $do,
+            Instruction::GetField($name) => $do,
+            Instruction::Panic($name) => $do,
+            Instruction::Rem($name) => $do,
+            Instruction::HostCall($name) => $do,
+            Instruction::PrintI($name) => $do,
+            Instruction::PrintB($name) => $do,
+            Instruction::PrintS($name) => $do,
+            Instruction::ReadI($name) => $do,
+            Instruction::MakeList($name) => $do,
+            Instruction::Cons($name) => $do,
+            Instruction::Head($name) => $do,
+            Instruction::Tail($name) => $do,
+            Instruction::IsEmpty($name) => $do,
+            Instruction::EmptyMap($name) => $do,
+            Instruction::MapInsert($name) => $do,
+            Instruction::MapGet($name) => $do,
+            Instruction::MapRemove($name) => $do,
+            Instruction::MapContains($name) => $do,
+            Instruction::Assert($name) => $do,
+            Instruction::AssertEq($name) => $do,
+            Instruction::AssertEqB($name) => $do,
+            Instruction::MemoLoad($name) => $do,
+            Instruction::MemoStore($name) => $do,
         }
     };
 }
@@ -48,7 +111,12 @@ macro_rules! impl_from_variants {
     };
 }
 
-impl_from_variants! { PushI, AddI, FStop, Neg, CondJmp, Goto, Mul, PopCopy, Pop, PushCopy, Ret }
+impl_from_variants! {
+    PushI, AddI, FStop, Neg, CondJmp, Goto, Mul, PopCopy, Pop, Cmp, PushCopy, Ret, ResV, Call,
+    PushFn, CallIndirect, MakeClosure, MakeRecord, GetField, Panic, Rem, HostCall, PrintI, PrintB,
+    PrintS, ReadI, MakeList, Cons, Head, Tail, IsEmpty, EmptyMap, MapInsert, MapGet, MapRemove,
+    MapContains, Assert, AssertEq, AssertEqB, MemoLoad, MemoStore,
+}
 
 impl Instruction {
     pub(crate) fn push_i(i: i32) -> Instruction {
@@ -79,6 +147,10 @@ impl Instruction {
         Instruction::Mul(Mul)
     }
 
+    pub(crate) fn cmp() -> Instruction {
+        Instruction::Cmp(Cmp)
+    }
+
     pub(crate) fn pop_copy(offset: u16) -> Instruction {
         Instruction::PopCopy(PopCopy(offset))
     }
@@ -91,11 +163,107 @@ impl Instruction {
         Instruction::PushCopy(PushCopy(offset))
     }
 
-    pub(crate) fn ret() -> Instruction {
-        // Note: our current functions always return a 1-sized value. As such,
-        // we don't handle complex situations where the stack must be shrunk of
-        // a variable amount depending on the output type size.
-        Instruction::Ret(Ret)
+    pub(crate) fn ret(frame_size: u16) -> Instruction {
+        // Note: our current functions always return a 1-sized value and all
+        // the parameters are 1-sized. As such, we don't handle complex
+        // situations where the stack must be shrunk of a variable amount
+        // depending on the input and output type sizes.
+        Instruction::Ret(Ret(frame_size))
+    }
+
+    pub(crate) fn res_v(size: u16) -> Instruction {
+        Instruction::ResV(ResV(size))
+    }
+
+    pub(crate) fn call(function: String) -> Instruction {
+        Instruction::Call(Call(function))
+    }
+
+    pub(crate) fn push_fn(function: String) -> Instruction {
+        Instruction::PushFn(PushFn(function))
+    }
+
+    pub(crate) fn call_indirect() -> Instruction {
+        Instruction::CallIndirect(CallIndirect)
+    }
+
+    pub(crate) fn make_closure(label: u32, captures: u16) -> Instruction {
+        Instruction::MakeClosure(MakeClosure(label, captures))
+    }
+
+    pub(crate) fn make_record(fields: u16) -> Instruction {
+        Instruction::MakeRecord(MakeRecord(fields))
+    }
+
+    pub(crate) fn get_field(idx: u16) -> Instruction {
+        Instruction::GetField(GetField(idx))
+    }
+
+    pub(crate) fn panic(len: u16) -> Instruction {
+        Instruction::Panic(Panic(len))
+    }
+
+    pub(crate) fn rem() -> Instruction {
+        Instruction::Rem(Rem)
+    }
+
+    pub(crate) fn assert(len: u16) -> Instruction {
+        Instruction::Assert(Assert(len))
+    }
+
+    pub(crate) fn assert_eq(len: u16) -> Instruction {
+        Instruction::AssertEq(AssertEq(len))
+    }
+
+    pub(crate) fn assert_eq_b(len: u16) -> Instruction {
+        Instruction::AssertEqB(AssertEqB(len))
+    }
+
+    pub(crate) fn memo_load(function: String, params: u16, hit: u32) -> Instruction {
+        Instruction::MemoLoad(MemoLoad(function, params, hit))
+    }
+
+    pub(crate) fn memo_store(function: String, params: u16) -> Instruction {
+        Instruction::MemoStore(MemoStore(function, params))
+    }
+
+    pub(crate) fn host_call(function: u16, args: u16) -> Instruction {
+        Instruction::HostCall(HostCall(function, args))
+    }
+
+    pub(crate) fn print_i() -> Instruction {
+        Instruction::PrintI(PrintI)
+    }
+
+    pub(crate) fn print_b() -> Instruction {
+        Instruction::PrintB(PrintB)
+    }
+
+    pub(crate) fn print_s(len: u16) -> Instruction {
+        Instruction::PrintS(PrintS(len))
+    }
+
+    pub(crate) fn read_i() -> Instruction {
+        Instruction::ReadI(ReadI)
+    }
+
+    pub(crate) fn make_list(elements: u16) -> Instruction {
+        Instruction::MakeList(MakeList(elements))
+    }
+
+    /// Returns the instruction that implements a builtin function.
+    pub(crate) fn builtin(function: BuiltinFunction) -> Instruction {
+        match function {
+            BuiltinFunction::Cons => Instruction::Cons(Cons),
+            BuiltinFunction::Head => Instruction::Head(Head),
+            BuiltinFunction::Tail => Instruction::Tail(Tail),
+            BuiltinFunction::IsEmpty => Instruction::IsEmpty(IsEmpty),
+            BuiltinFunction::EmptyMap => Instruction::EmptyMap(EmptyMap),
+            BuiltinFunction::Insert => Instruction::MapInsert(MapInsert),
+            BuiltinFunction::Get => Instruction::MapGet(MapGet),
+            BuiltinFunction::Remove => Instruction::MapRemove(MapRemove),
+            BuiltinFunction::Contains => Instruction::MapContains(MapContains),
+        }
     }
 }
 
@@ -140,6 +308,28 @@ impl Resolvable for Mul {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Rem;
+
+impl Resolvable for Rem {
+    type Output = resolved_operations::Rem;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::Rem
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Cmp;
+
+impl Resolvable for Cmp {
+    type Output = resolved_operations::Cmp;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::Cmp
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub(crate) struct FStop;
 
@@ -243,16 +433,377 @@ impl Resolvable for PushCopy {
     }
 }
 
+/// Returns from a function, given the number of values that sit between the
+/// return value slot and the return address (ie: parameters and captured
+/// values).
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub(crate) struct Ret;
+pub(crate) struct Ret(pub u16);
 
 impl Resolvable for Ret {
     type Output = resolved_operations::Ret;
 
     fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        // The return address sits on top of the parameters, and all of them
+        // must be removed.
         resolved_operations::Ret {
-            shrink_offset: 1,
+            shrink_offset: self.0 + 1,
             ip_offset: 0,
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ResV(pub u16);
+
+impl Resolvable for ResV {
+    type Output = resolved_operations::ResV;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::ResV(self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Call(pub String);
+
+impl Resolvable for Call {
+    type Output = resolved_operations::Call;
+
+    fn resolve(&self, ctxt: &LabelResolutionContext) -> Self::Output {
+        let dest = ctxt
+            .labels()
+            .resolve_named(&self.0)
+            .expect("Failed to resolve called function");
+
+        resolved_operations::Call(dest)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PushFn(pub String);
+
+impl Resolvable for PushFn {
+    type Output = resolved_operations::PushFn;
+
+    fn resolve(&self, ctxt: &LabelResolutionContext) -> Self::Output {
+        let addr = ctxt
+            .labels()
+            .resolve_named(&self.0)
+            .expect("Failed to resolve function address");
+
+        resolved_operations::PushFn(addr)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct CallIndirect;
+
+impl Resolvable for CallIndirect {
+    type Output = resolved_operations::CallIndirect;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::CallIndirect
+    }
+}
+
+/// Packs the given number of captured values with the code that starts at a
+/// given anonymous label.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct MakeClosure(pub u32, pub u16);
+
+impl Resolvable for MakeClosure {
+    type Output = resolved_operations::MakeClosure;
+
+    fn resolve(&self, ctxt: &LabelResolutionContext) -> Self::Output {
+        let addr = ctxt
+            .labels()
+            .resolve_anonymous(self.0)
+            .expect("Failed to resolve closure code address");
+
+        resolved_operations::MakeClosure {
+            addr,
+            captures: self.1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct MakeRecord(pub u16);
+
+impl Resolvable for MakeRecord {
+    type Output = resolved_operations::MakeRecord;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::MakeRecord(self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct GetField(pub u16);
+
+impl Resolvable for GetField {
+    type Output = resolved_operations::GetField;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::GetField(self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Panic(pub u16);
+
+impl Resolvable for Panic {
+    type Output = resolved_operations::Panic;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::Panic(self.0)
+    }
+}
+
+/// Calls a host function: the first field is the index of the function, the
+/// second one is its number of arguments.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct HostCall(pub u16, pub u16);
+
+impl Resolvable for HostCall {
+    type Output = resolved_operations::HostCall;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::HostCall {
+            function: self.0,
+            args: self.1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct PrintI;
+
+impl Resolvable for PrintI {
+    type Output = resolved_operations::PrintI;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::PrintI
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct PrintB;
+
+impl Resolvable for PrintB {
+    type Output = resolved_operations::PrintB;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::PrintB
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct PrintS(pub u16);
+
+impl Resolvable for PrintS {
+    type Output = resolved_operations::PrintS;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::PrintS(self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct ReadI;
+
+impl Resolvable for ReadI {
+    type Output = resolved_operations::ReadI;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::ReadI
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct MakeList(pub u16);
+
+impl Resolvable for MakeList {
+    type Output = resolved_operations::MakeList;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::MakeList(self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Cons;
+
+impl Resolvable for Cons {
+    type Output = resolved_operations::Cons;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::Cons
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Head;
+
+impl Resolvable for Head {
+    type Output = resolved_operations::Head;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::Head
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Tail;
+
+impl Resolvable for Tail {
+    type Output = resolved_operations::Tail;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::Tail
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct IsEmpty;
+
+impl Resolvable for IsEmpty {
+    type Output = resolved_operations::IsEmpty;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::IsEmpty
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct EmptyMap;
+
+impl Resolvable for EmptyMap {
+    type Output = resolved_operations::EmptyMap;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::EmptyMap
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct MapInsert;
+
+impl Resolvable for MapInsert {
+    type Output = resolved_operations::MapInsert;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::MapInsert
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct MapGet;
+
+impl Resolvable for MapGet {
+    type Output = resolved_operations::MapGet;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::MapGet
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct MapRemove;
+
+impl Resolvable for MapRemove {
+    type Output = resolved_operations::MapRemove;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::MapRemove
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct MapContains;
+
+impl Resolvable for MapContains {
+    type Output = resolved_operations::MapContains;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::MapContains
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Assert(pub u16);
+
+impl Resolvable for Assert {
+    type Output = resolved_operations::Assert;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::Assert(self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct AssertEq(pub u16);
+
+impl Resolvable for AssertEq {
+    type Output = resolved_operations::AssertEq;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::AssertEq(self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct AssertEqB(pub u16);
+
+impl Resolvable for AssertEqB {
+    type Output = resolved_operations::AssertEqB;
+
+    fn resolve(&self, _ctxt: &LabelResolutionContext) -> Self::Output {
+        resolved_operations::AssertEqB(self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MemoLoad(pub String, pub u16, pub u32);
+
+impl Resolvable for MemoLoad {
+    type Output = resolved_operations::MemoLoad;
+
+    fn resolve(&self, ctxt: &LabelResolutionContext) -> Self::Output {
+        let MemoLoad(function, params, hit) = self;
+
+        let function = ctxt
+            .labels()
+            .resolve_named(function)
+            .expect("Failed to resolve memoized function");
+        let hit_addr = ctxt
+            .labels()
+            .resolve_anonymous(*hit)
+            .expect("Failed to resolve cache hit address");
+
+        resolved_operations::MemoLoad {
+            function,
+            params: *params,
+            hit_addr,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MemoStore(pub String, pub u16);
+
+impl Resolvable for MemoStore {
+    type Output = resolved_operations::MemoStore;
+
+    fn resolve(&self, ctxt: &LabelResolutionContext) -> Self::Output {
+        let function = ctxt
+            .labels()
+            .resolve_named(&self.0)
+            .expect("Failed to resolve memoized function");
+
+        resolved_operations::MemoStore {
+            function,
+            params: self.1,
+        }
+    }
+}
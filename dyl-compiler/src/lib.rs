@@ -2,61 +2,117 @@ use std::path::Path;
 
 use anyhow::{Context, Result};
 
-use dyl_bytecode::Instruction;
+use dyl_bytecode::{symbols::Symbols, Instruction};
 
 #[cfg(test)]
 mod macros;
 
 mod ast;
+mod attributes;
+mod const_eval;
 mod context;
+mod expansion;
+mod features;
+mod free_vars;
+mod inference;
 mod instruction;
 mod io;
 mod lowering;
+mod memo;
+mod modules;
+mod monomorphization;
 mod parser;
+#[cfg(test)]
+mod test_support;
 mod ty;
 mod type_checker;
 
+/// Settings that change the generated bytecode.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    strip_contracts: bool,
+}
+
+impl Options {
+    /// Removes the `requires` and `ensures` checks from the bytecode. The
+    /// clauses are still type checked.
+    pub fn strip_contracts(self) -> Options {
+        Options {
+            strip_contracts: true,
+        }
+    }
+}
+
+/// Compiles the program stored in `i`, and writes its symbols followed by its
+/// bytecode to `o`. [`Instruction::program_from_bytes`] reads them back.
 pub fn compile<PA, PB>(i: PA, o: PB) -> Result<()>
 where
     PA: AsRef<Path>,
     PB: AsRef<Path>,
 {
-    let content = io::read_program(i.as_ref())
-        .with_context(|| format!("Failed to read input file `{}`", i.as_ref().display()))?;
-
-    let (ctxt, ast) = parser::parse_input(content.as_str())?;
-
-    let ctxt = ctxt.into_typing_context();
-
-    // let ctxt = type_checker::check_ast(&ast, ctxt)?;
-
-    let ctxt = ctxt.into_lowering_context();
-
-    let (ctxt, instructions) = lowering::lower_ast(&ast, ctxt)?;
-
-    let ctxt = ctxt.into_label_resolution_context();
+    compile_with_options(i, o, &Options::default())
+}
 
-    let instructions = context::resolve_labels(instructions.as_slice(), &ctxt);
+pub fn compile_with_options<PA, PB>(i: PA, o: PB, options: &Options) -> Result<()>
+where
+    PA: AsRef<Path>,
+    PB: AsRef<Path>,
+{
+    let (instructions, symbols) = lower_program(i.as_ref(), options)?;
 
-    let output = Instruction::encode_multiple(&instructions);
+    // The symbols hold the name of the host functions and the number of
+    // arguments of `main`, which are needed to run the program.
+    let output = Instruction::encode_program(&instructions, &symbols);
 
     io::write_bytecode(o, output.as_slice()).context("Failed to write output bytecode")?;
 
     Ok(())
 }
 
-pub fn bytecode_from_program<P>(path: P) -> Result<Vec<Instruction>>
+/// Compiles a program, returning its bytecode along with the name of the
+/// functions it contains.
+pub fn bytecode_from_program<P>(path: P) -> Result<(Vec<Instruction>, Symbols)>
+where
+    P: AsRef<Path>,
+{
+    bytecode_from_program_with_options(path, &Options::default())
+}
+
+pub fn bytecode_from_program_with_options<P>(
+    path: P,
+    options: &Options,
+) -> Result<(Vec<Instruction>, Symbols)>
 where
     P: AsRef<Path>,
 {
-    let content = io::read_program(path.as_ref())
-        .with_context(|| format!("Failed to read input file `{}`", path.as_ref().display()))?;
+    lower_program(path.as_ref(), options)
+}
+
+fn lower_program(path: &Path, options: &Options) -> Result<(Vec<Instruction>, Symbols)> {
+    let content = io::read_program(path)
+        .with_context(|| format!("Failed to read input file `{}`", path.display()))?;
 
-    let (ctxt, ast) = parser::parse_input(content.as_str())?;
+    let (ctxt, ast) = modules::load_program(path, content.as_str())?;
 
     let ctxt = ctxt.into_typing_context();
 
-    // let ctxt = type_checker::check_ast(&ast, ctxt)?;
+    let (ctxt, ()) = attributes::check_attributes(&ast, ctxt)?;
+
+    let (ctxt, ast) = inference::infer_types(&ast, ctxt)?;
+
+    let ctxt = type_checker::check_ast(&ast, ctxt)?;
+
+    let ast = if options.strip_contracts {
+        ast.without_contracts()
+    } else {
+        ast
+    };
+
+    let (ctxt, ast) = const_eval::evaluate_consts(&ast, ctxt)?;
+
+    let (ctxt, ast) = monomorphization::monomorphize(&ast, ctxt)?;
+
+    let (ctxt, ()) = memo::check_memo_fns(&ast, ctxt)?;
 
     let ctxt = ctxt.into_lowering_context();
 
@@ -66,5 +122,5 @@ where
 
     let final_instructions = context::resolve_labels(instructions.as_slice(), &ctxt);
 
-    Ok(final_instructions)
+    Ok((final_instructions, ctxt.symbols()))
 }
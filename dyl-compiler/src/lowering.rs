@@ -1,10 +1,16 @@
+use std::cmp::Ordering;
+
 use crate::{
     ast::{
-        Addition, Binding, Bindings, Bool, ExprKind, Function, Ident, If, Integer, Multiplication,
-        Program, Subtraction,
+        Addition, Assert, AssertKind, Binding, Bindings, Bool, Break, Builtin, Call, Comparison,
+        Continue, ExprKind, ExternFunction, FieldAccess, For, Function, Ident, If, Integer, Lambda,
+        ListLiteral, Match, Multiplication, Panic, Param, Print, PrintArg, Program, ReadInt,
+        RecordLiteral, Remainder, Statement, Subtraction, Try, Unit, Variant, RESULT,
     },
-    context::{CompilerPassError, LoweringContext},
+    context::{CompilerPassError, LoopFrame, LoweringContext},
+    free_vars::FreeVariables,
     instruction::Instruction,
+    ty::Ty,
 };
 
 pub(crate) fn lower_ast(
@@ -31,16 +37,19 @@ impl Lowerable for Program {
         collector: &mut Vec<Instruction>,
         ctxt: &mut LoweringContext,
     ) -> LoweringResult {
+        self.functions()
+            .iter()
+            .for_each(|f| ctxt.add_function(f.name().to_owned()));
+        self.externs()
+            .iter()
+            .for_each(|e| ctxt.add_function(e.name().to_owned()));
+
         let main_fn_data = self
             .functions()
             .iter()
             .enumerate()
             .find(|(_, f)| f.name() == "main");
 
-        if main_fn_data.is_none() {
-            ctxt.errors().add("No `main` function found");
-        }
-
         // We want to lower the main function first, so that the instructions
         // to be executed start at offset 0.
         //
@@ -49,19 +58,13 @@ impl Lowerable for Program {
 
         // We don't perform early return because we want to catch as much
         // lowering errors as possible.
-        let main_fn_lowering = main_fn_data
-            .ok_or(())
-            .and_then(|(_, node)| node.lower(collector, ctxt));
-
-        if main_fn_lowering.is_ok() {
-            // We must remove the final `pop_copy` and `ret` instruction, as
-            // the main function does not return the way other functions do.
-            collector.truncate(collector.len() - 2);
-
-            // For the same reason, we must add the full stop instruction.
-            let full_stop = Instruction::f_stop();
-            collector.push(full_stop);
-        }
+        let main_fn_lowering = match main_fn_data {
+            Some((_, main)) => lower_main(main, collector, ctxt),
+            None => {
+                ctxt.errors().add("No `main` function found");
+                Err(())
+            }
+        };
 
         let idx_to_avoid = main_fn_data.map(|(idx, _)| idx);
 
@@ -80,30 +83,320 @@ impl Lowerable for Program {
             .fold(Ok(()), Result::and)
             .and(main_fn_lowering);
 
-        rslt
+        self.externs()
+            .iter()
+            .map(|extern_| extern_.lower(collector, ctxt))
+            .fold(rslt, Result::and)
+    }
+}
+
+/// Lowers the `main` function.
+///
+/// The main function does not return the way other functions do: there is no
+/// caller to return to, so the program is stopped instead. Its arguments are
+/// pushed on the stack by the VM before the program starts.
+fn lower_main(
+    main: &Function,
+    collector: &mut Vec<Instruction>,
+    ctxt: &mut LoweringContext,
+) -> LoweringResult {
+    let label_check = define_function(main.name(), collector, ctxt);
+
+    let params_check = main
+        .params()
+        .iter()
+        .map(|param| match param.ty() {
+            Ty::Int => Ok(()),
+            ty => {
+                ctxt.errors().add(format!(
+                    "Parameter `{}` of `main` must be an integer, found type `{}`",
+                    param.name(),
+                    ty
+                ));
+                Err(())
+            }
+        })
+        .fold(Ok(()), Result::and);
+
+    let subcontext = ctxt.stack().new_subcontext();
+
+    main.params().iter().for_each(|param| {
+        ctxt.stack_mut().push_anonymous();
+        ctxt.stack_mut()
+            .name_top_anonymous(param.name().to_owned())
+            .unwrap();
+    });
+    ctxt.set_main_params(main.params().len() as u16);
+
+    let body_exp = CheckedBody { function: main }.lower(collector, ctxt);
+    ctxt.stack_mut().drop_subcontext(subcontext);
+
+    // The VM expects the value returned by `main` to be the only one left on
+    // the stack.
+    let params = main.params().len() as u16;
+    if params > 0 {
+        collector.push(Instruction::pop_copy(params));
     }
+    if params > 1 {
+        collector.push(Instruction::pop(params - 1));
+    }
+
+    collector.push(Instruction::f_stop());
+
+    label_check.and(params_check).and(body_exp)
 }
 
+/// Stack name of the slot in which a function writes its return value.
+const RETURN_SLOT: &str = "#return";
+
+/// Stack name of the return address of the current function.
+const RETURN_ADDRESS: &str = "#ip";
+
 impl Lowerable for Function {
     fn lower(
         &self,
         collector: &mut Vec<Instruction>,
         ctxt: &mut LoweringContext,
     ) -> LoweringResult {
-        ctxt.labels_mut()
-            .new_named(self.name().to_string(), collector.len() as u32);
+        let label_check = define_function(self.name(), collector, ctxt);
 
-        self.body().lower(collector, ctxt)?;
+        let params = self.params().iter().map(Param::name);
+        let body = CheckedBody { function: self };
 
-        let rslt_copy_instr = Instruction::pop_copy(1);
-        let return_instr = Instruction::ret();
+        lower_function_body(params, &body, collector, ctxt).and(label_check)
+    }
+}
 
-        collector.extend([rslt_copy_instr, return_instr]);
+/// Names the position of the next instruction after a function.
+fn define_function(
+    name: &str,
+    collector: &[Instruction],
+    ctxt: &mut LoweringContext,
+) -> LoweringResult {
+    ctxt.labels_mut()
+        .new_named(name.to_owned(), collector.len() as u32)
+        .map_err(|_| {
+            ctxt.errors()
+                .add(format!("Function `{}` is defined multiple times", name))
+        })
+}
 
-        Ok(())
+/// The body of a function, surrounded by the checks of its contract and, for
+/// `#[memo]` functions, by the accesses to its cache.
+struct CheckedBody<'a> {
+    function: &'a Function,
+}
+
+impl Lowerable for CheckedBody<'_> {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let name = self.function.name();
+        let params = self.function.params().len() as u16;
+
+        // On a hit, the cached value is returned right away: it has been
+        // checked against the contract already.
+        let cache_hit = self.function.is_memo().then(|| {
+            let hit = ctxt.labels_mut().new_anonymous();
+            collector.push(Instruction::memo_load(name.to_owned(), params, hit));
+            hit
+        });
+
+        // Early returns check the postconditions as well.
+        *ctxt.postconditions_mut() = self
+            .function
+            .ensures()
+            .map(|clause| (clause.condition().clone(), clause.violation_message(name)))
+            .collect();
+
+        let requires_exp = self
+            .function
+            .requires()
+            .map(|clause| {
+                lower_check(
+                    clause.condition(),
+                    &clause.violation_message(name),
+                    collector,
+                    ctxt,
+                )
+            })
+            .fold(Ok(()), Result::and);
+
+        let body_exp = self.function.body().lower(collector, ctxt);
+        let ensures_exp = lower_postconditions(collector, ctxt);
+
+        // Memoized functions return integers or booleans, so they can't
+        // return early with `?`.
+        if let Some(hit) = cache_hit {
+            collector.push(Instruction::memo_store(name.to_owned(), params));
+            ctxt.labels_mut()
+                .set_position(hit, collector.len() as u32)
+                .unwrap();
+        }
+
+        requires_exp.and(body_exp).and(ensures_exp)
+    }
+}
+
+/// Panics with `message` if `condition` does not hold. Leaves the stack
+/// untouched.
+fn lower_check(
+    condition: &ExprKind,
+    message: &str,
+    collector: &mut Vec<Instruction>,
+    ctxt: &mut LoweringContext,
+) -> LoweringResult {
+    let condition_exp = condition.lower(collector, ctxt);
+
+    let len = match push_bytes(message, collector) {
+        Some(len) => len,
+        None => {
+            ctxt.errors().add("Contract violation message is too long");
+            return Err(());
+        }
+    };
+
+    collector.push(Instruction::assert(len));
+    ctxt.stack_mut().pop_top_anonymous().unwrap();
+
+    condition_exp
+}
+
+/// Checks the postconditions of the current function against the value on
+/// top of the stack, which is about to be returned.
+fn lower_postconditions(
+    collector: &mut Vec<Instruction>,
+    ctxt: &mut LoweringContext,
+) -> LoweringResult {
+    let postconditions = ctxt.postconditions().to_vec();
+
+    postconditions
+        .iter()
+        .map(|(condition, message)| {
+            let subcontext = ctxt.stack().new_subcontext();
+
+            collector.push(Instruction::push_copy(0));
+            ctxt.stack_mut().push_anonymous();
+            ctxt.stack_mut()
+                .name_top_anonymous(RESULT.to_owned())
+                .unwrap();
+
+            let check_exp = lower_check(condition, message, collector, ctxt);
+
+            collector.push(Instruction::pop(1));
+            ctxt.stack_mut().drop_subcontext(subcontext);
+
+            check_exp
+        })
+        .fold(Ok(()), Result::and)
+}
+
+impl ExternFunction {
+    /// Lowers a function that forwards its arguments to the host function,
+    /// so that extern functions can be used like any other function.
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let label_check = define_function(self.name(), collector, ctxt);
+
+        let function = ctxt.host_function(self.host_name());
+        let body = HostCall {
+            function,
+            params: self.params(),
+        };
+        let params = self.params().iter().map(Param::name);
+
+        // The arguments are only copied, which can't fail.
+        lower_function_body(params, &body, collector, ctxt).unwrap();
+
+        label_check
     }
 }
 
+/// The body of the function generated for an extern function.
+struct HostCall<'a> {
+    function: u16,
+    params: &'a [Param],
+}
+
+impl Lowerable for HostCall<'_> {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let args = self
+            .params
+            .iter()
+            .map(|p| ExprKind::ident(p.name().to_owned()).lower(collector, ctxt))
+            .fold(Ok(()), Result::and);
+
+        collector.push(Instruction::host_call(
+            self.function,
+            self.params.len() as u16,
+        ));
+
+        self.params.iter().for_each(|_| {
+            ctxt.stack_mut().pop_top_anonymous().unwrap();
+        });
+        ctxt.stack_mut().push_anonymous();
+
+        args
+    }
+}
+
+/// Lowers the body of a function or of a lambda, followed by the code that
+/// returns to the caller.
+///
+/// When a function is called, the stack contains the slot for the return
+/// value, the values of `frame` (in order) and the return address. Nothing
+/// else is accessible from the function body.
+fn lower_function_body<'a>(
+    frame: impl Iterator<Item = &'a str>,
+    body: &impl Lowerable,
+    collector: &mut Vec<Instruction>,
+    ctxt: &mut LoweringContext,
+) -> LoweringResult {
+    let outer_stack = std::mem::take(ctxt.stack_mut());
+    let outer_loops = std::mem::take(ctxt.loops_mut());
+    let outer_postconditions = std::mem::take(ctxt.postconditions_mut());
+
+    let names = std::iter::once(RETURN_SLOT)
+        .chain(frame)
+        .chain(std::iter::once(RETURN_ADDRESS));
+
+    names.for_each(|name| {
+        ctxt.stack_mut().push_anonymous();
+        ctxt.stack_mut()
+            .name_top_anonymous(name.to_owned())
+            .unwrap();
+    });
+
+    // The return slot and the return address are not part of the frame.
+    let frame_size = ctxt.stack().depth() as u16 - 2;
+
+    let body_exp = body.lower(collector, ctxt);
+
+    // The offset is computed with the return value on top of the stack,
+    // which is exactly what `pop_copy` expects.
+    let return_slot = ctxt.stack().resolve(RETURN_SLOT).unwrap();
+
+    *ctxt.stack_mut() = outer_stack;
+    *ctxt.loops_mut() = outer_loops;
+    *ctxt.postconditions_mut() = outer_postconditions;
+
+    let rslt_copy_instr = Instruction::pop_copy(return_slot);
+    let return_instr = Instruction::ret(frame_size);
+
+    collector.extend([rslt_copy_instr, return_instr]);
+
+    body_exp
+}
+
 impl Lowerable for ExprKind {
     fn lower(
         &self,
@@ -116,9 +409,32 @@ impl Lowerable for ExprKind {
             ExprKind::Subtraction(e) => e.lower(collector, ctxt),
             ExprKind::If(e) => e.lower(collector, ctxt),
             ExprKind::Multiplication(e) => e.lower(collector, ctxt),
+            ExprKind::Remainder(e) => e.lower(collector, ctxt),
             ExprKind::Bindings(e) => e.lower(collector, ctxt),
             ExprKind::Ident(e) => e.lower(collector, ctxt),
             ExprKind::Bool(e) => e.lower(collector, ctxt),
+            ExprKind::Unit(e) => e.lower(collector, ctxt),
+            ExprKind::For(e) => e.lower(collector, ctxt),
+            ExprKind::Break(e) => e.lower(collector, ctxt),
+            ExprKind::Continue(e) => e.lower(collector, ctxt),
+            ExprKind::Call(e) => e.lower(collector, ctxt),
+            ExprKind::Lambda(e) => e.lower(collector, ctxt),
+            ExprKind::RecordLiteral(e) => e.lower(collector, ctxt),
+            ExprKind::FieldAccess(e) => e.lower(collector, ctxt),
+            ExprKind::Variant(e) => e.lower(collector, ctxt),
+            ExprKind::Match(e) => e.lower(collector, ctxt),
+            ExprKind::Try(e) => e.lower(collector, ctxt),
+            ExprKind::Panic(e) => e.lower(collector, ctxt),
+            ExprKind::Comparison(e) => e.lower(collector, ctxt),
+            ExprKind::Print(e) => e.lower(collector, ctxt),
+            ExprKind::ReadInt(e) => e.lower(collector, ctxt),
+            ExprKind::List(e) => e.lower(collector, ctxt),
+            ExprKind::Builtin(e) => e.lower(collector, ctxt),
+            ExprKind::Assert(e) => e.lower(collector, ctxt),
+
+            // Method calls are turned into regular calls during
+            // monomorphization, once the type of every receiver is known.
+            ExprKind::MethodCall(_) => unreachable!("Method call was not resolved"),
         }
     }
 }
@@ -187,17 +503,98 @@ impl Lowerable for Multiplication {
     }
 }
 
+impl Lowerable for Remainder {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let left_exp = self.left().lower(collector, ctxt);
+        let right_exp = self.right().lower(collector, ctxt);
+        collector.push(Instruction::rem());
+        ctxt.stack_mut().pop_top_anonymous().unwrap();
+
+        left_exp.and(right_exp)
+    }
+}
+
+impl Lowerable for Comparison {
+    /// Lowers a comparison by jumping on the ordering of its operands, and
+    /// pushing the corresponding boolean.
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let left_exp = self.left().lower(collector, ctxt);
+        let right_exp = self.right().lower(collector, ctxt);
+
+        let true_ = ctxt.labels_mut().new_anonymous();
+        let false_ = ctxt.labels_mut().new_anonymous();
+        let end = ctxt.labels_mut().new_anonymous();
+
+        let target = |ordering| match self.operator().holds(ordering) {
+            true => true_,
+            false => false_,
+        };
+
+        collector.extend([
+            Instruction::cmp(),
+            Instruction::cond_jmp(
+                target(Ordering::Less),
+                target(Ordering::Equal),
+                target(Ordering::Greater),
+            ),
+        ]);
+        ctxt.stack_mut().pop_top_anonymous().unwrap();
+        ctxt.stack_mut().pop_top_anonymous().unwrap();
+
+        ctxt.labels_mut()
+            .set_position(true_, collector.len() as u32)
+            .unwrap();
+        collector.extend([Instruction::push_i(1), Instruction::goto(end)]);
+
+        ctxt.labels_mut()
+            .set_position(false_, collector.len() as u32)
+            .unwrap();
+        collector.push(Instruction::push_i(0));
+
+        ctxt.labels_mut()
+            .set_position(end, collector.len() as u32)
+            .unwrap();
+        ctxt.stack_mut().push_anonymous();
+
+        left_exp.and(right_exp)
+    }
+}
+
 impl Lowerable for If {
     fn lower(
         &self,
         collector: &mut Vec<Instruction>,
         ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        self.lower_chain(collector, ctxt, None)
+    }
+}
+
+impl If {
+    /// Lowers an `if`, an `if`-`else` or an `if`-`else if` chain.
+    ///
+    /// Every `if` of a chain jumps directly to the end of the whole chain once
+    /// its consequent has been evaluated. As such, only the outermost `if`
+    /// defines the end label.
+    fn lower_chain(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+        chain_end: Option<u32>,
     ) -> LoweringResult {
         let condition_exp = self.condition().lower(collector, ctxt);
 
         let consequent_start = ctxt.labels_mut().new_anonymous();
         let alt_start = ctxt.labels_mut().new_anonymous();
-        let consequent_end = ctxt.labels_mut().new_anonymous();
+        let consequent_end = chain_end.unwrap_or_else(|| ctxt.labels_mut().new_anonymous());
 
         let cond = Instruction::cond_jmp(consequent_start, alt_start, consequent_start);
         let goto_end = Instruction::goto(consequent_end);
@@ -221,14 +618,21 @@ impl Lowerable for If {
             .set_position(alt_start, collector.len() as u32)
             .unwrap();
 
-        let alternative_exp = self.alternative().lower(collector, ctxt);
+        let alternative_exp = match self.alternative() {
+            ExprKind::If(alternative) => {
+                alternative.lower_chain(collector, ctxt, Some(consequent_end))
+            }
+            alternative => alternative.lower(collector, ctxt),
+        };
 
         ctxt.stack_mut().drop_subcontext(branches_subcontext);
         ctxt.stack_mut().push_anonymous();
 
-        ctxt.labels_mut()
-            .set_position(consequent_end, collector.len() as u32)
-            .unwrap();
+        if chain_end.is_none() {
+            ctxt.labels_mut()
+                .set_position(consequent_end, collector.len() as u32)
+                .unwrap();
+        }
 
         condition_exp.and(consequent_exp).and(alternative_exp)
     }
@@ -241,23 +645,47 @@ impl Lowerable for Bindings {
         ctxt: &mut LoweringContext,
     ) -> LoweringResult {
         let subcontext_id = ctxt.stack().new_subcontext();
-        let defines_exp = self
-            .defines()
+        let statements_exp = self
+            .statements()
             .iter()
-            .map(|b| b.lower(collector, ctxt))
+            .map(|s| s.lower(collector, ctxt))
             .fold(Ok(()), Result::and);
 
         let ending_exp = self.ending_expression().lower(collector, ctxt);
 
-        let len = self.defines().len() as u16;
+        let len = self.defines().count() as u16;
 
-        collector.push(Instruction::pop_copy(len));
-        collector.push(Instruction::pop(len - 1));
+        // Expression statements don't leave anything on the stack, so there
+        // may be nothing to clean up.
+        if len > 0 {
+            collector.push(Instruction::pop_copy(len));
+            collector.push(Instruction::pop(len - 1));
+        }
 
         ctxt.stack_mut().drop_subcontext(subcontext_id);
         ctxt.stack_mut().push_anonymous();
 
-        defines_exp.and(ending_exp)
+        statements_exp.and(ending_exp)
+    }
+}
+
+impl Lowerable for Statement {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        match self {
+            Statement::Binding(b) => b.lower(collector, ctxt),
+            Statement::Expr(e) => {
+                let expr_exp = e.lower(collector, ctxt);
+
+                collector.push(Instruction::pop(1));
+                ctxt.stack_mut().pop_top_anonymous().unwrap();
+
+                expr_exp
+            }
+        }
     }
 }
 
@@ -282,6 +710,13 @@ impl Lowerable for Ident {
         collector: &mut Vec<Instruction>,
         ctxt: &mut LoweringContext,
     ) -> LoweringResult {
+        if ctxt.is_function(self.name()) {
+            collector.push(Instruction::push_fn(self.name().to_owned()));
+            ctxt.stack_mut().push_anonymous();
+
+            return Ok(());
+        }
+
         let stack_offset = match ctxt.stack().resolve(self.name()) {
             Some(offset) => offset,
             None => {
@@ -302,98 +737,1016 @@ impl Lowerable for Ident {
     }
 }
 
-impl Lowerable for Bool {
+impl Lowerable for Call {
     fn lower(
         &self,
         collector: &mut Vec<Instruction>,
         ctxt: &mut LoweringContext,
     ) -> LoweringResult {
-        let num = if self.value() { 1 } else { 0 };
-
-        collector.push(Instruction::push_i(num));
+        // Reserve space for the return value.
+        collector.push(Instruction::res_v(1));
         ctxt.stack_mut().push_anonymous();
 
-        Ok(())
-    }
-}
+        let args_exp = self
+            .args()
+            .iter()
+            .map(|arg| arg.lower(collector, ctxt))
+            .fold(Ok(()), Result::and);
 
-#[cfg(test)]
-fn lower(expr: &impl Lowerable) -> (Vec<Instruction>, LoweringContext) {
-    let mut collector = Vec::new();
-    let mut ctxt = LoweringContext::new();
+        // Functions that are called by name are called directly. Anything
+        // else is evaluated to a function value, which is then called.
+        let direct_callee = match self.callee() {
+            ExprKind::Ident(ident) if ctxt.is_function(ident.name()) => Some(ident.name()),
+            _ => None,
+        };
 
-    if expr.lower(&mut collector, &mut ctxt).is_err() {
-        println!("{}", ctxt.errors());
-        panic!("called `Result::unwrap()` on an `Err` value: ()")
-    }
+        let callee_exp = match direct_callee {
+            Some(name) => {
+                collector.push(Instruction::call(name.to_owned()));
+                Ok(())
+            }
 
-    (collector, ctxt)
-}
+            None => {
+                let callee_exp = self.callee().lower(collector, ctxt);
+                collector.push(Instruction::call_indirect());
+                ctxt.stack_mut().pop_top_anonymous().unwrap();
 
-#[cfg(test)]
-#[test]
-fn lowering_can_fail() {
-    let ast = ExprKind::ident("undefined".to_owned());
-    let mut collector = Vec::new();
-    let mut ctxt = LoweringContext::new();
+                callee_exp
+            }
+        };
 
-    assert!(ast.lower(&mut collector, &mut ctxt).is_err());
+        // The callee removes the arguments from the stack, leaving only the
+        // return value.
+        self.args()
+            .iter()
+            .for_each(|_| ctxt.stack_mut().pop_top_anonymous().unwrap());
+
+        args_exp.and(callee_exp)
+    }
 }
 
-#[cfg(test)]
-mod program {
-    use crate::inline_program;
+impl Lowerable for Lambda {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        // A lambda is converted to a function which takes the captured values
+        // as additional parameters, placed after the regular ones. Its code is
+        // emitted in place, and jumped over.
+        let free_variables = FreeVariables::of_lambda(self);
+
+        // Free variables that are not on the stack are either functions or
+        // undefined variables. In both cases, they don't need to be captured.
+        let captures = free_variables
+            .used()
+            .iter()
+            .filter(|name| ctxt.stack().resolve(name).is_some())
+            .cloned()
+            .collect::<Vec<_>>();
 
-    use super::*;
+        let code_start = ctxt.labels_mut().new_anonymous();
+        let code_end = ctxt.labels_mut().new_anonymous();
 
-    #[test]
-    fn simplest_test() {
-        let program: Program = inline_program! { fn main() { 42 } };
-        let (instrs, _) = lower(&program);
+        collector.push(Instruction::goto(code_end));
+        ctxt.labels_mut()
+            .set_position(code_start, collector.len() as u32)
+            .unwrap();
 
-        assert_eq!(instrs, [Instruction::push_i(42), Instruction::f_stop()]);
-    }
+        let frame = self
+            .params()
+            .iter()
+            .map(Param::name)
+            .chain(captures.iter().map(String::as_str));
 
-    #[test]
-    fn main_is_lowered_first() {
-        let program: Program = inline_program! {
-            fn ___() { 41 }
-            fn main() { 42 }
-        };
+        let body_exp = lower_function_body(frame, self.body(), collector, ctxt);
 
-        let (instrs, _) = lower(&program);
+        ctxt.labels_mut()
+            .set_position(code_end, collector.len() as u32)
+            .unwrap();
 
-        assert!(instrs.starts_with(&[Instruction::push_i(42), Instruction::f_stop()]));
-    }
+        captures.iter().for_each(|name| {
+            let offset = ctxt.stack().resolve(name).unwrap();
+            collector.push(Instruction::push_copy(offset));
+            ctxt.stack_mut().push_anonymous();
+        });
 
-    #[test]
-    fn main_instrs_are_removed() {
-        let program: Program = inline_program! { fn main() { 42 } };
-        let (instrs, _) = lower(&program);
+        collector.push(Instruction::make_closure(code_start, captures.len() as u16));
+
+        captures
+            .iter()
+            .for_each(|_| ctxt.stack_mut().pop_top_anonymous().unwrap());
+        ctxt.stack_mut().push_anonymous();
 
-        assert!(!instrs.ends_with(&[Instruction::ret()]));
+        body_exp
     }
 }
 
-#[cfg(test)]
-mod function {
-    use crate::inline_fn;
+impl Lowerable for RecordLiteral {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        // Fields were sorted in declaration order during monomorphization.
+        let fields_exp = self
+            .fields()
+            .iter()
+            .map(|(_, value)| value.lower(collector, ctxt))
+            .fold(Ok(()), Result::and);
 
-    use super::*;
+        collector.push(Instruction::make_record(self.fields().len() as u16));
 
-    #[test]
-    fn body_is_lowered() {
-        let f: Function = inline_fn! { fn f() { 42 } };
-        let (instrs, _) = lower(&f);
+        self.fields()
+            .iter()
+            .for_each(|_| ctxt.stack_mut().pop_top_anonymous().unwrap());
+        ctxt.stack_mut().push_anonymous();
 
-        assert_eq!(
-            instrs,
-            [
-                Instruction::push_i(42),
-                Instruction::pop_copy(1),
-                Instruction::ret()
-            ]
-        );
+        fields_exp
+    }
+}
+
+impl Lowerable for FieldAccess {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        // Fields are resolved during monomorphization, once the type of every
+        // record is known.
+        let idx = self.index().expect("Field access was not resolved");
+
+        let record_exp = self.record().lower(collector, ctxt);
+        collector.push(Instruction::get_field(idx));
+
+        record_exp
+    }
+}
+
+impl Lowerable for Variant {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        // Values of built-in types are records, whose first field is the tag
+        // of their constructor and whose second field is the payload, if
+        // any.
+        collector.push(Instruction::push_i(self.constructor().tag()));
+        ctxt.stack_mut().push_anonymous();
+
+        let payload_exp = match self.payload() {
+            Some(payload) => payload.lower(collector, ctxt),
+            None => Ok(()),
+        };
+
+        let len = if self.payload().is_some() { 2 } else { 1 };
+        collector.push(Instruction::make_record(len));
+
+        (0..len).for_each(|_| ctxt.stack_mut().pop_top_anonymous().unwrap());
+        ctxt.stack_mut().push_anonymous();
+
+        payload_exp
+    }
+}
+
+impl Lowerable for Match {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let match_subcontext = ctxt.stack().new_subcontext();
+
+        let scrutinee_exp = self.scrutinee().lower(collector, ctxt);
+        let arms_subcontext = ctxt.stack().new_subcontext();
+
+        let arm_labels = self
+            .arms()
+            .iter()
+            .map(|_| ctxt.labels_mut().new_anonymous())
+            .collect::<Vec<_>>();
+        let match_end = ctxt.labels_mut().new_anonymous();
+
+        // Every built-in type has two constructors, tagged with `0` and `1`.
+        // Matches are exhaustive, as the type checker ran before.
+        let label_of = |tag| {
+            self.arms()
+                .iter()
+                .position(|arm| arm.pattern().constructor().tag() == tag)
+                .map(|idx| arm_labels[idx])
+                .unwrap_or(match_end)
+        };
+
+        collector.extend([
+            Instruction::push_copy(0),
+            Instruction::get_field(0),
+            Instruction::cond_jmp(label_of(0), label_of(0), label_of(1)),
+        ]);
+
+        let arms_exp = self
+            .arms()
+            .iter()
+            .zip(&arm_labels)
+            .enumerate()
+            .map(|(idx, (arm, label))| {
+                ctxt.labels_mut()
+                    .set_position(*label, collector.len() as u32)
+                    .unwrap();
+
+                if let Some(name) = arm.pattern().binding() {
+                    collector.extend([Instruction::push_copy(0), Instruction::get_field(1)]);
+                    ctxt.stack_mut().push_anonymous();
+                    ctxt.stack_mut()
+                        .name_top_anonymous(name.to_owned())
+                        .unwrap();
+                }
+
+                let body_exp = arm.body().lower(collector, ctxt);
+
+                // Only the value of the arm is kept, the scrutinee and the
+                // payload are discarded.
+                let len = (ctxt.stack().depth() - match_subcontext - 1) as u16;
+                collector.push(Instruction::pop_copy(len));
+                if len > 1 {
+                    collector.push(Instruction::pop(len - 1));
+                }
+
+                if idx + 1 < self.arms().len() {
+                    collector.push(Instruction::goto(match_end));
+                }
+
+                ctxt.stack_mut().drop_subcontext(arms_subcontext);
+
+                body_exp
+            })
+            .fold(Ok(()), Result::and);
+
+        ctxt.labels_mut()
+            .set_position(match_end, collector.len() as u32)
+            .unwrap();
+
+        ctxt.stack_mut().drop_subcontext(match_subcontext);
+        ctxt.stack_mut().push_anonymous();
+
+        scrutinee_exp.and(arms_exp)
+    }
+}
+
+impl Lowerable for Try {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let operand_exp = self.operand().lower(collector, ctxt);
+
+        let failure = ctxt.labels_mut().new_anonymous();
+        let success = ctxt.labels_mut().new_anonymous();
+
+        collector.extend([
+            Instruction::push_copy(0),
+            Instruction::get_field(0),
+            Instruction::cond_jmp(failure, failure, success),
+        ]);
+
+        ctxt.labels_mut()
+            .set_position(failure, collector.len() as u32)
+            .unwrap();
+
+        // A `None` or an `Err` has the same representation whatever the type
+        // of the successful value, so it is returned as is.
+        let return_exp = lower_early_return(collector, ctxt);
+
+        ctxt.labels_mut()
+            .set_position(success, collector.len() as u32)
+            .unwrap();
+
+        collector.push(Instruction::get_field(1));
+
+        operand_exp.and(return_exp)
+    }
+}
+
+impl Lowerable for Panic {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let len = match push_bytes(self.message(), collector) {
+            Some(len) => len,
+            None => {
+                ctxt.errors().add("Panic message is too long");
+                return Err(());
+            }
+        };
+
+        // The VM never comes back from the `panic` instruction. The stack
+        // still gets a value so that the code that follows can be lowered as
+        // usual.
+        collector.push(Instruction::panic(len));
+
+        ctxt.stack_mut().push_anonymous();
+
+        Ok(())
+    }
+}
+
+impl Lowerable for Print {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let arg_exp = match self.arg() {
+            PrintArg::Nothing => Ok(()),
+
+            PrintArg::Str(s) => match push_bytes(s, collector) {
+                Some(len) => {
+                    collector.push(Instruction::print_s(len));
+                    Ok(())
+                }
+                None => {
+                    ctxt.errors().add("Printed string is too long");
+                    Err(())
+                }
+            },
+
+            PrintArg::Value(arg) | PrintArg::Bool(arg) => {
+                let arg_exp = arg.lower(collector, ctxt);
+
+                collector.push(match self.arg() {
+                    PrintArg::Bool(_) => Instruction::print_b(),
+                    _ => Instruction::print_i(),
+                });
+                ctxt.stack_mut().pop_top_anonymous().unwrap();
+
+                arg_exp
+            }
+        };
+
+        if self.newline() {
+            collector.extend([
+                Instruction::push_i(i32::from(b'\n')),
+                Instruction::print_s(1),
+            ]);
+        }
+
+        collector.push(Instruction::push_i(0));
+        ctxt.stack_mut().push_anonymous();
+
+        arg_exp
+    }
+}
+
+impl Lowerable for ListLiteral {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let elements_exp = self
+            .elements()
+            .iter()
+            .map(|element| element.lower(collector, ctxt))
+            .fold(Ok(()), Result::and);
+
+        collector.push(Instruction::make_list(self.elements().len() as u16));
+
+        self.elements()
+            .iter()
+            .for_each(|_| ctxt.stack_mut().pop_top_anonymous().unwrap());
+        ctxt.stack_mut().push_anonymous();
+
+        elements_exp
+    }
+}
+
+impl Lowerable for Builtin {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let args_exp = self
+            .args()
+            .iter()
+            .map(|arg| arg.lower(collector, ctxt))
+            .fold(Ok(()), Result::and);
+
+        collector.push(Instruction::builtin(self.function()));
+
+        self.args()
+            .iter()
+            .for_each(|_| ctxt.stack_mut().pop_top_anonymous().unwrap());
+        ctxt.stack_mut().push_anonymous();
+
+        args_exp
+    }
+}
+
+impl Lowerable for Assert {
+    /// Lowers an assertion so that the message is only pushed when the
+    /// assertion fails, right before the instruction that panics with it.
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let operands_exp = self
+            .operands()
+            .into_iter()
+            .map(|operand| operand.lower(collector, ctxt))
+            .fold(Ok(()), Result::and);
+
+        let failure = ctxt.labels_mut().new_anonymous();
+        let success = ctxt.labels_mut().new_anonymous();
+
+        // The operands are kept on the stack for the failure path, which
+        // hands them to the instruction that panics.
+        match self.kind() {
+            AssertKind::True(_) => collector.extend([
+                Instruction::push_copy(0),
+                Instruction::cond_jmp(failure, failure, success),
+            ]),
+            AssertKind::Eq(..) | AssertKind::EqBool(..) => collector.extend([
+                Instruction::push_copy(1),
+                Instruction::push_copy(1),
+                Instruction::cmp(),
+                Instruction::cond_jmp(failure, success, failure),
+            ]),
+        }
+
+        ctxt.labels_mut()
+            .set_position(failure, collector.len() as u32)
+            .unwrap();
+
+        let len = match push_bytes(&self.message(), collector) {
+            Some(len) => len,
+            None => {
+                ctxt.errors().add("Assertion message is too long");
+                return Err(());
+            }
+        };
+
+        collector.push(match self.kind() {
+            AssertKind::True(_) => Instruction::assert(len),
+            AssertKind::Eq(..) => Instruction::assert_eq(len),
+            AssertKind::EqBool(..) => Instruction::assert_eq_b(len),
+        });
+
+        ctxt.labels_mut()
+            .set_position(success, collector.len() as u32)
+            .unwrap();
+
+        let operands = self.operands().len() as u16;
+        collector.push(Instruction::pop(operands));
+        (0..operands).for_each(|_| ctxt.stack_mut().pop_top_anonymous().unwrap());
+
+        // Just like `print`, an assertion evaluates to `()`.
+        collector.push(Instruction::push_i(0));
+        ctxt.stack_mut().push_anonymous();
+
+        operands_exp
+    }
+}
+
+impl Lowerable for ReadInt {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        collector.push(Instruction::read_i());
+        ctxt.stack_mut().push_anonymous();
+
+        Ok(())
+    }
+}
+
+/// Pushes the bytes of a string one by one, for the `panic`, `assert` and
+/// `print_s` instructions. Returns the number of pushed bytes, or `None` if
+/// there are too many of them to be popped by a single instruction.
+fn push_bytes(s: &str, collector: &mut Vec<Instruction>) -> Option<u16> {
+    let len = u16::try_from(s.len()).ok()?;
+
+    collector.extend(s.bytes().map(|byte| Instruction::push_i(i32::from(byte))));
+
+    Some(len)
+}
+
+/// Returns the value on top of the stack from the current function, whatever
+/// has been pushed since the function was called.
+fn lower_early_return(
+    collector: &mut Vec<Instruction>,
+    ctxt: &mut LoweringContext,
+) -> LoweringResult {
+    let postconditions_exp = lower_postconditions(collector, ctxt);
+    let stack = ctxt.stack();
+
+    match (stack.resolve(RETURN_SLOT), stack.resolve(RETURN_ADDRESS)) {
+        (Some(return_slot), Some(return_address)) => {
+            // The offsets are computed with the value on top of the stack,
+            // which is exactly what `pop_copy` expects.
+            collector.push(Instruction::pop_copy(return_slot));
+
+            let to_drop = return_address - 1;
+            if to_drop > 0 {
+                collector.push(Instruction::pop(to_drop));
+            }
+
+            // Only the parameters sit between the return slot and the return
+            // address.
+            collector.push(Instruction::ret(return_slot - return_address - 1));
+        }
+
+        // The `main` function stops the program instead, which requires the
+        // value to be the only one on the stack.
+        _ => {
+            let to_drop = stack.depth() as u16 - 1;
+            if to_drop > 0 {
+                collector.push(Instruction::pop_copy(to_drop));
+            }
+            if to_drop > 1 {
+                collector.push(Instruction::pop(to_drop - 1));
+            }
+
+            collector.push(Instruction::f_stop());
+        }
+    }
+
+    postconditions_exp
+}
+
+impl Lowerable for Bool {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        let num = if self.value() { 1 } else { 0 };
+
+        collector.push(Instruction::push_i(num));
+        ctxt.stack_mut().push_anonymous();
+
+        Ok(())
+    }
+}
+
+impl Lowerable for Unit {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        // `()` carries no information, but every expression must leave a
+        // value on the stack.
+        collector.push(Instruction::push_i(0));
+        ctxt.stack_mut().push_anonymous();
+
+        Ok(())
+    }
+}
+
+/// Stack name of the hidden loop counter.
+///
+/// It can't be written by the user, as it is not a valid identifier.
+const LOOP_COUNTER: &str = "#counter";
+
+/// Stack name of the hidden (exclusive) loop upper bound.
+const LOOP_BOUND: &str = "#bound";
+
+impl Lowerable for For {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        // A `for` loop is desugared to the following code:
+        //
+        //     counter = start
+        //     bound = end
+        //   loop_start:
+        //     if counter >= bound (> if inclusive) goto loop_end
+        //     variable = counter
+        //     body
+        //   loop_continue:
+        //     if counter == bound (if inclusive) goto loop_end
+        //     counter = counter + 1
+        //     goto loop_start
+        //   loop_end:
+        //     ()
+        //
+        // The counter is compared with the bound directly and never goes past
+        // it, so that neither can overflow.
+        let loop_subcontext = ctxt.stack().new_subcontext();
+
+        let start_exp = self.range().start().lower(collector, ctxt);
+        ctxt.stack_mut()
+            .name_top_anonymous(LOOP_COUNTER.to_owned())
+            .unwrap();
+
+        let end_exp = self.range().end().lower(collector, ctxt);
+        ctxt.stack_mut()
+            .name_top_anonymous(LOOP_BOUND.to_owned())
+            .unwrap();
+
+        let loop_start = ctxt.labels_mut().new_anonymous();
+        let loop_body = ctxt.labels_mut().new_anonymous();
+        let loop_continue = ctxt.labels_mut().new_anonymous();
+        let loop_end = ctxt.labels_mut().new_anonymous();
+
+        ctxt.labels_mut()
+            .set_position(loop_start, collector.len() as u32)
+            .unwrap();
+
+        push_hidden(LOOP_COUNTER, collector, ctxt);
+        push_hidden(LOOP_BOUND, collector, ctxt);
+        collector.push(Instruction::cmp());
+        ctxt.stack_mut().pop_top_anonymous().unwrap();
+
+        let on_bound = match self.range().is_inclusive() {
+            true => loop_body,
+            false => loop_end,
+        };
+        collector.push(Instruction::cond_jmp(loop_body, on_bound, loop_end));
+        ctxt.stack_mut().pop_top_anonymous().unwrap();
+
+        ctxt.labels_mut()
+            .set_position(loop_body, collector.len() as u32)
+            .unwrap();
+
+        let frame = LoopFrame {
+            break_label: loop_end,
+            continue_label: loop_continue,
+            depth: ctxt.stack().depth(),
+        };
+        ctxt.loops_mut().enter(frame);
+
+        push_hidden(LOOP_COUNTER, collector, ctxt);
+        ctxt.stack_mut()
+            .name_top_anonymous(self.variable().to_owned())
+            .unwrap();
+
+        let body_exp = self.body().lower(collector, ctxt);
+
+        // Both the loop variable and the value of the body are discarded.
+        collector.push(Instruction::pop(2));
+        ctxt.stack_mut().pop_top_anonymous().unwrap();
+        ctxt.stack_mut().drop_subcontext(frame.depth);
+
+        ctxt.loops_mut().exit();
+
+        ctxt.labels_mut()
+            .set_position(loop_continue, collector.len() as u32)
+            .unwrap();
+
+        if self.range().is_inclusive() {
+            let increment = ctxt.labels_mut().new_anonymous();
+
+            push_hidden(LOOP_COUNTER, collector, ctxt);
+            push_hidden(LOOP_BOUND, collector, ctxt);
+            collector.extend([
+                Instruction::cmp(),
+                Instruction::cond_jmp(increment, loop_end, increment),
+            ]);
+            ctxt.stack_mut().pop_top_anonymous().unwrap();
+            ctxt.stack_mut().pop_top_anonymous().unwrap();
+
+            ctxt.labels_mut()
+                .set_position(increment, collector.len() as u32)
+                .unwrap();
+        }
+
+        push_hidden(LOOP_COUNTER, collector, ctxt);
+        collector.extend([Instruction::push_i(1), Instruction::add_i()]);
+        let counter_offset = ctxt.stack().resolve(LOOP_COUNTER).unwrap();
+        collector.extend([
+            Instruction::pop_copy(counter_offset),
+            Instruction::goto(loop_start),
+        ]);
+        ctxt.stack_mut().pop_top_anonymous().unwrap();
+
+        ctxt.labels_mut()
+            .set_position(loop_end, collector.len() as u32)
+            .unwrap();
+
+        collector.extend([Instruction::pop(2), Instruction::push_i(0)]);
+        ctxt.stack_mut().drop_subcontext(loop_subcontext);
+        ctxt.stack_mut().push_anonymous();
+
+        start_exp.and(end_exp).and(body_exp)
+    }
+}
+
+fn push_hidden(name: &str, collector: &mut Vec<Instruction>, ctxt: &mut LoweringContext) {
+    let offset = ctxt.stack().resolve(name).unwrap();
+    collector.push(Instruction::push_copy(offset));
+    ctxt.stack_mut().push_anonymous();
+}
+
+impl Lowerable for Break {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        lower_loop_jump(collector, ctxt, "break", |frame| frame.break_label)
+    }
+}
+
+impl Lowerable for Continue {
+    fn lower(
+        &self,
+        collector: &mut Vec<Instruction>,
+        ctxt: &mut LoweringContext,
+    ) -> LoweringResult {
+        lower_loop_jump(collector, ctxt, "continue", |frame| frame.continue_label)
+    }
+}
+
+/// Drops everything that has been pushed since the beginning of the current
+/// iteration of the innermost loop, then jumps to one of its labels.
+fn lower_loop_jump(
+    collector: &mut Vec<Instruction>,
+    ctxt: &mut LoweringContext,
+    keyword: &str,
+    destination: impl FnOnce(&LoopFrame) -> u32,
+) -> LoweringResult {
+    // The code that follows a jump is never executed. We still pretend that
+    // the jump evaluates to `()`, so that the stack layout stays consistent.
+    let rslt = match ctxt.loops().innermost().copied() {
+        Some(frame) => {
+            let to_drop = (ctxt.stack().depth() - frame.depth) as u16;
+            if to_drop > 0 {
+                collector.push(Instruction::pop(to_drop));
+            }
+
+            collector.push(Instruction::goto(destination(&frame)));
+
+            Ok(())
+        }
+
+        None => {
+            ctxt.errors()
+                .add(format!("`{}` outside of a loop", keyword));
+
+            Err(())
+        }
+    };
+
+    ctxt.stack_mut().push_anonymous();
+
+    rslt
+}
+
+#[cfg(test)]
+fn lower(expr: &impl Lowerable) -> (Vec<Instruction>, LoweringContext) {
+    let mut collector = Vec::new();
+    let mut ctxt = LoweringContext::new();
+
+    if expr.lower(&mut collector, &mut ctxt).is_err() {
+        println!("{}", ctxt.errors());
+        panic!("called `Result::unwrap()` on an `Err` value: ()")
+    }
+
+    (collector, ctxt)
+}
+
+#[cfg(test)]
+#[test]
+fn lowering_can_fail() {
+    let ast = ExprKind::ident("undefined".to_owned());
+    let mut collector = Vec::new();
+    let mut ctxt = LoweringContext::new();
+
+    assert!(ast.lower(&mut collector, &mut ctxt).is_err());
+}
+
+#[cfg(test)]
+mod program {
+    use crate::inline_program;
+
+    use super::*;
+
+    #[test]
+    fn simplest_test() {
+        let program: Program = inline_program! { fn main() { 42 } };
+        let (instrs, _) = lower(&program);
+
+        assert_eq!(instrs, [Instruction::push_i(42), Instruction::f_stop()]);
+    }
+
+    #[test]
+    fn main_is_lowered_first() {
+        let program: Program = inline_program! {
+            fn ___() { 41 }
+            fn main() { 42 }
+        };
+
+        let (instrs, _) = lower(&program);
+
+        assert!(instrs.starts_with(&[Instruction::push_i(42), Instruction::f_stop()]));
+    }
+
+    #[test]
+    fn main_instrs_are_removed() {
+        let program: Program = inline_program! { fn main() { 42 } };
+        let (instrs, _) = lower(&program);
+
+        assert!(!instrs.ends_with(&[Instruction::ret(0)]));
+    }
+
+    #[test]
+    fn main_arguments_are_dropped_before_stopping() {
+        let params = vec![
+            Param::new("a".to_owned(), Ty::Int),
+            Param::new("b".to_owned(), Ty::Int),
+        ];
+        let body = ExprKind::ident("b".to_owned());
+        let main = Function::new("main".to_owned(), Vec::new(), params, None, body);
+        let (instrs, ctxt) = lower(&Program::new(vec![main]));
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_copy(0),
+                Instruction::pop_copy(2),
+                Instruction::pop(1),
+                Instruction::f_stop(),
+            ]
+        );
+
+        let symbols = ctxt.into_label_resolution_context().symbols();
+        assert_eq!(symbols.main_params(), 2);
+    }
+
+    #[test]
+    fn main_takes_integers() {
+        let params = vec![Param::new("a".to_owned(), Ty::Bool)];
+        let main = Function::new(
+            "main".to_owned(),
+            Vec::new(),
+            params,
+            None,
+            ExprKind::integer(0),
+        );
+        let program = Program::new(vec![main]);
+        let mut ctxt = LoweringContext::new();
+
+        assert!(program.lower(&mut Vec::new(), &mut ctxt).is_err());
+        assert_eq!(
+            ctxt.errors().to_string(),
+            "Parameter `a` of `main` must be an integer, found type `bool`\n"
+        );
+    }
+
+    #[test]
+    fn extern_functions_forward_to_the_host() {
+        let params = vec![
+            Param::new("a".to_owned(), crate::ty::Ty::Int),
+            Param::new("b".to_owned(), crate::ty::Ty::Int),
+        ];
+        let extern_ = ExternFunction::new("add".to_owned(), params, crate::ty::Ty::Int);
+        let program: Program = inline_program! { fn main() { 42 } };
+        let (instrs, ctxt) = lower(&program.with_externs(vec![extern_]));
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_i(42),
+                Instruction::f_stop(),
+                Instruction::push_copy(2),
+                Instruction::push_copy(2),
+                Instruction::host_call(0, 2),
+                Instruction::pop_copy(4),
+                Instruction::ret(2),
+            ]
+        );
+        assert_eq!(ctxt.labels().resolve_named("add"), Ok(2));
+
+        let symbols = ctxt.into_label_resolution_context().symbols();
+        assert_eq!(symbols.host_functions(), ["add"]);
+    }
+}
+
+#[cfg(test)]
+mod function {
+    use crate::{
+        ast::{Clause, ClauseKind},
+        inline_fn,
+        ty::Ty,
+    };
+
+    use super::*;
+
+    #[test]
+    fn body_is_lowered() {
+        let f: Function = inline_fn! { fn f() { 42 } };
+        let (instrs, _) = lower(&f);
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_i(42),
+                Instruction::pop_copy(2),
+                Instruction::ret(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn params_are_accessible() {
+        let f = Function::new(
+            "f".to_owned(),
+            Vec::new(),
+            vec![
+                Param::new("a".to_owned(), Ty::Int),
+                Param::new("b".to_owned(), Ty::Int),
+            ],
+            Some(Ty::Int),
+            ExprKind::ident("a".to_owned()),
+        );
+        let (instrs, _) = lower(&f);
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_copy(2),
+                Instruction::pop_copy(4),
+                Instruction::ret(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn contracts_are_checked() {
+        let ident = |name: &str| ExprKind::ident(name.to_owned());
+        let clauses = vec![
+            Clause::new(ClauseKind::Requires, ident("a"), "a".to_owned()),
+            Clause::new(ClauseKind::Ensures, ident("result"), "result".to_owned()),
+        ];
+        let f = Function::new(
+            "f".to_owned(),
+            Vec::new(),
+            vec![Param::new("a".to_owned(), Ty::Int)],
+            Some(Ty::Int),
+            ident("a"),
+        )
+        .with_clauses(clauses);
+        let (instrs, _) = lower(&f);
+
+        let message = |s: &str| {
+            s.bytes()
+                .map(|byte| Instruction::push_i(i32::from(byte)))
+                .collect::<Vec<_>>()
+        };
+        let expected = [
+            vec![Instruction::push_copy(1)],
+            message("Contract violation: `f` requires `a`"),
+            vec![
+                Instruction::assert(36),
+                Instruction::push_copy(1),
+                Instruction::push_copy(0),
+                Instruction::push_copy(0),
+            ],
+            message("Contract violation: `f` ensures `result`"),
+            vec![
+                Instruction::assert(40),
+                Instruction::pop(1),
+                Instruction::pop_copy(3),
+                Instruction::ret(1),
+            ],
+        ]
+        .concat();
+
+        assert_eq!(instrs, expected);
+    }
+
+    #[test]
+    fn memo_results_are_cached() {
+        let f = Function::new(
+            "f".to_owned(),
+            Vec::new(),
+            vec![Param::new("a".to_owned(), Ty::Int)],
+            Some(Ty::Int),
+            ExprKind::ident("a".to_owned()),
+        )
+        .memo();
+        let (instrs, ctxt) = lower(&f);
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::memo_load("f".to_owned(), 1, 0),
+                Instruction::push_copy(1),
+                Instruction::memo_store("f".to_owned(), 1),
+                Instruction::pop_copy(3),
+                Instruction::ret(1)
+            ]
+        );
+        assert_eq!(ctxt.labels().resolve_anonymous(0), Ok(3));
     }
 
     #[test]
@@ -403,6 +1756,177 @@ mod function {
 
         assert!(ctxt.labels().resolve_named("foo").is_ok());
     }
+
+    #[test]
+    fn method_label_is_mangled() {
+        let f = Function::new(
+            crate::ast::method_name(None, &Ty::Int, "abs"),
+            Vec::new(),
+            vec![Param::new("self".to_owned(), Ty::Int)],
+            Some(Ty::Int),
+            ExprKind::ident("self".to_owned()),
+        );
+        let (_, ctxt) = lower(&f);
+
+        assert!(ctxt.labels().resolve_named("int::abs").is_ok());
+        assert!(ctxt.labels().resolve_named("abs").is_err());
+    }
+}
+
+#[cfg(test)]
+mod call {
+    use super::*;
+
+    fn ctxt_with_function(name: &str) -> LoweringContext {
+        let mut ctxt = LoweringContext::new();
+        ctxt.add_function(name.to_owned());
+
+        ctxt
+    }
+
+    #[test]
+    fn direct_call() {
+        let mut ctxt = ctxt_with_function("f");
+        let mut instrs = Vec::new();
+        let expr = ExprKind::call(
+            ExprKind::ident("f".to_owned()),
+            vec![ExprKind::integer(1), ExprKind::integer(2)],
+        );
+
+        expr.lower(&mut instrs, &mut ctxt).unwrap();
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::res_v(1),
+                Instruction::push_i(1),
+                Instruction::push_i(2),
+                Instruction::call("f".to_owned()),
+            ]
+        );
+        assert_eq!(ctxt.stack().depth(), 1);
+    }
+
+    #[test]
+    fn indirect_call() {
+        let mut ctxt = LoweringContext::new();
+        ctxt.stack_mut().push_named("g".to_owned());
+        let mut instrs = Vec::new();
+        let expr = ExprKind::call(ExprKind::ident("g".to_owned()), vec![ExprKind::integer(1)]);
+
+        expr.lower(&mut instrs, &mut ctxt).unwrap();
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::res_v(1),
+                Instruction::push_i(1),
+                Instruction::push_copy(2),
+                Instruction::call_indirect(),
+            ]
+        );
+        assert_eq!(ctxt.stack().depth(), 2);
+    }
+
+    #[test]
+    fn function_reference() {
+        let mut ctxt = ctxt_with_function("f");
+        let mut instrs = Vec::new();
+
+        ExprKind::ident("f".to_owned())
+            .lower(&mut instrs, &mut ctxt)
+            .unwrap();
+
+        assert_eq!(instrs, [Instruction::push_fn("f".to_owned())]);
+    }
+
+    #[test]
+    fn variables_shadow_functions() {
+        let mut ctxt = ctxt_with_function("f");
+        ctxt.stack_mut().push_named("f".to_owned());
+        let mut instrs = Vec::new();
+
+        ExprKind::ident("f".to_owned())
+            .lower(&mut instrs, &mut ctxt)
+            .unwrap();
+
+        assert_eq!(instrs, [Instruction::push_copy(0)]);
+    }
+}
+
+#[cfg(test)]
+mod lambda {
+    use crate::{ast::Param, ty::Ty};
+
+    use super::*;
+
+    fn times_k() -> ExprKind {
+        ExprKind::lambda(
+            vec![Param::new("x".to_owned(), Ty::Int)],
+            ExprKind::multiplication(
+                ExprKind::ident("x".to_owned()),
+                ExprKind::ident("k".to_owned()),
+            ),
+        )
+    }
+
+    #[test]
+    fn generated_instructions() {
+        let mut ctxt = LoweringContext::new();
+        ctxt.stack_mut().push_named("k".to_owned());
+        let mut instrs = Vec::new();
+
+        times_k().lower(&mut instrs, &mut ctxt).unwrap();
+
+        // Stack inside the lambda: [return slot, x, k, return address].
+        assert_eq!(
+            instrs,
+            [
+                Instruction::goto(1),
+                Instruction::push_copy(2),
+                Instruction::push_copy(2),
+                Instruction::mul(),
+                Instruction::pop_copy(4),
+                Instruction::ret(2),
+                Instruction::push_copy(0),
+                Instruction::make_closure(0, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn label_effects() {
+        let mut ctxt = LoweringContext::new();
+        ctxt.stack_mut().push_named("k".to_owned());
+
+        times_k().lower(&mut Vec::new(), &mut ctxt).unwrap();
+
+        assert_eq!(ctxt.labels().resolve_anonymous(0), Ok(1));
+        assert_eq!(ctxt.labels().resolve_anonymous(1), Ok(6));
+    }
+
+    #[test]
+    fn stack_effects() {
+        let mut ctxt = LoweringContext::new();
+        ctxt.stack_mut().push_named("k".to_owned());
+
+        times_k().lower(&mut Vec::new(), &mut ctxt).unwrap();
+
+        assert_eq!(ctxt.stack().depth(), 2);
+        assert_eq!(ctxt.stack().resolve("k"), Some(1));
+    }
+
+    #[test]
+    fn functions_are_not_captured() {
+        let mut ctxt = LoweringContext::new();
+        ctxt.add_function("f".to_owned());
+        let mut instrs = Vec::new();
+
+        let expr = ExprKind::lambda(Vec::new(), ExprKind::ident("f".to_owned()));
+        expr.lower(&mut instrs, &mut ctxt).unwrap();
+
+        assert_eq!(instrs.last(), Some(&Instruction::make_closure(0, 0)));
+    }
 }
 
 #[cfg(test)]
@@ -516,44 +2040,159 @@ mod subtraction {
     }
 }
 
+#[cfg(test)]
+mod comparison {
+    use crate::ast::ComparisonOperator;
+
+    use super::*;
+
+    #[test]
+    fn jumps_on_the_ordering_of_the_operands() {
+        let comparison = ExprKind::comparison(
+            ComparisonOperator::Le,
+            ExprKind::integer(1),
+            ExprKind::integer(2),
+        );
+        let (instrs, ctxt) = lower(&comparison);
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_i(1),
+                Instruction::push_i(2),
+                Instruction::cmp(),
+                Instruction::cond_jmp(0, 0, 1),
+                Instruction::push_i(1),
+                Instruction::goto(2),
+                Instruction::push_i(0),
+            ]
+        );
+        assert_eq!(ctxt.labels().resolve_anonymous(0).unwrap(), 4);
+        assert_eq!(ctxt.labels().resolve_anonymous(1).unwrap(), 6);
+        assert_eq!(ctxt.labels().resolve_anonymous(2).unwrap(), 7);
+        assert_eq!(ctxt.stack().depth(), 1);
+    }
+
+    #[test]
+    fn every_operator_compares_with_cmp() {
+        // Label 0 pushes `true` and label 1 pushes `false`.
+        let operators = [
+            (ComparisonOperator::Lt, (0, 1, 1)),
+            (ComparisonOperator::Le, (0, 0, 1)),
+            (ComparisonOperator::Gt, (1, 1, 0)),
+            (ComparisonOperator::Ge, (1, 0, 0)),
+            (ComparisonOperator::Eq, (1, 0, 1)),
+            (ComparisonOperator::Ne, (0, 1, 0)),
+        ];
+
+        for (operator, (less, equal, greater)) in operators {
+            let comparison = ExprKind::comparison(
+                operator,
+                ExprKind::integer(i32::MIN),
+                ExprKind::integer(i32::MAX),
+            );
+            let (instrs, _) = lower(&comparison);
+
+            assert_eq!(
+                instrs[..4],
+                [
+                    Instruction::push_i(i32::MIN),
+                    Instruction::push_i(i32::MAX),
+                    Instruction::cmp(),
+                    Instruction::cond_jmp(less, equal, greater),
+                ]
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod if_ {
     use crate::inline_expr;
 
-    use super::*;
+    use super::*;
+
+    fn simple_if() -> ExprKind {
+        inline_expr! { if 1 { 42 } else { -1 } }
+    }
+
+    #[test]
+    fn generated_instructions() {
+        let (left, _) = lower(&simple_if());
+
+        assert_eq!(
+            left,
+            [
+                Instruction::push_i(1),
+                Instruction::cond_jmp(0, 1, 0),
+                Instruction::push_i(42),
+                Instruction::goto(2),
+                Instruction::push_i(-1),
+            ],
+        );
+    }
+
+    #[test]
+    fn label_effects() {
+        let (_, ctxt) = lower(&simple_if());
+
+        assert_eq!(ctxt.labels().resolve_anonymous(0).unwrap(), 2);
+        assert_eq!(ctxt.labels().resolve_anonymous(1).unwrap(), 4);
+        assert_eq!(ctxt.labels().resolve_anonymous(2).unwrap(), 5);
+    }
+
+    #[test]
+    fn stack_effects() {
+        let (_, ctxt) = lower(&simple_if());
+
+        assert_eq!(ctxt.stack().depth(), 1);
+        assert!(ctxt.stack().top().unwrap().is_empty());
+    }
 
-    fn simple_if() -> ExprKind {
-        inline_expr! { if 1 { 42 } else { -1 } }
+    fn else_if_chain() -> ExprKind {
+        ExprKind::if_(
+            ExprKind::bool_(true),
+            ExprKind::integer(1),
+            ExprKind::if_(
+                ExprKind::bool_(false),
+                ExprKind::integer(2),
+                ExprKind::integer(3),
+            ),
+        )
     }
 
     #[test]
-    fn generated_instructions() {
-        let (left, _) = lower(&simple_if());
+    fn else_if_chain_instructions() {
+        let (left, _) = lower(&else_if_chain());
 
         assert_eq!(
             left,
             [
                 Instruction::push_i(1),
                 Instruction::cond_jmp(0, 1, 0),
-                Instruction::push_i(42),
+                Instruction::push_i(1),
                 Instruction::goto(2),
-                Instruction::push_i(-1),
+                Instruction::push_i(0),
+                Instruction::cond_jmp(3, 4, 3),
+                Instruction::push_i(2),
+                Instruction::goto(2),
+                Instruction::push_i(3),
             ],
         );
     }
 
     #[test]
-    fn label_effects() {
-        let (_, ctxt) = lower(&simple_if());
+    fn else_if_chain_label_effects() {
+        let (_, ctxt) = lower(&else_if_chain());
 
-        assert_eq!(ctxt.labels().resolve_anonymous(0).unwrap(), 2);
-        assert_eq!(ctxt.labels().resolve_anonymous(1).unwrap(), 4);
-        assert_eq!(ctxt.labels().resolve_anonymous(2).unwrap(), 5);
+        assert_eq!(ctxt.labels().resolve_anonymous(2).unwrap(), 9);
+        assert_eq!(ctxt.labels().resolve_anonymous(4).unwrap(), 8);
+        assert!(ctxt.labels().resolve_anonymous(5).is_err());
     }
 
     #[test]
-    fn stack_effects() {
-        let (_, ctxt) = lower(&simple_if());
+    fn else_if_chain_stack_effects() {
+        let (_, ctxt) = lower(&else_if_chain());
 
         assert_eq!(ctxt.stack().depth(), 1);
         assert!(ctxt.stack().top().unwrap().is_empty());
@@ -620,6 +2259,206 @@ mod bindings {
     }
 }
 
+#[cfg(test)]
+mod statements {
+    use crate::ast::Statement;
+
+    use super::*;
+
+    fn statements() -> ExprKind {
+        ExprKind::statements(
+            vec![
+                Statement::Expr(ExprKind::integer(101)),
+                Statement::Binding(Binding::new("a".to_owned(), ExprKind::integer(42))),
+            ],
+            ExprKind::ident("a".to_owned()),
+        )
+    }
+
+    #[test]
+    fn generated_instructions() {
+        let (bytecode, _) = lower(&statements());
+
+        assert_eq!(
+            bytecode,
+            [
+                Instruction::push_i(101),
+                Instruction::pop(1),
+                Instruction::push_i(42),
+                Instruction::push_copy(0),
+                Instruction::pop_copy(1),
+                Instruction::pop(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_cleanup_without_bindings() {
+        let expr = ExprKind::statements(
+            vec![Statement::Expr(ExprKind::integer(101))],
+            ExprKind::unit(),
+        );
+        let (bytecode, _) = lower(&expr);
+
+        assert_eq!(
+            bytecode,
+            [
+                Instruction::push_i(101),
+                Instruction::pop(1),
+                Instruction::push_i(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn stack_effects() {
+        let (_, ctxt) = lower(&statements());
+
+        assert_eq!(ctxt.stack().depth(), 1);
+        assert!(ctxt.stack().top().unwrap().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod for_ {
+    use crate::ast::{Range, Statement};
+
+    use super::*;
+
+    fn simple_for(body: ExprKind) -> ExprKind {
+        ExprKind::for_(
+            "i".to_owned(),
+            Range::new(ExprKind::integer(0), ExprKind::integer(10), false),
+            body,
+        )
+    }
+
+    #[test]
+    fn generated_instructions() {
+        let (left, _) = lower(&simple_for(ExprKind::unit()));
+
+        assert_eq!(
+            left,
+            [
+                Instruction::push_i(0),
+                Instruction::push_i(10),
+                // loop_start (2)
+                Instruction::push_copy(1),
+                Instruction::push_copy(1),
+                Instruction::cmp(),
+                Instruction::cond_jmp(1, 3, 3),
+                // loop_body (6)
+                Instruction::push_copy(1),
+                Instruction::push_i(0),
+                Instruction::pop(2),
+                // loop_continue (9)
+                Instruction::push_copy(1),
+                Instruction::push_i(1),
+                Instruction::add_i(),
+                Instruction::pop_copy(2),
+                Instruction::goto(0),
+                // loop_end (14)
+                Instruction::pop(2),
+                Instruction::push_i(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn inclusive_range_stops_at_the_bound() {
+        let expr = ExprKind::for_(
+            "i".to_owned(),
+            Range::new(ExprKind::integer(0), ExprKind::integer(10), true),
+            ExprKind::unit(),
+        );
+        let (left, _) = lower(&expr);
+
+        assert_eq!(
+            left[2..6],
+            [
+                Instruction::push_copy(1),
+                Instruction::push_copy(1),
+                Instruction::cmp(),
+                Instruction::cond_jmp(1, 1, 3),
+            ]
+        );
+        // The loop is left before the counter goes past the bound.
+        assert_eq!(
+            left[9..13],
+            [
+                Instruction::push_copy(1),
+                Instruction::push_copy(1),
+                Instruction::cmp(),
+                Instruction::cond_jmp(4, 3, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn label_effects() {
+        let (_, ctxt) = lower(&simple_for(ExprKind::unit()));
+
+        assert_eq!(ctxt.labels().resolve_anonymous(0).unwrap(), 2);
+        assert_eq!(ctxt.labels().resolve_anonymous(1).unwrap(), 6);
+        assert_eq!(ctxt.labels().resolve_anonymous(2).unwrap(), 9);
+        assert_eq!(ctxt.labels().resolve_anonymous(3).unwrap(), 14);
+    }
+
+    #[test]
+    fn stack_effects() {
+        let (_, ctxt) = lower(&simple_for(ExprKind::unit()));
+
+        assert_eq!(ctxt.stack().depth(), 1);
+        assert!(ctxt.stack().top().unwrap().is_empty());
+    }
+
+    #[test]
+    fn loop_variable_is_defined() {
+        let (left, _) = lower(&simple_for(ExprKind::statements(
+            vec![Statement::Expr(ExprKind::ident("i".to_owned()))],
+            ExprKind::unit(),
+        )));
+
+        assert_eq!(left[7], Instruction::push_copy(0));
+    }
+
+    #[test]
+    fn break_drops_iteration_values() {
+        let body = ExprKind::bindings(
+            vec![Binding::new("a".to_owned(), ExprKind::integer(1))],
+            ExprKind::break_(),
+        );
+        let (left, _) = lower(&simple_for(body));
+
+        // `i` and `a` are dropped before jumping to the end of the loop.
+        assert_eq!(left[8..10], [Instruction::pop(2), Instruction::goto(3)]);
+    }
+
+    #[test]
+    fn continue_jumps_to_next_iteration() {
+        let (left, _) = lower(&simple_for(ExprKind::continue_()));
+
+        assert_eq!(left[7..9], [Instruction::pop(1), Instruction::goto(2)]);
+    }
+
+    #[test]
+    fn jump_outside_of_loop_fails() {
+        let mut ctxt = LoweringContext::new();
+        let mut instructions = Vec::new();
+
+        assert!(ExprKind::break_()
+            .lower(&mut instructions, &mut ctxt)
+            .is_err());
+        assert!(ExprKind::continue_()
+            .lower(&mut instructions, &mut ctxt)
+            .is_err());
+        assert_eq!(
+            ctxt.errors().to_string(),
+            "`break` outside of a loop\n`continue` outside of a loop\n"
+        );
+    }
+}
+
 #[cfg(test)]
 mod binding {
     use super::*;
@@ -742,3 +2581,232 @@ mod bool {
         assert!(ctxt.stack().top().unwrap().is_empty());
     }
 }
+
+#[cfg(test)]
+mod records {
+    use super::*;
+
+    #[test]
+    fn literal_and_field_access() {
+        let mut ctxt = LoweringContext::new();
+        let mut instrs = Vec::new();
+        let literal = ExprKind::record_literal(
+            "P".to_owned(),
+            vec![
+                ("x".to_owned(), ExprKind::integer(1)),
+                ("y".to_owned(), ExprKind::integer(2)),
+            ],
+        );
+        let expr = ExprKind::FieldAccess(FieldAccess::new(literal, "y".to_owned()).with_index(1));
+
+        expr.lower(&mut instrs, &mut ctxt).unwrap();
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_i(1),
+                Instruction::push_i(2),
+                Instruction::make_record(2),
+                Instruction::get_field(1),
+            ]
+        );
+        assert_eq!(ctxt.stack().depth(), 1);
+    }
+}
+
+#[cfg(test)]
+mod options {
+    use crate::{ast::Constructor, ty::Ty};
+
+    use super::*;
+
+    #[test]
+    fn variants_are_tagged_records() {
+        let (instrs, ctxt) = lower(&ExprKind::variant(
+            Constructor::Some,
+            Some(ExprKind::integer(42)),
+        ));
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_i(1),
+                Instruction::push_i(42),
+                Instruction::make_record(2),
+            ]
+        );
+        assert_eq!(ctxt.stack().depth(), 1);
+    }
+
+    #[test]
+    fn try_returns_early() {
+        let function = Function::new(
+            "f".to_owned(),
+            Vec::new(),
+            vec![Param::new("o".to_owned(), Ty::Err)],
+            None,
+            ExprKind::try_(ExprKind::ident("o".to_owned())),
+        );
+
+        let (instrs, _) = lower(&function);
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_copy(1),
+                Instruction::push_copy(0),
+                Instruction::get_field(0),
+                Instruction::cond_jmp(0, 0, 1),
+                // The `None` is returned as is.
+                Instruction::pop_copy(3),
+                Instruction::ret(1),
+                Instruction::get_field(1),
+                Instruction::pop_copy(3),
+                Instruction::ret(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn panic_pushes_its_message() {
+        let (instrs, ctxt) = lower(&ExprKind::panic("oh".to_owned()));
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_i(111),
+                Instruction::push_i(104),
+                Instruction::panic(2),
+            ]
+        );
+        assert_eq!(ctxt.stack().depth(), 1);
+    }
+}
+
+#[cfg(test)]
+mod print {
+    use crate::ast::PrintArg;
+
+    use super::*;
+
+    #[test]
+    fn prints_a_value() {
+        let arg = PrintArg::Bool(Box::new(ExprKind::bool_(true)));
+        let (instrs, ctxt) = lower(&ExprKind::print(arg, false));
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_i(1),
+                Instruction::print_b(),
+                Instruction::push_i(0),
+            ]
+        );
+        assert_eq!(ctxt.stack().depth(), 1);
+    }
+
+    #[test]
+    fn println_pushes_its_string_and_a_newline() {
+        let arg = PrintArg::Str("ok".to_owned());
+        let (instrs, ctxt) = lower(&ExprKind::print(arg, true));
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_i(111),
+                Instruction::push_i(107),
+                Instruction::print_s(2),
+                Instruction::push_i(10),
+                Instruction::print_s(1),
+                Instruction::push_i(0),
+            ]
+        );
+        assert_eq!(ctxt.stack().depth(), 1);
+    }
+}
+
+#[cfg(test)]
+mod lists {
+    use crate::ast::BuiltinFunction;
+
+    use super::*;
+
+    #[test]
+    fn list_literal() {
+        let list = ExprKind::list(vec![ExprKind::integer(1), ExprKind::integer(2)]);
+        let (instrs, ctxt) = lower(&list);
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_i(1),
+                Instruction::push_i(2),
+                Instruction::make_list(2),
+            ]
+        );
+        assert_eq!(ctxt.stack().depth(), 1);
+    }
+
+    #[test]
+    fn builtin_call() {
+        let list = ExprKind::list(Vec::new());
+        let call = ExprKind::builtin(BuiltinFunction::Cons, vec![ExprKind::integer(1), list]);
+        let (instrs, ctxt) = lower(&call);
+
+        assert_eq!(
+            instrs,
+            [
+                Instruction::push_i(1),
+                Instruction::make_list(0),
+                Instruction::builtin(BuiltinFunction::Cons),
+            ]
+        );
+        assert_eq!(ctxt.stack().depth(), 1);
+    }
+}
+
+#[cfg(test)]
+mod assertions {
+    use crate::ast::{AssertKind, Span};
+
+    use super::*;
+
+    #[test]
+    fn assert_eq() {
+        let kind = AssertKind::Eq(ExprKind::integer(1), ExprKind::integer(2));
+        let assert = ExprKind::assert(kind, Span::new(Some("a".to_owned()), 1, 2));
+        let (instrs, ctxt) = lower(&assert);
+
+        let message = "Assertion failed at a:1:2"
+            .bytes()
+            .map(|byte| Instruction::push_i(i32::from(byte)));
+        let expected = [
+            Instruction::push_i(1),
+            Instruction::push_i(2),
+            Instruction::push_copy(1),
+            Instruction::push_copy(1),
+            Instruction::cmp(),
+            Instruction::cond_jmp(0, 1, 0),
+        ]
+        .into_iter()
+        .chain(message)
+        .chain([
+            Instruction::assert_eq(25),
+            Instruction::pop(2),
+            Instruction::push_i(0),
+        ])
+        .collect::<Vec<_>>();
+
+        assert_eq!(instrs, expected);
+        assert_eq!(ctxt.stack().depth(), 1);
+    }
+
+    #[test]
+    fn assert_eq_on_booleans() {
+        let kind = AssertKind::EqBool(ExprKind::bool_(true), ExprKind::bool_(false));
+        let assert = ExprKind::assert(kind, Span::new(None, 1, 2));
+        let (instrs, _) = lower(&assert);
+
+        assert_eq!(instrs[instrs.len() - 3], Instruction::assert_eq_b(23));
+    }
+}
@@ -95,7 +95,7 @@ macro_rules! parse_block_inner {
         [ let $name:ident = $( $tt:tt )* ]
         $parsed:tt
     ) => {
-        crate::parse_block_inner! {
+        $crate::parse_block_inner! {
             @munching_expr [ $( $tt )* ]  [ $name ]
             $parsed
         }
@@ -105,7 +105,7 @@ macro_rules! parse_block_inner {
         @munching_expr [ ; $( $tt:tt )* ] [ $name:ident $( $value:tt )* ]
         [ $( $parsed:tt )* ]
     ) => {
-        crate::parse_block_inner! {
+        $crate::parse_block_inner! {
             [ $( $tt )* ]
             [ $( $parsed )* ($name, $( $value )* ) ]
         }
@@ -115,7 +115,7 @@ macro_rules! parse_block_inner {
         @munching_expr [ $head:tt $( $tail:tt )* ] [ $( $current:tt )* ]
         $parsed:tt
     ) => {
-        crate::parse_block_inner! {
+        $crate::parse_block_inner! {
             @munching_expr [ $( $tail )* ] [ $( $current )* $head ]
             $parsed
         }
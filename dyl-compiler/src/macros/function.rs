@@ -26,7 +26,7 @@ impl From<Function> for ast::Function {
         let name = name.to_string();
         let body = ast::ExprKind::from(body);
 
-        ast::Function::new(name, body)
+        ast::Function::new(name, Vec::new(), Vec::new(), None, body)
     }
 }
 
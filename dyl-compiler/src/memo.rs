@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::{Function, Program},
+    context::{PassResult, TypingContext},
+    features::{Feature, FeatureFinder},
+    ty::Ty,
+};
+
+/// Checks that the results of the functions marked `#[memo]` can be cached.
+///
+/// Such a function must take integers and return an integer or a boolean, so
+/// that the cache is keyed by plain values and doesn't keep objects alive. It
+/// must also be pure: neither it nor the functions it calls may read or
+/// write anything, call extern functions or call a function through a value.
+///
+/// Method calls are expected to be resolved already.
+pub(crate) fn check_memo_fns(ast: &Program, ctxt: TypingContext) -> PassResult<TypingContext, ()> {
+    let rslt = check_program(ast, &ctxt);
+    ctxt.wrap_result(rslt)
+}
+
+fn check_program(ast: &Program, ctxt: &TypingContext) -> Result<(), ()> {
+    let impurities = impurities(ast);
+
+    let rslt = ast
+        .functions()
+        .iter()
+        .filter(|f| f.is_memo())
+        .map(|f| {
+            check_memo_fn(f, &impurities).map_err(|e| {
+                ctxt.errs()
+                    .set_file(ast.file_of(f.name()).map(str::to_owned));
+                ctxt.errs().add(e)
+            })
+        })
+        .fold(Ok(()), Result::and);
+    ctxt.errs().set_file(None);
+
+    rslt
+}
+
+fn check_memo_fn(function: &Function, impurities: &HashMap<&str, Impurity>) -> Result<(), String> {
+    if function.name() == "main" {
+        return Err("`main` can't be `#[memo]`".to_owned());
+    }
+
+    let signature_is_cacheable = function.params().iter().all(|p| p.ty() == &Ty::Int)
+        && function
+            .ret_ty()
+            .is_some_and(|ty| matches!(ty, Ty::Int | Ty::Bool));
+
+    if !signature_is_cacheable {
+        return Err(format!(
+            "`#[memo]` function `{}` can only take integers and return an integer or a boolean",
+            function.name()
+        ));
+    }
+
+    match impurities.get(function.name()) {
+        None => Ok(()),
+        Some(Impurity::Effect(what)) => Err(format!(
+            "`#[memo]` function `{}` can't use {}",
+            function.name(),
+            what
+        )),
+        Some(Impurity::Call(callee)) => Err(format!(
+            "`#[memo]` function `{}` calls `{}`, which is not pure",
+            function.name(),
+            callee
+        )),
+    }
+}
+
+/// Why a function is not pure.
+#[derive(Clone, Debug, PartialEq)]
+enum Impurity {
+    /// The function itself has a side effect, or may have one.
+    Effect(String),
+    /// The function calls a function that is not pure.
+    Call(String),
+}
+
+/// Returns the functions of the program that are not pure, along with the
+/// reason why.
+fn impurities(ast: &Program) -> HashMap<&str, Impurity> {
+    let finder = FeatureFinder::new(ast);
+    let features = ast
+        .functions()
+        .iter()
+        .map(|f| (f.name(), finder.features(f)))
+        .collect::<Vec<_>>();
+
+    let mut impurities = features
+        .iter()
+        .filter_map(|(name, features)| {
+            let effect = features.iter().find(|f| may_have_side_effects(f))?;
+            Some((*name, Impurity::Effect(effect.description())))
+        })
+        .collect::<HashMap<_, _>>();
+
+    // Impurity spreads from callees to callers, until it stops spreading.
+    loop {
+        let newly_impure = features
+            .iter()
+            .filter(|(name, _)| !impurities.contains_key(name))
+            .filter_map(|(name, features)| {
+                let callee = features.iter().find_map(|feature| match feature {
+                    Feature::Call(callee) if impurities.contains_key(callee) => Some(*callee),
+                    _ => None,
+                })?;
+                Some((*name, Impurity::Call(callee.to_owned())))
+            })
+            .collect::<Vec<_>>();
+
+        if newly_impure.is_empty() {
+            return impurities;
+        }
+
+        impurities.extend(newly_impure);
+    }
+}
+
+/// Returns whether using `feature` may have a side effect. Calls to other
+/// functions of the program are checked separately.
+fn may_have_side_effects(feature: &Feature) -> bool {
+    match feature {
+        Feature::ExternCall(_)
+        | Feature::IndirectCall
+        | Feature::MethodCall
+        | Feature::Print
+        | Feature::ReadInt => true,
+        Feature::Call(_)
+        | Feature::Lambda
+        | Feature::Record
+        | Feature::OptionOrResult
+        | Feature::Collection => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::check;
+
+    use super::*;
+
+    #[test]
+    fn pure_recursive_functions_are_accepted() {
+        let errs = check(
+            "#[memo]
+             fn fib(n: int) -> int { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+             #[memo]
+             fn even(n: int) -> bool { if n == 0 { true } else { odd(n - 1) } }
+             fn odd(n: int) -> bool { if n == 0 { false } else { even(n - 1) } }",
+            check_program,
+        );
+
+        assert_eq!(errs, "");
+    }
+
+    #[test]
+    fn signature_must_be_cacheable() {
+        let errs = check(
+            "#[memo] fn f(b: bool) -> int { 1 }
+             #[memo] fn g(n: int) { }
+             #[memo] fn main() -> int { 1 }",
+            check_program,
+        );
+
+        assert_eq!(
+            errs,
+            "`#[memo]` function `f` can only take integers and return an integer or a boolean\n\
+             `#[memo]` function `g` can only take integers and return an integer or a boolean\n\
+             `main` can't be `#[memo]`\n"
+        );
+    }
+
+    #[test]
+    fn side_effects_are_rejected() {
+        let errs = check(
+            "extern fn now() -> int;
+             fn log(n: int) -> int { println(n); n }
+             fn helper(n: int) -> int { log(n) }
+             #[memo] fn a(n: int) -> int { read_int() }
+             #[memo] fn b(n: int) -> int { now() }
+             #[memo] fn c(n: int) -> int { let f = |x: int| x; f(n) }
+             #[memo] fn d(n: int) -> int { helper(n) }",
+            check_program,
+        );
+
+        assert_eq!(
+            errs,
+            "`#[memo]` function `a` can't use `read_int`\n\
+             `#[memo]` function `b` can't use the extern function `now`\n\
+             `#[memo]` function `c` can't use indirect calls\n\
+             `#[memo]` function `d` calls `helper`, which is not pure\n"
+        );
+    }
+
+    #[test]
+    fn errors_are_prefixed_with_the_file() {
+        let (_, ast) = crate::parser::parse_input("#[memo] fn f(b: bool) -> int { 1 }").unwrap();
+        let files = [("f".to_owned(), "a.dyl".to_owned())].into_iter().collect();
+        let ctxt = TypingContext::new();
+
+        let _ = check_program(&ast.with_files(files), &ctxt);
+
+        assert_eq!(
+            ctxt.errs().to_string(),
+            "a.dyl: `#[memo]` function `f` can only take integers and return an integer or a boolean\n"
+        );
+    }
+}
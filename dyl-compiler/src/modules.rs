@@ -0,0 +1,764 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result as AnyResult;
+
+use crate::{
+    ast::{self, Impl, Item, MethodSignature, Program, Record, Trait},
+    context::{ParsingContext, PassResult},
+    expansion,
+    free_vars::FreeVariables,
+    io,
+    parser::{self, resolve_record_names, substitute_params},
+    ty::Ty,
+};
+
+/// The source of the prelude, whose public functions are available in every
+/// module without being imported.
+const PRELUDE: &str = include_str!("prelude.dyl");
+
+/// The module in which the prelude is loaded.
+const PRELUDE_MODULE: &str = "prelude";
+
+/// Parses the program whose root module is stored in `root`, along with the
+/// modules it declares with `mod`.
+///
+/// The module `a::b` is stored in `a/b.dyl`, next to the root module. Its
+/// functions and records are renamed after their path, so that the rest of
+/// the compiler deals with a single module.
+///
+/// The prelude is compiled along with the program, but only the functions
+/// that the program uses are kept.
+pub(crate) fn load_program(root: &Path, content: &str) -> PassResult<ParsingContext, Program> {
+    load_program_with(root, content, |path| io::read_program(path))
+}
+
+fn load_program_with(
+    root: &Path,
+    content: &str,
+    mut read: impl FnMut(&Path) -> AnyResult<String>,
+) -> PassResult<ParsingContext, Program> {
+    let ctxt = ParsingContext::new();
+    let mut loader = Loader {
+        root_dir: root.parent().map(Path::to_path_buf).unwrap_or_default(),
+        read: &mut read,
+        ctxt: &ctxt,
+        modules: Vec::new(),
+    };
+
+    let loading = loader
+        .load(Vec::new(), root.to_path_buf(), content)
+        .and(loader.load(
+            vec![PRELUDE_MODULE.to_owned()],
+            PathBuf::from("<prelude>"),
+            PRELUDE,
+        ));
+    let modules = qualify_records(loader.modules);
+
+    let files = modules
+        .iter()
+        .flat_map(|module| {
+            module
+                .items
+                .iter()
+                .flat_map(|item| match item {
+                    Item::Function(f) => vec![module.qualify(f.name())],
+                    Item::Impl(_, methods) => methods.iter().map(|m| m.name().to_owned()).collect(),
+                    _ => Vec::new(),
+                })
+                .map(move |function| (function, module.file.clone()))
+        })
+        .collect();
+
+    let items = prune_prelude(resolve_names(modules, &ctxt));
+    let program = loading.map(|()| {
+        parser::program_from_items(items)
+            .with_operator_traits()
+            .with_builtins()
+            .with_files(files)
+    });
+
+    ctxt.wrap_result(program)
+}
+
+/// The declarations of a source file.
+struct Module {
+    path: Vec<String>,
+    file: String,
+    items: Vec<Item>,
+}
+
+impl Module {
+    /// Returns the name that a function declared in the module has in the
+    /// whole program.
+    fn qualify(&self, name: &str) -> String {
+        resolve_path(&self.path, &[name])
+    }
+}
+
+struct Loader<'a, R> {
+    root_dir: PathBuf,
+    read: &'a mut R,
+    ctxt: &'a ParsingContext,
+    modules: Vec<Module>,
+}
+
+impl<R> Loader<'_, R>
+where
+    R: FnMut(&Path) -> AnyResult<String>,
+{
+    fn load(&mut self, path: Vec<String>, file: PathBuf, content: &str) -> Result<(), ()> {
+        let file = file.display().to_string();
+
+        let file_ctxt = ParsingContext::for_file(file.clone());
+        let parsed = parser::parse_items(content, &file_ctxt);
+
+        if parsed.is_err() && file_ctxt.errors().is_empty() {
+            file_ctxt.errors().add(format!("{}: Syntax error", file));
+        }
+        self.ctxt.merge(file_ctxt);
+
+        let items = parsed?;
+        let items = expansion::expand_macros(items, &file, self.ctxt)?;
+
+        let submodules = items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Mod(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        self.modules.push(Module {
+            path: path.clone(),
+            file: file.clone(),
+            items,
+        });
+
+        submodules
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                if path.is_empty() && name == PRELUDE_MODULE {
+                    self.ctxt
+                        .errors()
+                        .add(format!("{}: Module name `{}` is reserved", file, name));
+                    return Err(());
+                }
+
+                if submodules[..idx].contains(name) {
+                    self.ctxt.errors().add(format!(
+                        "{}: Module `{}` is declared multiple times",
+                        file, name
+                    ));
+                    return Err(());
+                }
+
+                let mut submodule = path.clone();
+                submodule.push(name.clone());
+
+                let submodule_file = self
+                    .root_dir
+                    .join(submodule.join("/"))
+                    .with_extension("dyl");
+
+                match (self.read)(&submodule_file) {
+                    Ok(content) => self.load(submodule, submodule_file, &content),
+                    Err(_) => {
+                        self.ctxt.errors().add(format!(
+                            "{}: Failed to read module `{}` from `{}`",
+                            file,
+                            name,
+                            submodule_file.display()
+                        ));
+                        Err(())
+                    }
+                }
+            })
+            .fold(Ok(()), Result::and)
+    }
+}
+
+/// Returns the name of the function at `path`, relative to the module at
+/// `module`. Paths starting with `crate` are relative to the root module.
+fn resolve_path(module: &[String], path: &[&str]) -> String {
+    match path {
+        ["crate", path @ ..] => path.join("::"),
+        _ => module
+            .iter()
+            .map(String::as_str)
+            .chain(path.iter().copied())
+            .collect::<Vec<_>>()
+            .join("::"),
+    }
+}
+
+/// Renames the records of every module after their path, along with the
+/// types and the record literals that refer to them. Records are visible in
+/// the module that declares them, and in the modules that import them.
+///
+/// The name of a method depends on the type it is implemented for, so
+/// methods are renamed too.
+fn qualify_records(modules: Vec<Module>) -> Vec<Module> {
+    let records = modules
+        .iter()
+        .flat_map(|module| {
+            module.items.iter().filter_map(move |item| match item {
+                Item::Record(record) => Some(module.qualify(record.name())),
+                _ => None,
+            })
+        })
+        .collect::<HashSet<_>>();
+
+    modules
+        .into_iter()
+        .map(|module| {
+            let mut scope = HashMap::new();
+
+            module.items.iter().for_each(|item| match item {
+                Item::Record(record) => {
+                    let qualified = module.qualify(record.name());
+                    scope.insert(record.name().to_owned(), Ty::Record(qualified));
+                }
+
+                Item::Use(path) => {
+                    let segments = path.iter().map(String::as_str).collect::<Vec<_>>();
+                    let record = resolve_path(&module.path, &segments);
+
+                    if records.contains(&record) {
+                        scope.insert(path.last().unwrap().clone(), Ty::Record(record));
+                    }
+                }
+
+                _ => {}
+            });
+
+            let items = module
+                .items
+                .iter()
+                .map(|item| qualify_item(item, &module, &scope))
+                .collect();
+
+            Module { items, ..module }
+        })
+        .collect()
+}
+
+/// Replaces the names of the records in `scope` by their path.
+fn qualify_item(item: &Item, module: &Module, scope: &HashMap<String, Ty>) -> Item {
+    match item {
+        Item::Function(f) => Item::Function(resolve_record_names(f.clone(), scope)),
+
+        Item::Impl(impl_, methods) => {
+            let ty = impl_.ty().substitute(scope);
+            let methods = impl_
+                .methods()
+                .iter()
+                .zip(methods)
+                .map(|(name, method)| {
+                    let method = resolve_record_names(method.clone(), scope);
+                    let name = ast::method_name(impl_.trait_(), &ty, name);
+
+                    method.with_name_and_body(name, method.body().clone())
+                })
+                .collect();
+
+            let impl_ = match impl_.trait_() {
+                Some(trait_) => Impl::new(trait_.to_owned(), ty, impl_.methods().to_vec()),
+                None => Impl::inherent(ty, impl_.methods().to_vec()),
+            };
+
+            Item::Impl(impl_, methods)
+        }
+
+        Item::Record(record) => Item::Record(Record::new(
+            module.qualify(record.name()),
+            substitute_params(record.fields(), scope),
+        )),
+
+        Item::Trait(trait_) => {
+            let methods = trait_
+                .methods()
+                .iter()
+                .map(|m| {
+                    let params = substitute_params(m.params(), scope);
+                    MethodSignature::new(m.name().to_owned(), params, m.ret_ty().substitute(scope))
+                })
+                .collect();
+
+            Item::Trait(Trait::new(trait_.name().to_owned(), methods))
+        }
+
+        Item::Extern(e) => Item::Extern(e.with_signature(
+            substitute_params(e.params(), scope),
+            e.ret_ty().substitute(scope),
+        )),
+
+        Item::Mod(_) | Item::Use(_) | Item::Macro(_) => item.clone(),
+    }
+}
+
+/// Replaces the names of the functions of every module by their path, and
+/// returns the declarations of the whole program.
+fn resolve_names(modules: Vec<Module>, ctxt: &ParsingContext) -> Vec<Item> {
+    // Maps the path of every function to the module that declares it and
+    // whether it is public.
+    let functions = modules
+        .iter()
+        .flat_map(|module| {
+            module.items.iter().filter_map(move |item| {
+                let (name, public) = declared_function(item)?;
+                Some((module.qualify(name), (&module.path, public)))
+            })
+        })
+        .collect::<HashMap<_, _>>();
+
+    // Records are already named after their path.
+    let records = modules
+        .iter()
+        .flat_map(|module| &module.items)
+        .filter_map(|item| match item {
+            Item::Record(record) => Some(record.name()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    // Private functions are visible in the module that declares them and in
+    // its submodules.
+    let check_visibility = |module: &Module, function: &str| match functions.get(function) {
+        Some((declared_in, public)) if !public && !module.path.starts_with(declared_in) => {
+            ctxt.errors().add(format!(
+                "{}: Function `{}` is private",
+                module.file, function
+            ));
+        }
+        _ => {}
+    };
+
+    let prelude = modules
+        .iter()
+        .filter(|module| module.path == [PRELUDE_MODULE])
+        .flat_map(|module| {
+            module
+                .items
+                .iter()
+                .filter_map(move |item| match declared_function(item) {
+                    Some((name, true)) => Some((name.to_owned(), module.qualify(name))),
+                    _ => None,
+                })
+        })
+        .collect::<HashMap<_, _>>();
+
+    modules
+        .iter()
+        .flat_map(|module| {
+            // Functions of the module and imports shadow the prelude.
+            let mut scope = prelude.clone();
+            scope.extend(module.items.iter().filter_map(|item| {
+                let (name, _) = declared_function(item)?;
+                Some((name.to_owned(), module.qualify(name)))
+            }));
+
+            module.items.iter().for_each(|item| {
+                if let Item::Use(path) = item {
+                    let segments = path.iter().map(String::as_str).collect::<Vec<_>>();
+                    let function = resolve_path(&module.path, &segments);
+
+                    if functions.contains_key(&function) {
+                        check_visibility(module, &function);
+                        scope.insert(path.last().unwrap().clone(), function);
+                    } else if !records.contains(function.as_str()) {
+                        ctxt.errors().add(format!(
+                            "{}: Unresolved import `{}`",
+                            module.file,
+                            path.join("::")
+                        ));
+                    }
+                }
+            });
+
+            // Local variables shadow functions, and names that are not known
+            // here are left for the type checker to report.
+            let mut resolve = |name: &str| {
+                if name.contains("::") {
+                    let segments = name.split("::").collect::<Vec<_>>();
+                    let function = resolve_path(&module.path, &segments);
+
+                    functions.contains_key(&function).then(|| {
+                        check_visibility(module, &function);
+                        function
+                    })
+                } else {
+                    scope.get(name).cloned()
+                }
+            };
+
+            module
+                .items
+                .iter()
+                .filter_map(|item| match item {
+                    Item::Function(f) => {
+                        let body = f.body().rename_free_idents(&mut resolve);
+                        let f = f
+                            .with_name_and_body(module.qualify(f.name()), body)
+                            .map_clauses(|condition| condition.rename_free_idents(&mut resolve));
+
+                        Some(Item::Function(f))
+                    }
+
+                    Item::Impl(impl_, methods) => {
+                        let methods = methods
+                            .iter()
+                            .map(|m| {
+                                let body = m.body().rename_free_idents(&mut resolve);
+                                m.with_name_and_body(m.name().to_owned(), body).map_clauses(
+                                    |condition| condition.rename_free_idents(&mut resolve),
+                                )
+                            })
+                            .collect();
+
+                        Some(Item::Impl(impl_.clone(), methods))
+                    }
+
+                    Item::Extern(e) => Some(Item::Extern(e.with_name(module.qualify(e.name())))),
+
+                    Item::Trait(_) | Item::Record(_) => Some(item.clone()),
+
+                    Item::Mod(_) | Item::Use(_) | Item::Macro(_) => None,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Returns the name of the function that an item declares, and whether it is
+/// public.
+fn declared_function(item: &Item) -> Option<(&str, bool)> {
+    match item {
+        Item::Function(f) => Some((f.name(), f.is_public())),
+        Item::Extern(e) => Some((e.name(), e.is_public())),
+        _ => None,
+    }
+}
+
+/// Removes the functions of the prelude that the rest of the program doesn't
+/// use, directly or through other functions of the prelude.
+fn prune_prelude(items: Vec<Item>) -> Vec<Item> {
+    let prefix = format!("{}::", PRELUDE_MODULE);
+    let is_prelude =
+        |item: &Item| matches!(item, Item::Function(f) if f.name().starts_with(&prefix));
+
+    let mut pending = items
+        .iter()
+        .filter(|item| !is_prelude(item))
+        .flat_map(|item| match item {
+            Item::Function(f) => vec![f],
+            Item::Impl(_, methods) => methods.iter().collect(),
+            _ => Vec::new(),
+        })
+        .flat_map(|f| FreeVariables::of_function(f).used().to_vec())
+        .collect::<Vec<_>>();
+
+    let mut used = HashSet::new();
+    while let Some(name) = pending.pop() {
+        if !name.starts_with(&prefix) || !used.insert(name.clone()) {
+            continue;
+        }
+
+        let callees = items.iter().find_map(|item| match item {
+            Item::Function(f) if f.name() == name => {
+                Some(FreeVariables::of_function(f).used().to_vec())
+            }
+            _ => None,
+        });
+        pending.extend(callees.unwrap_or_default());
+    }
+
+    items
+        .into_iter()
+        .filter(|item| match item {
+            Item::Function(f) if is_prelude(item) => used.contains(f.name()),
+            _ => true,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use crate::{
+        ast::{Binding, BuiltinFunction, ExprKind, Param, Span},
+        ty::Ty,
+    };
+
+    use super::*;
+
+    fn load(files: &[(&str, &str)]) -> (Result<Program, ()>, String) {
+        let files = files
+            .iter()
+            .map(|(path, content)| (PathBuf::from(path), content.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        let root = Path::new("main.dyl");
+        let ctxt = ParsingContext::new();
+        let mut loader = Loader {
+            root_dir: PathBuf::new(),
+            read: &mut |path: &Path| files.get(path).cloned().ok_or_else(|| anyhow!("missing")),
+            ctxt: &ctxt,
+            modules: Vec::new(),
+        };
+
+        let loading = loader.load(Vec::new(), root.to_path_buf(), &files[root]);
+        let items = resolve_names(qualify_records(loader.modules), &ctxt);
+        let program = loading.map(|()| parser::program_from_items(items));
+
+        (program, ctxt.errors().to_string())
+    }
+
+    fn function_names(program: &Program) -> Vec<&str> {
+        program.functions().iter().map(|f| f.name()).collect()
+    }
+
+    #[test]
+    fn functions_are_named_after_their_module() {
+        let (program, errs) = load(&[
+            ("main.dyl", "mod a; fn main() { a::f() }"),
+            ("a.dyl", "mod b; pub fn f() { b::g() }"),
+            ("a/b.dyl", "pub fn g() { crate::main() }"),
+        ]);
+        let program = program.unwrap();
+
+        assert_eq!(errs, "");
+        assert_eq!(function_names(&program), ["main", "a::f", "a::b::g"]);
+        assert_eq!(
+            program.functions()[1].body(),
+            &ExprKind::call_at(
+                ExprKind::ident("a::b::g".to_owned()),
+                Vec::new(),
+                Span::new(Some("a.dyl".to_owned()), 1, 25)
+            )
+        );
+        assert_eq!(
+            program.functions()[2].body(),
+            &ExprKind::call_at(
+                ExprKind::ident("main".to_owned()),
+                Vec::new(),
+                Span::new(Some("a/b.dyl".to_owned()), 1, 25)
+            )
+        );
+    }
+
+    #[test]
+    fn records_are_named_after_their_module() {
+        let (program, errs) = load(&[
+            (
+                "main.dyl",
+                "mod a; struct P { x: int } fn main() -> int { a::f().get() }",
+            ),
+            (
+                "a.dyl",
+                "struct P { y: int }
+                 impl P { fn get(self) -> int { self.y } }
+                 pub fn f() -> P { P { y: 1 } }",
+            ),
+        ]);
+        let program = program.unwrap();
+
+        assert_eq!(errs, "");
+        let records = program
+            .records()
+            .iter()
+            .map(|r| r.name())
+            .collect::<Vec<_>>();
+        assert_eq!(records, ["P", "a::P"]);
+        assert_eq!(function_names(&program), ["main", "a::P::get", "a::f"]);
+
+        let f = &program.functions()[2];
+        assert_eq!(f.ret_ty(), Some(&Ty::Record("a::P".to_owned())));
+        assert_eq!(
+            f.body(),
+            &ExprKind::record_literal(
+                "a::P".to_owned(),
+                vec![("y".to_owned(), ExprKind::integer(1))]
+            )
+        );
+    }
+
+    #[test]
+    fn use_imports_a_record() {
+        let (program, errs) = load(&[
+            ("main.dyl", "mod a; use a::P; fn main(p: P) -> int { 0 }"),
+            ("a.dyl", "struct P { x: int }"),
+        ]);
+
+        assert_eq!(errs, "");
+        assert_eq!(
+            program.unwrap().functions()[0].params(),
+            [Param::new("p".to_owned(), Ty::Record("a::P".to_owned()))]
+        );
+    }
+
+    #[test]
+    fn use_imports_a_function() {
+        let (program, errs) = load(&[
+            ("main.dyl", "mod a; use a::f; fn main() { let g = f; f(g) }"),
+            ("a.dyl", "pub fn f(x: int) -> int { x }"),
+        ]);
+
+        assert_eq!(errs, "");
+        assert_eq!(
+            program.unwrap().functions()[0].body(),
+            &ExprKind::bindings(
+                vec![Binding::new(
+                    "g".to_owned(),
+                    ExprKind::ident("a::f".to_owned())
+                )],
+                ExprKind::call_at(
+                    ExprKind::ident("a::f".to_owned()),
+                    vec![ExprKind::ident("g".to_owned())],
+                    Span::new(Some("main.dyl".to_owned()), 1, 42)
+                ),
+            )
+        );
+    }
+
+    #[test]
+    fn local_variables_shadow_functions() {
+        let (program, _) = load(&[("main.dyl", "fn f() {} fn main() { |f: int| f }")]);
+
+        assert_eq!(
+            program.unwrap().functions()[1].body(),
+            &ExprKind::lambda(
+                vec![Param::new("f".to_owned(), Ty::Int)],
+                ExprKind::ident("f".to_owned())
+            )
+        );
+    }
+
+    #[test]
+    fn private_functions() {
+        let (_, errs) = load(&[
+            ("main.dyl", "mod a; use a::f; fn main() { a::f() }"),
+            ("a.dyl", "fn f() {}"),
+        ]);
+
+        assert_eq!(
+            errs,
+            "main.dyl: Function `a::f` is private\n\
+             main.dyl: Function `a::f` is private\n"
+        );
+    }
+
+    #[test]
+    fn unknown_modules_and_imports() {
+        let (program, errs) = load(&[("main.dyl", "mod a; use b::f; fn main() {}")]);
+
+        assert!(program.is_err());
+        assert_eq!(
+            errs,
+            "main.dyl: Failed to read module `a` from `a.dyl`\n\
+             main.dyl: Unresolved import `b::f`\n"
+        );
+    }
+
+    #[test]
+    fn extern_functions_keep_their_host_name() {
+        let (program, errs) = load(&[
+            ("main.dyl", "mod io; fn main() { io::flush() }"),
+            ("io.dyl", "pub extern fn flush();"),
+        ]);
+        let program = program.unwrap();
+
+        assert_eq!(errs, "");
+        assert_eq!(program.externs()[0].name(), "io::flush");
+        assert_eq!(program.externs()[0].host_name(), "flush");
+        assert_eq!(
+            program.functions()[0].body(),
+            &ExprKind::call_at(
+                ExprKind::ident("io::flush".to_owned()),
+                Vec::new(),
+                Span::new(Some("main.dyl".to_owned()), 1, 30)
+            )
+        );
+    }
+
+    fn load_with_prelude(content: &str) -> (Result<Program, ()>, String) {
+        let (ctxt, program) =
+            load_program_with(Path::new("main.dyl"), content, |_| Err(anyhow!("missing")))
+                .map_or_else(
+                    |_| (ParsingContext::new(), Err(())),
+                    |(ctxt, program)| (ctxt, Ok(program)),
+                );
+
+        (program, ctxt.errors().to_string())
+    }
+
+    #[test]
+    fn only_used_prelude_functions_are_kept() {
+        let (program, errs) = load_with_prelude("fn main() -> int { let f = gcd; f(4, 6) }");
+        let program = program.unwrap();
+
+        assert_eq!(errs, "");
+        // The implementations of the operator traits come last.
+        assert_eq!(
+            function_names(&program)[..3],
+            ["main", "prelude::abs", "prelude::gcd"]
+        );
+    }
+
+    #[test]
+    fn functions_shadow_the_prelude() {
+        let (program, _) = load_with_prelude("fn min() {} fn main() { min() }");
+        let program = program.unwrap();
+
+        assert_eq!(function_names(&program)[..2], ["min", "main"]);
+        assert!(!function_names(&program).contains(&"prelude::min"));
+        assert_eq!(
+            program.functions()[1].body(),
+            &ExprKind::call_at(
+                ExprKind::ident("min".to_owned()),
+                Vec::new(),
+                Span::new(Some("main.dyl".to_owned()), 1, 28)
+            )
+        );
+    }
+
+    #[test]
+    fn builtins_are_shadowed() {
+        let (program, _) = load_with_prelude(
+            "fn get(x: int) -> int { x }
+             fn main(l: [int]) -> int { let tail = |x: int| x; get(1) + tail(2) + head(l) }",
+        );
+        let program = program.unwrap();
+
+        let call = |name: &str, arg, column| {
+            ExprKind::call_at(
+                ExprKind::ident(name.to_owned()),
+                vec![ExprKind::integer(arg)],
+                Span::new(Some("main.dyl".to_owned()), 2, column),
+            )
+        };
+        let ending = match program.functions()[1].body() {
+            ExprKind::Bindings(body) => body.ending_expression(),
+            body => panic!("Unexpected body `{:?}`", body),
+        };
+
+        assert_eq!(
+            ending,
+            &ExprKind::addition(
+                ExprKind::addition(call("get", 1, 67), call("tail", 2, 77)),
+                ExprKind::builtin(BuiltinFunction::Head, vec![ExprKind::ident("l".to_owned())]),
+            )
+        );
+    }
+
+    #[test]
+    fn prelude_module_is_reserved() {
+        let (_, errs) = load(&[("main.dyl", "mod prelude; fn main() {}")]);
+
+        assert_eq!(errs, "main.dyl: Module name `prelude` is reserved\n");
+    }
+}
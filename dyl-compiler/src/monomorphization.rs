@@ -0,0 +1,664 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    ast::{
+        Arm, AssertKind, Binding, Bindings, Call, ClauseKind, ExprKind, FieldAccess, For, Function,
+        If, Lambda, MethodCall, Operator, Param, PrintArg, Program, Range, Statement, RESULT,
+    },
+    context::{PassResult, TypingContext},
+    ty::Ty,
+    type_checker::{instantiate, resolve_operator, Typed},
+};
+
+/// The maximum number of instances a single generic function can have.
+///
+/// Reaching this limit most likely means that a generic function calls
+/// itself with ever-growing type arguments.
+const MAX_INSTANCES: usize = 64;
+
+/// Replaces every generic function by one copy per set of type arguments it
+/// is called with.
+///
+/// Each copy is named after the function and its type arguments (for
+/// instance `identity<int>`), which is not a valid identifier. Calls are
+/// renamed accordingly. Generic functions that are never called are removed.
+///
+/// As the type of every expression is concrete once this is done, method
+/// calls and overloaded operators are replaced by calls to the function that
+/// implements the method, and fields are resolved to their position in the
+/// record.
+pub(crate) fn monomorphize(
+    ast: &Program,
+    mut ctxt: TypingContext,
+) -> PassResult<TypingContext, Program> {
+    let mut instantiator = Instantiator::new(ast);
+
+    let mut functions = ast
+        .functions()
+        .iter()
+        .filter(|f| f.generics().is_empty())
+        .map(|f| instantiator.instantiate(f, &HashMap::new(), f.name().to_owned(), &mut ctxt))
+        .collect::<Vec<_>>();
+
+    while let Some((name, function, substitution)) = instantiator.queue.pop_front() {
+        let instance = instantiator.instantiate(function, &substitution, name, &mut ctxt);
+        functions.push(instance);
+    }
+
+    let rslt = match instantiator.failed {
+        true => Err(()),
+        false => Ok(ast.with_functions(functions)),
+    };
+
+    ctxt.wrap_result(rslt)
+}
+
+struct Instantiator<'a> {
+    generics: HashMap<&'a str, &'a Function>,
+    instances: HashMap<&'a str, Vec<String>>,
+    queue: VecDeque<(String, &'a Function, HashMap<String, Ty>)>,
+    failed: bool,
+}
+
+impl<'a> Instantiator<'a> {
+    fn new(ast: &'a Program) -> Instantiator<'a> {
+        let generics = ast
+            .functions()
+            .iter()
+            .filter(|f| !f.generics().is_empty())
+            .map(|f| (f.name(), f))
+            .collect();
+
+        Instantiator {
+            generics,
+            instances: HashMap::new(),
+            queue: VecDeque::new(),
+            failed: false,
+        }
+    }
+
+    fn instantiate(
+        &mut self,
+        function: &Function,
+        substitution: &HashMap<String, Ty>,
+        name: String,
+        ctxt: &mut TypingContext,
+    ) -> Function {
+        let params = function
+            .params()
+            .iter()
+            .map(|p| Param::new(p.name().to_owned(), p.ty().substitute(substitution)))
+            .collect::<Vec<_>>();
+
+        let ret_ty = function.ret_ty().map(|ty| ty.substitute(substitution));
+
+        let subctxt = ctxt.new_subcontext();
+        params
+            .iter()
+            .for_each(|p| ctxt.add_binding(p.name().to_owned(), p.ty().clone()));
+
+        let body = self.rewrite_expr(function.body(), substitution, ctxt);
+        let clauses = function
+            .clauses()
+            .iter()
+            .map(|clause| {
+                let clause_ctxt = ctxt.new_subcontext();
+                if clause.kind() == ClauseKind::Ensures {
+                    let result_ty = ret_ty.clone().unwrap_or(Ty::Err);
+                    ctxt.add_binding(RESULT.to_owned(), result_ty);
+                }
+
+                let condition = self.rewrite_expr(clause.condition(), substitution, ctxt);
+                ctxt.drop_subcontext(clause_ctxt);
+
+                clause.with_condition(condition)
+            })
+            .collect();
+
+        ctxt.drop_subcontext(subctxt);
+
+        Function::new(name, Vec::new(), params, ret_ty, body)
+            .with_clauses(clauses)
+            .with_attributes(function.attributes().to_vec())
+    }
+
+    /// Returns the name of the instance of `function` that corresponds to the
+    /// given type arguments, and schedules its creation if needed.
+    fn instance_name(
+        &mut self,
+        function: &'a Function,
+        substitution: HashMap<String, Ty>,
+        ctxt: &TypingContext,
+    ) -> String {
+        let type_args = function
+            .generics()
+            .iter()
+            .map(|g| substitution.get(g).cloned().unwrap_or(Ty::Err).to_string())
+            .collect::<Vec<_>>();
+
+        let name = format!("{}<{}>", function.name(), type_args.join(", "));
+
+        let instances = self.instances.entry(function.name()).or_default();
+
+        if !instances.contains(&name) {
+            if instances.len() == MAX_INSTANCES {
+                if !self.failed {
+                    ctxt.errs().add(format!(
+                        "Generic function `{}` is instantiated too many times",
+                        function.name()
+                    ));
+                }
+
+                self.failed = true;
+                return name;
+            }
+
+            instances.push(name.clone());
+            self.queue.push_back((name.clone(), function, substitution));
+        }
+
+        name
+    }
+
+    fn rewrite_expr(
+        &mut self,
+        expr: &ExprKind,
+        substitution: &HashMap<String, Ty>,
+        ctxt: &mut TypingContext,
+    ) -> ExprKind {
+        match expr {
+            ExprKind::Addition(e) => self.rewrite_operator(e, substitution, ctxt),
+
+            ExprKind::Subtraction(e) => self.rewrite_operator(e, substitution, ctxt),
+
+            ExprKind::Multiplication(e) => self.rewrite_operator(e, substitution, ctxt),
+            ExprKind::Remainder(e) => self.rewrite_operator(e, substitution, ctxt),
+
+            ExprKind::If(e) => ExprKind::If(If::new(
+                self.rewrite_expr(e.condition(), substitution, ctxt),
+                self.rewrite_expr(e.consequent(), substitution, ctxt),
+                self.rewrite_expr(e.alternative(), substitution, ctxt),
+            )),
+
+            ExprKind::Bindings(e) => {
+                ExprKind::Bindings(self.rewrite_bindings(e, substitution, ctxt))
+            }
+
+            ExprKind::For(e) => {
+                let range = Range::new(
+                    self.rewrite_expr(e.range().start(), substitution, ctxt),
+                    self.rewrite_expr(e.range().end(), substitution, ctxt),
+                    e.range().is_inclusive(),
+                );
+
+                let subctxt = ctxt.new_subcontext();
+                ctxt.add_binding(e.variable().to_owned(), Ty::Int);
+                let body = self.rewrite_expr(e.body(), substitution, ctxt);
+                ctxt.drop_subcontext(subctxt);
+
+                ExprKind::For(For::new(e.variable().to_owned(), range, body))
+            }
+
+            ExprKind::Call(e) => ExprKind::Call(self.rewrite_call(e, substitution, ctxt)),
+
+            ExprKind::MethodCall(e) => self.rewrite_method_call(e, substitution, ctxt),
+
+            ExprKind::Lambda(e) => {
+                let params = e
+                    .params()
+                    .iter()
+                    .map(|p| Param::new(p.name().to_owned(), p.ty().substitute(substitution)))
+                    .collect::<Vec<_>>();
+
+                let subctxt = ctxt.new_subcontext();
+                params
+                    .iter()
+                    .for_each(|p| ctxt.add_binding(p.name().to_owned(), p.ty().clone()));
+                let body = self.rewrite_expr(e.body(), substitution, ctxt);
+                ctxt.drop_subcontext(subctxt);
+
+                ExprKind::Lambda(Lambda::new(params, body))
+            }
+
+            ExprKind::RecordLiteral(e) => {
+                // Fields are evaluated in declaration order, which is the
+                // order they are stored in.
+                let fields = match ctxt.resolve_record(e.name()).cloned() {
+                    Some(record) => record
+                        .fields()
+                        .iter()
+                        .filter_map(|field| e.fields().iter().find(|(n, _)| n == field.name()))
+                        .cloned()
+                        .collect(),
+                    None => e.fields().to_vec(),
+                };
+
+                let fields = fields
+                    .into_iter()
+                    .map(|(name, value)| (name, self.rewrite_expr(&value, substitution, ctxt)))
+                    .collect();
+
+                ExprKind::record_literal(e.name().to_owned(), fields)
+            }
+
+            ExprKind::FieldAccess(e) => {
+                let record = self.rewrite_expr(e.record(), substitution, ctxt);
+                let access = FieldAccess::new(record, e.field().to_owned());
+
+                // The field exists, as the type checker ran before.
+                match e.resolve(ctxt) {
+                    Ok(Some((idx, _))) => ExprKind::FieldAccess(access.with_index(idx as u16)),
+                    _ => ExprKind::FieldAccess(access),
+                }
+            }
+
+            ExprKind::Variant(e) => {
+                let payload = e
+                    .payload()
+                    .map(|payload| self.rewrite_expr(payload, substitution, ctxt));
+
+                ExprKind::variant(e.constructor(), payload)
+            }
+
+            ExprKind::Match(e) => {
+                let scrutinee = self.rewrite_expr(e.scrutinee(), substitution, ctxt);
+                let scrutinee_ty = e.scrutinee().get_output(ctxt).unwrap_or(Ty::Err);
+
+                let arms = e
+                    .arms()
+                    .iter()
+                    .map(|arm| {
+                        let subctxt = ctxt.new_subcontext();
+                        if let Some(name) = arm.pattern().binding() {
+                            let constructor = arm.pattern().constructor();
+                            let ty = constructor.payload_ty(&scrutinee_ty).unwrap_or(Ty::Err);
+                            ctxt.add_binding(name.to_owned(), ty);
+                        }
+                        let body = self.rewrite_expr(arm.body(), substitution, ctxt);
+                        ctxt.drop_subcontext(subctxt);
+
+                        Arm::new(arm.pattern().clone(), body)
+                    })
+                    .collect();
+
+                ExprKind::match_(scrutinee, arms)
+            }
+
+            ExprKind::Try(e) => ExprKind::try_(self.rewrite_expr(e.operand(), substitution, ctxt)),
+
+            ExprKind::Comparison(e) => ExprKind::comparison(
+                e.operator(),
+                self.rewrite_expr(e.left(), substitution, ctxt),
+                self.rewrite_expr(e.right(), substitution, ctxt),
+            ),
+
+            ExprKind::List(e) => {
+                let elements = e
+                    .elements()
+                    .iter()
+                    .map(|e| self.rewrite_expr(e, substitution, ctxt));
+
+                ExprKind::list(elements.collect())
+            }
+
+            ExprKind::Builtin(e) => {
+                let args = e
+                    .args()
+                    .iter()
+                    .map(|arg| self.rewrite_expr(arg, substitution, ctxt));
+
+                ExprKind::builtin(e.function(), args.collect())
+            }
+
+            // Booleans are integers at runtime, the type of the operands
+            // tells how they must be shown when the assertion fails.
+            ExprKind::Assert(e) => {
+                let assert =
+                    e.map_operands(|operand| self.rewrite_expr(operand, substitution, ctxt));
+
+                match (assert.kind(), e.kind()) {
+                    (AssertKind::Eq(left, right), AssertKind::Eq(original, _))
+                        if matches!(original.get_output(ctxt), Ok(Ty::Bool)) =>
+                    {
+                        let kind = AssertKind::EqBool(left.clone(), right.clone());
+                        ExprKind::assert(kind, assert.span().clone())
+                    }
+                    _ => ExprKind::Assert(assert),
+                }
+            }
+
+            // Booleans are integers at runtime, the type of the argument
+            // tells how it must be printed.
+            ExprKind::Print(e) => {
+                let print = e.map_arg(|arg| self.rewrite_expr(arg, substitution, ctxt));
+
+                match (print.arg(), e.arg()) {
+                    (PrintArg::Value(arg), PrintArg::Value(original))
+                        if matches!(original.get_output(ctxt), Ok(Ty::Bool)) =>
+                    {
+                        let arg = PrintArg::Bool(arg.clone());
+                        ExprKind::print(arg, print.newline())
+                    }
+                    _ => ExprKind::Print(print),
+                }
+            }
+
+            ExprKind::Integer(_)
+            | ExprKind::Ident(_)
+            | ExprKind::Bool(_)
+            | ExprKind::Unit(_)
+            | ExprKind::Break(_)
+            | ExprKind::Continue(_)
+            | ExprKind::Panic(_)
+            | ExprKind::ReadInt(_) => expr.clone(),
+        }
+    }
+
+    fn rewrite_bindings(
+        &mut self,
+        bindings: &Bindings,
+        substitution: &HashMap<String, Ty>,
+        ctxt: &mut TypingContext,
+    ) -> Bindings {
+        let subctxt = ctxt.new_subcontext();
+
+        let statements = bindings
+            .statements()
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::Binding(b) => {
+                    let value = self.rewrite_expr(b.value(), substitution, ctxt);
+
+                    // The value is well-typed, as the type checker ran before.
+                    let ty = b.value().get_output(ctxt).unwrap_or(Ty::Err);
+                    ctxt.add_binding(b.name().to_owned(), ty);
+
+                    Statement::Binding(Binding::new(b.name().to_owned(), value))
+                }
+
+                Statement::Expr(e) => Statement::Expr(self.rewrite_expr(e, substitution, ctxt)),
+            })
+            .collect();
+
+        let ending = self.rewrite_expr(bindings.ending_expression(), substitution, ctxt);
+
+        ctxt.drop_subcontext(subctxt);
+
+        Bindings::from_statements(statements, ending)
+    }
+
+    fn rewrite_call(
+        &mut self,
+        call: &Call,
+        substitution: &HashMap<String, Ty>,
+        ctxt: &mut TypingContext,
+    ) -> Call {
+        let args = call
+            .args()
+            .iter()
+            .map(|arg| self.rewrite_expr(arg, substitution, ctxt))
+            .collect();
+
+        let generic_callee = call.generic_callee(ctxt).and_then(|signature| {
+            let function = match call.callee() {
+                ExprKind::Ident(ident) => *self.generics.get(ident.name())?,
+                _ => return None,
+            };
+
+            Some((function, signature))
+        });
+
+        let callee = match generic_callee {
+            Some((function, signature)) => {
+                let type_args = instantiate(&signature, call.args(), ctxt).unwrap_or_default();
+                let name = self.instance_name(function, type_args, ctxt);
+
+                ExprKind::ident(name)
+            }
+
+            None => self.rewrite_expr(call.callee(), substitution, ctxt),
+        };
+
+        call.with_callee_and_args(callee, args)
+    }
+
+    fn rewrite_operator<O: Operator>(
+        &mut self,
+        operator: &O,
+        substitution: &HashMap<String, Ty>,
+        ctxt: &mut TypingContext,
+    ) -> ExprKind {
+        let (left, right) = operator.operands();
+
+        match resolve_operator(operator, ctxt) {
+            Ok(Some(resolved)) => {
+                let call = MethodCall::new(left.clone(), O::METHOD.to_owned(), vec![right.clone()])
+                    .desugar(&resolved);
+                ExprKind::Call(self.rewrite_call(&call, substitution, ctxt))
+            }
+
+            _ => O::expr(
+                self.rewrite_expr(left, substitution, ctxt),
+                self.rewrite_expr(right, substitution, ctxt),
+            ),
+        }
+    }
+
+    fn rewrite_method_call(
+        &mut self,
+        call: &MethodCall,
+        substitution: &HashMap<String, Ty>,
+        ctxt: &mut TypingContext,
+    ) -> ExprKind {
+        // The method exists, as the type checker ran before.
+        match call.resolve(ctxt) {
+            Ok(Some(resolved)) => {
+                let call = call.desugar(&resolved);
+                ExprKind::Call(self.rewrite_call(&call, substitution, ctxt))
+            }
+
+            _ => ExprKind::MethodCall(call.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity() -> Function {
+        Function::new(
+            "identity".to_owned(),
+            vec!["T".to_owned()],
+            vec![Param::new("x".to_owned(), Ty::Param("T".to_owned()))],
+            Some(Ty::Param("T".to_owned())),
+            ExprKind::ident("x".to_owned()),
+        )
+    }
+
+    fn main(body: ExprKind) -> Function {
+        Function::new("main".to_owned(), Vec::new(), Vec::new(), None, body)
+    }
+
+    fn call_identity(arg: ExprKind) -> ExprKind {
+        ExprKind::call(ExprKind::ident("identity".to_owned()), vec![arg])
+    }
+
+    fn typing_context(ast: &Program) -> TypingContext {
+        crate::type_checker::check_ast(ast, TypingContext::new()).unwrap()
+    }
+
+    fn function_names(ast: &Program) -> Vec<&str> {
+        ast.functions().iter().map(Function::name).collect()
+    }
+
+    #[test]
+    fn one_instance_per_type() {
+        let body = ExprKind::addition(
+            call_identity(ExprKind::integer(1)),
+            ExprKind::if_(
+                call_identity(ExprKind::bool_(true)),
+                call_identity(ExprKind::integer(2)),
+                ExprKind::integer(3),
+            ),
+        );
+        let ast = Program::new(vec![main(body), identity()]);
+
+        let ctxt = typing_context(&ast);
+        let (_, ast) = monomorphize(&ast, ctxt).unwrap();
+
+        assert_eq!(
+            function_names(&ast),
+            ["main", "identity<int>", "identity<bool>"]
+        );
+    }
+
+    #[test]
+    fn instances_are_concrete() {
+        let ast = Program::new(vec![main(call_identity(ExprKind::integer(1))), identity()]);
+
+        let ctxt = typing_context(&ast);
+        let (_, ast) = monomorphize(&ast, ctxt).unwrap();
+
+        let instance = &ast.functions()[1];
+        assert!(instance.generics().is_empty());
+        assert_eq!(instance.params()[0].ty(), &Ty::Int);
+        assert_eq!(instance.ret_ty(), Some(&Ty::Int));
+    }
+
+    #[test]
+    fn calls_are_renamed() {
+        let ast = Program::new(vec![main(call_identity(ExprKind::integer(1))), identity()]);
+
+        let ctxt = typing_context(&ast);
+        let (_, ast) = monomorphize(&ast, ctxt).unwrap();
+
+        assert_eq!(
+            ast.functions()[0].body(),
+            &ExprKind::call(
+                ExprKind::ident("identity<int>".to_owned()),
+                vec![ExprKind::integer(1)]
+            )
+        );
+    }
+
+    #[test]
+    fn unused_generic_functions_are_removed() {
+        let ast = Program::new(vec![main(ExprKind::integer(1)), identity()]);
+
+        let ctxt = typing_context(&ast);
+        let (_, ast) = monomorphize(&ast, ctxt).unwrap();
+
+        assert_eq!(function_names(&ast), ["main"]);
+    }
+
+    #[test]
+    fn method_calls_are_resolved() {
+        let (_, ast) = crate::parser::parse_input(
+            "trait Score { fn score(self) -> int; }
+             impl Score for int { fn score(self) -> int { self } }
+             fn total<T: Score>(x: T) -> int { x.score() }
+             fn main() { total(1) }",
+        )
+        .unwrap();
+
+        let ctxt = typing_context(&ast);
+        let (_, ast) = monomorphize(&ast, ctxt).unwrap();
+
+        let instance = ast.functions().iter().find(|f| f.name() == "total<int>");
+        assert_eq!(
+            instance.unwrap().body(),
+            &ExprKind::call(
+                ExprKind::ident("<int as Score>::score".to_owned()),
+                vec![ExprKind::ident("x".to_owned())]
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod records {
+    use super::*;
+
+    fn monomorphized(input: &str) -> Program {
+        let (_, ast) = crate::parser::parse_input(input).unwrap();
+        let ctxt = crate::type_checker::check_ast(&ast, TypingContext::new()).unwrap();
+
+        monomorphize(&ast, ctxt).unwrap().1
+    }
+
+    fn body<'a>(ast: &'a Program, name: &str) -> &'a ExprKind {
+        ast.functions()
+            .iter()
+            .find(|f| f.name() == name)
+            .unwrap()
+            .body()
+    }
+
+    fn ident(name: &str) -> ExprKind {
+        ExprKind::ident(name.to_owned())
+    }
+
+    #[test]
+    fn overloaded_operators_become_calls() {
+        let ast = monomorphized(
+            "struct P { x: int }
+             impl Add for P { fn add(self, other: P) -> P { self } }
+             fn main() { let p = P { x: 1 }; p + p }",
+        );
+
+        let ending = match body(&ast, "main") {
+            ExprKind::Bindings(b) => b.ending_expression(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            ending,
+            &ExprKind::call(ident("<P as Add>::add"), vec![ident("p"), ident("p")])
+        );
+    }
+
+    #[test]
+    fn integer_operators_are_kept() {
+        let ast = monomorphized("fn double<T: Add>(x: T) -> T { x + x } fn main() { double(2) }");
+
+        assert_eq!(
+            body(&ast, "double<int>"),
+            &ExprKind::addition(ident("x"), ident("x"))
+        );
+    }
+
+    #[test]
+    fn boolean_equality_assertions_are_told_apart() {
+        let ast = monomorphized("fn main() { assert_eq(true, false) }");
+
+        let kind = match body(&ast, "main") {
+            ExprKind::Assert(assert) => assert.kind(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            kind,
+            &AssertKind::EqBool(ExprKind::bool_(true), ExprKind::bool_(false))
+        );
+    }
+
+    #[test]
+    fn fields_are_resolved() {
+        let ast = monomorphized(
+            "struct P { x: int, y: int }
+             fn main() { P { y: 2, x: 1 }.y }",
+        );
+
+        let literal = ExprKind::record_literal(
+            "P".to_owned(),
+            vec![
+                ("x".to_owned(), ExprKind::integer(1)),
+                ("y".to_owned(), ExprKind::integer(2)),
+            ],
+        );
+        let access = FieldAccess::new(literal, "y".to_owned()).with_index(1);
+
+        assert_eq!(body(&ast, "main"), &ExprKind::FieldAccess(access));
+    }
+}
@@ -1,63 +1,624 @@
 use nom::{
     branch::alt,
-    bytes::complete::tag as nom_tag,
+    bytes::complete::{tag as nom_tag, take_while},
     character::complete::{
         alpha1 as nom_alpha1, alphanumeric1 as nom_alphanumeric1, digit1, multispace0,
     },
-    combinator::{all_consuming, map, opt, recognize},
+    combinator::{all_consuming, map, not, opt, peek, recognize},
     error::{Error as NomError, ErrorKind, ParseError},
-    multi::{fold_many1, many0, many1},
-    sequence::{delimited, pair, preceded, terminated, tuple},
+    multi::{fold_many0, many0, separated_list0, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
     Err, Parser,
 };
 use nom_locate::LocatedSpan;
 
+use std::collections::HashMap;
+
 use crate::{
-    ast::{Binding, ExprKind, Function, Program},
-    context::{ParsingContext, PassResult},
+    ast::{
+        self, Arm, AssertKind, Attribute, Binding, Bound, Call, Clause, ClauseKind,
+        ComparisonOperator, Constructor, ExprKind, ExternFunction, Function, Impl, Item, Macro,
+        MacroRule, MethodSignature, Param, Pattern, PrintArg, Program, Range, Record, Span,
+        Statement, Trait,
+    },
+    context::ParsingContext,
+    ty::Ty,
 };
 
+#[cfg(test)]
+use crate::context::PassResult;
+
+/// Parses a program made of a single source file.
+#[cfg(test)]
 pub(crate) fn parse_input(input_code: &str) -> PassResult<ParsingContext, Program> {
     let parsing_ctxt = ParsingContext::new();
-    let input = LocatedSpan::new_extra(input_code, &parsing_ctxt);
 
-    let parsed = program(input);
+    let parsed = parse_items(input_code, &parsing_ctxt).map(|items| {
+        program_from_items(items)
+            .with_operator_traits()
+            .with_builtins()
+    });
 
     parsing_ctxt.wrap_result(parsed)
 }
 
+/// Parses the declarations of a source file, without merging them into a
+/// program.
+pub(crate) fn parse_items(input_code: &str, ctxt: &ParsingContext) -> Result<Vec<Item>, ()> {
+    let input = LocatedSpan::new_extra(input_code, ctxt);
+
+    all_consuming(many0(item))(input)
+        .map(|(_, items)| items)
+        .map_err(drop)
+}
+
 type Input<'a> = LocatedSpan<&'a str, &'a ParsingContext>;
 type IResult<'a, O, E = NomError<Input<'a>>> = nom::IResult<Input<'a>, O, E>;
 
-fn program(input: Input) -> Result<Program, ()> {
-    program_with_tail(input).map(|(_, ast)| ast).map_err(drop)
+#[cfg(test)]
+fn program_with_tail(input: Input) -> IResult<Program> {
+    map(all_consuming(many0(item)), program_from_items)(input)
 }
 
-fn program_with_tail(input: Input) -> IResult<Program> {
-    map(all_consuming(many0(function)), Program::new)(input)
+/// Merges declarations into a program. The `mod` and `use` declarations are
+/// expected to be resolved beforehand, and the macros to be expanded, so they
+/// are ignored.
+pub(crate) fn program_from_items(items: Vec<Item>) -> Program {
+    let mut functions = Vec::new();
+    let mut traits = Vec::new();
+    let mut impls = Vec::new();
+    let mut records = Vec::new();
+    let mut externs = Vec::new();
+
+    items.into_iter().for_each(|item| match item {
+        Item::Function(function) => functions.push(function),
+        Item::Trait(trait_) => traits.push(trait_),
+        Item::Impl(impl_, methods) => {
+            impls.push(impl_);
+            functions.extend(methods);
+        }
+        Item::Record(record) => records.push(record),
+        Item::Extern(extern_) => externs.push(extern_),
+        Item::Mod(_) | Item::Use(_) | Item::Macro(_) => {}
+    });
+
+    // Type names are parsed as type parameters. The ones that name a record
+    // are resolved now that every record is known.
+    let names = records
+        .iter()
+        .map(|r| (r.name().to_owned(), Ty::Record(r.name().to_owned())))
+        .collect::<HashMap<_, _>>();
+
+    let functions = functions
+        .into_iter()
+        .map(|f| resolve_record_names(f, &names))
+        .collect();
+
+    let traits = traits
+        .into_iter()
+        .map(|trait_| {
+            let methods = trait_
+                .methods()
+                .iter()
+                .map(|m| {
+                    let params = substitute_params(m.params(), &names);
+                    MethodSignature::new(m.name().to_owned(), params, m.ret_ty().substitute(&names))
+                })
+                .collect();
+
+            Trait::new(trait_.name().to_owned(), methods)
+        })
+        .collect();
+
+    let impls = impls
+        .into_iter()
+        .map(|impl_| {
+            let ty = impl_.ty().substitute(&names);
+            let methods = impl_.methods().to_vec();
+
+            match impl_.trait_() {
+                Some(trait_) => Impl::new(trait_.to_owned(), ty, methods),
+                None => Impl::inherent(ty, methods),
+            }
+        })
+        .collect();
+
+    let records = records
+        .iter()
+        .map(|r| Record::new(r.name().to_owned(), substitute_params(r.fields(), &names)))
+        .collect();
+
+    let externs = externs
+        .iter()
+        .map(|e| {
+            e.with_signature(
+                substitute_params(e.params(), &names),
+                e.ret_ty().substitute(&names),
+            )
+        })
+        .collect();
+
+    Program::with_traits(functions, traits, impls)
+        .with_records(records)
+        .with_externs(externs)
 }
 
-fn function(input: Input) -> IResult<Function> {
-    let (tail, _) = keyword("fn")(input)?;
+/// Replaces the record names of a function signature, of its lambdas and of
+/// its record literals. Type parameters shadow records with the same name.
+pub(crate) fn resolve_record_names(function: Function, records: &HashMap<String, Ty>) -> Function {
+    let mut names = records.clone();
+    function.generics().iter().for_each(|generic| {
+        names.remove(generic);
+    });
+
+    let params = substitute_params(function.params(), &names);
+    let ret_ty = function.ret_ty().map(|ty| ty.substitute(&names));
+    let mut substitute = |params: &[Param]| substitute_params(params, &names);
+    let mut literal = |name: &str| match names.get(name) {
+        Some(Ty::Record(record)) => record.clone(),
+        _ => name.to_owned(),
+    };
+    let body = function
+        .body()
+        .map_annotations(&mut substitute, &mut literal);
+
+    function
+        .with_signature_and_body(function.generics().to_vec(), params, ret_ty, body)
+        .map_clauses(|condition| condition.map_annotations(&mut substitute, &mut literal))
+}
+
+pub(crate) fn substitute_params(
+    params: &[Param],
+    substitution: &HashMap<String, Ty>,
+) -> Vec<Param> {
+    params
+        .iter()
+        .map(|p| Param::new(p.name().to_owned(), p.ty().substitute(substitution)))
+        .collect()
+}
+
+fn item(input: Input) -> IResult<Item> {
+    let (input, _) = multispace0(input)?;
+    let (tail, attributes) = many0(attribute)(input)?;
+    let (tail, item) = undecorated_item(tail)?;
+
+    let item = match item {
+        Item::Function(f) => Item::Function(f.with_attributes(attributes)),
+        item => {
+            if !attributes.is_empty() {
+                let message = format!(
+                    "{}: Attributes can only be applied to functions",
+                    span(&input)
+                );
+                input.extra.errors().add(message);
+            }
+
+            item
+        }
+    };
+
+    Ok((tail, item))
+}
+
+/// Parses `#[name]` or `#[name(args)]`, where each argument is a name or an
+/// integer. Attributes are checked against the known ones once the whole
+/// program has been parsed.
+fn attribute(input: Input) -> IResult<Attribute> {
+    let (input, _) = multispace0(input)?;
+    let (tail, _) = pair(hash, left_square)(input)?;
+    let (tail, name) = ident(tail)?;
+    let (tail, args) = opt(delimited(
+        left_par,
+        separated_list0(comma, attribute_arg),
+        expect(right_par, epsilon_recover("`)`")),
+    ))(tail)?;
+    let (tail, _) = expect(right_square, epsilon_recover("`]`"))(tail)?;
+
+    let attribute = Attribute::new(name, args.unwrap_or_default(), span(&input));
+
+    Ok((tail, attribute))
+}
+
+fn attribute_arg(input: Input) -> IResult<String> {
+    let integer = map(space_insignificant(digit1), |i: Input| i.to_string());
+
+    alt((ident, integer))(input)
+}
+
+fn undecorated_item(input: Input) -> IResult<Item> {
+    alt((
+        map(function, Item::Function),
+        map(preceded(pub_, function), |f| Item::Function(f.public())),
+        map(const_function, Item::Function),
+        map(preceded(pub_, const_function), |f| {
+            Item::Function(f.public())
+        }),
+        map(trait_item, Item::Trait),
+        impl_item,
+        map(record_item, Item::Record),
+        map(extern_item, Item::Extern),
+        map(preceded(pub_, extern_item), |e| Item::Extern(e.public())),
+        mod_item,
+        use_item,
+        map(macro_item, Item::Macro),
+    ))(input)
+}
+
+/// Parses `macro_rules! name { (params) => { body }; ... }`, where each
+/// parameter is written `$name:expr`.
+fn macro_item(input: Input) -> IResult<Macro> {
+    let (tail, _) = keyword("macro_rules")(input)?;
+    let (tail, _) = bang(tail)?;
+    let (tail, name) = ident(tail)?;
+
+    let (tail, rules) = delimited(
+        left_curly,
+        terminated(separated_list0(semicolon, macro_rule), opt(semicolon)),
+        expect(right_curly, epsilon_recover("`}`")),
+    )(tail)?;
+
+    Ok((tail, Macro::new(name, rules)))
+}
+
+fn macro_rule(input: Input) -> IResult<MacroRule> {
+    let metavariable = preceded(dollar, terminated(ident, pair(colon, keyword("expr"))));
+
+    let (tail, params) = delimited(
+        left_par,
+        separated_list0(comma, metavariable),
+        expect(right_par, epsilon_recover("`)`")),
+    )(input)?;
+    let (tail, _) = expect(fat_arrow, epsilon_recover("`=>`"))(tail)?;
+    let (tail, body) = block(tail)?;
+
+    Ok((tail, MacroRule::new(params, body)))
+}
+
+/// Parses `extern fn name(params) -> ty;`. Parameters must be annotated.
+fn extern_item(input: Input) -> IResult<ExternFunction> {
+    let (tail, _) = keyword("extern")(input)?;
+    let (tail, _) = fn_(tail)?;
+    let (tail, name) = ident(tail)?;
+
+    let (tail, params) = delimited(
+        left_par,
+        separated_list0(comma, annotated_param),
+        expect(right_par, epsilon_recover("`)`")),
+    )(tail)?;
+
+    let (tail, ret_ty) = opt(preceded(arrow, ty))(tail)?;
+    let (tail, _) = expect(semicolon, epsilon_recover("`;`"))(tail)?;
+
+    // Just like function types, a missing return type means `()`.
+    let ret_ty = ret_ty.unwrap_or(Ty::Unit);
+
+    Ok((tail, ExternFunction::new(name, params, ret_ty)))
+}
+
+fn mod_item(input: Input) -> IResult<Item> {
+    let (tail, _) = keyword("mod")(input)?;
+    let (tail, name) = terminated(ident, expect(semicolon, epsilon_recover("`;`")))(tail)?;
+
+    Ok((tail, Item::Mod(name)))
+}
+
+fn use_item(input: Input) -> IResult<Item> {
+    let (tail, _) = keyword("use")(input)?;
+    let (tail, path) = terminated(
+        separated_list1(double_colon, ident),
+        expect(semicolon, epsilon_recover("`;`")),
+    )(tail)?;
+
+    Ok((tail, Item::Use(path)))
+}
+
+fn record_item(input: Input) -> IResult<Record> {
+    let (tail, _) = struct_(input)?;
     let (tail, name) = ident(tail)?;
 
-    let (tail, _) = left_par(tail)?;
+    let (tail, fields) = delimited(
+        left_curly,
+        terminated(separated_list0(comma, annotated_param), opt(comma)),
+        expect(right_curly, epsilon_recover("`}`")),
+    )(tail)?;
+
+    Ok((tail, Record::new(name, fields)))
+}
+
+fn function(input: Input) -> IResult<Function> {
+    let (tail, _) = fn_(input)?;
+    let (tail, name) = ident(tail)?;
 
-    let (tail, _) = right_par(tail)?;
+    let (tail, generics) = opt(delimited(
+        left_angle,
+        separated_list0(comma, generic),
+        expect(right_angle, epsilon_recover("`>`")),
+    ))(tail)?;
+    let (generics, bounds) = generics.unwrap_or_default().into_iter().fold(
+        (Vec::new(), Vec::new()),
+        |(mut generics, mut bounds), (generic, traits)| {
+            bounds.extend(traits.into_iter().map(|t| Bound::new(generic.clone(), t)));
+            generics.push(generic);
+            (generics, bounds)
+        },
+    );
+
+    let (tail, params) = delimited(
+        left_par,
+        separated_list0(comma, param),
+        expect(right_par, epsilon_recover("`)`")),
+    )(tail)?;
+
+    let (tail, ret_ty) = opt(preceded(arrow, ty))(tail)?;
+    let (tail, clauses) = many0(clause)(tail)?;
 
     let (tail, body) = block(tail)?;
 
-    let function = Function::new(name, body);
+    let function = Function::new(name, generics, params, ret_ty, body)
+        .with_bounds(bounds)
+        .with_clauses(clauses);
 
     Ok((tail, function))
 }
 
+/// Parses `requires condition` or `ensures condition`, keeping the source of
+/// the condition for the error raised when it doesn't hold.
+fn clause(input: Input) -> IResult<Clause> {
+    let (input, kind) = alt((
+        map(keyword("requires"), |()| ClauseKind::Requires),
+        map(keyword("ensures"), |()| ClauseKind::Ensures),
+    ))(input)?;
+    let (tail, condition) = expr(input)?;
+
+    let source = &input.fragment()[..input.fragment().len() - tail.fragment().len()];
+
+    Ok((tail, Clause::new(kind, condition, source.trim().to_owned())))
+}
+
+/// Parses `const fn`, whose calls may be evaluated at compile time.
+fn const_function(input: Input) -> IResult<Function> {
+    map(preceded(keyword("const"), function), Function::const_)(input)
+}
+
+/// Parses a type parameter and the traits it must implement, as in
+/// `T: Show + Eq`.
+fn generic(input: Input) -> IResult<(String, Vec<String>)> {
+    let (tail, name) = ident(input)?;
+    let (tail, traits) = opt(preceded(colon, separated_list0(plus, ident)))(tail)?;
+
+    Ok((tail, (name, traits.unwrap_or_default())))
+}
+
+fn trait_item(input: Input) -> IResult<Trait> {
+    let (tail, _) = trait_(input)?;
+    let (tail, name) = ident(tail)?;
+
+    let (tail, methods) = delimited(
+        left_curly,
+        many0(method_signature),
+        expect(right_curly, epsilon_recover("`}`")),
+    )(tail)?;
+
+    Ok((tail, Trait::new(name, methods)))
+}
+
+fn method_signature(input: Input) -> IResult<MethodSignature> {
+    let (tail, _) = fn_(input)?;
+    let (tail, name) = ident(tail)?;
+
+    let (tail, params) = delimited(
+        left_par,
+        separated_list0(comma, param),
+        expect(right_par, epsilon_recover("`)`")),
+    )(tail)?;
+    let params = params
+        .into_iter()
+        .map(|p| self_param(p, &Ty::Param("Self".to_owned())))
+        .collect();
+
+    let (tail, ret_ty) = opt(preceded(arrow, ty))(tail)?;
+    let (tail, _) = expect(semicolon, epsilon_recover("`;`"))(tail)?;
+
+    // Just like function types, a missing return type means `()`.
+    let ret_ty = ret_ty.unwrap_or(Ty::Unit);
+
+    Ok((tail, MethodSignature::new(name, params, ret_ty)))
+}
+
+/// Parses an `impl Trait for Type` or `impl Type` block. Its methods are
+/// turned into regular functions, named after the trait and the type.
+fn impl_item(input: Input) -> IResult<Item> {
+    let (tail, _) = impl_(input)?;
+    let trait_impl = map(pair(ident, preceded(for_, ty)), |(trait_, ty)| {
+        (Some(trait_), ty)
+    });
+    let (tail, (trait_, ty)) = alt((trait_impl, map(ty, |ty| (None, ty))))(tail)?;
+
+    let (tail, methods) = delimited(
+        left_curly,
+        many0(method),
+        expect(right_curly, epsilon_recover("`}`")),
+    )(tail)?;
+
+    let names = methods.iter().map(|m| m.name().to_owned()).collect();
+
+    let self_ty = [("Self".to_owned(), ty.clone())].into_iter().collect();
+    let methods = methods
+        .into_iter()
+        .map(|method| {
+            let params = method
+                .params()
+                .iter()
+                .map(|p| self_param(p.clone(), &ty))
+                .map(|p| Param::new(p.name().to_owned(), p.ty().substitute(&self_ty)))
+                .collect();
+            let ret_ty = method.ret_ty().map(|ty| ty.substitute(&self_ty));
+
+            Function::new(
+                ast::method_name(trait_.as_deref(), &ty, method.name()),
+                method.generics().to_vec(),
+                params,
+                ret_ty,
+                method.body().clone(),
+            )
+            .with_bounds(method.bounds().to_vec())
+            .with_attributes(method.attributes().to_vec())
+        })
+        .collect();
+
+    let impl_ = match trait_ {
+        Some(trait_) => Impl::new(trait_, ty, names),
+        None => Impl::inherent(ty, names),
+    };
+
+    Ok((tail, Item::Impl(impl_, methods)))
+}
+
+/// Parses a method of an `impl` block, along with its attributes.
+fn method(input: Input) -> IResult<Function> {
+    let (tail, attributes) = many0(attribute)(input)?;
+    let (tail, method) = function(tail)?;
+
+    Ok((tail, method.with_attributes(attributes)))
+}
+
+/// The `self` parameter of methods has the type that implements the method.
+fn self_param(param: Param, self_ty: &Ty) -> Param {
+    match (param.name(), param.ty()) {
+        ("self", Ty::Unannotated) => Param::new(param.name().to_owned(), self_ty.clone()),
+        _ => param,
+    }
+}
+
+fn annotated_param(input: Input) -> IResult<Param> {
+    map(pair(terminated(ident, colon), ty), |(name, ty)| {
+        Param::new(name, ty)
+    })(input)
+}
+
+fn param(input: Input) -> IResult<Param> {
+    let (tail, name) = ident(input)?;
+    let (tail, ty) = opt(preceded(colon, ty))(tail)?;
+
+    // The type of unannotated parameters is filled in by type inference.
+    let ty = ty.unwrap_or(Ty::Unannotated);
+
+    Ok((tail, Param::new(name, ty)))
+}
+
+fn ty(input: Input) -> IResult<Ty> {
+    alt((
+        map(keyword("int"), |()| Ty::Int),
+        map(keyword("bool"), |()| Ty::Bool),
+        map(pair(left_par, right_par), |_| Ty::Unit),
+        function_ty,
+        option_ty,
+        result_ty,
+        list_ty,
+        map_ty,
+        map(ident, Ty::Param),
+    ))(input)
+}
+
+/// Parses `Option<T>`, or its shorthand `?T`.
+fn option_ty(input: Input) -> IResult<Ty> {
+    let long = preceded(keyword("Option"), delimited(left_angle, ty, right_angle));
+    let short = preceded(question_mark, ty);
+
+    map(alt((long, short)), |inner| Ty::Option(Box::new(inner)))(input)
+}
+
+/// Parses `Result<T, E>`.
+fn result_ty(input: Input) -> IResult<Ty> {
+    let (tail, _) = keyword("Result")(input)?;
+    let (tail, (ok, err)) = delimited(
+        left_angle,
+        pair(terminated(ty, comma), ty),
+        expect(right_angle, epsilon_recover("`>`")),
+    )(tail)?;
+
+    Ok((tail, Ty::Result(Box::new(ok), Box::new(err))))
+}
+
+/// Parses `Map<K, V>`.
+fn map_ty(input: Input) -> IResult<Ty> {
+    let (tail, _) = keyword("Map")(input)?;
+    let (tail, (key, value)) = delimited(
+        left_angle,
+        pair(terminated(ty, comma), ty),
+        expect(right_angle, epsilon_recover("`>`")),
+    )(tail)?;
+
+    Ok((tail, Ty::Map(Box::new(key), Box::new(value))))
+}
+
+/// Parses `[T]`.
+fn list_ty(input: Input) -> IResult<Ty> {
+    let (tail, inner) = delimited(
+        left_square,
+        ty,
+        expect(right_square, epsilon_recover("`]`")),
+    )(input)?;
+
+    Ok((tail, Ty::List(Box::new(inner))))
+}
+
+fn function_ty(input: Input) -> IResult<Ty> {
+    let (tail, _) = fn_(input)?;
+    let (tail, params) = delimited(left_par, separated_list0(comma, ty), right_par)(tail)?;
+    let (tail, ret_ty) = opt(preceded(arrow, ty))(tail)?;
+
+    // Just like functions, function types return `()` by default.
+    let ret_ty = ret_ty.unwrap_or(Ty::Unit);
+
+    Ok((tail, Ty::Function(params, Box::new(ret_ty))))
+}
+
 fn block(input: Input) -> IResult<ExprKind> {
-    delimited(left_curly, alt((bindings, expr)), right_curly)(input)
+    delimited(left_curly, bindings, right_curly)(input)
 }
 
+/// Parses an expression, which may be a comparison between two integers.
+/// Comparisons can't be chained.
 fn expr(input: Input) -> IResult<ExprKind> {
-    alt((level_0_expression, level_1_expression, atomic_expr))(input)
+    let (tail, first) = atomic_expr(input)?;
+    expr_from(first, tail)
+}
+
+/// Parses the rest of an expression whose first atomic expression has
+/// already been parsed.
+fn expr_from(first: ExprKind, input: Input) -> IResult<ExprKind> {
+    let (tail, lhs) = level_0_expression_from(first, input)?;
+    let (tail, comparison) = opt(pair(comparison_operator, level_0_expression))(tail)?;
+
+    let expr = match comparison {
+        Some((operator, rhs)) => ExprKind::comparison(operator, lhs, rhs),
+        None => lhs,
+    };
+
+    Ok((tail, expr))
+}
+
+fn comparison_operator(input: Input) -> IResult<ComparisonOperator> {
+    // Two-character operators must be tried before their prefixes.
+    let operators = alt((
+        tag("<="),
+        tag(">="),
+        tag("=="),
+        tag("!="),
+        tag("<"),
+        tag(">"),
+    ));
+
+    map(operators, |operator| match operator {
+        "<=" => ComparisonOperator::Le,
+        ">=" => ComparisonOperator::Ge,
+        "==" => ComparisonOperator::Eq,
+        "!=" => ComparisonOperator::Ne,
+        "<" => ComparisonOperator::Lt,
+        ">" => ComparisonOperator::Gt,
+        _ => unreachable!(),
+    })(input)
 }
 
 fn integer(input: Input) -> IResult<ExprKind> {
@@ -70,10 +631,15 @@ fn integer(input: Input) -> IResult<ExprKind> {
 }
 
 fn level_0_expression(input: Input) -> IResult<ExprKind> {
-    let (tail, first) = alt((level_1_expression, atomic_expr))(input)?;
+    let (tail, first) = atomic_expr(input)?;
+    level_0_expression_from(first, tail)
+}
+
+fn level_0_expression_from(first: ExprKind, input: Input) -> IResult<ExprKind> {
+    let (tail, first) = level_1_expression_from(first, input)?;
 
-    fold_many1(
-        tuple((level_0_operator, alt((level_1_expression, atomic_expr)))),
+    fold_many0(
+        tuple((level_0_operator, level_1_expression)),
         first,
         |left, (operator, right)| operator.make_expr(left, right),
     )(tail)
@@ -106,34 +672,221 @@ impl Level0Operator {
 
 fn level_1_expression(input: Input) -> IResult<ExprKind> {
     let (tail, first) = atomic_expr(input)?;
-    fold_many1(tuple((star, atomic_expr)), first, |lhs, (_, rhs)| {
-        ExprKind::multiplication(lhs, rhs)
-    })(tail)
+    level_1_expression_from(first, tail)
+}
+
+fn level_1_expression_from(first: ExprKind, input: Input) -> IResult<ExprKind> {
+    let (tail, first) = call_expr_from(first, input)?;
+
+    fold_many0(
+        tuple((level_1_operator, call_expr)),
+        first,
+        |left, (operator, right)| operator.make_expr(left, right),
+    )(tail)
+}
+
+fn level_1_operator(input: Input) -> IResult<Level1Operator> {
+    let operators = space_insignificant(alt((tag("*"), tag("%"))));
+
+    map(operators, |operator| match operator {
+        "*" => Level1Operator::Star,
+        "%" => Level1Operator::Percent,
+        _ => unreachable!(),
+    })(input)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Level1Operator {
+    Star,
+    Percent,
+}
+
+impl Level1Operator {
+    fn make_expr(self, lhs: ExprKind, rhs: ExprKind) -> ExprKind {
+        let expression_maker = match self {
+            Level1Operator::Star => ExprKind::multiplication,
+            Level1Operator::Percent => ExprKind::remainder,
+        };
+
+        expression_maker(lhs, rhs)
+    }
+}
+
+fn call_expr(input: Input) -> IResult<ExprKind> {
+    let (tail, callee) = atomic_expr(input)?;
+    call_expr_from(callee, tail)
+}
+
+fn call_expr_from(callee: ExprKind, input: Input) -> IResult<ExprKind> {
+    let call = call_postfix;
+    let method_call = map(pair(preceded(dot, ident), call_args), |(method, args)| {
+        Postfix::MethodCall(method, args)
+    });
+    let field_access = map(preceded(dot, ident), Postfix::FieldAccess);
+    let try_ = map(question_mark, |()| Postfix::Try);
+
+    fold_many0(
+        alt((call, method_call, field_access, try_)),
+        callee,
+        |callee, postfix| match postfix {
+            Postfix::Call(args, span) => ExprKind::Call(Call::new(callee, args).with_span(span)),
+            Postfix::MethodCall(method, args) => ExprKind::method_call(callee, method, args),
+            Postfix::FieldAccess(field) => ExprKind::field_access(callee, field),
+            Postfix::Try => ExprKind::try_(callee),
+        },
+    )(input)
+}
+
+enum Postfix {
+    Call(Vec<ExprKind>, Span),
+    MethodCall(String, Vec<ExprKind>),
+    FieldAccess(String),
+    Try,
+}
+
+/// Parses the arguments of a call, along with the location of the opening
+/// parenthesis, which is reported by the errors about the call.
+fn call_postfix(input: Input) -> IResult<Postfix> {
+    let (input, _) = multispace0(input)?;
+    let span = span(&input);
+    let (tail, args) = call_args(input)?;
+
+    Ok((tail, Postfix::Call(args, span)))
 }
 
-fn star(input: Input) -> IResult<()> {
-    map(space_insignificant(tag("*")), drop)(input)
+fn call_args(input: Input) -> IResult<Vec<ExprKind>> {
+    delimited(
+        left_par,
+        separated_list0(comma, expr),
+        expect(right_par, epsilon_recover("`)`")),
+    )(input)
 }
 
 fn if_else(input: Input) -> IResult<ExprKind> {
     let (tail, _) = if_(input)?;
     let (tail, condition) = expr(tail)?;
     let (tail, consequent) = block(tail)?;
-    let (tail, _) = else_(tail)?;
-    let (tail, alternative) = block(tail)?;
+    let (tail, alternative) = opt(preceded(else_, alt((if_else, block))))(tail)?;
+
+    // A missing `else` branch evaluates to `()`, just like an empty block.
+    let alternative = alternative.unwrap_or_else(ExprKind::unit);
 
     let if_ = ExprKind::if_(condition, consequent, alternative);
     Ok((tail, if_))
 }
 
+/// Parses the content of a block. Each statement is parsed once, and the
+/// token that follows it tells whether it ends the block.
 fn bindings(input: Input) -> IResult<ExprKind> {
-    let (tail, bs) = many1(binding)(input)?;
-    let (tail, ending) = expr(tail)?;
-    let bindings = ExprKind::bindings(bs, ending);
+    let mut stmts = Vec::new();
+    let mut tail = input;
+
+    let ending = loop {
+        if peek(right_curly)(tail).is_ok() {
+            break ExprKind::unit();
+        }
+
+        let (new_tail, stmt) = statement(tail)?;
+        tail = new_tail;
+
+        match stmt {
+            ParsedStatement::Statement(stmt) => stmts.push(stmt),
+            ParsedStatement::Ending(ending) => break ending,
+        }
+    };
+
+    let bindings = if stmts.is_empty() {
+        ending
+    } else {
+        ExprKind::statements(stmts, ending)
+    };
 
     Ok((tail, bindings))
 }
 
+fn statement(input: Input) -> IResult<ParsedStatement> {
+    if peek(let_)(input).is_ok() {
+        let binding = map(binding, Statement::Binding);
+        return map(binding, ParsedStatement::Statement)(input);
+    }
+
+    let (tail, (expr, block_like)) = statement_expr(input)?;
+    let (tail, semicolon) = opt(semicolon)(tail)?;
+    let ends_block = peek(right_curly)(tail).is_ok();
+
+    // Just like in Rust, a block-like expression needs no semicolon to be
+    // a statement, unless it ends the block.
+    let stmt = match semicolon {
+        Some(()) => ParsedStatement::Statement(Statement::Expr(expr)),
+        None if block_like && !ends_block => ParsedStatement::Statement(Statement::Expr(expr)),
+        None => ParsedStatement::Ending(expr),
+    };
+
+    Ok((tail, stmt))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ParsedStatement {
+    Statement(Statement),
+    Ending(ExprKind),
+}
+
+/// Parses the expression of a statement, and tells whether it is a
+/// block-like expression that is not followed by an operator.
+fn statement_expr(input: Input) -> IResult<(ExprKind, bool)> {
+    if peek(block_like_start)(input).is_err() {
+        return map(expr, |expr| (expr, false))(input);
+    }
+
+    let (tail, first) = block_like_expr(input)?;
+    let (rest, expr) = expr_from(first, tail)?;
+    let block_like = rest.location_offset() == tail.location_offset();
+
+    Ok((rest, (expr, block_like)))
+}
+
+/// Expressions that can be used as statements without a trailing semicolon.
+fn block_like_expr(input: Input) -> IResult<ExprKind> {
+    alt((if_else, for_loop, block))(input)
+}
+
+fn block_like_start(input: Input) -> IResult<()> {
+    alt((if_, for_, left_curly))(input)
+}
+
+fn for_loop(input: Input) -> IResult<ExprKind> {
+    let (tail, _) = for_(input)?;
+    let (tail, variable) = ident(tail)?;
+    let (tail, _) = in_(tail)?;
+    let (tail, range) = range(tail)?;
+    let (tail, body) = block(tail)?;
+
+    Ok((tail, ExprKind::for_(variable, range, body)))
+}
+
+fn range(input: Input) -> IResult<Range> {
+    let (tail, start) = expr(input)?;
+    let (tail, inclusive) = range_operator(tail)?;
+    let (tail, end) = expr(tail)?;
+
+    Ok((tail, Range::new(start, end, inclusive)))
+}
+
+fn range_operator(input: Input) -> IResult<bool> {
+    map(
+        space_insignificant(alt((tag("..="), tag("..")))),
+        |operator| operator == "..=",
+    )(input)
+}
+
+fn break_expr(input: Input) -> IResult<ExprKind> {
+    map(break_, |()| ExprKind::break_())(input)
+}
+
+fn continue_expr(input: Input) -> IResult<ExprKind> {
+    map(continue_, |()| ExprKind::continue_())(input)
+}
+
 fn binding(input: Input) -> IResult<Binding> {
     let (tail, name) = delimited(let_, ident, expect(equal, epsilon_recover("`=`")))(input)?;
     let (tail, value) = terminated(expr, expect(semicolon, epsilon_recover("`;`")))(tail)?;
@@ -141,12 +894,241 @@ fn binding(input: Input) -> IResult<Binding> {
 }
 
 fn atomic_expr(input: Input) -> IResult<ExprKind> {
-    alt((integer, if_else, block, bool_expr, ident_expr))(input)
+    alt((
+        integer,
+        if_else,
+        for_loop,
+        block,
+        bool_expr,
+        break_expr,
+        continue_expr,
+        lambda,
+        match_expr,
+        variant,
+        panic_expr,
+        print_expr,
+        read_int_expr,
+        assert_expr,
+        macro_call,
+        list_literal,
+        record_literal,
+        metavariable,
+        ident_expr,
+    ))(input)
 }
 
-fn ident_expr(input: Input) -> IResult<ExprKind> {
+/// Parses an invocation of a macro, such as `square!(x)`, into a call to
+/// `square!`. Macros are expanded before the program is type checked.
+fn macro_call(input: Input) -> IResult<ExprKind> {
+    let (tail, name) = terminated(ident, bang)(input)?;
+    let (tail, args) = delimited(
+        left_par,
+        separated_list0(comma, expr),
+        expect(right_par, epsilon_recover("`)`")),
+    )(tail)?;
+
+    Ok((
+        tail,
+        ExprKind::call(ExprKind::ident(format!("{}!", name)), args),
+    ))
+}
+
+/// Parses a metavariable of a macro rule, such as `$x`, which is replaced by
+/// an argument of the macro when it is expanded.
+fn metavariable(input: Input) -> IResult<ExprKind> {
+    let (tail, name) = preceded(dollar, ident)(input)?;
+    Ok((tail, ExprKind::ident(format!("${}", name))))
+}
+
+/// Parses `Some(value)`, `None`, `Ok(value)` or `Err(error)`.
+fn variant(input: Input) -> IResult<ExprKind> {
+    let with_payload = |constructor: Constructor| {
+        map(
+            preceded(
+                keyword(constructor.name()),
+                delimited(left_par, expr, right_par),
+            ),
+            move |value| ExprKind::variant(constructor, Some(value)),
+        )
+    };
+    let none = map(keyword("None"), |()| {
+        ExprKind::variant(Constructor::None, None)
+    });
+
+    alt((
+        with_payload(Constructor::Some),
+        none,
+        with_payload(Constructor::Ok),
+        with_payload(Constructor::Err),
+    ))(input)
+}
+
+/// Parses `panic("message")`.
+fn panic_expr(input: Input) -> IResult<ExprKind> {
+    let (tail, _) = keyword("panic")(input)?;
+    let (tail, message) = delimited(
+        left_par,
+        string_literal,
+        expect(right_par, epsilon_recover("`)`")),
+    )(tail)?;
+
+    Ok((tail, ExprKind::panic(message)))
+}
+
+/// Parses `print(arg)` or `println(arg)`, where the argument is either a
+/// string literal or an expression. The argument of `println` is optional.
+fn print_expr(input: Input) -> IResult<ExprKind> {
+    let (tail, newline) = alt((
+        map(keyword("println"), |()| true),
+        map(keyword("print"), |()| false),
+    ))(input)?;
+
+    let arg = alt((
+        map(string_literal, PrintArg::Str),
+        map(expr, |e| PrintArg::Value(Box::new(e))),
+    ));
+    let (tail, arg) = delimited(
+        left_par,
+        opt(arg),
+        expect(right_par, epsilon_recover("`)`")),
+    )(tail)?;
+
+    Ok((
+        tail,
+        ExprKind::print(arg.unwrap_or(PrintArg::Nothing), newline),
+    ))
+}
+
+/// Parses `[a, b, c]`.
+fn list_literal(input: Input) -> IResult<ExprKind> {
+    let (tail, elements) = delimited(
+        left_square,
+        terminated(separated_list0(comma, expr), opt(comma)),
+        expect(right_square, epsilon_recover("`]`")),
+    )(input)?;
+
+    Ok((tail, ExprKind::list(elements)))
+}
+
+/// Parses `assert(condition)` or `assert_eq(left, right)`, along with their
+/// location, which is reported when the assertion fails.
+fn assert_expr(input: Input) -> IResult<ExprKind> {
+    let (input, _) = multispace0(input)?;
+    let span = span(&input);
+
+    let assert_eq = map(
+        preceded(
+            keyword("assert_eq"),
+            delimited(
+                left_par,
+                separated_pair(expr, comma, expr),
+                expect(right_par, epsilon_recover("`)`")),
+            ),
+        ),
+        |(left, right)| AssertKind::Eq(left, right),
+    );
+    let assert = map(
+        preceded(
+            keyword("assert"),
+            delimited(left_par, expr, expect(right_par, epsilon_recover("`)`"))),
+        ),
+        AssertKind::True,
+    );
+
+    let (tail, kind) = alt((assert_eq, assert))(input)?;
+
+    Ok((tail, ExprKind::assert(kind, span)))
+}
+
+/// Parses `read_int()`.
+fn read_int_expr(input: Input) -> IResult<ExprKind> {
+    let (tail, _) = keyword("read_int")(input)?;
+    let (tail, _) = pair(left_par, expect(right_par, epsilon_recover("`)`")))(tail)?;
+
+    Ok((tail, ExprKind::read_int()))
+}
+
+/// Parses a string literal. Escape sequences are not supported yet.
+fn string_literal(input: Input) -> IResult<String> {
+    let (tail, content) =
+        space_insignificant(delimited(tag("\""), take_while(|c| c != '"'), tag("\"")))(input)?;
+
+    Ok((tail, content.fragment().to_string()))
+}
+
+fn match_expr(input: Input) -> IResult<ExprKind> {
+    let (tail, _) = match_(input)?;
+    let (tail, scrutinee) = expr(tail)?;
+    let (tail, arms) = delimited(
+        left_curly,
+        terminated(separated_list1(comma, arm), opt(comma)),
+        expect(right_curly, epsilon_recover("`}`")),
+    )(tail)?;
+
+    Ok((tail, ExprKind::match_(scrutinee, arms)))
+}
+
+fn arm(input: Input) -> IResult<Arm> {
+    let (tail, pattern) = pattern(input)?;
+    let (tail, _) = expect(fat_arrow, epsilon_recover("`=>`"))(tail)?;
+    let (tail, body) = expr(tail)?;
+
+    Ok((tail, Arm::new(pattern, body)))
+}
+
+fn pattern(input: Input) -> IResult<Pattern> {
+    let with_binding = |constructor: Constructor| {
+        map(
+            preceded(
+                keyword(constructor.name()),
+                delimited(left_par, ident, right_par),
+            ),
+            move |name| Pattern::new(constructor, Some(name)),
+        )
+    };
+    let none = map(keyword("None"), |()| Pattern::new(Constructor::None, None));
+
+    alt((
+        with_binding(Constructor::Some),
+        none,
+        with_binding(Constructor::Ok),
+        with_binding(Constructor::Err),
+    ))(input)
+}
+
+/// Parses a record literal such as `Point { x: 1, y: 2 }`. At least one
+/// field is required, so that `n { ... }` in `for i in 0..n { ... }` is not
+/// mistaken for a literal.
+fn record_literal(input: Input) -> IResult<ExprKind> {
     let (tail, name) = ident(input)?;
-    Ok((tail, ExprKind::ident(name)))
+
+    let field = pair(terminated(ident, colon), expr);
+    let (tail, fields) = delimited(
+        left_curly,
+        terminated(separated_list1(comma, field), opt(comma)),
+        expect(right_curly, epsilon_recover("`}`")),
+    )(tail)?;
+
+    Ok((tail, ExprKind::record_literal(name, fields)))
+}
+
+fn lambda(input: Input) -> IResult<ExprKind> {
+    let (tail, params) = delimited(
+        pipe,
+        separated_list0(comma, param),
+        expect(pipe, epsilon_recover("`|`")),
+    )(input)?;
+
+    let (tail, body) = expr(tail)?;
+
+    Ok((tail, ExprKind::lambda(params, body)))
+}
+
+/// Parses a name, which may be qualified by the modules it belongs to, as in
+/// `utils::helper`.
+fn ident_expr(input: Input) -> IResult<ExprKind> {
+    let (tail, path) = separated_list1(double_colon, ident)(input)?;
+    Ok((tail, ExprKind::ident(path.join("::"))))
 }
 
 fn bool_expr(input: Input) -> IResult<ExprKind> {
@@ -178,30 +1160,130 @@ fn else_(input: Input) -> IResult<()> {
     keyword("else")(input)
 }
 
-fn let_(input: Input) -> IResult<()> {
-    keyword("let")(input)
+fn fn_(input: Input) -> IResult<()> {
+    keyword("fn")(input)
 }
 
-fn true_(input: Input) -> IResult<()> {
-    keyword("true")(input)
+fn trait_(input: Input) -> IResult<()> {
+    keyword("trait")(input)
 }
 
-fn false_(input: Input) -> IResult<()> {
-    keyword("false")(input)
+fn struct_(input: Input) -> IResult<()> {
+    keyword("struct")(input)
 }
 
-fn equal(input: Input) -> IResult<()> {
-    map(space_insignificant(tag("=")), drop)(input)
+fn impl_(input: Input) -> IResult<()> {
+    keyword("impl")(input)
 }
 
-fn semicolon(input: Input) -> IResult<()> {
-    map(space_insignificant(tag(";")), drop)(input)
+fn match_(input: Input) -> IResult<()> {
+    keyword("match")(input)
 }
 
-fn keyword(kw: &str) -> impl Fn(Input) -> IResult<()> + '_ {
-    move |input| {
-        let (tail, _) = map(preceded(multispace0, tag(kw)), drop)(input)?;
-        let next_is_alphabetic = tail
+fn let_(input: Input) -> IResult<()> {
+    keyword("let")(input)
+}
+
+fn for_(input: Input) -> IResult<()> {
+    keyword("for")(input)
+}
+
+fn in_(input: Input) -> IResult<()> {
+    keyword("in")(input)
+}
+
+fn break_(input: Input) -> IResult<()> {
+    keyword("break")(input)
+}
+
+fn continue_(input: Input) -> IResult<()> {
+    keyword("continue")(input)
+}
+
+fn true_(input: Input) -> IResult<()> {
+    keyword("true")(input)
+}
+
+fn false_(input: Input) -> IResult<()> {
+    keyword("false")(input)
+}
+
+fn equal(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("=")), drop)(input)
+}
+
+fn fat_arrow(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("=>")), drop)(input)
+}
+
+/// Parses `!`, as long as it doesn't start `!=`.
+fn bang(input: Input) -> IResult<()> {
+    map(
+        space_insignificant(terminated(tag("!"), not(tag("=")))),
+        drop,
+    )(input)
+}
+
+fn hash(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("#")), drop)(input)
+}
+
+fn dollar(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("$")), drop)(input)
+}
+
+fn question_mark(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("?")), drop)(input)
+}
+
+fn left_angle(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("<")), drop)(input)
+}
+
+fn right_angle(input: Input) -> IResult<()> {
+    map(space_insignificant(tag(">")), drop)(input)
+}
+
+fn pipe(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("|")), drop)(input)
+}
+
+fn plus(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("+")), drop)(input)
+}
+
+fn dot(input: Input) -> IResult<()> {
+    map(space_insignificant(tag(".")), drop)(input)
+}
+
+fn comma(input: Input) -> IResult<()> {
+    map(space_insignificant(tag(",")), drop)(input)
+}
+
+fn double_colon(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("::")), drop)(input)
+}
+
+fn pub_(input: Input) -> IResult<()> {
+    keyword("pub")(input)
+}
+
+fn colon(input: Input) -> IResult<()> {
+    map(space_insignificant(tag(":")), drop)(input)
+}
+
+fn arrow(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("->")), drop)(input)
+}
+
+fn semicolon(input: Input) -> IResult<()> {
+    map(space_insignificant(tag(";")), drop)(input)
+}
+
+fn keyword(kw: &str) -> impl Fn(Input) -> IResult<()> + '_ {
+    move |input| {
+        let (tail, _) = map(preceded(multispace0, tag(kw)), drop)(input)?;
+        let next_is_alphabetic = tail
             .chars()
             .next()
             .map(char::is_alphabetic)
@@ -224,163 +1306,942 @@ fn right_curly(input: Input) -> IResult<()> {
     map(space_insignificant(tag("}")), drop)(input)
 }
 
+fn left_square(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("[")), drop)(input)
+}
+
+fn right_square(input: Input) -> IResult<()> {
+    map(space_insignificant(tag("]")), drop)(input)
+}
+
 fn left_par(input: Input) -> IResult<()> {
     map(space_insignificant(tag("(")), drop)(input)
 }
 
-fn right_par(input: Input) -> IResult<()> {
-    map(space_insignificant(tag(")")), drop)(input)
-}
+fn right_par(input: Input) -> IResult<()> {
+    map(space_insignificant(tag(")")), drop)(input)
+}
+
+fn space_insignificant<'a, O, E>(
+    parser: impl Parser<Input<'a>, O, E>,
+) -> impl FnMut(Input<'a>) -> IResult<'a, O, E>
+where
+    E: ParseError<Input<'a>>,
+{
+    delimited(multispace0, parser, multispace0)
+}
+
+fn expect<O, P, R>(mut parser: P, mut recovery: R) -> impl FnMut(Input) -> IResult<Option<O>>
+where
+    P: FnMut(Input) -> IResult<O>,
+    R: FnMut(Input, ErrorKind) -> Option<Input>,
+{
+    move |input| match parser(input) {
+        Ok((tail, data)) => Ok((tail, Some(data))),
+        Err(Err::Incomplete(_)) => panic!("Parser returned Incomplete variant"),
+        Err(Err::Error(NomError { input, code })) | Err(Err::Failure(NomError { input, code })) => {
+            match recovery(input, code) {
+                Some(tail) => Ok((tail, None)),
+                None => Ok((input, None)),
+            }
+        }
+    }
+}
+
+fn epsilon_recover(token: &str) -> impl Fn(Input, ErrorKind) -> Option<Input> + '_ {
+    move |input, _| {
+        let message = format!("{}: Excepted {}", span(&input), token);
+        input.extra.errors().add(message);
+
+        Some(input)
+    }
+}
+
+/// Returns the location of the beginning of the input.
+fn span(input: &Input) -> Span {
+    Span::new(
+        input.extra.file().map(str::to_owned),
+        input.location_line(),
+        input.get_utf8_column(),
+    )
+}
+
+fn tag(t: &str) -> impl FnMut(Input) -> IResult<&str> + '_ {
+    move |input: Input| {
+        map(nom_tag(t), |matched: LocatedSpan<&str, _>| {
+            *matched.fragment()
+        })(input)
+    }
+}
+
+fn alphanumeric1(input: Input<'_>) -> IResult<'_, &str> {
+    map(nom_alphanumeric1, |matched: LocatedSpan<&str, _>| {
+        *matched.fragment()
+    })(input)
+}
+
+fn alpha1(input: Input<'_>) -> IResult<'_, &str> {
+    map(nom_alpha1, |matched: LocatedSpan<&str, _>| {
+        *matched.fragment()
+    })(input)
+}
+
+#[cfg(test)]
+fn parse_and_own<O>(
+    f: impl Fn(Input) -> IResult<O>,
+    input: &str,
+) -> (Result<O, Err<NomError<()>>>, ParsingContext) {
+    let parsing_ctxt = ParsingContext::new();
+    let input = LocatedSpan::new_extra(input, &parsing_ctxt);
+    let parsing_status = f(input).map_err(own_nom_err).map(|(_, parsed)| parsed);
+
+    (parsing_status, parsing_ctxt)
+}
+
+#[cfg(test)]
+fn own_nom_err(err: Err<nom::error::Error<Input>>) -> Err<nom::error::Error<()>> {
+    match err {
+        Err::Error(e) => Err::Error(own_nom_error(e)),
+        Err::Failure(f) => Err::Failure(own_nom_error(f)),
+        Err::Incomplete(needed) => Err::Incomplete(needed),
+    }
+}
+
+#[cfg(test)]
+fn own_nom_error(err: NomError<Input>) -> NomError<()> {
+    let NomError { code, .. } = err;
+    let input = ();
+    NomError { input, code }
+}
+
+#[cfg(test)]
+macro_rules! parse {
+    ($rule:ident $slice:expr) => {{
+        parse_and_own($rule, $slice)
+    }};
+}
+
+#[cfg(test)]
+mod program {
+    use crate::inline_program;
+
+    use super::*;
+
+    #[test]
+    fn single_main_func() {
+        let (left, _) = parse! { program_with_tail "fn main() { 42 }" };
+        let right = Ok(inline_program! {
+            fn main() { 42 }
+        });
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn handles_multiple_functions() {
+        let (left, _) = parse! { program_with_tail "fn main() { 42 } fn main_() { 42 }" };
+        let right = Ok(inline_program! {
+            fn main() { 42 }
+            fn main_() { 42 }
+        });
+
+        assert_eq!(left, right);
+    }
+}
+
+#[cfg(test)]
+mod function {
+    use crate::inline_fn;
+
+    use super::*;
+
+    #[test]
+    fn handles_bindings() {
+        let (left, _) = parse! { function "fn main() { let a = 40; let b = 2; a + b }" };
+        let right = Ok(inline_fn! {
+            fn main() {
+                let a = 40;
+                let b = 2;
+                a + b
+            }
+        });
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn handles_expression() {
+        let (left, _) = parse! { function "fn main() { 1 + 2 + 2 }" };
+        let right = Ok(inline_fn! {
+            fn main() {
+                1 + 2 + 2
+            }
+        });
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn handles_signature() {
+        let (left, _) = parse! { function "fn add(a: int, b: int) -> int { a + b }" };
+        let right = Ok(Function::new(
+            "add".to_owned(),
+            Vec::new(),
+            vec![
+                Param::new("a".to_owned(), Ty::Int),
+                Param::new("b".to_owned(), Ty::Int),
+            ],
+            Some(Ty::Int),
+            ExprKind::addition(
+                ExprKind::ident("a".to_owned()),
+                ExprKind::ident("b".to_owned()),
+            ),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn handles_contracts() {
+        let (left, _) = parse! {
+            function "fn f(x: int) -> int requires x <= 10 ensures result >= x { x }"
+        };
+        let ident = |name: &str| ExprKind::ident(name.to_owned());
+        let clauses = vec![
+            Clause::new(
+                ClauseKind::Requires,
+                ExprKind::comparison(ComparisonOperator::Le, ident("x"), ExprKind::integer(10)),
+                "x <= 10".to_owned(),
+            ),
+            Clause::new(
+                ClauseKind::Ensures,
+                ExprKind::comparison(ComparisonOperator::Ge, ident("result"), ident("x")),
+                "result >= x".to_owned(),
+            ),
+        ];
+        let right = Ok(Function::new(
+            "f".to_owned(),
+            Vec::new(),
+            vec![Param::new("x".to_owned(), Ty::Int)],
+            Some(Ty::Int),
+            ident("x"),
+        )
+        .with_clauses(clauses));
+
+        assert_eq!(left, right);
+    }
+}
+
+#[cfg(test)]
+mod generics {
+    use super::*;
+
+    #[test]
+    fn type_parameters() {
+        let (left, _) = parse! { function "fn first<A, B>(a: A, b: B) -> A { a }" };
+        let right = Ok(Function::new(
+            "first".to_owned(),
+            vec!["A".to_owned(), "B".to_owned()],
+            vec![
+                Param::new("a".to_owned(), Ty::Param("A".to_owned())),
+                Param::new("b".to_owned(), Ty::Param("B".to_owned())),
+            ],
+            Some(Ty::Param("A".to_owned())),
+            ExprKind::ident("a".to_owned()),
+        ));
+
+        assert_eq!(left, right);
+    }
+    #[test]
+    fn bounds() {
+        let (left, _) = parse! { function "fn f<T: Show + Eq, U>(x: T, y: U) {}" };
+        let left = left.unwrap();
+
+        assert_eq!(left.generics(), ["T", "U"]);
+        assert_eq!(
+            left.bounds(),
+            [
+                Bound::new("T".to_owned(), "Show".to_owned()),
+                Bound::new("T".to_owned(), "Eq".to_owned()),
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod traits {
+    use super::*;
+
+    #[test]
+    fn trait_declaration() {
+        let (left, _) =
+            parse! { trait_item "trait Show { fn show(self) -> int; fn reset(self, x: int); }" };
+        let right = Ok(Trait::new(
+            "Show".to_owned(),
+            vec![
+                MethodSignature::new(
+                    "show".to_owned(),
+                    vec![Param::new("self".to_owned(), Ty::Param("Self".to_owned()))],
+                    Ty::Int,
+                ),
+                MethodSignature::new(
+                    "reset".to_owned(),
+                    vec![
+                        Param::new("self".to_owned(), Ty::Param("Self".to_owned())),
+                        Param::new("x".to_owned(), Ty::Int),
+                    ],
+                    Ty::Unit,
+                ),
+            ],
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn impl_methods_become_functions() {
+        let (left, _) =
+            parse! { program_with_tail "impl Show for bool { fn show(self) -> Self { self } }" };
+        let left = left.unwrap();
+
+        assert_eq!(
+            left.impls(),
+            [Impl::new(
+                "Show".to_owned(),
+                Ty::Bool,
+                vec!["show".to_owned()]
+            )]
+        );
+        assert_eq!(
+            left.functions(),
+            [Function::new(
+                "<bool as Show>::show".to_owned(),
+                Vec::new(),
+                vec![Param::new("self".to_owned(), Ty::Bool)],
+                Some(Ty::Bool),
+                ExprKind::ident("self".to_owned()),
+            )]
+        );
+    }
+
+    #[test]
+    fn inherent_impl() {
+        let (left, _) = parse! { program_with_tail "impl int { fn abs(self) -> int { self } }" };
+        let left = left.unwrap();
+
+        assert_eq!(
+            left.impls(),
+            [Impl::inherent(Ty::Int, vec!["abs".to_owned()])]
+        );
+        assert_eq!(left.functions()[0].name(), "int::abs");
+        assert_eq!(
+            left.functions()[0].params(),
+            [Param::new("self".to_owned(), Ty::Int)]
+        );
+    }
+
+    #[test]
+    fn method_call() {
+        let (left, _) = parse! { expr "x.show(1).reset()" };
+        let right = Ok(ExprKind::method_call(
+            ExprKind::method_call(
+                ExprKind::ident("x".to_owned()),
+                "show".to_owned(),
+                vec![ExprKind::integer(1)],
+            ),
+            "reset".to_owned(),
+            Vec::new(),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn method_call_on_literal() {
+        let (left, _) = parse! { expr "2 * 4.show()" };
+        let right = Ok(ExprKind::multiplication(
+            ExprKind::integer(2),
+            ExprKind::method_call(ExprKind::integer(4), "show".to_owned(), Vec::new()),
+        ));
+
+        assert_eq!(left, right);
+    }
+}
+
+#[cfg(test)]
+mod records {
+    use super::*;
+
+    #[test]
+    fn record_declaration() {
+        let (left, _) = parse! { record_item "struct Point { x: int, y: int, }" };
+        let right = Ok(Record::new(
+            "Point".to_owned(),
+            vec![
+                Param::new("x".to_owned(), Ty::Int),
+                Param::new("y".to_owned(), Ty::Int),
+            ],
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn record_names_are_resolved() {
+        let (left, _) = parse! {
+            program_with_tail "fn id<T>(p: Point, t: T) -> Point { p } struct Point { T: T }"
+        };
+        let left = left.unwrap();
+
+        let point = Ty::Record("Point".to_owned());
+        assert_eq!(
+            left.functions()[0].params(),
+            [
+                Param::new("p".to_owned(), point.clone()),
+                Param::new("t".to_owned(), Ty::Param("T".to_owned())),
+            ]
+        );
+        assert_eq!(left.functions()[0].ret_ty(), Some(&point));
+    }
+
+    #[test]
+    fn record_literal() {
+        let (left, _) = parse! { expr "Point { x: 1, y: a + 1 }" };
+        let right = Ok(ExprKind::record_literal(
+            "Point".to_owned(),
+            vec![
+                ("x".to_owned(), ExprKind::integer(1)),
+                (
+                    "y".to_owned(),
+                    ExprKind::addition(ExprKind::ident("a".to_owned()), ExprKind::integer(1)),
+                ),
+            ],
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn field_access() {
+        let (left, _) = parse! { expr "p.x * q.pos.y" };
+        let right = Ok(ExprKind::multiplication(
+            ExprKind::field_access(ExprKind::ident("p".to_owned()), "x".to_owned()),
+            ExprKind::field_access(
+                ExprKind::field_access(ExprKind::ident("q".to_owned()), "pos".to_owned()),
+                "y".to_owned(),
+            ),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn loop_body_is_not_a_literal() {
+        let (left, _) = parse! { expr "for i in 0..n { x }" };
+
+        assert!(matches!(left, Ok(ExprKind::For(_))));
+    }
+}
+
+#[cfg(test)]
+mod ty {
+    use super::*;
+
+    #[test]
+    fn simple_types() {
+        assert_eq!(parse! { ty "int" }.0, Ok(Ty::Int));
+        assert_eq!(parse! { ty "bool" }.0, Ok(Ty::Bool));
+        assert_eq!(parse! { ty "()" }.0, Ok(Ty::Unit));
+    }
+
+    #[test]
+    fn function_type() {
+        let (left, _) = parse! { ty "fn(int, fn() -> bool) -> int" };
+        let right = Ok(Ty::Function(
+            vec![Ty::Int, Ty::Function(Vec::new(), Box::new(Ty::Bool))],
+            Box::new(Ty::Int),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn function_type_returns_unit_by_default() {
+        let (left, _) = parse! { ty "fn(int)" };
+        let right = Ok(Ty::Function(vec![Ty::Int], Box::new(Ty::Unit)));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn option_type() {
+        let option = |ty| Ty::Option(Box::new(ty));
+
+        assert_eq!(parse! { ty "Option<int>" }.0, Ok(option(Ty::Int)));
+        assert_eq!(parse! { ty "??bool" }.0, Ok(option(option(Ty::Bool))));
+    }
+
+    #[test]
+    fn result_type() {
+        let (left, _) = parse! { ty "Result<int, ?bool>" };
+        let right = Ok(Ty::Result(
+            Box::new(Ty::Int),
+            Box::new(Ty::Option(Box::new(Ty::Bool))),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn list_type() {
+        let (left, _) = parse! { ty "[[int]]" };
+        let right = Ty::List(Box::new(Ty::List(Box::new(Ty::Int))));
+
+        assert_eq!(left, Ok(right));
+    }
+
+    #[test]
+    fn map_type() {
+        let (left, _) = parse! { ty "Map<int, [bool]>" };
+        let right = Ty::Map(Box::new(Ty::Int), Box::new(Ty::List(Box::new(Ty::Bool))));
+
+        assert_eq!(left, Ok(right));
+    }
+}
+
+#[cfg(test)]
+mod modules {
+    use super::*;
+
+    #[test]
+    fn public_function() {
+        let (left, _) = parse! { item "pub fn f() -> int { 1 }" };
+        let right = Ok(Item::Function(
+            Function::new(
+                "f".to_owned(),
+                Vec::new(),
+                Vec::new(),
+                Some(Ty::Int),
+                ExprKind::integer(1),
+            )
+            .public(),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn memo_function() {
+        let (left, _) = parse! { item "#[memo] pub fn f(n: int) -> int { n }" };
+        let right = Ok(Item::Function(
+            Function::new(
+                "f".to_owned(),
+                Vec::new(),
+                vec![Param::new("n".to_owned(), Ty::Int)],
+                Some(Ty::Int),
+                ExprKind::ident("n".to_owned()),
+            )
+            .public()
+            .memo(),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn attributes() {
+        let (left, ctxt) = parse! { item "#[test]\n#[inline(always)] fn f() {}" };
+        let attributes = vec![
+            Attribute::new("test".to_owned(), Vec::new(), Span::new(None, 1, 1)),
+            Attribute::new(
+                "inline".to_owned(),
+                vec!["always".to_owned()],
+                Span::new(None, 2, 1),
+            ),
+        ];
+        let right = Ok(Item::Function(
+            Function::new(
+                "f".to_owned(),
+                Vec::new(),
+                Vec::new(),
+                None,
+                ExprKind::unit(),
+            )
+            .with_attributes(attributes),
+        ));
+
+        assert_eq!(left, right);
+        assert_eq!(ctxt.errors().to_string(), "");
+    }
+
+    #[test]
+    fn methods_have_attributes() {
+        let (left, _) =
+            parse! { program_with_tail "impl int { #[inline] fn zero(self) -> int { 0 } }" };
+        let left = left.unwrap();
+
+        assert_eq!(
+            left.functions()[0].attributes(),
+            [Attribute::new(
+                "inline".to_owned(),
+                Vec::new(),
+                Span::new(None, 1, 12)
+            )]
+        );
+    }
+
+    #[test]
+    fn attributes_apply_to_functions_only() {
+        let (_, ctxt) = parse! { item "#[memo] struct Point { x: int }" };
+
+        assert_eq!(
+            ctxt.errors().to_string(),
+            "1:1: Attributes can only be applied to functions\n"
+        );
+    }
+
+    #[test]
+    fn const_function() {
+        let (left, _) = parse! { item "pub const fn f() -> int { 1 }" };
+        let right = Ok(Item::Function(
+            Function::new(
+                "f".to_owned(),
+                Vec::new(),
+                Vec::new(),
+                Some(Ty::Int),
+                ExprKind::integer(1),
+            )
+            .const_()
+            .public(),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn macro_rules() {
+        let (left, _) =
+            parse! { item "macro_rules! max { ($a:expr, $b:expr) => { $a }; () => { 0 } }" };
+        let rules = vec![
+            MacroRule::new(
+                vec!["a".to_owned(), "b".to_owned()],
+                ExprKind::ident("$a".to_owned()),
+            ),
+            MacroRule::new(Vec::new(), ExprKind::integer(0)),
+        ];
+        let right = Ok(Item::Macro(Macro::new("max".to_owned(), rules)));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn extern_function() {
+        let (left, _) = parse! { item "pub extern fn clamp(x: int, max: int) -> int;" };
+        let params = vec![
+            Param::new("x".to_owned(), Ty::Int),
+            Param::new("max".to_owned(), Ty::Int),
+        ];
+        let right = Ok(Item::Extern(
+            ExternFunction::new("clamp".to_owned(), params, Ty::Int).public(),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn extern_function_returns_unit_by_default() {
+        let (left, _) = parse! { item "extern fn flush();" };
+        let right = Ok(Item::Extern(ExternFunction::new(
+            "flush".to_owned(),
+            Vec::new(),
+            Ty::Unit,
+        )));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn extern_parameters_are_annotated() {
+        let (_, ctxt) = parse! { item "extern fn f(x);" };
+
+        assert!(ctxt
+            .errors()
+            .to_string()
+            .starts_with("1:13: Excepted `)`\n"));
+    }
+
+    #[test]
+    fn mod_and_use() {
+        assert_eq!(
+            parse! { item "mod utils;" }.0,
+            Ok(Item::Mod("utils".to_owned()))
+        );
+        assert_eq!(
+            parse! { item "use utils::helper;" }.0,
+            Ok(Item::Use(vec!["utils".to_owned(), "helper".to_owned()]))
+        );
+    }
+
+    #[test]
+    fn qualified_name() {
+        let (left, _) = parse! { expr "utils :: helper(1)" };
+        let right = Ok(ExprKind::call_at(
+            ExprKind::ident("utils::helper".to_owned()),
+            vec![ExprKind::integer(1)],
+            Span::new(None, 1, 16),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn errors_name_the_file() {
+        let ctxt = ParsingContext::for_file("utils.dyl".to_owned());
+        let _ = parse_items("fn f() { let a = 1 a }", &ctxt);
+
+        assert_eq!(ctxt.errors().to_string(), "utils.dyl:1:20: Excepted `;`\n");
+    }
+}
+
+#[cfg(test)]
+mod options {
+    use super::*;
+
+    fn ident(name: &str) -> ExprKind {
+        ExprKind::ident(name.to_owned())
+    }
+
+    #[test]
+    fn variants() {
+        let (left, _) = parse! { expr "Some(None)" };
+        let right = Ok(ExprKind::variant(
+            Constructor::Some,
+            Some(ExprKind::variant(Constructor::None, None)),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn names_starting_with_a_constructor_are_idents() {
+        let (left, _) = parse! { expr "Nonea" };
+
+        assert_eq!(left, Ok(ident("Nonea")));
+    }
+
+    #[test]
+    fn match_() {
+        let (left, _) = parse! { expr "match a { Some(x) => x, None => 0, }" };
+        let right = Ok(ExprKind::match_(
+            ident("a"),
+            vec![
+                Arm::new(
+                    Pattern::new(Constructor::Some, Some("x".to_owned())),
+                    ident("x"),
+                ),
+                Arm::new(Pattern::new(Constructor::None, None), ExprKind::integer(0)),
+            ],
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn results() {
+        let (left, _) = parse! { expr "match Ok(1) { Err(e) => Err(e), Ok(x) => Ok(x) }" };
+        let right = Ok(ExprKind::match_(
+            ExprKind::variant(Constructor::Ok, Some(ExprKind::integer(1))),
+            vec![
+                Arm::new(
+                    Pattern::new(Constructor::Err, Some("e".to_owned())),
+                    ExprKind::variant(Constructor::Err, Some(ident("e"))),
+                ),
+                Arm::new(
+                    Pattern::new(Constructor::Ok, Some("x".to_owned())),
+                    ExprKind::variant(Constructor::Ok, Some(ident("x"))),
+                ),
+            ],
+        ));
+
+        assert_eq!(left, right);
+    }
 
-fn space_insignificant<'a, O, E>(
-    parser: impl Parser<Input<'a>, O, E>,
-) -> impl FnMut(Input<'a>) -> IResult<'a, O, E>
-where
-    E: ParseError<Input<'a>>,
-{
-    delimited(multispace0, parser, multispace0)
-}
+    #[test]
+    fn panic() {
+        let (left, _) = parse! { expr "panic( \"no value, sorry\" )" };
 
-fn expect<O, P, R>(mut parser: P, mut recovery: R) -> impl FnMut(Input) -> IResult<Option<O>>
-where
-    P: FnMut(Input) -> IResult<O>,
-    R: FnMut(Input, ErrorKind) -> Option<Input>,
-{
-    move |input| match parser(input) {
-        Ok((tail, data)) => Ok((tail, Some(data))),
-        Err(Err::Incomplete(_)) => panic!("Parser returned Incomplete variant"),
-        Err(Err::Error(NomError { input, code })) | Err(Err::Failure(NomError { input, code })) => {
-            match recovery(input, code) {
-                Some(tail) => Ok((tail, None)),
-                None => Ok((input, None)),
-            }
-        }
+        assert_eq!(left, Ok(ExprKind::panic("no value, sorry".to_owned())));
     }
-}
 
-fn epsilon_recover(token: &str) -> impl Fn(Input, ErrorKind) -> Option<Input> + '_ {
-    move |input, _| {
-        let line = input.location_line();
-        let col = input.get_utf8_column();
+    #[test]
+    fn print() {
+        let (left, _) = parse! { expr "print(\"a = \")" };
+        let right = ExprKind::print(PrintArg::Str("a = ".to_owned()), false);
+        assert_eq!(left, Ok(right));
+
+        let (left, _) = parse! { expr "println(a + 1)" };
+        let arg = ExprKind::addition(ident("a"), ExprKind::integer(1));
+        let right = ExprKind::print(PrintArg::Value(Box::new(arg)), true);
+        assert_eq!(left, Ok(right));
+    }
 
-        let message = format!("{}:{}: Excepted {}", line, col, token);
-        input.extra.errors().add(message);
+    #[test]
+    fn println_without_argument() {
+        let (left, _) = parse! { expr "println()" };
 
-        Some(input)
+        assert_eq!(left, Ok(ExprKind::print(PrintArg::Nothing, true)));
     }
-}
 
-fn tag(t: &str) -> impl FnMut(Input) -> IResult<&str> + '_ {
-    move |input: Input| {
-        map(nom_tag(t), |matched: LocatedSpan<&str, _>| {
-            *matched.fragment()
-        })(input)
+    #[test]
+    fn list_literals() {
+        let (left, _) = parse! { expr "[1, a + 1,]" };
+        let right = ExprKind::list(vec![
+            ExprKind::integer(1),
+            ExprKind::addition(ident("a"), ExprKind::integer(1)),
+        ]);
+        assert_eq!(left, Ok(right));
+
+        let (left, _) = parse! { expr "[]" };
+        assert_eq!(left, Ok(ExprKind::list(Vec::new())));
     }
-}
 
-fn alphanumeric1(input: Input) -> IResult<&str> {
-    map(nom_alphanumeric1, |matched: LocatedSpan<&str, _>| {
-        *matched.fragment()
-    })(input)
-}
+    #[test]
+    fn macro_call() {
+        let (left, _) = parse! { expr "max!(a, 1) != b" };
+        let call = ExprKind::call(ident("max!"), vec![ident("a"), ExprKind::integer(1)]);
+        let right = ExprKind::comparison(ComparisonOperator::Ne, call, ident("b"));
 
-fn alpha1(input: Input) -> IResult<&str> {
-    map(nom_alpha1, |matched: LocatedSpan<&str, _>| {
-        *matched.fragment()
-    })(input)
-}
+        assert_eq!(left, Ok(right));
+    }
 
-#[cfg(test)]
-fn parse_and_own<O>(
-    f: impl Fn(Input) -> IResult<O>,
-    input: &str,
-) -> (Result<O, Err<NomError<()>>>, ParsingContext) {
-    let parsing_ctxt = ParsingContext::new();
-    let input = LocatedSpan::new_extra(input, &parsing_ctxt);
-    let parsing_status = f(input).map_err(own_nom_err).map(|(_, parsed)| parsed);
+    #[test]
+    fn assertions() {
+        let (left, _) = parse! { expr "{ assert(b);\n  assert_eq(x, 1) }" };
+        let right = ExprKind::statements(
+            vec![Statement::Expr(ExprKind::assert(
+                AssertKind::True(ident("b")),
+                Span::new(None, 1, 3),
+            ))],
+            ExprKind::assert(
+                AssertKind::Eq(ident("x"), ExprKind::integer(1)),
+                Span::new(None, 2, 3),
+            ),
+        );
+
+        assert_eq!(left, Ok(right));
+    }
 
-    (parsing_status, parsing_ctxt)
-}
+    #[test]
+    fn read_int() {
+        let (left, _) = parse! { expr "read_int() * 2" };
+        let right = ExprKind::multiplication(ExprKind::read_int(), ExprKind::integer(2));
 
-#[cfg(test)]
-fn own_nom_err(err: Err<nom::error::Error<Input>>) -> Err<nom::error::Error<()>> {
-    match err {
-        Err::Error(e) => Err::Error(own_nom_error(e)),
-        Err::Failure(f) => Err::Failure(own_nom_error(f)),
-        Err::Incomplete(needed) => Err::Incomplete(needed),
+        assert_eq!(left, Ok(right));
     }
-}
 
-#[cfg(test)]
-fn own_nom_error(err: NomError<Input>) -> NomError<()> {
-    let NomError { code, .. } = err;
-    let input = ();
-    NomError { input, code }
-}
+    #[test]
+    fn try_binds_tighter_than_operators() {
+        let (left, _) = parse! { expr "f(a)? + b.c?" };
+        let right = Ok(ExprKind::addition(
+            ExprKind::try_(ExprKind::call_at(
+                ident("f"),
+                vec![ident("a")],
+                Span::new(None, 1, 2),
+            )),
+            ExprKind::try_(ExprKind::field_access(ident("b"), "c".to_owned())),
+        ));
 
-#[cfg(test)]
-macro_rules! parse {
-    ($rule:ident $slice:expr) => {{
-        parse_and_own($rule, $slice)
-    }};
+        assert_eq!(left, right);
+    }
 }
 
 #[cfg(test)]
-mod program {
-    use crate::inline_program;
-
+mod lambda {
     use super::*;
 
     #[test]
-    fn single_main_func() {
-        let (left, _) = parse! { program_with_tail "fn main() { 42 }" };
-        let right = Ok(inline_program! {
-            fn main() { 42 }
-        });
+    fn unannotated_params() {
+        let (left, _) = parse! { expr "|x| x * k" };
+        let right = Ok(ExprKind::lambda(
+            vec![Param::new("x".to_owned(), Ty::Unannotated)],
+            ExprKind::multiplication(
+                ExprKind::ident("x".to_owned()),
+                ExprKind::ident("k".to_owned()),
+            ),
+        ));
 
         assert_eq!(left, right);
     }
 
     #[test]
-    fn handles_multiple_functions() {
-        let (left, _) = parse! { program_with_tail "fn main() { 42 } fn main_() { 42 }" };
-        let right = Ok(inline_program! {
-            fn main() { 42 }
-            fn main_() { 42 }
-        });
+    fn annotated_params() {
+        let (left, _) = parse! { expr "|a: int, b| b" };
+        let right = Ok(ExprKind::lambda(
+            vec![
+                Param::new("a".to_owned(), Ty::Int),
+                Param::new("b".to_owned(), Ty::Unannotated),
+            ],
+            ExprKind::ident("b".to_owned()),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn no_params() {
+        let (left, _) = parse! { expr "|| 42" };
+        let right = Ok(ExprKind::lambda(Vec::new(), ExprKind::integer(42)));
 
         assert_eq!(left, right);
     }
 }
 
 #[cfg(test)]
-mod function {
-    use crate::inline_fn;
-
+mod call {
     use super::*;
 
     #[test]
-    fn handles_bindings() {
-        let (left, _) = parse! { function "fn main() { let a = 40; let b = 2; a + b }" };
-        let right = Ok(inline_fn! {
-            fn main() {
-                let a = 40;
-                let b = 2;
-                a + b
-            }
-        });
+    fn with_arguments() {
+        let (left, _) = parse! { expr "f(1, x + 1)" };
+        let right = Ok(ExprKind::call_at(
+            ExprKind::ident("f".to_owned()),
+            vec![
+                ExprKind::integer(1),
+                ExprKind::addition(ExprKind::ident("x".to_owned()), ExprKind::integer(1)),
+            ],
+            Span::new(None, 1, 2),
+        ));
 
         assert_eq!(left, right);
     }
 
     #[test]
-    fn handles_expression() {
-        let (left, _) = parse! { function "fn main() { 1 + 2 + 2 }" };
-        let right = Ok(inline_fn! {
-            fn main() {
-                1 + 2 + 2
-            }
-        });
+    fn chained() {
+        let (left, _) = parse! { expr "f()(2)" };
+        let right = Ok(ExprKind::call_at(
+            ExprKind::call_at(
+                ExprKind::ident("f".to_owned()),
+                Vec::new(),
+                Span::new(None, 1, 2),
+            ),
+            vec![ExprKind::integer(2)],
+            Span::new(None, 1, 4),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn binds_tighter_than_operators() {
+        let (left, _) = parse! { expr "2 * f(1)" };
+        let right = Ok(ExprKind::multiplication(
+            ExprKind::integer(2),
+            ExprKind::call_at(
+                ExprKind::ident("f".to_owned()),
+                vec![ExprKind::integer(1)],
+                Span::new(None, 1, 6),
+            ),
+        ));
 
         assert_eq!(left, right);
     }
@@ -490,8 +2351,11 @@ mod add_and_sub {
     use super::*;
 
     #[test]
-    fn single_factor_fails() {
-        assert!(parse! { level_0_expression "42" }.0.is_err());
+    fn single_factor() {
+        let (left, _) = parse! { level_0_expression "42" };
+        let right = Ok(ExprKind::integer(42));
+
+        assert_eq!(left, right);
     }
 
     #[test]
@@ -568,6 +2432,17 @@ mod mul {
 
         assert_eq!(left, right);
     }
+
+    #[test]
+    fn remainder_is_left_associative() {
+        let (left, _) = parse! { level_1_expression "7 % 4 * 3" };
+        let right = Ok(ExprKind::multiplication(
+            ExprKind::remainder(ExprKind::integer(7), ExprKind::integer(4)),
+            ExprKind::integer(3),
+        ));
+
+        assert_eq!(left, right);
+    }
 }
 
 #[cfg(test)]
@@ -585,6 +2460,62 @@ mod math {
     }
 }
 
+#[cfg(test)]
+mod comparison {
+    use crate::inline_expr;
+
+    use super::*;
+
+    #[test]
+    fn binds_looser_than_arithmetic() {
+        let (left, _) = parse! { expr "a + 1 <= 2 * b" };
+        let right = Ok(ExprKind::comparison(
+            ComparisonOperator::Le,
+            inline_expr! { a + 1 },
+            inline_expr! { 2 * b },
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn every_operator() {
+        let operators = [
+            ("<", ComparisonOperator::Lt),
+            ("<=", ComparisonOperator::Le),
+            (">", ComparisonOperator::Gt),
+            (">=", ComparisonOperator::Ge),
+            ("==", ComparisonOperator::Eq),
+            ("!=", ComparisonOperator::Ne),
+        ];
+
+        operators.iter().for_each(|(symbol, operator)| {
+            let (left, _) = parse! { expr &format!("x {} 1", symbol) };
+            let right = Ok(ExprKind::comparison(
+                *operator,
+                ExprKind::ident("x".to_owned()),
+                ExprKind::integer(1),
+            ));
+
+            assert_eq!(left, right);
+        });
+    }
+
+    #[test]
+    fn stops_after_the_first_comparison() {
+        let (left, _) = parse! { expr "1 < 2 < 3" };
+
+        assert_eq!(
+            left,
+            Ok(ExprKind::comparison(
+                ComparisonOperator::Lt,
+                ExprKind::integer(1),
+                ExprKind::integer(2),
+            ))
+        );
+    }
+}
+
 #[cfg(test)]
 mod if_else {
     use crate::inline_expr;
@@ -633,6 +2564,42 @@ mod if_else {
         assert_eq!(left, right);
     }
 
+    #[test]
+    fn else_if_chain() {
+        let (left, _) = parse! { if_else "if a { 1 } else if b { 2 } else { 3 }" };
+        let right = Ok(ExprKind::if_(
+            ExprKind::ident("a".to_owned()),
+            ExprKind::integer(1),
+            ExprKind::if_(
+                ExprKind::ident("b".to_owned()),
+                ExprKind::integer(2),
+                ExprKind::integer(3),
+            ),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn else_if_is_nested_if() {
+        let (left, _) = parse! { if_else "if a { 1 } else if b { 2 } else { 3 }" };
+        let (right, _) = parse! { if_else "if a { 1 } else { if b { 2 } else { 3 } }" };
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn missing_else_is_unit() {
+        let (left, _) = parse! { if_else "if a { 1 }" };
+        let right = Ok(ExprKind::if_(
+            ExprKind::ident("a".to_owned()),
+            ExprKind::integer(1),
+            ExprKind::unit(),
+        ));
+
+        assert_eq!(left, right);
+    }
+
     #[test]
     fn bindings_as_consequent_and_alternative() {
         let (left, _) = parse! { if_else "if 1 { let a = 0; a } else { let a = 0; a }" };
@@ -745,6 +2712,174 @@ mod bindings {
 
         assert_eq!(left, right);
     }
+
+    #[test]
+    fn deeply_nested_blocks() {
+        let depth = 64;
+        let input = format!("{}42{}", "{ ".repeat(depth), " }".repeat(depth));
+
+        let (left, _) = parse! { block input.as_str() };
+        let right = Ok(ExprKind::integer(42));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn errors_in_nested_blocks_are_reported_once() {
+        let depth = 8;
+        let input = format!(
+            "{}if a {{ let b = 1 b }}{}",
+            "{ ".repeat(depth),
+            " }".repeat(depth)
+        );
+
+        let (left, ctxt) = parse! { block input.as_str() };
+
+        assert!(left.is_ok());
+        assert_eq!(ctxt.errors().to_string(), "1:34: Excepted `;`\n");
+    }
+}
+
+#[cfg(test)]
+mod statements {
+    use crate::{ast::Statement, inline_expr};
+
+    use super::*;
+
+    #[test]
+    fn expression_statement() {
+        let (left, _) = parse! { block "{ 1 + 1; 42 }" };
+        let right = Ok(ExprKind::statements(
+            vec![Statement::Expr(ExprKind::addition(
+                ExprKind::integer(1),
+                ExprKind::integer(1),
+            ))],
+            ExprKind::integer(42),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn if_statement_without_semicolon() {
+        let (left, _) = parse! { block "{ if a { b } let c = 1; c }" };
+        let right = Ok(ExprKind::statements(
+            vec![
+                Statement::Expr(ExprKind::if_(
+                    ExprKind::ident("a".to_owned()),
+                    ExprKind::ident("b".to_owned()),
+                    ExprKind::unit(),
+                )),
+                Statement::Binding(Binding::new("c".to_owned(), ExprKind::integer(1))),
+            ],
+            ExprKind::ident("c".to_owned()),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn trailing_if_is_ending_expression() {
+        let (left, _) = parse! { block "{ if a { 1 } else { 2 } }" };
+        let right = Ok(inline_expr! {
+            if a { 1 } else { 2 }
+        });
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn missing_ending_expression_is_unit() {
+        let (left, _) = parse! { block "{ let a = 1; }" };
+        let right = Ok(ExprKind::bindings(
+            vec![Binding::new("a".to_owned(), ExprKind::integer(1))],
+            ExprKind::unit(),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn empty_block() {
+        let (left, _) = parse! { block "{}" };
+        let right = Ok(ExprKind::unit());
+
+        assert_eq!(left, right);
+    }
+}
+
+#[cfg(test)]
+mod for_loop {
+    use crate::ast::{Range, Statement};
+
+    use super::*;
+
+    #[test]
+    fn exclusive_range() {
+        let (left, _) = parse! { for_loop "for i in 0..n { }" };
+        let right = Ok(ExprKind::for_(
+            "i".to_owned(),
+            Range::new(ExprKind::integer(0), ExprKind::ident("n".to_owned()), false),
+            ExprKind::unit(),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn inclusive_range() {
+        let (left, _) = parse! { for_loop "for i in 1 ..= n + 1 { }" };
+        let right = Ok(ExprKind::for_(
+            "i".to_owned(),
+            Range::new(
+                ExprKind::integer(1),
+                ExprKind::addition(ExprKind::ident("n".to_owned()), ExprKind::integer(1)),
+                true,
+            ),
+            ExprKind::unit(),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let (left, _) = parse! { for_loop "for i in 0..n { continue; break; }" };
+        let right = Ok(ExprKind::for_(
+            "i".to_owned(),
+            Range::new(ExprKind::integer(0), ExprKind::ident("n".to_owned()), false),
+            ExprKind::statements(
+                vec![
+                    Statement::Expr(ExprKind::continue_()),
+                    Statement::Expr(ExprKind::break_()),
+                ],
+                ExprKind::unit(),
+            ),
+        ));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn loop_as_statement() {
+        let (left, _) = parse! { block "{ for i in 0..n { a + i; } a }" };
+        let right = Ok(ExprKind::statements(
+            vec![Statement::Expr(ExprKind::for_(
+                "i".to_owned(),
+                Range::new(ExprKind::integer(0), ExprKind::ident("n".to_owned()), false),
+                ExprKind::statements(
+                    vec![Statement::Expr(ExprKind::addition(
+                        ExprKind::ident("a".to_owned()),
+                        ExprKind::ident("i".to_owned()),
+                    ))],
+                    ExprKind::unit(),
+                ),
+            ))],
+            ExprKind::ident("a".to_owned()),
+        ));
+
+        assert_eq!(left, right);
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,13 @@
+//! Helpers shared by the tests of the compiler passes.
+
+use crate::{ast::Program, context::TypingContext};
+
+/// Parses `input`, runs `pass` on it and returns the errors it reported.
+pub(crate) fn check(input: &str, pass: fn(&Program, &TypingContext) -> Result<(), ()>) -> String {
+    let (_, ast) = crate::parser::parse_input(input).unwrap();
+    let ctxt = TypingContext::new();
+
+    let _ = pass(&ast, &ctxt);
+
+    ctxt.errs().to_string()
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     error::Error,
     fmt::{self, Display, Formatter},
 };
@@ -7,6 +8,28 @@ use std::{
 pub(crate) enum Ty {
     Bool,
     Int,
+    Unit,
+    Function(Vec<Ty>, Box<Ty>),
+    /// A record declared with `struct`, referred to by its name.
+    Record(String),
+    /// An optional value, written `Option<T>` or `?T`.
+    Option(Box<Ty>),
+    /// Either a value or an error, written `Result<T, E>`.
+    Result(Box<Ty>, Box<Ty>),
+    /// An immutable linked list, written `[T]`.
+    List(Box<Ty>),
+    /// An immutable map from keys to values, written `Map<K, V>`.
+    Map(Box<Ty>, Box<Ty>),
+    /// A type parameter of a generic function.
+    Param(String),
+    /// A type that is yet to be inferred.
+    Var(u32),
+    /// The type of expressions that never produce a value, such as `panic`.
+    /// It is compatible with any other type.
+    Never,
+    /// The type of a parameter that is not annotated in the source. The
+    /// inference pass replaces it with the inferred type.
+    Unannotated,
 
     Err,
 }
@@ -17,8 +40,106 @@ impl Ty {
             (Ty::Err, Ty::Err) => Ok(Ty::Err),
             (Ty::Err, other) => Ok(other),
             (this, Ty::Err) => Ok(this),
+            (Ty::Never, other) => Ok(other),
+            (this, Ty::Never) => Ok(this),
             (lhs, rhs) if lhs == rhs => Ok(lhs),
 
+            (Ty::Function(lhs_params, lhs_ret), Ty::Function(rhs_params, rhs_ret))
+                if lhs_params.len() == rhs_params.len() =>
+            {
+                let error = || UnificationError {
+                    left: Ty::Function(lhs_params.clone(), lhs_ret.clone()),
+                    right: Ty::Function(rhs_params.clone(), rhs_ret.clone()),
+                };
+
+                let params = lhs_params
+                    .iter()
+                    .zip(rhs_params.iter())
+                    .map(|(lhs, rhs)| lhs.clone().unify_with(rhs.clone()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| error())?;
+
+                let ret = lhs_ret
+                    .as_ref()
+                    .clone()
+                    .unify_with(rhs_ret.as_ref().clone())
+                    .map_err(|_| error())?;
+
+                Ok(Ty::Function(params, Box::new(ret)))
+            }
+
+            (Ty::Option(lhs), Ty::Option(rhs)) => {
+                let error = || UnificationError {
+                    left: Ty::Option(lhs.clone()),
+                    right: Ty::Option(rhs.clone()),
+                };
+
+                let inner = lhs
+                    .as_ref()
+                    .clone()
+                    .unify_with(rhs.as_ref().clone())
+                    .map_err(|_| error())?;
+
+                Ok(Ty::Option(Box::new(inner)))
+            }
+
+            (Ty::List(lhs), Ty::List(rhs)) => {
+                let error = || UnificationError {
+                    left: Ty::List(lhs.clone()),
+                    right: Ty::List(rhs.clone()),
+                };
+
+                let inner = lhs
+                    .as_ref()
+                    .clone()
+                    .unify_with(rhs.as_ref().clone())
+                    .map_err(|_| error())?;
+
+                Ok(Ty::List(Box::new(inner)))
+            }
+
+            (Ty::Result(lhs_ok, lhs_err), Ty::Result(rhs_ok, rhs_err)) => {
+                let error = || UnificationError {
+                    left: Ty::Result(lhs_ok.clone(), lhs_err.clone()),
+                    right: Ty::Result(rhs_ok.clone(), rhs_err.clone()),
+                };
+
+                let ok = lhs_ok
+                    .as_ref()
+                    .clone()
+                    .unify_with(rhs_ok.as_ref().clone())
+                    .map_err(|_| error())?;
+
+                let err = lhs_err
+                    .as_ref()
+                    .clone()
+                    .unify_with(rhs_err.as_ref().clone())
+                    .map_err(|_| error())?;
+
+                Ok(Ty::Result(Box::new(ok), Box::new(err)))
+            }
+
+            (Ty::Map(lhs_key, lhs_value), Ty::Map(rhs_key, rhs_value)) => {
+                let error = || UnificationError {
+                    left: Ty::Map(lhs_key.clone(), lhs_value.clone()),
+                    right: Ty::Map(rhs_key.clone(), rhs_value.clone()),
+                };
+
+                let key = lhs_key
+                    .as_ref()
+                    .clone()
+                    .unify_with(rhs_key.as_ref().clone())
+                    .map_err(|_| error())?;
+
+                let value = lhs_value
+                    .as_ref()
+                    .clone()
+                    .unify_with(rhs_value.as_ref().clone())
+                    .map_err(|_| error())?;
+
+                Ok(Ty::Map(Box::new(key), Box::new(value)))
+            }
+
             (left, right) => Err(UnificationError { left, right }),
         }
     }
@@ -31,11 +152,190 @@ impl Ty {
         self.expect(&Ty::Int)
     }
 
+    pub(crate) fn expect_unit(&self) -> Result<(), UnexpectedTypeError> {
+        self.expect(&Ty::Unit)
+    }
+
+    pub(crate) fn expect_function(&self) -> Result<(&[Ty], &Ty), ExpectedFunctionError> {
+        match self {
+            Ty::Function(params, ret) => Ok((params.as_slice(), ret.as_ref())),
+
+            _ => Err(ExpectedFunctionError { got: self.clone() }),
+        }
+    }
+
+    /// Calls `f` on each type parameter that appears in the type.
+    pub(crate) fn for_each_param(&self, f: &mut impl FnMut(&str)) {
+        match self {
+            Ty::Param(name) => f(name),
+            Ty::Function(params, ret) => {
+                params.iter().for_each(|param| param.for_each_param(f));
+                ret.for_each_param(f);
+            }
+            Ty::Option(inner) | Ty::List(inner) => inner.for_each_param(f),
+            Ty::Result(ok, err) | Ty::Map(ok, err) => {
+                ok.for_each_param(f);
+                err.for_each_param(f);
+            }
+            Ty::Bool
+            | Ty::Int
+            | Ty::Unit
+            | Ty::Record(_)
+            | Ty::Var(_)
+            | Ty::Never
+            | Ty::Unannotated
+            | Ty::Err => {}
+        }
+    }
+
+    /// Calls `f` on each type variable that appears in the type.
+    pub(crate) fn for_each_var(&self, f: &mut impl FnMut(u32)) {
+        match self {
+            Ty::Var(var) => f(*var),
+            Ty::Function(params, ret) => {
+                params.iter().for_each(|param| param.for_each_var(f));
+                ret.for_each_var(f);
+            }
+            Ty::Option(inner) | Ty::List(inner) => inner.for_each_var(f),
+            Ty::Result(ok, err) | Ty::Map(ok, err) => {
+                ok.for_each_var(f);
+                err.for_each_var(f);
+            }
+            Ty::Bool
+            | Ty::Int
+            | Ty::Unit
+            | Ty::Record(_)
+            | Ty::Param(_)
+            | Ty::Never
+            | Ty::Unannotated
+            | Ty::Err => {}
+        }
+    }
+
+    fn contains_var(&self, var: u32) -> bool {
+        let mut contains = false;
+        self.for_each_var(&mut |v| contains |= v == var);
+        contains
+    }
+
+    pub(crate) fn is_generic(&self) -> bool {
+        let mut is_generic = false;
+        self.for_each_param(&mut |_| is_generic = true);
+        is_generic
+    }
+
+    /// Replaces the type parameters that are in `substitution`.
+    pub(crate) fn substitute(&self, substitution: &HashMap<String, Ty>) -> Ty {
+        match self {
+            Ty::Param(name) => substitution
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| self.clone()),
+
+            Ty::Function(params, ret) => Ty::Function(
+                params.iter().map(|p| p.substitute(substitution)).collect(),
+                Box::new(ret.substitute(substitution)),
+            ),
+
+            Ty::Option(inner) => Ty::Option(Box::new(inner.substitute(substitution))),
+
+            Ty::List(inner) => Ty::List(Box::new(inner.substitute(substitution))),
+
+            Ty::Result(ok, err) => Ty::Result(
+                Box::new(ok.substitute(substitution)),
+                Box::new(err.substitute(substitution)),
+            ),
+
+            Ty::Map(key, value) => Ty::Map(
+                Box::new(key.substitute(substitution)),
+                Box::new(value.substitute(substitution)),
+            ),
+
+            Ty::Bool
+            | Ty::Int
+            | Ty::Unit
+            | Ty::Record(_)
+            | Ty::Var(_)
+            | Ty::Never
+            | Ty::Unannotated
+            | Ty::Err => self.clone(),
+        }
+    }
+
+    /// Finds the value of the type parameters of `self` so that it matches
+    /// `concrete`. Type parameters that are already in `substitution` must
+    /// match their previous value.
+    pub(crate) fn infer_params(
+        &self,
+        concrete: &Ty,
+        substitution: &mut HashMap<String, Ty>,
+    ) -> Result<(), UnexpectedTypeError> {
+        let mismatch = |substitution: &HashMap<String, Ty>| UnexpectedTypeError {
+            expected: self.substitute(substitution),
+            got: concrete.clone(),
+        };
+
+        match (self, concrete) {
+            (Ty::Param(name), _) => match substitution.get(name).cloned() {
+                Some(previous) => {
+                    let unified = previous
+                        .unify_with(concrete.clone())
+                        .map_err(|_| mismatch(substitution))?;
+                    substitution.insert(name.clone(), unified);
+                    Ok(())
+                }
+
+                None => {
+                    substitution.insert(name.clone(), concrete.clone());
+                    Ok(())
+                }
+            },
+
+            (Ty::Function(params, ret), Ty::Function(concrete_params, concrete_ret))
+                if params.len() == concrete_params.len() =>
+            {
+                params
+                    .iter()
+                    .zip(concrete_params)
+                    .try_for_each(|(param, concrete)| param.infer_params(concrete, substitution))
+                    .and_then(|()| ret.infer_params(concrete_ret, substitution))
+                    .map_err(|_| mismatch(substitution))
+            }
+
+            (Ty::Option(inner), Ty::Option(concrete_inner))
+            | (Ty::List(inner), Ty::List(concrete_inner)) => inner
+                .infer_params(concrete_inner, substitution)
+                .map_err(|_| mismatch(substitution)),
+
+            (Ty::Result(ok, err), Ty::Result(concrete_ok, concrete_err))
+            | (Ty::Map(ok, err), Ty::Map(concrete_ok, concrete_err)) => ok
+                .infer_params(concrete_ok, substitution)
+                .and_then(|()| err.infer_params(concrete_err, substitution))
+                .map_err(|_| mismatch(substitution)),
+
+            _ => concrete.expect(self),
+        }
+    }
+
     #[inline]
-    fn expect(&self, expected: &Ty) -> Result<(), UnexpectedTypeError> {
+    pub(crate) fn expect(&self, expected: &Ty) -> Result<(), UnexpectedTypeError> {
         match (self, expected) {
             (lhs, rhs) if lhs == rhs => Ok(()),
             (Ty::Err, _) => Ok(()),
+            (_, Ty::Err) => Ok(()),
+            (Ty::Never, _) => Ok(()),
+            (Ty::Function(..), Ty::Function(..))
+            | (Ty::Option(_), Ty::Option(_))
+            | (Ty::List(_), Ty::List(_))
+            | (Ty::Result(..), Ty::Result(..))
+            | (Ty::Map(..), Ty::Map(..)) => self
+                .clone()
+                .unify_with(expected.clone())
+                .map(drop)
+                .map_err(|_| UnexpectedTypeError {
+                    expected: expected.clone(),
+                    got: self.clone(),
+                }),
 
             _ => Err(UnexpectedTypeError {
                 expected: expected.clone(),
@@ -48,15 +348,157 @@ impl Ty {
 impl Display for Ty {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            Ty::Bool => "bool",
-            Ty::Int => "int",
+            Ty::Bool => "bool".fmt(f),
+            Ty::Int => "int".fmt(f),
+            Ty::Unit => "()".fmt(f),
+
+            Ty::Function(params, ret) => {
+                write!(f, "fn(")?;
+
+                params.iter().enumerate().try_for_each(|(idx, param)| {
+                    let separator = if idx == 0 { "" } else { ", " };
+                    write!(f, "{}{}", separator, param)
+                })?;
+
+                write!(f, ") -> {}", ret)
+            }
 
-            Ty::Err => "{type error}",
+            Ty::Option(inner) => write!(f, "Option<{}>", inner),
+            Ty::Result(ok, err) => write!(f, "Result<{}, {}>", ok, err),
+            Ty::List(inner) => write!(f, "[{}]", inner),
+            Ty::Map(key, value) => write!(f, "Map<{}, {}>", key, value),
+
+            Ty::Record(name) | Ty::Param(name) => name.fmt(f),
+            Ty::Var(var) => write!(f, "?{}", var),
+            Ty::Never => "!".fmt(f),
+            Ty::Unannotated => "_".fmt(f),
+
+            Ty::Err => "{type error}".fmt(f),
         }
-            .fmt(f)
     }
 }
 
+/// Maps type variables to the type they stand for.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Substitution(HashMap<u32, Ty>);
+
+impl Substitution {
+    /// Replaces the type variables of `ty` as much as possible.
+    pub(crate) fn apply(&self, ty: &Ty) -> Ty {
+        match ty {
+            Ty::Var(var) => match self.0.get(var) {
+                Some(bound) => self.apply(bound),
+                None => ty.clone(),
+            },
+
+            Ty::Function(params, ret) => Ty::Function(
+                params.iter().map(|param| self.apply(param)).collect(),
+                Box::new(self.apply(ret)),
+            ),
+
+            Ty::Option(inner) => Ty::Option(Box::new(self.apply(inner))),
+
+            Ty::List(inner) => Ty::List(Box::new(self.apply(inner))),
+
+            Ty::Result(ok, err) => Ty::Result(Box::new(self.apply(ok)), Box::new(self.apply(err))),
+
+            Ty::Map(key, value) => Ty::Map(Box::new(self.apply(key)), Box::new(self.apply(value))),
+
+            Ty::Bool
+            | Ty::Int
+            | Ty::Unit
+            | Ty::Record(_)
+            | Ty::Param(_)
+            | Ty::Never
+            | Ty::Unannotated
+            | Ty::Err => ty.clone(),
+        }
+    }
+
+    /// Extends the substitution so that `lhs` and `rhs` become equal.
+    ///
+    /// Type parameters are considered as opaque types. `Ty::Err` stands for
+    /// the type of names that don't exist, which the type checker reports, so
+    /// it is compatible with any type.
+    pub(crate) fn unify(&mut self, lhs: &Ty, rhs: &Ty) -> Result<(), SubstitutionError> {
+        let (lhs, rhs) = (self.apply(lhs), self.apply(rhs));
+        let error = || {
+            SubstitutionError::Mismatch(UnificationError {
+                left: lhs.clone(),
+                right: rhs.clone(),
+            })
+        };
+        // Infinite types are reported as is, wherever they are found.
+        let nested = |e| match e {
+            SubstitutionError::Mismatch(_) => error(),
+            infinite => infinite,
+        };
+
+        match (&lhs, &rhs) {
+            (Ty::Err, _) | (_, Ty::Err) => Ok(()),
+            (Ty::Never, _) | (_, Ty::Never) => Ok(()),
+
+            (Ty::Var(a), Ty::Var(b)) if a == b => Ok(()),
+
+            (Ty::Var(var), other) | (other, Ty::Var(var)) => {
+                // A type can't contain itself.
+                if other.contains_var(*var) {
+                    return Err(SubstitutionError::Infinite(*var, other.clone()));
+                }
+
+                self.0.insert(*var, other.clone());
+                Ok(())
+            }
+
+            (Ty::Function(lhs_params, lhs_ret), Ty::Function(rhs_params, rhs_ret))
+                if lhs_params.len() == rhs_params.len() =>
+            {
+                lhs_params
+                    .iter()
+                    .zip(rhs_params)
+                    .try_for_each(|(l, r)| self.unify(l, r))
+                    .and_then(|()| self.unify(lhs_ret, rhs_ret))
+                    .map_err(nested)
+            }
+
+            (Ty::Option(lhs), Ty::Option(rhs)) | (Ty::List(lhs), Ty::List(rhs)) => {
+                self.unify(lhs, rhs).map_err(nested)
+            }
+
+            (Ty::Result(lhs_ok, lhs_err), Ty::Result(rhs_ok, rhs_err))
+            | (Ty::Map(lhs_ok, lhs_err), Ty::Map(rhs_ok, rhs_err)) => self
+                .unify(lhs_ok, rhs_ok)
+                .and_then(|()| self.unify(lhs_err, rhs_err))
+                .map_err(nested),
+
+            (l, r) if l == r => Ok(()),
+
+            _ => Err(error()),
+        }
+    }
+}
+
+/// The reason why a [`Substitution`] can't make two types equal.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum SubstitutionError {
+    Mismatch(UnificationError),
+    /// A type variable would have to contain itself.
+    Infinite(u32, Ty),
+}
+
+impl Display for SubstitutionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            SubstitutionError::Mismatch(e) => e.fmt(f),
+            SubstitutionError::Infinite(var, ty) => {
+                write!(f, "Infinite type `{} = {}`", Ty::Var(*var), ty)
+            }
+        }
+    }
+}
+
+impl Error for SubstitutionError {}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct UnificationError {
     pub(crate) left: Ty,
@@ -92,3 +534,16 @@ impl Display for UnexpectedTypeError {
 }
 
 impl Error for UnexpectedTypeError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ExpectedFunctionError {
+    pub(crate) got: Ty,
+}
+
+impl Display for ExpectedFunctionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Expected function, found type `{}`", self.got)
+    }
+}
+
+impl Error for ExpectedFunctionError {}
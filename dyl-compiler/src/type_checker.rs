@@ -1,27 +1,465 @@
-use anyhow::{anyhow, Error as AnyError, Result as AnyResult};
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, ensure, Context, Error as AnyError, Result as AnyResult};
 
 use crate::{
     ast::{
-        Addition, Binding, Bindings, Bool, ExprKind, Ident, If, Integer, Multiplication,
-        Subtraction,
+        self, Addition, Arm, Assert, AssertKind, Binding, Bindings, Bool, Break, Builtin, Call,
+        ClauseKind, Comparison, Constructor, Continue, ExprKind, FieldAccess, For, Function, Ident,
+        If, Integer, Lambda, ListLiteral, Match, MethodCall, Multiplication, Operator, Panic,
+        Param, Print, Program, ReadInt, RecordLiteral, Remainder, Statement, Subtraction, Try,
+        Unit, Variant, RESULT,
     },
     context::{CompilerPassError, TypingContext},
     ty::Ty,
 };
 
 pub(crate) fn check_ast(
-    ast: &ExprKind,
+    ast: &Program,
     mut ctxt: TypingContext,
 ) -> Result<TypingContext, CompilerPassError> {
-    let children_check = ast.check_inputs(&mut ctxt);
+    let records_check = declare_records(ast, &mut ctxt);
+    let traits_check = declare_traits(ast, &mut ctxt).and(records_check);
+    let traits_check = check_unique_functions(ast, &ctxt).and(traits_check);
+    declare_functions(ast, &mut ctxt);
+
+    let impls_check = ast
+        .impls()
+        .iter()
+        .map(|impl_| check_impl(impl_, &ctxt))
+        .fold(traits_check, Result::and);
+
+    let functions_check = ast
+        .functions()
+        .iter()
+        .map(|function| {
+            ctxt.errs()
+                .set_file(ast.file_of(function.name()).map(str::to_owned));
+            check_function(function, &mut ctxt)
+        })
+        .fold(impls_check, Result::and);
+    ctxt.errs().set_file(None);
+
+    ctxt.wrap_result(functions_check).map(|(ctxt, ())| ctxt)
+}
+
+/// Adds the records of the program to the context.
+fn declare_records(ast: &Program, ctxt: &mut TypingContext) -> Result<(), ()> {
+    let mut rslt = Ok(());
+
+    ast.records().iter().for_each(|record| {
+        if ctxt.resolve_record(record.name()).is_some() {
+            ctxt.errs().add(format!(
+                "Record `{}` is defined multiple times",
+                record.name()
+            ));
+            rslt = Err(());
+        }
+
+        // An empty literal would be ambiguous with a block.
+        if record.fields().is_empty() {
+            ctxt.errs().add(format!(
+                "Record `{}` must have at least one field",
+                record.name()
+            ));
+            rslt = Err(());
+        }
+
+        record.fields().iter().enumerate().for_each(|(idx, field)| {
+            if record.fields()[..idx]
+                .iter()
+                .any(|f| f.name() == field.name())
+            {
+                ctxt.errs().add(format!(
+                    "Field `{}` is defined multiple times in `{}`",
+                    field.name(),
+                    record.name()
+                ));
+                rslt = Err(());
+            }
+
+            field.ty().for_each_param(&mut |name| {
+                ctxt.errs().add(format!("Unknown type `{}`", name));
+                rslt = Err(());
+            });
+        });
+
+        ctxt.add_record(record.clone());
+    });
+
+    rslt
+}
+
+/// Adds the traits, their implementations and the bounds of every function
+/// to the context.
+fn declare_traits(ast: &Program, ctxt: &mut TypingContext) -> Result<(), ()> {
+    let mut rslt = Ok(());
+
+    ast.traits().iter().for_each(|trait_| {
+        trait_.methods().iter().for_each(|method| {
+            if !takes_self(method.params()) {
+                ctxt.errs().add(format!(
+                    "Method `{}` of trait `{}` must take `self` as first parameter",
+                    method.name(),
+                    trait_.name()
+                ));
+                rslt = Err(());
+            }
+
+            // Trait methods have no body to infer their parameters from.
+            method
+                .params()
+                .iter()
+                .filter(|p| p.ty() == &Ty::Unannotated)
+                .for_each(|param| {
+                    ctxt.errs().add(format!(
+                        "Parameter `{}` of method `{}` of trait `{}` must have a type",
+                        param.name(),
+                        method.name(),
+                        trait_.name()
+                    ));
+                    rslt = Err(());
+                });
+        });
+
+        ctxt.traits_mut().add_trait(trait_.clone());
+    });
+
+    ast.impls().iter().for_each(|impl_| {
+        impl_.ty().for_each_param(&mut |name| {
+            ctxt.errs().add(format!("Unknown type `{}`", name));
+            rslt = Err(());
+        });
+
+        let trait_ = match impl_.trait_() {
+            Some(trait_) => trait_,
+            None => return,
+        };
+
+        if ctxt.traits().resolve_trait(trait_).is_none() {
+            ctxt.errs().add(format!("Unknown trait `{}`", trait_));
+            rslt = Err(());
+        } else if ctxt.traits().implements(impl_.ty(), trait_) {
+            ctxt.errs().add(format!(
+                "Trait `{}` is implemented multiple times for `{}`",
+                trait_,
+                impl_.ty()
+            ));
+            rslt = Err(());
+        } else {
+            ctxt.traits_mut()
+                .add_impl(trait_.to_owned(), impl_.ty().clone());
+        }
+    });
+
+    let mut inherent_methods = Vec::new();
+    ast.impls()
+        .iter()
+        .filter(|impl_| impl_.trait_().is_none())
+        .flat_map(|impl_| impl_.methods().iter().map(move |m| (impl_.ty(), m)))
+        .for_each(|(ty, method)| {
+            if inherent_methods.contains(&(ty, method)) {
+                ctxt.errs().add(format!(
+                    "Method `{}` is defined multiple times for `{}`",
+                    method, ty
+                ));
+                rslt = Err(());
+            }
+
+            let name = ast::method_name(None, ty, method);
+            let function = ast.functions().iter().find(|f| f.name() == name);
+            if !function.is_some_and(|f| takes_self(f.params())) {
+                ctxt.errs().add(format!(
+                    "Method `{}` of `impl {}` must take `self` as first parameter",
+                    method, ty
+                ));
+                rslt = Err(());
+            }
+
+            inherent_methods.push((ty, method));
+        });
+
+    ast.functions().iter().for_each(|function| {
+        ctxt.traits_mut()
+            .add_function_bounds(function.name().to_owned(), function.bounds().to_vec())
+    });
+
+    rslt
+}
+
+fn takes_self(params: &[Param]) -> bool {
+    params.first().is_some_and(|p| p.name() == "self")
+}
+
+/// Checks that an implementation provides exactly the methods of its trait,
+/// with the right signatures.
+fn check_impl(impl_: &ast::Impl, ctxt: &TypingContext) -> Result<(), ()> {
+    let trait_ = match impl_.trait_() {
+        Some(trait_) => ctxt.traits().resolve_trait(trait_),
+        None => return Ok(()),
+    };
+
+    let trait_ = match trait_ {
+        Some(trait_) => trait_,
+        // Already reported when declaring the implementation.
+        None => return Err(()),
+    };
+
+    let mut rslt = Ok(());
+
+    trait_
+        .methods()
+        .iter()
+        .filter(|method| !impl_.methods().iter().any(|m| m == method.name()))
+        .for_each(|method| {
+            ctxt.errs().add(format!(
+                "Missing method `{}` in implementation of `{}` for `{}`",
+                method.name(),
+                trait_.name(),
+                impl_.ty()
+            ));
+            rslt = Err(());
+        });
+
+    impl_.methods().iter().for_each(|name| {
+        let expected = match trait_.method(name) {
+            Some(method) => method.ty_for(impl_.ty()),
+            None => {
+                ctxt.errs().add(format!(
+                    "Method `{}` is not a member of trait `{}`",
+                    name,
+                    trait_.name()
+                ));
+                rslt = Err(());
+                return;
+            }
+        };
+
+        let function = ast::method_name(Some(trait_.name()), impl_.ty(), name);
+        let found = ctxt.resolve_function(&function).cloned().unwrap_or(Ty::Err);
+
+        if found.expect(&expected).is_err() {
+            ctxt.errs().add(format!(
+                "Method `{}` has type `{}` but trait `{}` expects `{}`",
+                name,
+                found,
+                trait_.name(),
+                expected
+            ));
+            rslt = Err(());
+        }
+    });
+
+    rslt
+}
+
+/// Checks that no two functions have the same name. Methods are checked
+/// along with their implementation.
+fn check_unique_functions(ast: &Program, ctxt: &TypingContext) -> Result<(), ()> {
+    let methods = ast
+        .impls()
+        .iter()
+        .flat_map(|impl_| {
+            impl_
+                .methods()
+                .iter()
+                .map(|m| ast::method_name(impl_.trait_(), impl_.ty(), m))
+        })
+        .collect::<Vec<_>>();
+
+    let names = ast
+        .functions()
+        .iter()
+        .map(Function::name)
+        .chain(ast.externs().iter().map(|e| e.name()))
+        .filter(|name| !methods.iter().any(|m| m == name))
+        .collect::<Vec<_>>();
+
+    let mut rslt = Ok(());
+
+    names.iter().enumerate().for_each(|(idx, name)| {
+        if names[..idx].contains(name) {
+            ctxt.errs().set_file(ast.file_of(name).map(str::to_owned));
+            ctxt.errs()
+                .add(format!("Function `{}` is defined multiple times", name));
+            rslt = Err(());
+        }
+    });
+    ctxt.errs().set_file(None);
 
-    let program_ty = ast.get_output(&mut ctxt).map_err(|e| ctxt.errs().add(e));
+    rslt
+}
+
+/// Adds the signature of every function of the program to the context, so
+/// that functions can be referenced before their definition.
+///
+/// Functions without a return type annotation return whatever their body
+/// evaluates to. As bodies may refer to other functions, return types are
+/// refined until nothing changes anymore.
+fn declare_functions(ast: &Program, ctxt: &mut TypingContext) {
+    ast.externs().iter().for_each(|e| {
+        ctxt.add_function(e.name().to_owned(), e.ty());
+    });
+
+    ast.functions().iter().for_each(|function| {
+        let ret_ty = function.ret_ty().cloned().unwrap_or(Ty::Err);
+        ctxt.add_function(function.name().to_owned(), signature(function, ret_ty));
+    });
+
+    for _ in 0..ast.functions().len() {
+        let mut changed = false;
+
+        for function in ast.functions().iter().filter(|f| f.ret_ty().is_none()) {
+            let subctxt = ctxt.new_subcontext();
+            enter_function(function, ctxt);
+            let ret_ty = function.body().get_output(ctxt).unwrap_or(Ty::Err);
+            ctxt.drop_subcontext(subctxt);
+
+            let ty = signature(function, ret_ty);
+            if ctxt.resolve_function(function.name()) != Some(&ty) {
+                ctxt.add_function(function.name().to_owned(), ty);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+}
+
+fn signature(function: &Function, ret_ty: Ty) -> Ty {
+    let params = function.params().iter().map(|p| p.ty().clone()).collect();
+    Ty::Function(params, Box::new(ret_ty))
+}
+
+/// Makes the parameters, the bounds and the return type of a function
+/// available to its body.
+fn enter_function(function: &Function, ctxt: &mut TypingContext) {
+    add_params(function.params(), ctxt);
+    ctxt.traits_mut().set_bounds(function.bounds().to_vec());
+
+    let ret_ty = ctxt
+        .resolve_function(function.name())
+        .and_then(|ty| ty.expect_function().ok())
+        .map(|(_, ret_ty)| ret_ty.clone())
+        .unwrap_or(Ty::Err);
+    ctxt.set_return_ty(Some(ret_ty));
+}
+
+fn add_params(params: &[Param], ctxt: &mut TypingContext) {
+    params
+        .iter()
+        .for_each(|param| ctxt.add_binding(param.name().to_owned(), param.ty().clone()));
+}
+
+/// Checks that the signature of a function only refers to its own type
+/// parameters, and that all of them can be inferred from the arguments.
+fn check_generics(function: &Function, ctxt: &TypingContext) -> Result<(), ()> {
+    let mut rslt = Ok(());
+
+    let signature_tys = function
+        .params()
+        .iter()
+        .map(Param::ty)
+        .chain(function.ret_ty());
+
+    signature_tys.for_each(|ty| {
+        ty.for_each_param(&mut |name| {
+            if !function.generics().iter().any(|g| g == name) {
+                ctxt.errs().add(format!("Unknown type `{}`", name));
+                rslt = Err(());
+            }
+        })
+    });
+
+    function.bounds().iter().for_each(|bound| {
+        if !function.generics().iter().any(|g| g == bound.param()) {
+            ctxt.errs().add(format!("Unknown type `{}`", bound.param()));
+            rslt = Err(());
+        }
+
+        if ctxt.traits().resolve_trait(bound.trait_()).is_none() {
+            ctxt.errs()
+                .add(format!("Unknown trait `{}`", bound.trait_()));
+            rslt = Err(());
+        }
+    });
+
+    function.generics().iter().for_each(|generic| {
+        let mut is_used = false;
+        function.params().iter().for_each(|param| {
+            param
+                .ty()
+                .for_each_param(&mut |name| is_used |= name == generic)
+        });
+
+        if !is_used {
+            ctxt.errs().add(format!(
+                "Type parameter `{}` of `{}` must appear in its parameters",
+                generic,
+                function.name()
+            ));
+            rslt = Err(());
+        }
+    });
+
+    rslt
+}
+
+fn check_function(function: &Function, ctxt: &mut TypingContext) -> Result<(), ()> {
+    let generics_check = check_generics(function, ctxt);
+
+    let subctxt = ctxt.new_subcontext();
+    enter_function(function, ctxt);
+
+    let children_check = function.body().check_inputs(ctxt);
+    let body_ty = function
+        .body()
+        .get_output(ctxt)
+        .and_then(|ty| match function.ret_ty() {
+            Some(ret_ty) => ty.expect(ret_ty).map_err(AnyError::new),
+            None => Ok(()),
+        })
+        .map_err(|e| ctxt.errs().add(e));
+    let clauses_check = check_clauses(function, ctxt);
+
+    ctxt.drop_subcontext(subctxt);
+
+    generics_check
+        .and(body_ty)
+        .and(children_check)
+        .and(clauses_check)
+}
 
-    ctxt.wrap_result(program_ty.and(children_check))
-        .map(|(ctxt, ())| ctxt)
+/// Checks that the clauses of a function are booleans. The value returned by
+/// the function is available to `ensures` clauses as `result`.
+fn check_clauses(function: &Function, ctxt: &mut TypingContext) -> Result<(), ()> {
+    function
+        .clauses()
+        .iter()
+        .map(|clause| {
+            let subctxt = ctxt.new_subcontext();
+            if clause.kind() == ClauseKind::Ensures {
+                let ret_ty = ctxt.return_ty().cloned().unwrap_or(Ty::Err);
+                ctxt.add_binding(RESULT.to_owned(), ret_ty);
+            }
+
+            let children_check = clause.condition().check_inputs(ctxt);
+            let condition_is_bool = clause
+                .condition()
+                .get_output(ctxt)
+                .and_then(|ty| ty.expect_bool().map_err(AnyError::new))
+                .map_err(|e| ctxt.errs().add(e.to_string()));
+
+            ctxt.drop_subcontext(subctxt);
+
+            children_check.and(condition_is_bool)
+        })
+        .fold(Ok(()), Result::and)
 }
 
-trait Typed {
+pub(crate) trait Typed {
     fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()>;
 
     fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty>;
@@ -35,9 +473,29 @@ impl Typed for ExprKind {
             ExprKind::Bindings(bindings) => bindings.check_inputs(ctxt),
             ExprKind::Ident(ident) => ident.check_inputs(ctxt),
             ExprKind::Multiplication(multiplication) => multiplication.check_inputs(ctxt),
+            ExprKind::Remainder(remainder) => remainder.check_inputs(ctxt),
             ExprKind::Subtraction(subtraction) => subtraction.check_inputs(ctxt),
             ExprKind::If(if_) => if_.check_inputs(ctxt),
             ExprKind::Bool(bool_) => bool_.check_inputs(ctxt),
+            ExprKind::Unit(unit) => unit.check_inputs(ctxt),
+            ExprKind::For(for_) => for_.check_inputs(ctxt),
+            ExprKind::Break(break_) => break_.check_inputs(ctxt),
+            ExprKind::Continue(continue_) => continue_.check_inputs(ctxt),
+            ExprKind::Call(call) => call.check_inputs(ctxt),
+            ExprKind::MethodCall(method_call) => method_call.check_inputs(ctxt),
+            ExprKind::Lambda(lambda) => lambda.check_inputs(ctxt),
+            ExprKind::RecordLiteral(literal) => literal.check_inputs(ctxt),
+            ExprKind::FieldAccess(access) => access.check_inputs(ctxt),
+            ExprKind::Variant(variant) => variant.check_inputs(ctxt),
+            ExprKind::Match(match_) => match_.check_inputs(ctxt),
+            ExprKind::Try(try_) => try_.check_inputs(ctxt),
+            ExprKind::Panic(panic) => panic.check_inputs(ctxt),
+            ExprKind::Comparison(comparison) => comparison.check_inputs(ctxt),
+            ExprKind::Print(print) => print.check_inputs(ctxt),
+            ExprKind::ReadInt(read_int) => read_int.check_inputs(ctxt),
+            ExprKind::List(list) => list.check_inputs(ctxt),
+            ExprKind::Builtin(builtin) => builtin.check_inputs(ctxt),
+            ExprKind::Assert(assert) => assert.check_inputs(ctxt),
         }
     }
 
@@ -48,36 +506,109 @@ impl Typed for ExprKind {
             ExprKind::Bindings(bindings) => bindings.get_output(ctxt),
             ExprKind::Ident(ident) => ident.get_output(ctxt),
             ExprKind::Multiplication(multiplication) => multiplication.get_output(ctxt),
+            ExprKind::Remainder(remainder) => remainder.get_output(ctxt),
             ExprKind::Subtraction(subtraction) => subtraction.get_output(ctxt),
             ExprKind::If(if_) => if_.get_output(ctxt),
             ExprKind::Bool(bool_) => bool_.get_output(ctxt),
+            ExprKind::Unit(unit) => unit.get_output(ctxt),
+            ExprKind::For(for_) => for_.get_output(ctxt),
+            ExprKind::Break(break_) => break_.get_output(ctxt),
+            ExprKind::Continue(continue_) => continue_.get_output(ctxt),
+            ExprKind::Call(call) => call.get_output(ctxt),
+            ExprKind::MethodCall(method_call) => method_call.get_output(ctxt),
+            ExprKind::Lambda(lambda) => lambda.get_output(ctxt),
+            ExprKind::RecordLiteral(literal) => literal.get_output(ctxt),
+            ExprKind::FieldAccess(access) => access.get_output(ctxt),
+            ExprKind::Variant(variant) => variant.get_output(ctxt),
+            ExprKind::Match(match_) => match_.get_output(ctxt),
+            ExprKind::Try(try_) => try_.get_output(ctxt),
+            ExprKind::Panic(panic) => panic.get_output(ctxt),
+            ExprKind::Comparison(comparison) => comparison.get_output(ctxt),
+            ExprKind::Print(print) => print.get_output(ctxt),
+            ExprKind::ReadInt(read_int) => read_int.get_output(ctxt),
+            ExprKind::List(list) => list.get_output(ctxt),
+            ExprKind::Builtin(builtin) => builtin.get_output(ctxt),
+            ExprKind::Assert(assert) => assert.get_output(ctxt),
         }
     }
 }
 
 impl Typed for Addition {
     fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
-        let operands_are_valid = self
-            .left()
-            .check_inputs(ctxt)
-            .and(self.right().check_inputs(ctxt));
+        check_operator(self, ctxt)
+    }
 
-        let left_is_int = self
-            .left()
-            .get_output(ctxt)
-            .and_then(|ty| ty.expect_int().map_err(AnyError::new))
-            .map_err(|err| ctxt.errs().add(err));
-        let right_is_int = self
-            .right()
-            .get_output(ctxt)
-            .and_then(|ty| ty.expect_int().map_err(AnyError::new))
-            .map_err(|err| ctxt.errs().add(err));
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        operator_output(self, ctxt)
+    }
+}
+
+/// Resolves an operator to the method of its trait when the left operand
+/// implements it. Returns `None` if both operands must be integers instead.
+pub(crate) fn resolve_operator<O: Operator>(
+    operator: &O,
+    ctxt: &mut TypingContext,
+) -> AnyResult<Option<ResolvedMethod>> {
+    let (left, _) = operator.operands();
+    // An ill-typed operand is reported when checking it against `int`.
+    let ty = left.get_output(ctxt).unwrap_or(Ty::Err);
+
+    let implements = ctxt.traits().implements(&ty, O::TRAIT);
+    let method = ctxt
+        .traits()
+        .resolve_trait(O::TRAIT)
+        .and_then(|trait_| trait_.method(O::METHOD));
+
+    match (ty, method) {
+        (Ty::Int | Ty::Err, _) => Ok(None),
+
+        (ty, Some(method)) if implements => Ok(Some(ResolvedMethod {
+            trait_: Some(O::TRAIT.to_owned()),
+            signature: method.ty_for(&ty),
+            receiver: ty,
+        })),
+
+        (ty @ (Ty::Record(_) | Ty::Param(_)), _) => {
+            bail!("Type `{}` does not implement trait `{}`", ty, O::TRAIT)
+        }
 
-        operands_are_valid.and(left_is_int).and(right_is_int)
+        _ => Ok(None),
     }
+}
 
-    fn get_output(&self, _ctxt: &mut TypingContext) -> AnyResult<Ty> {
-        Ok(Ty::Int)
+fn check_operator<O: Operator>(operator: &O, ctxt: &mut TypingContext) -> Result<(), ()> {
+    let (left, right) = operator.operands();
+    let operands_are_valid = left.check_inputs(ctxt).and(right.check_inputs(ctxt));
+
+    let (left_ty, right_ty) = match resolve_operator(operator, ctxt) {
+        Ok(Some(resolved)) => {
+            let (params, _) = resolved.signature.expect_function().unwrap();
+            (params[0].clone(), params[1].clone())
+        }
+        Ok(None) => (Ty::Int, Ty::Int),
+        // Reported when computing the output type.
+        Err(_) => return Err(()),
+    };
+
+    let left_is_valid = left
+        .get_output(ctxt)
+        .and_then(|ty| ty.expect(&left_ty).map_err(AnyError::new))
+        .map_err(|err| ctxt.errs().add(err));
+    let right_is_valid = right
+        .get_output(ctxt)
+        .and_then(|ty| ty.expect(&right_ty).map_err(AnyError::new))
+        .map_err(|err| ctxt.errs().add(err));
+
+    operands_are_valid.and(left_is_valid).and(right_is_valid)
+}
+
+fn operator_output<O: Operator>(operator: &O, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+    match resolve_operator(operator, ctxt)? {
+        Some(resolved) => {
+            let (_, ret) = resolved.signature.expect_function()?;
+            Ok(ret.clone())
+        }
+        None => Ok(Ty::Int),
     }
 }
 
@@ -95,31 +626,45 @@ impl Typed for Bindings {
     fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
         let subctxt = ctxt.new_subcontext();
 
-        let mut bindings_are_valid = Ok(());
-
-        self.defines().iter().for_each(|binding| {
-            bindings_are_valid = bindings_are_valid.and(binding.check_inputs(ctxt));
-
-            // Next bindings and final expression may use this binding. Let's
-            // add it to the context.
-            let binding_ty = binding
-                .value()
-                .get_output(ctxt)
-                .map_err(|err| ctxt.errs().add(err))
-                .unwrap_or(Ty::Err);
-            ctxt.add_binding(binding.name().to_owned(), binding_ty);
+        let mut statements_are_valid = Ok(());
+
+        self.statements().iter().for_each(|stmt| {
+            statements_are_valid = statements_are_valid.and(stmt.check_inputs(ctxt));
+
+            match stmt {
+                Statement::Binding(binding) => {
+                    // Next bindings and final expression may use this binding.
+                    // Let's add it to the context.
+                    let binding_ty = binding
+                        .value()
+                        .get_output(ctxt)
+                        .map_err(|err| ctxt.errs().add(err))
+                        .unwrap_or(Ty::Err);
+                    ctxt.add_binding(binding.name().to_owned(), binding_ty);
+                }
+
+                Statement::Expr(e) => {
+                    // The value of an expression statement is discarded, but
+                    // it must still be well-typed.
+                    statements_are_valid = statements_are_valid.and(
+                        e.get_output(ctxt)
+                            .map(drop)
+                            .map_err(|err| ctxt.errs().add(err)),
+                    );
+                }
+            }
         });
 
         let final_is_valid = self.ending_expression().check_inputs(ctxt);
         ctxt.drop_subcontext(subctxt);
 
-        bindings_are_valid.and(final_is_valid)
+        statements_are_valid.and(final_is_valid)
     }
 
     fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
         let subctxt = ctxt.new_subcontext();
 
-        self.defines().iter().for_each(|binding| {
+        self.defines().for_each(|binding| {
             // Next bindings and final expression may use this binding. Let's
             // add it to the context.
             let binding_ty = binding.value().get_output(ctxt).unwrap_or(Ty::Err);
@@ -133,6 +678,22 @@ impl Typed for Bindings {
     }
 }
 
+impl Typed for Statement {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        match self {
+            Statement::Binding(binding) => binding.check_inputs(ctxt),
+            Statement::Expr(e) => e.check_inputs(ctxt),
+        }
+    }
+
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        match self {
+            Statement::Binding(binding) => binding.get_output(ctxt),
+            Statement::Expr(e) => e.get_output(ctxt),
+        }
+    }
+}
+
 impl Typed for Binding {
     fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
         self.value().check_inputs(ctxt)
@@ -145,11 +706,23 @@ impl Typed for Binding {
 
 impl Typed for Ident {
     fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
-        ctxt.resolve_binding(self.name()).map(drop).ok_or(())
+        ctxt.resolve_value(self.name()).map(drop).ok_or(())
     }
 
     fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
-        ctxt.resolve_binding(self.name())
+        if ctxt.resolve_binding(self.name()).is_none() {
+            let is_generic = ctxt
+                .resolve_function(self.name())
+                .is_some_and(Ty::is_generic);
+
+            ensure!(
+                !is_generic,
+                "Generic function `{}` can only be called directly",
+                self.name()
+            );
+        }
+
+        ctxt.resolve_value(self.name())
             .cloned()
             .ok_or_else(|| anyhow!("Variable `{}` not in scope", self.name()))
     }
@@ -157,33 +730,37 @@ impl Typed for Ident {
 
 impl Typed for Multiplication {
     fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
-        let operands_are_valid = self
-            .left()
-            .check_inputs(ctxt)
-            .and(self.right().check_inputs(ctxt));
+        check_operator(self, ctxt)
+    }
 
-        let left_is_int = self
-            .left()
-            .get_output(ctxt)
-            .and_then(|ty| ty.expect_int().map_err(AnyError::new))
-            .map_err(|e| ctxt.errs().add(e.to_string()));
-        let right_is_int = self
-            .right()
-            .get_output(ctxt)
-            .and_then(|ty| ty.expect_int().map_err(AnyError::new))
-            .map_err(|e| ctxt.errs().add(e.to_string()));
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        operator_output(self, ctxt)
+    }
+}
 
-        operands_are_valid.and(left_is_int).and(right_is_int)
+impl Typed for Remainder {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        check_operator(self, ctxt)
     }
 
-    fn get_output(&self, _ctxt: &mut TypingContext) -> AnyResult<Ty> {
-        Ok(Ty::Int)
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        operator_output(self, ctxt)
     }
 }
 
 impl Typed for Subtraction {
     fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
-        let operands_are_valid = self
+        check_operator(self, ctxt)
+    }
+
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        operator_output(self, ctxt)
+    }
+}
+
+impl Typed for Comparison {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let children_check = self
             .left()
             .check_inputs(ctxt)
             .and(self.right().check_inputs(ctxt));
@@ -192,18 +769,18 @@ impl Typed for Subtraction {
             .left()
             .get_output(ctxt)
             .and_then(|ty| ty.expect_int().map_err(AnyError::new))
-            .map_err(|e| ctxt.errs().add(e.to_string()));
+            .map_err(|e| ctxt.errs().add(e));
         let right_is_int = self
             .right()
             .get_output(ctxt)
             .and_then(|ty| ty.expect_int().map_err(AnyError::new))
-            .map_err(|e| ctxt.errs().add(e.to_string()));
+            .map_err(|e| ctxt.errs().add(e));
 
-        operands_are_valid.and(left_is_int).and(right_is_int)
+        children_check.and(left_is_int).and(right_is_int)
     }
 
     fn get_output(&self, _ctxt: &mut TypingContext) -> AnyResult<Ty> {
-        Ok(Ty::Int)
+        Ok(Ty::Bool)
     }
 }
 
@@ -252,138 +829,1443 @@ impl Typed for Bool {
     }
 }
 
-#[cfg(test)]
-mod addition {
-    use super::*;
-
-    fn sample_addition() -> ExprKind {
-        ExprKind::addition(ExprKind::integer(41), ExprKind::integer(1))
+impl Typed for Unit {
+    fn check_inputs(&self, _ctxt: &mut TypingContext) -> Result<(), ()> {
+        Ok(())
     }
 
-    fn bool_on_left() -> ExprKind {
-        ExprKind::addition(ExprKind::bool_(true), ExprKind::integer(42))
+    fn get_output(&self, _ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        Ok(Ty::Unit)
     }
+}
 
-    #[test]
-    fn returns_integer() {
-        let mut ctxt = TypingContext::new();
-        let expr = sample_addition();
-        let output = expr.get_output(&mut ctxt);
-
-        assert_eq!(output.unwrap(), Ty::Int);
-    }
+impl Typed for For {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let range = self.range();
 
-    #[test]
-    fn expects_integers_ok() {
-        let mut ctxt = TypingContext::new();
-        let expr = sample_addition();
+        let bounds_are_valid = range
+            .start()
+            .check_inputs(ctxt)
+            .and(range.end().check_inputs(ctxt));
 
-        assert!(expr.check_inputs(&mut ctxt).is_ok());
-    }
+        let start_is_int = range
+            .start()
+            .get_output(ctxt)
+            .and_then(|ty| ty.expect_int().map_err(AnyError::new))
+            .map_err(|e| ctxt.errs().add(e));
+        let end_is_int = range
+            .end()
+            .get_output(ctxt)
+            .and_then(|ty| ty.expect_int().map_err(AnyError::new))
+            .map_err(|e| ctxt.errs().add(e));
 
-    #[test]
-    fn expects_integers_err() {
-        let mut ctxt = TypingContext::new();
+        let subctxt = ctxt.new_subcontext();
+        ctxt.add_binding(self.variable().to_owned(), Ty::Int);
 
-        assert!(bool_on_left().check_inputs(&mut ctxt).is_err());
-    }
+        let body_is_valid = self.body().check_inputs(ctxt);
+        let body_is_unit = self
+            .body()
+            .get_output(ctxt)
+            .and_then(|ty| ty.expect_unit().map_err(AnyError::new))
+            .map_err(|e| ctxt.errs().add(e));
 
-    #[test]
-    fn double_addition_with_labels() {
-        let expr = ExprKind::addition(
-            ExprKind::addition(
-                ExprKind::ident("a".to_owned()),
-                ExprKind::ident("b".to_owned()),
-            ),
-            ExprKind::integer(1),
-        );
+        ctxt.drop_subcontext(subctxt);
 
-        let mut ctxt = TypingContext::new();
-        ctxt.add_binding("a".to_owned(), Ty::Int);
-        ctxt.add_binding("b".to_owned(), Ty::Int);
+        bounds_are_valid
+            .and(start_is_int)
+            .and(end_is_int)
+            .and(body_is_valid)
+            .and(body_is_unit)
+    }
 
-        assert!(expr.check_inputs(&mut ctxt).is_ok());
-        assert_eq!(expr.get_output(&mut ctxt).unwrap(), Ty::Int);
+    fn get_output(&self, _ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        Ok(Ty::Unit)
     }
 }
 
-#[cfg(test)]
-mod integer {
-    use super::*;
+impl Call {
+    /// Returns the signature of the callee if it is a generic function.
+    pub(crate) fn generic_callee(&self, ctxt: &TypingContext) -> Option<Ty> {
+        let name = match self.callee() {
+            ExprKind::Ident(ident) => ident.name(),
+            _ => return None,
+        };
 
-    fn sample_integer() -> ExprKind {
-        ExprKind::integer(42)
-    }
+        if ctxt.resolve_binding(name).is_some() {
+            return None;
+        }
 
-    #[test]
-    fn inputs_always_successful() {
-        let mut ctxt = TypingContext::new();
-        assert!(sample_integer().check_inputs(&mut ctxt).is_ok());
+        ctxt.resolve_function(name)
+            .filter(|ty| ty.is_generic())
+            .cloned()
     }
+}
 
-    #[test]
-    fn output_always_integer() {
-        let mut ctxt = TypingContext::new();
-        assert_eq!(sample_integer().get_output(&mut ctxt).unwrap(), Ty::Int);
+impl Call {
+    /// Checks that the type arguments of a generic callee implement the
+    /// traits they are bound to.
+    fn check_bounds(
+        &self,
+        substitution: &HashMap<String, Ty>,
+        ctxt: &TypingContext,
+    ) -> Result<(), ()> {
+        let name = match self.callee() {
+            ExprKind::Ident(ident) => ident.name(),
+            _ => return Ok(()),
+        };
+
+        let mut rslt = Ok(());
+
+        ctxt.traits()
+            .function_bounds(name)
+            .iter()
+            .for_each(|bound| {
+                let ty = substitution.get(bound.param()).unwrap_or(&Ty::Err);
+
+                if !ctxt.traits().implements(ty, bound.trait_()) {
+                    ctxt.errs().add(format!(
+                        "Type `{}` does not implement trait `{}`",
+                        ty,
+                        bound.trait_()
+                    ));
+                    rslt = Err(());
+                }
+            });
+
+        rslt
     }
 }
 
-#[cfg(test)]
-mod bindings {
-    use crate::ast::Binding;
+/// Computes the value of the type parameters of a generic function from the
+/// arguments it is called with.
+pub(crate) fn instantiate(
+    signature: &Ty,
+    args: &[ExprKind],
+    ctxt: &mut TypingContext,
+) -> AnyResult<HashMap<String, Ty>> {
+    let (params, _) = signature.expect_function()?;
+
+    ensure!(
+        params.len() == args.len(),
+        "Expected {} argument(s), found {}",
+        params.len(),
+        args.len()
+    );
+
+    let mut substitution = HashMap::new();
+
+    params.iter().zip(args).try_for_each(|(param, arg)| {
+        let arg_ty = arg.get_output(ctxt)?;
+        param
+            .infer_params(&arg_ty, &mut substitution)
+            .map_err(AnyError::new)
+    })?;
 
-    use super::*;
+    // Type parameters may only have been matched against erroneous types.
+    signature.for_each_param(&mut |param| {
+        substitution.entry(param.to_owned()).or_insert(Ty::Err);
+    });
 
-    fn sample_bindings() -> ExprKind {
-        ExprKind::Bindings(Bindings::from_vec(
-            vec![
-                Binding::new("a".to_owned(), ExprKind::integer(40)),
-                Binding::new("b".to_owned(), ExprKind::integer(1)),
-            ],
-            ExprKind::addition(
-                ExprKind::addition(
-                    ExprKind::ident("a".to_owned()),
-                    ExprKind::ident("b".to_owned()),
-                ),
-                ExprKind::integer(1),
-            ),
-        ))
-    }
+    Ok(substitution)
+}
 
-    fn bindings_with_type_errors() -> ExprKind {
-        ExprKind::single_binding(
-            "foo".to_owned(),
-            ExprKind::addition(ExprKind::bool_(true), ExprKind::integer(42)),
-            ExprKind::ident("foo".to_owned()),
-        )
-    }
+impl Typed for Call {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let callee_check = self.callee().check_inputs(ctxt);
+        let children_check = self
+            .args()
+            .iter()
+            .map(|arg| arg.check_inputs(ctxt))
+            .fold(callee_check, Result::and);
 
-    fn bindings_with_unknown_ident() -> ExprKind {
-        ExprKind::Bindings(Bindings::from_vec(
-            vec![Binding::new("foo".to_owned(), ExprKind::integer(42))],
-            ExprKind::ident("bar".to_owned()),
-        ))
-    }
+        if let Some(signature) = self.generic_callee(ctxt) {
+            let instantiation_check = instantiate(&signature, self.args(), ctxt)
+                .map_err(|e| ctxt.errs().add(e))
+                .and_then(|substitution| self.check_bounds(&substitution, ctxt));
 
-    #[test]
-    fn check_input_ok() {
-        let mut ctxt = TypingContext::new();
+            return children_check.and(instantiation_check);
+        }
 
-        assert!(sample_bindings().check_inputs(&mut ctxt).is_ok());
+        // A callee that is not a function is reported when computing the
+        // output type of the call.
+        let callee_ty = self.callee().get_output(ctxt).unwrap_or(Ty::Err);
+        let params = match callee_ty.expect_function() {
+            Ok((params, _)) => params,
+            Err(_) if callee_ty == Ty::Err => return children_check,
+            Err(_) => return Err(()),
+        };
+
+        if params.len() != self.args().len() {
+            ctxt.errs().add(format!(
+                "Expected {} argument(s), found {}",
+                params.len(),
+                self.args().len()
+            ));
+
+            return Err(());
+        }
+
+        let args_check = params
+            .iter()
+            .zip(self.args())
+            .map(|(param, arg)| {
+                arg.get_output(ctxt)
+                    .and_then(|ty| ty.expect(param).map_err(AnyError::new))
+                    .map_err(|e| ctxt.errs().add(e))
+            })
+            .fold(Ok(()), Result::and);
+
+        children_check.and(args_check)
     }
 
-    #[test]
-    fn check_inputs_type_error() {
-        let mut ctxt = TypingContext::new();
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        if let Some(signature) = self.generic_callee(ctxt) {
+            // Instantiation errors are reported when checking the inputs.
+            let ty = instantiate(&signature, self.args(), ctxt)
+                .ok()
+                .and_then(|substitution| {
+                    let (_, ret) = signature.expect_function().ok()?;
+                    Some(ret.substitute(&substitution))
+                })
+                .unwrap_or(Ty::Err);
+
+            return Ok(ty);
+        }
+
+        match self.callee().get_output(ctxt)? {
+            Ty::Err => Ok(Ty::Err),
+            callee_ty => {
+                let (_, ret) = callee_ty.expect_function()?;
+                Ok(ret.clone())
+            }
+        }
+    }
+}
+
+/// A method call, resolved to the implementation of the method.
+pub(crate) struct ResolvedMethod {
+    /// The trait that provides the method, if the method is not implemented
+    /// directly for the type of the receiver.
+    pub(crate) trait_: Option<String>,
+    pub(crate) receiver: Ty,
+    /// The type of the method, with `self` as first parameter.
+    pub(crate) signature: Ty,
+}
+
+impl ResolvedMethod {
+    /// Returns whether the method is implemented by a generic function. Trait
+    /// methods are not, even when called on a type parameter.
+    fn is_generic(&self) -> bool {
+        self.trait_.is_none() && self.signature.is_generic()
+    }
+}
+
+impl MethodCall {
+    /// Finds the implementation of the method for the type of the receiver.
+    /// Returns `None` if the receiver is ill-typed.
+    ///
+    /// Methods implemented directly for a type take precedence over methods
+    /// provided by traits.
+    pub(crate) fn resolve(&self, ctxt: &mut TypingContext) -> AnyResult<Option<ResolvedMethod>> {
+        let receiver = self.receiver().get_output(ctxt)?;
+
+        if receiver == Ty::Err {
+            return Ok(None);
+        }
+
+        let inherent = ast::method_name(None, &receiver, self.method());
+        if let Some(signature) = ctxt.resolve_function(&inherent) {
+            return Ok(Some(ResolvedMethod {
+                trait_: None,
+                signature: signature.clone(),
+                receiver,
+            }));
+        }
+
+        let traits = ctxt.traits();
+        let candidates = traits
+            .traits_with_method(self.method())
+            .filter(|trait_| traits.implements(&receiver, trait_.name()))
+            .collect::<Vec<_>>();
+
+        let trait_ = match candidates.as_slice() {
+            [trait_] => trait_,
+            [] => bail!(
+                "No method `{}` found for type `{}`",
+                self.method(),
+                receiver
+            ),
+            _ => bail!(
+                "Method `{}` is ambiguous for type `{}`",
+                self.method(),
+                receiver
+            ),
+        };
+
+        let signature = trait_.method(self.method()).unwrap().ty_for(&receiver);
+
+        Ok(Some(ResolvedMethod {
+            trait_: Some(trait_.name().to_owned()),
+            receiver,
+            signature,
+        }))
+    }
+
+    /// Returns the call to the function that implements the method.
+    pub(crate) fn desugar(&self, resolved: &ResolvedMethod) -> Call {
+        let name = ast::method_name(
+            resolved.trait_.as_deref(),
+            &resolved.receiver,
+            self.method(),
+        );
+        let args = std::iter::once(self.receiver())
+            .chain(self.args())
+            .cloned()
+            .collect();
+
+        Call::new(ExprKind::ident(name), args)
+    }
+}
+
+impl Typed for MethodCall {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let resolved = self.resolve(ctxt);
+
+        // Generic methods are checked just like a call to a generic function.
+        if let Ok(Some(resolved)) = &resolved {
+            if resolved.is_generic() {
+                return self.desugar(resolved).check_inputs(ctxt);
+            }
+        }
+
+        let receiver_check = self.receiver().check_inputs(ctxt);
+        let children_check = self
+            .args()
+            .iter()
+            .map(|arg| arg.check_inputs(ctxt))
+            .fold(receiver_check, Result::and);
+
+        // A method that can't be found is reported when computing the output
+        // type of the call.
+        let signature = match resolved {
+            Ok(Some(resolved)) => resolved.signature,
+            Ok(None) => return children_check,
+            Err(_) => return Err(()),
+        };
+
+        let (params, _) = signature.expect_function().unwrap();
+        let params = &params[1..];
+
+        if params.len() != self.args().len() {
+            ctxt.errs().add(format!(
+                "Expected {} argument(s), found {}",
+                params.len(),
+                self.args().len()
+            ));
+
+            return Err(());
+        }
+
+        let args_check = params
+            .iter()
+            .zip(self.args())
+            .map(|(param, arg)| {
+                arg.get_output(ctxt)
+                    .and_then(|ty| ty.expect(param).map_err(AnyError::new))
+                    .map_err(|e| ctxt.errs().add(e))
+            })
+            .fold(Ok(()), Result::and);
+
+        children_check.and(args_check)
+    }
+
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        match self.resolve(ctxt)? {
+            Some(resolved) if resolved.is_generic() => self.desugar(&resolved).get_output(ctxt),
+
+            Some(resolved) => {
+                let (_, ret) = resolved.signature.expect_function()?;
+                Ok(ret.clone())
+            }
+
+            None => Ok(Ty::Err),
+        }
+    }
+}
+
+// The return type of a lambda is only known once its body has been checked,
+// so `?` can't be used there.
+
+impl Typed for Lambda {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let subctxt = ctxt.new_subcontext();
+        add_params(self.params(), ctxt);
+        let outer_ret_ty = ctxt.set_return_ty(None);
+
+        let body_is_valid = self.body().check_inputs(ctxt);
+        let body_ty = self
+            .body()
+            .get_output(ctxt)
+            .map(drop)
+            .map_err(|e| ctxt.errs().add(e));
+
+        ctxt.set_return_ty(outer_ret_ty);
+        ctxt.drop_subcontext(subctxt);
+
+        body_is_valid.and(body_ty)
+    }
+
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        let subctxt = ctxt.new_subcontext();
+        add_params(self.params(), ctxt);
+        let outer_ret_ty = ctxt.set_return_ty(None);
+
+        let ret_ty = self.body().get_output(ctxt);
+
+        ctxt.set_return_ty(outer_ret_ty);
+        ctxt.drop_subcontext(subctxt);
+
+        let params = self.params().iter().map(|p| p.ty().clone()).collect();
+
+        Ok(Ty::Function(params, Box::new(ret_ty?)))
+    }
+}
+
+impl Typed for RecordLiteral {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let children_check = self
+            .fields()
+            .iter()
+            .map(|(_, value)| value.check_inputs(ctxt))
+            .fold(Ok(()), Result::and);
+
+        // An unknown record is reported when computing the output type.
+        let record = match ctxt.resolve_record(self.name()) {
+            Some(record) => record.clone(),
+            None => return Err(()),
+        };
+
+        let mut rslt = children_check;
+
+        self.fields()
+            .iter()
+            .enumerate()
+            .for_each(|(idx, (name, value))| {
+                if self.fields()[..idx].iter().any(|(n, _)| n == name) {
+                    ctxt.errs()
+                        .add(format!("Field `{}` is set multiple times", name));
+                    rslt = Err(());
+                }
+
+                let expected = match record.field(name) {
+                    Some((_, ty)) => ty,
+                    None => {
+                        ctxt.errs().add(format!(
+                            "Record `{}` has no field `{}`",
+                            record.name(),
+                            name
+                        ));
+                        rslt = Err(());
+                        return;
+                    }
+                };
+
+                if let Err(e) = value
+                    .get_output(ctxt)
+                    .and_then(|ty| ty.expect(expected).map_err(AnyError::new))
+                {
+                    ctxt.errs().add(e);
+                    rslt = Err(());
+                }
+            });
+
+        record
+            .fields()
+            .iter()
+            .filter(|field| !self.fields().iter().any(|(n, _)| n == field.name()))
+            .for_each(|field| {
+                ctxt.errs().add(format!(
+                    "Missing field `{}` in `{}`",
+                    field.name(),
+                    record.name()
+                ));
+                rslt = Err(());
+            });
+
+        rslt
+    }
+
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        ensure!(
+            ctxt.resolve_record(self.name()).is_some(),
+            "Unknown record `{}`",
+            self.name()
+        );
+
+        Ok(Ty::Record(self.name().to_owned()))
+    }
+}
+
+impl FieldAccess {
+    /// Returns the position of the field in the record and its type. Returns
+    /// `None` if the record is ill-typed.
+    pub(crate) fn resolve(&self, ctxt: &mut TypingContext) -> AnyResult<Option<(usize, Ty)>> {
+        let record = match self.record().get_output(ctxt)? {
+            Ty::Err => return Ok(None),
+            Ty::Record(name) => ctxt.resolve_record(&name),
+            _ => None,
+        };
+
+        record
+            .and_then(|record| record.field(self.field()))
+            .map(|(idx, ty)| Some((idx, ty.clone())))
+            .ok_or_else(|| {
+                let ty = self.record().get_output(ctxt).unwrap_or(Ty::Err);
+                anyhow!("No field `{}` on type `{}`", self.field(), ty)
+            })
+    }
+}
+
+impl Typed for FieldAccess {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        self.record().check_inputs(ctxt)
+    }
+
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        Ok(self.resolve(ctxt)?.map(|(_, ty)| ty).unwrap_or(Ty::Err))
+    }
+}
+
+impl Typed for Variant {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        match self.payload() {
+            Some(payload) => payload.check_inputs(ctxt),
+            None => Ok(()),
+        }
+    }
+
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        let payload_ty = match self.payload() {
+            Some(payload) => payload.get_output(ctxt)?,
+            None => Ty::Unit,
+        };
+
+        // Nothing tells the type of the other variants. It is left to
+        // whatever the value is compared to.
+        Ok(self.constructor().ty(payload_ty, || Ty::Err))
+    }
+}
+
+impl Match {
+    /// Makes the payload bound by the pattern of `arm` available to its body.
+    fn enter_arm(arm: &Arm, scrutinee_ty: &Ty, ctxt: &mut TypingContext) {
+        if let Some(name) = arm.pattern().binding() {
+            let payload_ty = arm
+                .pattern()
+                .constructor()
+                .payload_ty(scrutinee_ty)
+                .unwrap_or(Ty::Err);
+
+            ctxt.add_binding(name.to_owned(), payload_ty);
+        }
+    }
+
+    fn arm_ty(arm: &Arm, scrutinee_ty: &Ty, ctxt: &mut TypingContext) -> Ty {
+        let subctxt = ctxt.new_subcontext();
+        Match::enter_arm(arm, scrutinee_ty, ctxt);
+        let ty = arm.body().get_output(ctxt).unwrap_or(Ty::Err);
+        ctxt.drop_subcontext(subctxt);
+
+        ty
+    }
+}
+
+impl Typed for Match {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let mut rslt = self.scrutinee().check_inputs(ctxt);
+
+        let scrutinee_ty = match self.scrutinee().get_output(ctxt) {
+            Ok(ty) => ty,
+            Err(e) => {
+                ctxt.errs().add(e);
+                rslt = Err(());
+                Ty::Err
+            }
+        };
+
+        self.arms().iter().enumerate().for_each(|(idx, arm)| {
+            let constructor = arm.pattern().constructor();
+
+            if constructor.payload_ty(&scrutinee_ty).is_none() {
+                ctxt.errs().add(format!(
+                    "Pattern `{}` does not match type `{}`",
+                    constructor.name(),
+                    scrutinee_ty
+                ));
+                rslt = Err(());
+            }
+
+            if self.arms()[..idx]
+                .iter()
+                .any(|a| a.pattern().constructor() == constructor)
+            {
+                ctxt.errs().add(format!(
+                    "Pattern `{}` is matched multiple times",
+                    constructor.name()
+                ));
+                rslt = Err(());
+            }
+
+            let subctxt = ctxt.new_subcontext();
+            Match::enter_arm(arm, &scrutinee_ty, ctxt);
+            rslt = arm.body().check_inputs(ctxt).and(rslt);
+            ctxt.drop_subcontext(subctxt);
+        });
+
+        Constructor::of(&scrutinee_ty)
+            .iter()
+            .filter(|c| !self.arms().iter().any(|a| a.pattern().constructor() == **c))
+            .for_each(|c| {
+                ctxt.errs().add(format!("Missing pattern `{}`", c.name()));
+                rslt = Err(());
+            });
+
+        let arms_unify = self
+            .arms()
+            .iter()
+            .map(|arm| Match::arm_ty(arm, &scrutinee_ty, ctxt))
+            .try_fold(Ty::Err, Ty::unify_with)
+            .map(drop)
+            .map_err(|e| ctxt.errs().add(e.to_string()));
+
+        rslt.and(arms_unify)
+    }
+
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        let scrutinee_ty = self.scrutinee().get_output(ctxt).unwrap_or(Ty::Err);
+
+        self.arms()
+            .iter()
+            .map(|arm| Match::arm_ty(arm, &scrutinee_ty, ctxt))
+            .try_fold(Ty::Err, Ty::unify_with)
+            .map_err(AnyError::new)
+    }
+}
+
+impl Typed for Try {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let operand_check = self.operand().check_inputs(ctxt);
+        let operand_ty = self.operand().get_output(ctxt).unwrap_or(Ty::Err);
+
+        // Operands of any other type are reported by `get_output`.
+        let return_check = match (ctxt.return_ty(), &operand_ty) {
+            (None, _) => Err("The `?` operator can't be used in lambdas".to_owned()),
+            (Some(Ty::Err), _) | (Some(Ty::Option(_)), Ty::Option(_)) => Ok(()),
+            (Some(Ty::Result(_, ret_err)), Ty::Result(_, err)) if err.expect(ret_err).is_ok() => {
+                Ok(())
+            }
+            (Some(ty), Ty::Option(_)) => Err(format!(
+                "The `?` operator can only be used in functions that return `Option`, found `{}`",
+                ty
+            )),
+            (Some(ty), Ty::Result(_, err)) => Err(format!(
+                "The `?` operator can only be used in functions that return `Result<_, {}>`, found `{}`",
+                err, ty
+            )),
+            (Some(_), _) => Ok(()),
+        }
+        .map_err(|e| ctxt.errs().add(e));
+
+        operand_check.and(return_check)
+    }
+
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        let operand_ty = self.operand().get_output(ctxt)?;
+
+        match operand_ty {
+            Ty::Option(_) | Ty::Err => Ok(Constructor::Some.payload_ty(&operand_ty).unwrap()),
+            Ty::Result(..) => Ok(Constructor::Ok.payload_ty(&operand_ty).unwrap()),
+            _ => bail!(
+                "The `?` operator can only be applied to an `Option` or a `Result`, found type `{}`",
+                operand_ty
+            ),
+        }
+    }
+}
+
+impl Typed for Panic {
+    fn check_inputs(&self, _ctxt: &mut TypingContext) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn get_output(&self, _ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        Ok(Ty::Never)
+    }
+}
+
+impl Typed for Print {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let arg = match self.arg().expr() {
+            Some(arg) => arg,
+            None => return Ok(()),
+        };
+
+        let children_check = arg.check_inputs(ctxt);
+
+        let is_printable = arg
+            .get_output(ctxt)
+            .and_then(|ty| match ty {
+                Ty::Int | Ty::Bool | Ty::Never | Ty::Err => Ok(()),
+                _ => bail!(
+                    "`print` can only print integers, booleans and string literals, found type `{}`",
+                    ty
+                ),
+            })
+            .map_err(|e| ctxt.errs().add(e));
+
+        children_check.and(is_printable)
+    }
+
+    fn get_output(&self, _ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        Ok(Ty::Unit)
+    }
+}
+
+impl Typed for ListLiteral {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let children_check = self
+            .elements()
+            .iter()
+            .map(|element| element.check_inputs(ctxt))
+            .fold(Ok(()), Result::and);
+
+        let elements_check = self
+            .element_ty(ctxt)
+            .map(drop)
+            .map_err(|e| ctxt.errs().add(e));
+
+        children_check.and(elements_check)
+    }
+
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        // Mismatching elements are reported when checking the inputs.
+        let element = self.element_ty(ctxt).unwrap_or(Ty::Err);
+
+        Ok(Ty::List(Box::new(element)))
+    }
+}
+
+impl ListLiteral {
+    fn element_ty(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        // The empty list can hold elements of any type.
+        self.elements().iter().try_fold(Ty::Never, |element, e| {
+            let ty = e.get_output(ctxt)?;
+            element.unify_with(ty).map_err(AnyError::new)
+        })
+    }
+}
+
+impl Typed for Assert {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let children_check = self
+            .operands()
+            .into_iter()
+            .map(|operand| operand.check_inputs(ctxt))
+            .fold(Ok(()), Result::and);
+
+        let operand_tys = self
+            .operands()
+            .into_iter()
+            .map(|operand| operand.get_output(ctxt).map_err(|e| ctxt.errs().add(e)))
+            .collect::<Vec<_>>();
+
+        let operands_check = match self.kind() {
+            AssertKind::True(_) => operand_tys[0]
+                .clone()
+                .and_then(|ty| ty.expect_bool().map_err(|e| ctxt.errs().add(e.to_string()))),
+            // Booleans are integers at runtime, so `assert_eq` compares both.
+            // A wrong type is reported once, even when both operands have it.
+            AssertKind::Eq(..) | AssertKind::EqBool(..) => {
+                let tys = operand_tys.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+                match tys
+                    .iter()
+                    .find(|ty| !matches!(ty, Ty::Int | Ty::Bool | Ty::Never | Ty::Err))
+                {
+                    Some(ty) => {
+                        ctxt.errs().add(format!(
+                            "`assert_eq` only compares integers or booleans, found type `{}`",
+                            ty
+                        ));
+                        Err(())
+                    }
+                    None => tys[0].clone().unify_with(tys[1].clone()).map(drop).map_err(|e| {
+                        ctxt.errs().add(format!(
+                            "`assert_eq` compares values of the same type, found types `{}` and `{}`",
+                            e.left, e.right
+                        ))
+                    }),
+                }
+            }
+        };
+
+        children_check.and(operands_check)
+    }
+
+    fn get_output(&self, _ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        Ok(Ty::Unit)
+    }
+}
+
+impl Typed for Builtin {
+    fn check_inputs(&self, ctxt: &mut TypingContext) -> Result<(), ()> {
+        let children_check = self
+            .args()
+            .iter()
+            .map(|arg| arg.check_inputs(ctxt))
+            .fold(Ok(()), Result::and);
+
+        let substitution = instantiate(&self.function().signature(), self.args(), ctxt)
+            .with_context(|| format!("Invalid call to `{}`", self.function().name()))
+            .map_err(|e| ctxt.errs().add(format!("{:#}", e)))?;
+
+        // The VM orders map entries by key, which only works for integers.
+        let key_check = match substitution.get("K") {
+            Some(Ty::Int | Ty::Bool | Ty::Never | Ty::Err) | None => Ok(()),
+            Some(key) => {
+                ctxt.errs().add(format!(
+                    "Map keys must be integers or booleans, found type `{}`",
+                    key
+                ));
+                Err(())
+            }
+        };
+
+        children_check.and(key_check)
+    }
+
+    fn get_output(&self, ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        let signature = self.function().signature();
+
+        // Instantiation errors are reported when checking the inputs.
+        let ty = instantiate(&signature, self.args(), ctxt)
+            .ok()
+            .and_then(|substitution| {
+                let (_, ret) = signature.expect_function().ok()?;
+                Some(ret.substitute(&substitution))
+            })
+            .unwrap_or(Ty::Err);
+
+        Ok(ty)
+    }
+}
+
+impl Typed for ReadInt {
+    fn check_inputs(&self, _ctxt: &mut TypingContext) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn get_output(&self, _ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        Ok(Ty::Int)
+    }
+}
+
+// Jumping out of a loop never produces a value. As we don't have a "never"
+// type for now, `break` and `continue` are considered to be `()`.
+
+impl Typed for Break {
+    fn check_inputs(&self, _ctxt: &mut TypingContext) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn get_output(&self, _ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        Ok(Ty::Unit)
+    }
+}
+
+impl Typed for Continue {
+    fn check_inputs(&self, _ctxt: &mut TypingContext) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn get_output(&self, _ctxt: &mut TypingContext) -> AnyResult<Ty> {
+        Ok(Ty::Unit)
+    }
+}
+
+#[cfg(test)]
+mod addition {
+    use super::*;
+
+    fn sample_addition() -> ExprKind {
+        ExprKind::addition(ExprKind::integer(41), ExprKind::integer(1))
+    }
+
+    fn bool_on_left() -> ExprKind {
+        ExprKind::addition(ExprKind::bool_(true), ExprKind::integer(42))
+    }
+
+    #[test]
+    fn returns_integer() {
+        let mut ctxt = TypingContext::new();
+        let expr = sample_addition();
+        let output = expr.get_output(&mut ctxt);
+
+        assert_eq!(output.unwrap(), Ty::Int);
+    }
+
+    #[test]
+    fn expects_integers_ok() {
+        let mut ctxt = TypingContext::new();
+        let expr = sample_addition();
+
+        assert!(expr.check_inputs(&mut ctxt).is_ok());
+    }
+
+    #[test]
+    fn expects_integers_err() {
+        let mut ctxt = TypingContext::new();
+
+        assert!(bool_on_left().check_inputs(&mut ctxt).is_err());
+    }
+
+    #[test]
+    fn double_addition_with_labels() {
+        let expr = ExprKind::addition(
+            ExprKind::addition(
+                ExprKind::ident("a".to_owned()),
+                ExprKind::ident("b".to_owned()),
+            ),
+            ExprKind::integer(1),
+        );
+
+        let mut ctxt = TypingContext::new();
+        ctxt.add_binding("a".to_owned(), Ty::Int);
+        ctxt.add_binding("b".to_owned(), Ty::Int);
+
+        assert!(expr.check_inputs(&mut ctxt).is_ok());
+        assert_eq!(expr.get_output(&mut ctxt).unwrap(), Ty::Int);
+    }
+}
+
+#[cfg(test)]
+mod integer {
+    use super::*;
+
+    fn sample_integer() -> ExprKind {
+        ExprKind::integer(42)
+    }
+
+    #[test]
+    fn inputs_always_successful() {
+        let mut ctxt = TypingContext::new();
+        assert!(sample_integer().check_inputs(&mut ctxt).is_ok());
+    }
+
+    #[test]
+    fn output_always_integer() {
+        let mut ctxt = TypingContext::new();
+        assert_eq!(sample_integer().get_output(&mut ctxt).unwrap(), Ty::Int);
+    }
+}
+
+#[cfg(test)]
+mod bindings {
+    use crate::ast::Binding;
+
+    use super::*;
+
+    fn sample_bindings() -> ExprKind {
+        ExprKind::Bindings(Bindings::from_vec(
+            vec![
+                Binding::new("a".to_owned(), ExprKind::integer(40)),
+                Binding::new("b".to_owned(), ExprKind::integer(1)),
+            ],
+            ExprKind::addition(
+                ExprKind::addition(
+                    ExprKind::ident("a".to_owned()),
+                    ExprKind::ident("b".to_owned()),
+                ),
+                ExprKind::integer(1),
+            ),
+        ))
+    }
+
+    fn bindings_with_type_errors() -> ExprKind {
+        ExprKind::single_binding(
+            "foo".to_owned(),
+            ExprKind::addition(ExprKind::bool_(true), ExprKind::integer(42)),
+            ExprKind::ident("foo".to_owned()),
+        )
+    }
+
+    fn bindings_with_unknown_ident() -> ExprKind {
+        ExprKind::Bindings(Bindings::from_vec(
+            vec![Binding::new("foo".to_owned(), ExprKind::integer(42))],
+            ExprKind::ident("bar".to_owned()),
+        ))
+    }
+
+    #[test]
+    fn check_input_ok() {
+        let mut ctxt = TypingContext::new();
+
+        assert!(sample_bindings().check_inputs(&mut ctxt).is_ok());
+    }
+
+    #[test]
+    fn check_inputs_type_error() {
+        let mut ctxt = TypingContext::new();
+
+        assert!(bindings_with_type_errors().check_inputs(&mut ctxt).is_err());
+    }
+
+    #[test]
+    fn get_output_unknown_ident() {
+        let mut ctxt = TypingContext::new();
+
+        assert!(bindings_with_unknown_ident().get_output(&mut ctxt).is_err());
+    }
+}
+
+#[cfg(test)]
+mod statements {
+    use crate::ast::Statement;
+
+    use super::*;
+
+    #[test]
+    fn expression_statement_value_is_discarded() {
+        let mut ctxt = TypingContext::new();
+        let expr = ExprKind::statements(
+            vec![Statement::Expr(ExprKind::bool_(true))],
+            ExprKind::integer(42),
+        );
+
+        assert!(expr.check_inputs(&mut ctxt).is_ok());
+        assert_eq!(expr.get_output(&mut ctxt).unwrap(), Ty::Int);
+    }
+
+    #[test]
+    fn expression_statement_must_be_well_typed() {
+        let mut ctxt = TypingContext::new();
+        let expr = ExprKind::statements(
+            vec![Statement::Expr(ExprKind::addition(
+                ExprKind::bool_(true),
+                ExprKind::integer(1),
+            ))],
+            ExprKind::integer(42),
+        );
+
+        assert!(expr.check_inputs(&mut ctxt).is_err());
+    }
+
+    #[test]
+    fn missing_ending_expression_is_unit() {
+        let mut ctxt = TypingContext::new();
+        let expr = ExprKind::bindings(
+            vec![Binding::new("a".to_owned(), ExprKind::integer(1))],
+            ExprKind::unit(),
+        );
+
+        assert_eq!(expr.get_output(&mut ctxt).unwrap(), Ty::Unit);
+    }
+}
+
+#[cfg(test)]
+mod for_ {
+    use crate::ast::{Range, Statement};
+
+    use super::*;
+
+    fn for_(start: ExprKind, body: ExprKind) -> ExprKind {
+        ExprKind::for_(
+            "i".to_owned(),
+            Range::new(start, ExprKind::integer(10), false),
+            body,
+        )
+    }
+
+    #[test]
+    fn check_inputs_working() {
+        let mut ctxt = TypingContext::new();
+        let expr = for_(ExprKind::integer(0), ExprKind::unit());
+
+        assert!(expr.check_inputs(&mut ctxt).is_ok());
+        assert_eq!(expr.get_output(&mut ctxt).unwrap(), Ty::Unit);
+    }
+
+    #[test]
+    fn bounds_must_be_integers() {
+        let mut ctxt = TypingContext::new();
+        let expr = for_(ExprKind::bool_(true), ExprKind::unit());
+
+        assert!(expr.check_inputs(&mut ctxt).is_err());
+        assert_eq!(
+            ctxt.errs().to_string(),
+            "Expected type `int`, found type `bool`\n"
+        );
+    }
+
+    #[test]
+    fn variable_is_integer() {
+        let mut ctxt = TypingContext::new();
+        let body = ExprKind::statements(
+            vec![Statement::Expr(ExprKind::addition(
+                ExprKind::ident("i".to_owned()),
+                ExprKind::integer(1),
+            ))],
+            ExprKind::break_(),
+        );
+
+        assert!(for_(ExprKind::integer(0), body)
+            .check_inputs(&mut ctxt)
+            .is_ok());
+    }
+
+    #[test]
+    fn body_must_be_unit() {
+        let mut ctxt = TypingContext::new();
+        let expr = for_(ExprKind::integer(0), ExprKind::integer(42));
+
+        assert!(expr.check_inputs(&mut ctxt).is_err());
+    }
+}
+
+#[cfg(test)]
+mod call {
+    use super::*;
+
+    fn ctxt_with_add_one() -> TypingContext {
+        let mut ctxt = TypingContext::new();
+        ctxt.add_function(
+            "add_one".to_owned(),
+            Ty::Function(vec![Ty::Int], Box::new(Ty::Int)),
+        );
+
+        ctxt
+    }
+
+    fn call(callee: &str, args: Vec<ExprKind>) -> ExprKind {
+        ExprKind::call(ExprKind::ident(callee.to_owned()), args)
+    }
+
+    #[test]
+    fn check_inputs_working() {
+        let mut ctxt = ctxt_with_add_one();
+        let expr = call("add_one", vec![ExprKind::integer(41)]);
+
+        assert!(expr.check_inputs(&mut ctxt).is_ok());
+        assert_eq!(expr.get_output(&mut ctxt).unwrap(), Ty::Int);
+    }
+
+    #[test]
+    fn function_is_a_value() {
+        let mut ctxt = ctxt_with_add_one();
+        let expr = ExprKind::ident("add_one".to_owned());
+
+        assert_eq!(
+            expr.get_output(&mut ctxt).unwrap(),
+            Ty::Function(vec![Ty::Int], Box::new(Ty::Int))
+        );
+    }
+
+    #[test]
+    fn arity_mismatch() {
+        let mut ctxt = ctxt_with_add_one();
+        let expr = call("add_one", Vec::new());
+
+        assert!(expr.check_inputs(&mut ctxt).is_err());
+        assert_eq!(ctxt.errs().to_string(), "Expected 1 argument(s), found 0\n");
+    }
+
+    #[test]
+    fn argument_type_mismatch() {
+        let mut ctxt = ctxt_with_add_one();
+        let expr = call("add_one", vec![ExprKind::bool_(true)]);
+
+        assert!(expr.check_inputs(&mut ctxt).is_err());
+        assert_eq!(
+            ctxt.errs().to_string(),
+            "Expected type `int`, found type `bool`\n"
+        );
+    }
+
+    #[test]
+    fn callee_must_be_a_function() {
+        let mut ctxt = TypingContext::new();
+        ctxt.add_binding("x".to_owned(), Ty::Int);
+        let expr = call("x", Vec::new());
+
+        assert!(expr.check_inputs(&mut ctxt).is_err());
+        assert_eq!(
+            expr.get_output(&mut ctxt).unwrap_err().to_string(),
+            "Expected function, found type `int`"
+        );
+    }
+}
+
+#[cfg(test)]
+mod lambda {
+    use crate::ast::Param;
+
+    use super::*;
+
+    #[test]
+    fn captures_are_in_scope() {
+        let mut ctxt = TypingContext::new();
+        ctxt.add_binding("k".to_owned(), Ty::Int);
+        let expr = ExprKind::lambda(
+            vec![Param::new("x".to_owned(), Ty::Int)],
+            ExprKind::multiplication(
+                ExprKind::ident("x".to_owned()),
+                ExprKind::ident("k".to_owned()),
+            ),
+        );
+
+        assert!(expr.check_inputs(&mut ctxt).is_ok());
+        assert_eq!(
+            expr.get_output(&mut ctxt).unwrap(),
+            Ty::Function(vec![Ty::Int], Box::new(Ty::Int))
+        );
+    }
+
+    #[test]
+    fn params_are_not_visible_outside() {
+        let mut ctxt = TypingContext::new();
+        let expr = ExprKind::lambda(
+            vec![Param::new("x".to_owned(), Ty::Int)],
+            ExprKind::ident("x".to_owned()),
+        );
+
+        expr.check_inputs(&mut ctxt).unwrap();
+
+        assert_eq!(ctxt.resolve_binding("x"), None);
+    }
+
+    /// Infers the types of a program and checks it, returning the reported
+    /// errors.
+    fn check(input: &str) -> String {
+        let (ctxt, ast) = crate::parser::parse_input(input).unwrap();
+        let (mut ctxt, ast) =
+            crate::inference::infer_types(&ast, ctxt.into_typing_context()).unwrap();
+
+        declare_functions(&ast, &mut ctxt);
+        ast.functions().iter().for_each(|function| {
+            let _ = check_function(function, &mut ctxt);
+        });
+
+        ctxt.errs().to_string()
+    }
+
+    #[test]
+    fn calls_with_arguments_of_the_inferred_type() {
+        let errs = check("fn main() -> int { let k = 2; let f = |x| x * k; f(3) }");
+
+        assert_eq!(errs, "");
+    }
+
+    #[test]
+    fn calls_with_arguments_of_another_type() {
+        let errs = check("fn main() -> int { let f = |x| x + 1; f(true) }");
+
+        assert_eq!(errs, "Expected type `int`, found type `bool`\n");
+    }
+
+    #[test]
+    fn calls_with_arguments_of_another_annotated_type() {
+        let errs = check("fn main() -> int { let f = |b: bool| 1; f(2) }");
+
+        assert_eq!(errs, "Expected type `bool`, found type `int`\n");
+    }
+
+    #[test]
+    fn calls_capturing_closures_with_arguments_of_another_type() {
+        let errs = check("fn main() -> int { let k = 2; let f = |x| x * k; f(Some(1)) }");
+
+        assert_eq!(errs, "Expected type `int`, found type `Option<int>`\n");
+    }
+
+    #[test]
+    fn calls_with_too_many_arguments() {
+        let errs = check("fn main() -> int { let f = |x| x + 1; f(1, 2) }");
+
+        assert_eq!(errs, "Expected 1 argument(s), found 2\n");
+    }
+}
+
+#[cfg(test)]
+mod generics {
+    use crate::ast::Param;
+
+    use super::*;
+
+    fn t() -> Ty {
+        Ty::Param("T".to_owned())
+    }
+
+    fn ctxt_with_apply() -> TypingContext {
+        // fn apply<T>(f: fn(T) -> T, x: T) -> T
+        let mut ctxt = TypingContext::new();
+        ctxt.add_function(
+            "apply".to_owned(),
+            Ty::Function(
+                vec![Ty::Function(vec![t()], Box::new(t())), t()],
+                Box::new(t()),
+            ),
+        );
+
+        ctxt
+    }
+
+    fn call_apply(f: Ty, x: ExprKind) -> (ExprKind, TypingContext) {
+        let mut ctxt = ctxt_with_apply();
+        ctxt.add_binding("f".to_owned(), f);
+
+        let call = ExprKind::call(
+            ExprKind::ident("apply".to_owned()),
+            vec![ExprKind::ident("f".to_owned()), x],
+        );
+
+        (call, ctxt)
+    }
+
+    #[test]
+    fn type_arguments_are_inferred() {
+        let f = Ty::Function(vec![Ty::Bool], Box::new(Ty::Bool));
+        let (call, mut ctxt) = call_apply(f, ExprKind::bool_(true));
+
+        assert!(call.check_inputs(&mut ctxt).is_ok());
+        assert_eq!(call.get_output(&mut ctxt).unwrap(), Ty::Bool);
+    }
+
+    #[test]
+    fn type_arguments_must_agree() {
+        let f = Ty::Function(vec![Ty::Bool], Box::new(Ty::Bool));
+        let (call, mut ctxt) = call_apply(f, ExprKind::integer(1));
+
+        assert!(call.check_inputs(&mut ctxt).is_err());
+        assert_eq!(
+            ctxt.errs().to_string(),
+            "Expected type `bool`, found type `int`\n"
+        );
+    }
+
+    #[test]
+    fn generic_functions_are_not_values() {
+        let mut ctxt = ctxt_with_apply();
+        let expr = ExprKind::ident("apply".to_owned());
+
+        assert_eq!(
+            expr.get_output(&mut ctxt).unwrap_err().to_string(),
+            "Generic function `apply` can only be called directly"
+        );
+    }
+
+    #[test]
+    fn parameters_are_opaque() {
+        let mut ctxt = TypingContext::new();
+        let function = Function::new(
+            "f".to_owned(),
+            vec!["T".to_owned()],
+            vec![Param::new("x".to_owned(), t())],
+            None,
+            ExprKind::addition(ExprKind::ident("x".to_owned()), ExprKind::integer(1)),
+        );
+
+        assert!(check_function(&function, &mut ctxt).is_err());
+        assert_eq!(
+            ctxt.errs().to_string(),
+            "Type `T` does not implement trait `Add`\n"
+        );
+    }
+
+    #[test]
+    fn unknown_type() {
+        let mut ctxt = TypingContext::new();
+        let function = Function::new(
+            "f".to_owned(),
+            Vec::new(),
+            vec![Param::new("x".to_owned(), t())],
+            None,
+            ExprKind::unit(),
+        );
+
+        assert!(check_function(&function, &mut ctxt).is_err());
+        assert_eq!(ctxt.errs().to_string(), "Unknown type `T`\n");
+    }
+}
+
+#[cfg(test)]
+mod functions {
+    use crate::ast::Param;
+
+    use super::*;
+
+    #[test]
+    fn return_types_are_inferred() {
+        let program = Program::new(vec![
+            Function::new(
+                "main".to_owned(),
+                Vec::new(),
+                Vec::new(),
+                None,
+                ExprKind::call(ExprKind::ident("f".to_owned()), Vec::new()),
+            ),
+            Function::new(
+                "f".to_owned(),
+                Vec::new(),
+                Vec::new(),
+                None,
+                ExprKind::bool_(true),
+            ),
+        ]);
+
+        let ctxt = check_ast(&program, TypingContext::new()).unwrap();
+
+        assert_eq!(
+            ctxt.resolve_function("main"),
+            Some(&Ty::Function(Vec::new(), Box::new(Ty::Bool)))
+        );
+    }
+
+    #[test]
+    fn body_must_match_return_type() {
+        let mut ctxt = TypingContext::new();
+        let function = Function::new(
+            "f".to_owned(),
+            Vec::new(),
+            vec![Param::new("x".to_owned(), Ty::Int)],
+            Some(Ty::Bool),
+            ExprKind::ident("x".to_owned()),
+        );
 
-        assert!(bindings_with_type_errors().check_inputs(&mut ctxt).is_err());
+        assert!(check_function(&function, &mut ctxt).is_err());
+        assert_eq!(
+            ctxt.errs().to_string(),
+            "Expected type `bool`, found type `int`\n"
+        );
     }
 
     #[test]
-    fn get_output_unknown_ident() {
-        let mut ctxt = TypingContext::new();
-
-        assert!(bindings_with_unknown_ident().get_output(&mut ctxt).is_err());
+    fn functions_are_defined_once() {
+        let f = |value| {
+            Function::new(
+                "f".to_owned(),
+                Vec::new(),
+                Vec::new(),
+                Some(Ty::Int),
+                ExprKind::integer(value),
+            )
+        };
+        let program = Program::new(vec![f(1), f(2)]);
+        let ctxt = TypingContext::new();
+
+        assert!(check_unique_functions(&program, &ctxt).is_err());
+        assert_eq!(
+            ctxt.errs().to_string(),
+            "Function `f` is defined multiple times\n"
+        );
     }
 }
 
@@ -528,6 +2410,35 @@ mod if_ {
         assert_eq!(sample_if().get_output(&mut ctxt).unwrap(), Ty::Int);
     }
 
+    #[test]
+    fn missing_else_requires_unit_consequent() {
+        let mut ctxt = TypingContext::new();
+        let expr = ExprKind::if_(
+            ExprKind::bool_(true),
+            ExprKind::integer(101),
+            ExprKind::unit(),
+        );
+
+        assert!(expr.check_inputs(&mut ctxt).is_err());
+    }
+
+    #[test]
+    fn else_if_chain() {
+        let mut ctxt = TypingContext::new();
+        let expr = ExprKind::if_(
+            ExprKind::bool_(true),
+            ExprKind::integer(1),
+            ExprKind::if_(
+                ExprKind::bool_(false),
+                ExprKind::integer(2),
+                ExprKind::integer(3),
+            ),
+        );
+
+        assert!(expr.check_inputs(&mut ctxt).is_ok());
+        assert_eq!(expr.get_output(&mut ctxt).unwrap(), Ty::Int);
+    }
+
     #[test]
     fn get_output_consequent_and_alternative_different() {
         let mut ctxt = TypingContext::new();
@@ -560,3 +2471,569 @@ mod bool_ {
         assert_eq!(sample_bool().get_output(&mut ctxt).unwrap(), Ty::Bool);
     }
 }
+
+#[cfg(test)]
+mod traits {
+    use crate::ast::{Bound, Impl, MethodSignature, Trait};
+
+    use super::*;
+
+    fn t() -> Ty {
+        Ty::Param("T".to_owned())
+    }
+
+    fn ident(name: &str) -> ExprKind {
+        ExprKind::ident(name.to_owned())
+    }
+
+    /// trait Score { fn score(self) -> int; }
+    fn score_trait() -> Trait {
+        let self_param = Param::new("self".to_owned(), Ty::Param("Self".to_owned()));
+        let score = MethodSignature::new("score".to_owned(), vec![self_param], Ty::Int);
+
+        Trait::new("Score".to_owned(), vec![score])
+    }
+
+    /// impl Score for int { fn score(self) -> ret_ty { body } }
+    fn score_impl(ret_ty: Ty, body: ExprKind) -> (Impl, Function) {
+        let impl_ = Impl::new("Score".to_owned(), Ty::Int, vec!["score".to_owned()]);
+        let method = Function::new(
+            "<int as Score>::score".to_owned(),
+            Vec::new(),
+            vec![Param::new("self".to_owned(), Ty::Int)],
+            Some(ret_ty),
+            body,
+        );
+
+        (impl_, method)
+    }
+
+    /// fn total<T: Score>(x: T) -> int { x.score() }
+    fn total() -> Function {
+        Function::new(
+            "total".to_owned(),
+            vec!["T".to_owned()],
+            vec![Param::new("x".to_owned(), t())],
+            Some(Ty::Int),
+            ExprKind::method_call(ident("x"), "score".to_owned(), Vec::new()),
+        )
+        .with_bounds(vec![Bound::new("T".to_owned(), "Score".to_owned())])
+    }
+
+    fn main(body: ExprKind) -> Function {
+        Function::new("main".to_owned(), Vec::new(), Vec::new(), None, body)
+    }
+
+    /// Checks a program with the `Score` trait, returning the context and
+    /// the reported errors.
+    fn check(functions: Vec<Function>, impls: Vec<Impl>) -> (TypingContext, String) {
+        let program = Program::with_traits(functions, vec![score_trait()], impls);
+        let mut ctxt = TypingContext::new();
+
+        let _ = declare_traits(&program, &mut ctxt);
+        declare_functions(&program, &mut ctxt);
+
+        program.impls().iter().for_each(|impl_| {
+            let _ = check_impl(impl_, &ctxt);
+        });
+        program.functions().iter().for_each(|function| {
+            let _ = check_function(function, &mut ctxt);
+        });
+
+        let errs = ctxt.errs().to_string();
+        (ctxt, errs)
+    }
+
+    #[test]
+    fn method_call_on_implementor() {
+        let (impl_, method) = score_impl(Ty::Int, ident("self"));
+        let body = ExprKind::method_call(ExprKind::integer(4), "score".to_owned(), Vec::new());
+
+        let (ctxt, errs) = check(vec![main(body), method], vec![impl_]);
+
+        assert_eq!(errs, "");
+        assert_eq!(
+            ctxt.resolve_function("main"),
+            Some(&Ty::Function(Vec::new(), Box::new(Ty::Int)))
+        );
+    }
+
+    #[test]
+    fn method_call_on_bounded_parameter() {
+        let (impl_, method) = score_impl(Ty::Int, ident("self"));
+        let body = ExprKind::call(ident("total"), vec![ExprKind::integer(4)]);
+
+        assert_eq!(check(vec![main(body), total(), method], vec![impl_]).1, "");
+    }
+
+    #[test]
+    fn unknown_method() {
+        let body = ExprKind::method_call(ExprKind::bool_(true), "score".to_owned(), Vec::new());
+
+        assert_eq!(
+            check(vec![main(body)], Vec::new()).1,
+            "No method `score` found for type `bool`\n"
+        );
+    }
+
+    #[test]
+    fn unsatisfied_bound() {
+        let (impl_, method) = score_impl(Ty::Int, ident("self"));
+        let body = ExprKind::call(ident("total"), vec![ExprKind::bool_(true)]);
+
+        assert_eq!(
+            check(vec![main(body), total(), method], vec![impl_]).1,
+            "Type `bool` does not implement trait `Score`\n"
+        );
+    }
+
+    #[test]
+    fn mismatched_method_signature() {
+        let (impl_, method) = score_impl(Ty::Bool, ExprKind::bool_(true));
+
+        assert_eq!(
+            check(vec![method], vec![impl_]).1,
+            "Method `score` has type `fn(int) -> bool` but trait `Score` expects `fn(int) -> int`\n"
+        );
+    }
+
+    #[test]
+    fn missing_method() {
+        let impl_ = Impl::new("Score".to_owned(), Ty::Int, Vec::new());
+
+        assert_eq!(
+            check(Vec::new(), vec![impl_]).1,
+            "Missing method `score` in implementation of `Score` for `int`\n"
+        );
+    }
+
+    /// impl int { fn score(self) -> bool { true } }
+    fn inherent_score() -> (Impl, Function) {
+        let impl_ = Impl::inherent(Ty::Int, vec!["score".to_owned()]);
+        let method = Function::new(
+            "int::score".to_owned(),
+            Vec::new(),
+            vec![Param::new("self".to_owned(), Ty::Int)],
+            Some(Ty::Bool),
+            ExprKind::bool_(true),
+        );
+
+        (impl_, method)
+    }
+
+    #[test]
+    fn inherent_methods_take_precedence() {
+        let (trait_impl, trait_method) = score_impl(Ty::Int, ident("self"));
+        let (impl_, method) = inherent_score();
+        let body = ExprKind::method_call(ExprKind::integer(4), "score".to_owned(), Vec::new());
+
+        let (ctxt, errs) = check(
+            vec![main(body), trait_method, method],
+            vec![trait_impl, impl_],
+        );
+
+        assert_eq!(errs, "");
+        assert_eq!(
+            ctxt.resolve_function("main"),
+            Some(&Ty::Function(Vec::new(), Box::new(Ty::Bool)))
+        );
+    }
+
+    #[test]
+    fn inherent_methods_take_self() {
+        let impl_ = Impl::inherent(Ty::Int, vec!["zero".to_owned()]);
+        let function = Function::new(
+            "int::zero".to_owned(),
+            Vec::new(),
+            Vec::new(),
+            Some(Ty::Int),
+            ExprKind::integer(0),
+        );
+
+        assert_eq!(
+            check(vec![function], vec![impl_]).1,
+            "Method `zero` of `impl int` must take `self` as first parameter\n"
+        );
+    }
+
+    #[test]
+    fn trait_method_params_have_a_type() {
+        let (_, program) =
+            crate::parser::parse_input("trait Scale { fn scale(self, factor) -> int; }").unwrap();
+        let mut ctxt = TypingContext::new();
+
+        let _ = declare_traits(&program, &mut ctxt);
+
+        assert_eq!(
+            ctxt.errs().to_string(),
+            "Parameter `factor` of method `scale` of trait `Scale` must have a type\n"
+        );
+    }
+}
+
+#[cfg(test)]
+mod records {
+    use super::*;
+
+    fn check(input: &str) -> String {
+        let (ctxt, ast) = crate::parser::parse_input(input).unwrap();
+        let mut ctxt = ctxt.into_typing_context();
+
+        let _ = declare_records(&ast, &mut ctxt);
+        let _ = declare_traits(&ast, &mut ctxt);
+        declare_functions(&ast, &mut ctxt);
+
+        ast.functions().iter().for_each(|function| {
+            let _ = check_function(function, &mut ctxt);
+        });
+
+        ctxt.errs().to_string()
+    }
+
+    const POINT: &str = "struct Point { x: int, y: int }";
+
+    #[test]
+    fn literal_and_field_access() {
+        let errs = check(&format!(
+            "{} fn f() -> int {{ let p = Point {{ y: 2, x: 1 }}; p.x + p.y }}",
+            POINT
+        ));
+
+        assert_eq!(errs, "");
+    }
+
+    #[test]
+    fn literal_must_set_every_field_once() {
+        let errs = check(&format!(
+            "{} fn f() -> Point {{ Point {{ x: 1, x: 2, z: true }} }}",
+            POINT
+        ));
+
+        assert_eq!(
+            errs,
+            "Field `x` is set multiple times\n\
+             Record `Point` has no field `z`\n\
+             Missing field `y` in `Point`\n"
+        );
+    }
+
+    #[test]
+    fn field_types_are_checked() {
+        let errs = check(&format!(
+            "{} fn f() -> Point {{ Point {{ x: 1, y: true }} }}",
+            POINT
+        ));
+
+        assert_eq!(errs, "Expected type `int`, found type `bool`\n");
+    }
+
+    #[test]
+    fn unknown_field() {
+        let errs = check(&format!("{} fn f(p: Point) -> int {{ p.z }}", POINT));
+
+        assert_eq!(errs, "No field `z` on type `Point`\n");
+    }
+
+    #[test]
+    fn invalid_declarations() {
+        let errs = check("struct Empty {} struct Twice { a: int, a: int } struct Bad { b: Nope }");
+
+        assert_eq!(
+            errs,
+            "Record `Empty` must have at least one field\n\
+             Field `a` is defined multiple times in `Twice`\n\
+             Unknown type `Nope`\n"
+        );
+    }
+
+    #[test]
+    fn operators_dispatch_to_trait_methods() {
+        let errs = check(&format!(
+            "{} impl Add for Point {{ fn add(self, other: Point) -> Point {{ self }} }}
+             fn f(p: Point) -> Point {{ p + p }}",
+            POINT
+        ));
+
+        assert_eq!(errs, "");
+    }
+
+    #[test]
+    fn operators_require_an_implementation() {
+        let errs = check(&format!("{} fn f(p: Point) -> Point {{ p * p }}", POINT));
+
+        assert_eq!(errs, "Type `Point` does not implement trait `Mul`\n");
+    }
+
+    #[test]
+    fn operands_must_have_the_same_type() {
+        let errs = check(&format!(
+            "{} impl Sub for Point {{ fn sub(self, other: Point) -> Point {{ self }} }}
+             fn f(p: Point) -> Point {{ p - 1 }}",
+            POINT
+        ));
+
+        assert_eq!(errs, "Expected type `Point`, found type `int`\n");
+    }
+
+    #[test]
+    fn int_implements_operator_traits() {
+        let errs = check("fn double<T: Add>(x: T) -> T { x + x } fn f() -> int { double(2) }");
+
+        assert_eq!(errs, "");
+    }
+}
+
+#[cfg(test)]
+mod options {
+    use super::*;
+
+    fn check(input: &str) -> String {
+        let (ctxt, ast) = crate::parser::parse_input(input).unwrap();
+        let mut ctxt = ctxt.into_typing_context();
+
+        let _ = declare_traits(&ast, &mut ctxt);
+        declare_functions(&ast, &mut ctxt);
+
+        ast.functions().iter().for_each(|function| {
+            let _ = check_function(function, &mut ctxt);
+        });
+
+        ctxt.errs().to_string()
+    }
+
+    #[test]
+    fn variants_and_match() {
+        let errs = check(
+            "fn f(o: ?int) -> Option<int> {
+                 let n = match o { None => 0, Some(x) => x };
+                 if true { Some(n) } else { None }
+             }",
+        );
+
+        assert_eq!(errs, "");
+    }
+
+    #[test]
+    fn match_must_be_exhaustive() {
+        let errs = check("fn f(o: ?int) -> int { match o { Some(x) => x, Some(y) => y } }");
+
+        assert_eq!(
+            errs,
+            "Pattern `Some` is matched multiple times\n\
+             Missing pattern `None`\n"
+        );
+    }
+
+    #[test]
+    fn patterns_must_match_the_scrutinee() {
+        let errs = check("fn f() -> int { match 1 { Some(x) => x, None => 0 } }");
+
+        assert_eq!(
+            errs,
+            "Pattern `Some` does not match type `int`\n\
+             Pattern `None` does not match type `int`\n"
+        );
+    }
+
+    #[test]
+    fn try_unwraps_options() {
+        let errs = check("fn f(o: ?int) -> ?bool { let x = o? + 1; Some(true) }");
+
+        assert_eq!(errs, "");
+    }
+
+    #[test]
+    fn try_requires_an_option() {
+        let errs = check("fn f(o: int) -> ?int { o? }");
+
+        assert_eq!(
+            errs,
+            "The `?` operator can only be applied to an `Option` or a `Result`, found type `int`\n"
+        );
+    }
+
+    #[test]
+    fn try_requires_an_option_return_type() {
+        let errs = check(
+            "fn f(o: ?int) -> int { o? }
+             fn g(o: ?int) -> ?int { let f = |p: ?int| p?; o }",
+        );
+
+        assert_eq!(
+            errs,
+            "The `?` operator can only be used in functions that return `Option`, found `int`\n\
+             The `?` operator can't be used in lambdas\n"
+        );
+    }
+
+    #[test]
+    fn try_unwraps_results() {
+        let errs = check(
+            "fn f(r: Result<int, bool>) -> Result<int, bool> {
+                 let x = r? + 1;
+                 match r { Ok(y) => Ok(x + y), Err(e) => Err(e) }
+             }",
+        );
+
+        assert_eq!(errs, "");
+    }
+
+    #[test]
+    fn try_requires_the_same_error_type() {
+        let errs = check(
+            "fn f(r: Result<int, bool>) -> Result<int, int> { Ok(r?) }
+             fn g(r: Result<int, bool>) -> ?int { Some(r?) }
+             fn h(o: ?int) -> Result<int, bool> { Ok(o?) }",
+        );
+
+        assert_eq!(
+            errs,
+            "The `?` operator can only be used in functions that return `Result<_, bool>`, found `Result<int, int>`\n\
+             The `?` operator can only be used in functions that return `Result<_, bool>`, found `Option<int>`\n\
+             The `?` operator can only be used in functions that return `Option`, found `Result<int, bool>`\n"
+        );
+    }
+
+    #[test]
+    fn panic_has_any_type() {
+        let errs = check(
+            "fn f(o: ?int) -> int {
+                 let x = if true { panic(\"a\") } else { 1 };
+                 match o { Some(y) => y + x, None => panic(\"b\") }
+             }",
+        );
+
+        assert_eq!(errs, "");
+    }
+
+    #[test]
+    fn comparisons_are_between_integers() {
+        let errs = check(
+            "fn f(a: int, b: bool) -> bool {
+                 let c = a < 1;
+                 let d = 2 == b;
+                 a + 1 != a * 2
+             }",
+        );
+
+        assert_eq!(errs, "Expected type `int`, found type `bool`\n");
+    }
+
+    #[test]
+    fn lists() {
+        let errs = check(
+            "fn f(l: [int]) -> int { head(cons(1, l)) }
+             fn g() -> [bool] { tail([true, false]) }
+             fn h(l: [bool]) -> bool { if is_empty([]) { is_empty(l) } else { false } }",
+        );
+
+        assert_eq!(errs, "");
+    }
+
+    #[test]
+    fn list_elements_have_the_same_type() {
+        let errs = check(
+            "fn f(l: [int]) -> int {
+                 let a = [1, true];
+                 let b = cons(false, l);
+                 0
+             }",
+        );
+
+        assert_eq!(
+            errs,
+            "Expression returns two different types: `int` and `bool`\n\
+             Invalid call to `cons`: Expected type `[bool]`, found type `[int]`\n"
+        );
+    }
+
+    #[test]
+    fn maps() {
+        let errs = check(
+            "fn f(m: Map<int, bool>) -> ?bool { get(insert(m, 1, true), 2) }
+             fn g() -> Map<bool, [int]> { remove(insert(empty_map(), true, [1]), false) }
+             fn h(m: Map<int, int>) -> bool { contains(m, 1) }",
+        );
+
+        assert_eq!(errs, "");
+    }
+
+    #[test]
+    fn map_keys_are_integers_or_booleans() {
+        let errs = check(
+            "fn f(m: Map<int, int>, k: [int]) -> int {
+                 let a = insert(empty_map(), k, 1);
+                 let b = get(m, true);
+                 0
+             }",
+        );
+
+        assert_eq!(
+            errs,
+            "Map keys must be integers or booleans, found type `[int]`\n\
+             Invalid call to `get`: Expected type `int`, found type `bool`\n"
+        );
+    }
+
+    #[test]
+    fn assertions() {
+        let errs = check(
+            "fn f(a: int, b: bool) {
+                 assert(b);
+                 assert_eq(a, 1);
+                 assert(a);
+                 assert_eq(a, b);
+                 assert_eq(b, b);
+                 assert_eq(b, [a]);
+                 assert_eq([a], [a])
+             }",
+        );
+
+        assert_eq!(
+            errs,
+            "Expected type `bool`, found type `int`\n\
+             `assert_eq` compares values of the same type, found types `int` and `bool`\n\
+             `assert_eq` only compares integers or booleans, found type `[int]`\n\
+             `assert_eq` only compares integers or booleans, found type `[int]`\n"
+        );
+    }
+
+    #[test]
+    fn contracts() {
+        let errs = check(
+            "fn f(a: int, b: bool) -> bool
+                 requires b
+                 requires a
+                 ensures result
+                 ensures a + result
+             { b }
+             fn g(a: int) requires result { }",
+        );
+
+        assert_eq!(
+            errs,
+            "Expected type `bool`, found type `int`\n\
+             Expected type `int`, found type `bool`\n\
+             Expected type `bool`, found type `int`\n\
+             Variable `result` not in scope\n"
+        );
+    }
+
+    #[test]
+    fn only_integers_and_booleans_are_printed() {
+        let errs = check(
+            "fn f(a: int, b: bool, c: Option<int>) {
+                 print(\"a\");
+                 print(a);
+                 println(b);
+                 println();
+                 println(c)
+             }",
+        );
+
+        assert_eq!(
+            errs,
+            "`print` can only print integers, booleans and string literals, found type `Option<int>`\n"
+        );
+    }
+}
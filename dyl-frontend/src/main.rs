@@ -1,9 +1,33 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+
+use dyl_compiler::Options;
+
+/// Removes the contract checks from the compiled program.
+const STRIP_CONTRACTS_FLAG: &str = "--strip-contracts";
 
 fn main() -> Result<()> {
-    let bytecode = dyl_compiler::bytecode_from_program("main.dyl")?;
+    let (flags, args): (Vec<_>, Vec<_>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg == STRIP_CONTRACTS_FLAG);
+
+    let args = args
+        .into_iter()
+        .map(|arg| {
+            arg.parse()
+                .with_context(|| format!("Program argument `{}` is not an integer", arg))
+        })
+        .collect::<Result<Vec<i32>>>()?;
+
+    let options = if flags.is_empty() {
+        Options::default()
+    } else {
+        Options::default().strip_contracts()
+    };
+
+    let (bytecode, symbols) =
+        dyl_compiler::bytecode_from_program_with_options("main.dyl", &options)?;
 
-    dyl_vm::run_program(bytecode)?;
+    dyl_vm::run_program(bytecode, symbols, args)?;
 
     Ok(())
 }
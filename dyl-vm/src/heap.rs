@@ -0,0 +1,167 @@
+use std::mem;
+
+use anyhow::{anyhow, Result};
+
+use crate::value::Value;
+
+/// The number of bytes that can be allocated before the first collection, by
+/// default.
+pub(crate) const DEFAULT_GC_THRESHOLD: usize = 1 << 20;
+
+/// The objects allocated by a program, along with a mark-and-sweep collector.
+///
+/// Objects are referred to by their index, which is reused once the object
+/// has been freed. A collection is triggered when enough bytes have been
+/// allocated since the previous one.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Heap {
+    objects: Vec<Option<Vec<Value>>>,
+    free_slots: Vec<u32>,
+    allocated_since_collection: usize,
+    threshold: usize,
+    stats: GcStats,
+}
+
+/// Statistics about the memory managed by the garbage collector.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct GcStats {
+    /// The number of collections that have been run.
+    pub collections: u64,
+    /// The number of bytes that have been allocated.
+    pub bytes_allocated: u64,
+    /// The number of bytes that have been freed by the collector.
+    pub bytes_freed: u64,
+}
+
+impl Heap {
+    pub(crate) fn new(threshold: usize) -> Heap {
+        Heap {
+            objects: Vec::new(),
+            free_slots: Vec::new(),
+            allocated_since_collection: 0,
+            threshold,
+            stats: GcStats::default(),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> GcStats {
+        self.stats
+    }
+
+    /// Returns whether enough bytes have been allocated since the previous
+    /// collection to run a new one.
+    pub(crate) fn should_collect(&self) -> bool {
+        self.allocated_since_collection >= self.threshold
+    }
+
+    /// Allocates an object and returns its handle.
+    pub(crate) fn alloc(&mut self, fields: Vec<Value>) -> u32 {
+        let size = object_size(&fields);
+        self.allocated_since_collection += size;
+        self.stats.bytes_allocated += size as u64;
+
+        match self.free_slots.pop() {
+            Some(handle) => {
+                self.objects[handle as usize] = Some(fields);
+                handle
+            }
+            None => {
+                self.objects.push(Some(fields));
+                (self.objects.len() - 1) as u32
+            }
+        }
+    }
+
+    pub(crate) fn load(&self, handle: u32, idx: u16) -> Result<Value> {
+        self.object(handle)?
+            .get(idx as usize)
+            .cloned()
+            .ok_or_else(|| anyhow!("Object `{}` has no field `{}`", handle, idx))
+    }
+
+    pub(crate) fn store(&mut self, handle: u32, idx: u16, value: Value) -> Result<()> {
+        let field = self
+            .objects
+            .get_mut(handle as usize)
+            .and_then(Option::as_mut)
+            .ok_or_else(|| anyhow!("Invalid object handle `{}`", handle))?
+            .get_mut(idx as usize)
+            .ok_or_else(|| anyhow!("Object `{}` has no field `{}`", handle, idx))?;
+
+        *field = value;
+
+        Ok(())
+    }
+
+    fn object(&self, handle: u32) -> Result<&[Value]> {
+        self.objects
+            .get(handle as usize)
+            .and_then(Option::as_deref)
+            .ok_or_else(|| anyhow!("Invalid object handle `{}`", handle))
+    }
+
+    /// Frees every object that can't be reached from `roots`, either directly
+    /// or through other objects.
+    pub(crate) fn collect<'a>(&mut self, roots: impl IntoIterator<Item = &'a Value>) {
+        let mut marked = vec![false; self.objects.len()];
+        let mut to_visit = Vec::new();
+
+        roots
+            .into_iter()
+            .for_each(|root| push_handles(root, &mut to_visit));
+
+        while let Some(handle) = to_visit.pop() {
+            // Handles that don't refer to any object, such as those forged by
+            // host functions, are left for `load` and `store` to report.
+            let handle = handle as usize;
+            match marked.get_mut(handle) {
+                Some(marked) if !*marked => *marked = true,
+                _ => continue,
+            }
+
+            if let Some(fields) = &self.objects[handle] {
+                fields
+                    .iter()
+                    .for_each(|field| push_handles(field, &mut to_visit));
+            }
+        }
+
+        let mut freed = 0;
+        let free_slots = &mut self.free_slots;
+        self.objects
+            .iter_mut()
+            .zip(marked)
+            .enumerate()
+            .filter(|(_, (object, marked))| object.is_some() && !marked)
+            .for_each(|(handle, (object, _))| {
+                freed += object.take().map_or(0, |fields| object_size(&fields));
+                free_slots.push(handle as u32);
+            });
+
+        self.allocated_since_collection = 0;
+        self.stats.collections += 1;
+        self.stats.bytes_freed += freed as u64;
+    }
+}
+
+/// Pushes the handles a value refers to, including those held by the
+/// records, closures, lists and maps it contains.
+fn push_handles(value: &Value, handles: &mut Vec<u32>) {
+    match value {
+        Value::Handle(handle) => handles.push(*handle),
+        Value::Record(fields) => fields.iter().for_each(|f| push_handles(f, handles)),
+        Value::Closure(closure) => closure
+            .captures()
+            .iter()
+            .for_each(|v| push_handles(v, handles)),
+        Value::List(list) => list.iter().for_each(|v| push_handles(v, handles)),
+        Value::Map(map) => map.iter().for_each(|(_, v)| push_handles(v, handles)),
+        Value::Integer(_) | Value::InstructionPointer(_) | Value::Function(_) => {}
+    }
+}
+
+/// The number of bytes used by an object. The values it refers to through
+/// records, closures, lists or maps are not counted.
+fn object_size(fields: &[Value]) -> usize {
+    mem::size_of_val(fields)
+}
@@ -0,0 +1,33 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::value::Value;
+
+/// A function of the application running the program, that the program
+/// declares with `extern fn`.
+pub type HostFunction = Box<dyn FnMut(Vec<Value>) -> Result<Value>>;
+
+/// The functions that the application running a program provides to it,
+/// indexed by name.
+#[derive(Default)]
+pub struct HostFunctions(HashMap<String, HostFunction>);
+
+impl HostFunctions {
+    pub fn new() -> HostFunctions {
+        HostFunctions::default()
+    }
+
+    /// Registers a function, which receives the arguments of the call in
+    /// order. A function registered under the same name is replaced.
+    pub fn register<F>(&mut self, name: impl Into<String>, function: F)
+    where
+        F: FnMut(Vec<Value>) -> Result<Value> + 'static,
+    {
+        self.0.insert(name.into(), Box::new(function));
+    }
+
+    pub(crate) fn get_mut(&mut self, name: &str) -> Option<&mut HostFunction> {
+        self.0.get_mut(name)
+    }
+}
@@ -1,32 +1,270 @@
 use anyhow::{anyhow, bail, ensure, Context, Result};
 
-use dyl_bytecode::Instruction;
+use dyl_bytecode::{symbols::Symbols, Instruction};
 
-use crate::runnable::Runnable;
-use crate::{runnable::RunStatus, value::Value};
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, BufRead, Write},
+    sync::Arc,
+};
 
-pub(crate) struct Interpreter {
+use crate::runnable::Runnable;
+use crate::{
+    heap::{GcStats, Heap, DEFAULT_GC_THRESHOLD},
+    host::HostFunctions,
+    list::List,
+    map::Map,
+    memo::{MemoCaches, MemoStats},
+    runnable::RunStatus,
+    value::{Closure, Value},
+};
+
+/// Runs the bytecode of a program.
+pub struct Interpreter {
     code: Vec<Instruction>,
+    symbols: Symbols,
+    host_functions: HostFunctions,
+    args: Vec<i32>,
+    input: Box<dyn BufRead>,
+    /// The words of the last line read from the input that have not been
+    /// parsed yet.
+    pending_input: VecDeque<String>,
+    output: Box<dyn Write>,
+    heap: Heap,
+    memo: MemoCaches,
 }
 
 impl Interpreter {
-    pub(crate) fn from_instructions(code: Vec<Instruction>) -> Interpreter {
-        Interpreter { code }
+    /// Creates an interpreter for a program, which reads from the standard
+    /// input and writes to the standard output.
+    pub fn from_instructions(code: Vec<Instruction>) -> Interpreter {
+        let symbols = Symbols::new();
+        let host_functions = HostFunctions::new();
+        let args = Vec::new();
+        let input = Box::new(io::BufReader::new(io::stdin()));
+        let pending_input = VecDeque::new();
+        let output = Box::new(io::stdout());
+        let heap = Heap::new(DEFAULT_GC_THRESHOLD);
+        let memo = MemoCaches::default();
+
+        Interpreter {
+            code,
+            symbols,
+            host_functions,
+            args,
+            input,
+            pending_input,
+            output,
+            heap,
+            memo,
+        }
+    }
+
+    /// Sets the symbols of the program, which describe where it was when it
+    /// panicked and name the host functions it calls.
+    pub fn with_symbols(self, symbols: Symbols) -> Interpreter {
+        Interpreter { symbols, ..self }
+    }
+
+    pub fn with_host_functions(self, host_functions: HostFunctions) -> Interpreter {
+        Interpreter {
+            host_functions,
+            ..self
+        }
+    }
+
+    /// Sets the arguments passed to `main`.
+    pub fn with_args(self, args: Vec<i32>) -> Interpreter {
+        Interpreter { args, ..self }
+    }
+
+    /// Sets where `read_int` reads. Integers are separated by whitespace.
+    pub fn with_input(self, input: impl BufRead + 'static) -> Interpreter {
+        Interpreter {
+            input: Box::new(input),
+            ..self
+        }
+    }
+
+    /// Sets where `print` and `println` write.
+    pub fn with_output(self, output: impl Write + 'static) -> Interpreter {
+        Interpreter {
+            output: Box::new(output),
+            ..self
+        }
+    }
+
+    /// Sets the number of bytes the program can allocate between two garbage
+    /// collections.
+    pub fn with_gc_threshold(self, bytes: usize) -> Interpreter {
+        Interpreter {
+            heap: Heap::new(bytes),
+            ..self
+        }
+    }
+
+    /// Returns statistics about the objects allocated by the program so far.
+    pub fn gc_stats(&self) -> GcStats {
+        self.heap.stats()
+    }
+
+    /// Returns statistics about the cache of a memoized function. The
+    /// function is found by name in the symbols of the program.
+    pub fn memo_stats(&self, function: &str) -> MemoStats {
+        self.symbols
+            .function_addr(function)
+            .map(|addr| self.memo.stats(addr))
+            .unwrap_or_default()
+    }
+
+    /// Runs the program, returning the value returned by `main`.
+    pub fn run(&mut self) -> Result<Value> {
+        let rslt = self.run_to_end();
+
+        // What has been printed is flushed even if the program failed.
+        let flushed = self
+            .output
+            .flush()
+            .context("Failed to write program output");
+
+        rslt.and_then(|value| flushed.map(|()| value))
     }
 
-    pub(crate) fn run(&mut self) -> Result<Value> {
+    fn run_to_end(&mut self) -> Result<Value> {
+        let expected_args = self.symbols.main_params() as usize;
+        ensure!(
+            self.args.len() == expected_args,
+            "`main` expects {} arguments, found {}",
+            expected_args,
+            self.args.len()
+        );
+
         let mut state = RunningInterpreterState::new();
+        self.args
+            .iter()
+            .for_each(|arg| state.stack_mut().push_integer(*arg));
 
         let final_value = loop {
             match self.run_single(state)? {
                 RunStatus::Continue(new_state) => state = new_state,
                 RunStatus::Stop(val) => break val,
+                RunStatus::Panic(message, state) => bail!(self.panic(message, &state)),
+                RunStatus::HostCall(function, args, mut new_state) => {
+                    let value = self.call_host_function(function, args)?;
+                    new_state.stack_mut().push_value(value);
+                    state = new_state.continue_to_next();
+                }
+                RunStatus::Output(bytes, new_state) => {
+                    self.output
+                        .write_all(&bytes)
+                        .context("Failed to write program output")?;
+                    state = new_state.continue_to_next();
+                }
+                RunStatus::ReadInt(mut new_state) => {
+                    let n = self.read_int()?;
+                    new_state.stack_mut().push_integer(n);
+                    state = new_state.continue_to_next();
+                }
+                RunStatus::Alloc(fields, mut new_state) => {
+                    // The fields have been popped already, but must survive
+                    // the collection.
+                    if self.heap.should_collect() {
+                        let roots = new_state.stack().values().iter().chain(&fields);
+                        self.heap.collect(roots);
+                    }
+
+                    let handle = self.heap.alloc(fields);
+                    new_state.stack_mut().push_value(Value::Handle(handle));
+                    state = new_state.continue_to_next();
+                }
+                RunStatus::Load(handle, idx, mut new_state) => {
+                    let value = self.heap.load(handle, idx)?;
+                    new_state.stack_mut().push_value(value);
+                    state = new_state.continue_to_next();
+                }
+                RunStatus::Store(handle, idx, value, new_state) => {
+                    self.heap.store(handle, idx, value)?;
+                    state = new_state.continue_to_next();
+                }
+                RunStatus::MemoLoad(function, args, hit_addr, mut new_state) => {
+                    state = match self.memo.load(function, &args) {
+                        Some(value) => {
+                            new_state.stack_mut().push_value(value);
+                            new_state.continue_to(hit_addr)
+                        }
+                        None => new_state.continue_to_next(),
+                    };
+                }
+                RunStatus::MemoStore(function, args, value, new_state) => {
+                    self.memo.store(function, args, value);
+                    state = new_state.continue_to_next();
+                }
             }
         };
 
         Ok(final_value)
     }
 
+    fn call_host_function(&mut self, function: u16, args: Vec<Value>) -> Result<Value> {
+        let name = self
+            .symbols
+            .host_functions()
+            .get(function as usize)
+            .ok_or_else(|| anyhow!("Unknown host function `{}`", function))?;
+
+        let host_function = self
+            .host_functions
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("Host function `{}` is not registered", name))?;
+
+        host_function(args).with_context(|| format!("Host function `{}` failed", name))
+    }
+
+    fn read_int(&mut self) -> Result<i32> {
+        while self.pending_input.is_empty() {
+            let mut line = String::new();
+            let read = self
+                .input
+                .read_line(&mut line)
+                .context("Failed to read program input")?;
+
+            ensure!(
+                read > 0,
+                "Reached the end of the input while reading an integer"
+            );
+
+            self.pending_input
+                .extend(line.split_whitespace().map(str::to_owned));
+        }
+
+        let word = self.pending_input.pop_front().unwrap();
+
+        word.parse()
+            .with_context(|| format!("Failed to read an integer from `{}`", word))
+    }
+
+    /// Describes a panic, along with the functions that were being run,
+    /// innermost first.
+    fn panic(&self, message: String, state: &RunningInterpreterState) -> Panic {
+        // Return addresses point right after the call instruction.
+        let call_sites = state
+            .stack()
+            .return_addresses()
+            .map(|addr| addr.saturating_sub(1));
+
+        let backtrace = std::iter::once(state.ip())
+            .chain(call_sites)
+            .map(|addr| match self.symbols.function_at(addr) {
+                Some(name) => name.to_owned(),
+                None => format!("<unknown at {}>", addr),
+            })
+            .collect();
+
+        Panic { message, backtrace }
+    }
+
     fn run_single(&mut self, state: RunningInterpreterState) -> Result<RunStatus> {
         let instr = self
             .code
@@ -124,6 +362,75 @@ impl Stack {
         Ok(())
     }
 
+    pub(crate) fn push_function(&mut self, addr: u32) {
+        let value = Value::Function(addr);
+        self.0.push(value);
+    }
+
+    pub(crate) fn pop_callable(&mut self) -> Result<(u32, Vec<Value>)> {
+        self.pop()
+            .and_then(Value::try_into_callable)
+            .context("Failed to pop a function from the stack")
+    }
+
+    pub(crate) fn push_closure(&mut self, closure: Closure) {
+        let value = Value::Closure(Arc::new(closure));
+        self.0.push(value);
+    }
+
+    pub(crate) fn push_record(&mut self, fields: Vec<Value>) {
+        let value = Value::Record(Arc::new(fields));
+        self.0.push(value);
+    }
+
+    pub(crate) fn pop_handle(&mut self) -> Result<u32> {
+        self.pop()
+            .and_then(Value::try_into_handle)
+            .context("Failed to pop a handle from the stack")
+    }
+
+    pub(crate) fn pop_list(&mut self) -> Result<List> {
+        self.pop()
+            .and_then(Value::try_into_list)
+            .context("Failed to pop a list from the stack")
+    }
+
+    pub(crate) fn pop_map(&mut self) -> Result<Map> {
+        self.pop()
+            .and_then(Value::try_into_map)
+            .context("Failed to pop a map from the stack")
+    }
+
+    /// Returns the values of the stack, from bottom to top.
+    pub(crate) fn values(&self) -> &[Value] {
+        self.0.as_slice()
+    }
+
+    pub(crate) fn pop_record(&mut self) -> Result<Arc<Vec<Value>>> {
+        self.pop()
+            .and_then(Value::try_into_record)
+            .context("Failed to pop a record from the stack")
+    }
+
+    /// Pops the `n` values on top of the stack, in the order they were pushed.
+    pub(crate) fn pop_many(&mut self, n: u16) -> Result<Vec<Value>> {
+        let n = n as usize;
+        ensure!(n <= self.0.len(), "Out-of-bound stack access");
+
+        let values = self.0.split_off(self.0.len() - n);
+
+        Ok(values)
+    }
+
+    /// Returns the return addresses stored in the stack, from the innermost
+    /// call to the outermost one.
+    pub(crate) fn return_addresses(&self) -> impl Iterator<Item = u32> + '_ {
+        self.0.iter().rev().filter_map(|value| match value {
+            Value::InstructionPointer(addr) => Some(*addr),
+            _ => None,
+        })
+    }
+
     pub(crate) fn push_instruction_pointer(&mut self, idx: u32) {
         let value = Value::InstructionPointer(idx);
         self.0.push(value);
@@ -167,3 +474,22 @@ impl Stack {
         Ok(())
     }
 }
+
+/// The error returned when a program calls `panic`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Panic {
+    message: String,
+    backtrace: Vec<String>,
+}
+
+impl Display for Panic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Program panicked: {}", self.message)?;
+
+        self.backtrace
+            .iter()
+            .try_for_each(|function| write!(f, "\n    at {}", function))
+    }
+}
+
+impl Error for Panic {}
@@ -1,17 +1,34 @@
 use anyhow::Result;
 
-use dyl_bytecode::Instruction;
-use interpreter::Interpreter;
+use dyl_bytecode::{symbols::Symbols, Instruction};
 
+pub use heap::GcStats;
+pub use host::{HostFunction, HostFunctions};
+pub use interpreter::Interpreter;
+pub use list::List;
+pub use map::Map;
+pub use memo::MemoStats;
+pub use value::Value;
+
+mod heap;
+mod host;
 mod interpreter;
+mod list;
+mod map;
+mod memo;
 mod runnable;
 mod value;
 
 #[cfg(test)]
 mod tests;
 
-pub fn run_program(bytecode: Vec<Instruction>) -> Result<()> {
-    let return_value = Interpreter::from_instructions(bytecode).run()?;
+/// Runs a program, passing `args` to its `main` function. The symbols are
+/// used to describe where the program was when it panicked.
+pub fn run_program(bytecode: Vec<Instruction>, symbols: Symbols, args: Vec<i32>) -> Result<()> {
+    let return_value = Interpreter::from_instructions(bytecode)
+        .with_symbols(symbols)
+        .with_args(args)
+        .run()?;
     println!("{}", return_value);
 
     Ok(())
@@ -0,0 +1,90 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+
+use anyhow::{bail, Result};
+
+use crate::value::Value;
+
+/// An immutable singly linked list. Lists share their tail, so consing a value
+/// onto a list does not copy it.
+#[derive(Clone, Default)]
+pub struct List {
+    head: Option<Arc<Node>>,
+}
+
+struct Node {
+    value: Value,
+    next: List,
+}
+
+impl List {
+    pub(crate) fn from_values(values: Vec<Value>) -> List {
+        values
+            .into_iter()
+            .rev()
+            .fold(List::default(), |list, value| list.cons(value))
+    }
+
+    pub(crate) fn cons(self, value: Value) -> List {
+        List {
+            head: Some(Arc::new(Node { value, next: self })),
+        }
+    }
+
+    pub(crate) fn head(&self) -> Result<Value> {
+        match &self.head {
+            Some(node) => Ok(node.value.clone()),
+            None => bail!("Attempt to get the head of an empty list"),
+        }
+    }
+
+    pub(crate) fn tail(&self) -> Result<List> {
+        match &self.head {
+            Some(node) => Ok(node.next.clone()),
+            None => bail!("Attempt to get the tail of an empty list"),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Value> {
+        let mut current = self.head.as_deref();
+
+        std::iter::from_fn(move || {
+            let node = current?;
+            current = node.next.head.as_deref();
+            Some(&node.value)
+        })
+    }
+}
+
+// Dropping the nodes recursively may overflow the stack for long lists.
+impl Drop for List {
+    fn drop(&mut self) {
+        let mut current = self.head.take();
+
+        while let Some(node) = current {
+            current = match Arc::try_unwrap(node) {
+                Ok(mut node) => node.next.head.take(),
+                // The rest of the list is still used somewhere else.
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+impl PartialEq for List {
+    fn eq(&self, other: &List) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Debug for List {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
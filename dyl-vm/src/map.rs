@@ -0,0 +1,195 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+
+use crate::value::Value;
+
+/// An immutable map from integer keys to values, ordered by key so that
+/// iterating over it is deterministic.
+///
+/// Maps are balanced binary trees that share their nodes. Inserting or
+/// removing a key only copies the nodes on the path to it, so the previous
+/// version of the map stays valid.
+#[derive(Clone, Default)]
+pub struct Map {
+    root: Option<Arc<Node>>,
+}
+
+struct Node {
+    key: i32,
+    value: Value,
+    left: Map,
+    right: Map,
+    height: u8,
+}
+
+impl Map {
+    pub(crate) fn insert(self, key: i32, value: Value) -> Map {
+        self.inserted(key, value)
+    }
+
+    pub(crate) fn get(&self, key: i32) -> Option<Value> {
+        let mut current = self.root.as_deref();
+
+        while let Some(node) = current {
+            current = match key.cmp(&node.key) {
+                Ordering::Less => node.left.root.as_deref(),
+                Ordering::Equal => return Some(node.value.clone()),
+                Ordering::Greater => node.right.root.as_deref(),
+            };
+        }
+
+        None
+    }
+
+    pub(crate) fn remove(self, key: i32) -> Map {
+        match self.contains(key) {
+            true => self.removed(key),
+            false => self,
+        }
+    }
+
+    pub(crate) fn contains(&self, key: i32) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the entries of the map, in increasing key order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (i32, &Value)> {
+        let mut path = Vec::new();
+        let mut current = self.root.as_deref();
+
+        std::iter::from_fn(move || {
+            while let Some(node) = current {
+                path.push(node);
+                current = node.left.root.as_deref();
+            }
+
+            let node = path.pop()?;
+            current = node.right.root.as_deref();
+            Some((node.key, &node.value))
+        })
+    }
+
+    fn height(&self) -> u8 {
+        self.root.as_ref().map_or(0, |node| node.height)
+    }
+
+    fn node(key: i32, value: Value, left: Map, right: Map) -> Map {
+        let height = left.height().max(right.height()) + 1;
+
+        Map {
+            root: Some(Arc::new(Node {
+                key,
+                value,
+                left,
+                right,
+                height,
+            })),
+        }
+    }
+
+    /// Same as `node`, except that the subtrees are rotated when their
+    /// heights differ by two, which keeps the tree balanced.
+    fn balanced(key: i32, value: Value, left: Map, right: Map) -> Map {
+        if left.height() > right.height() + 1 {
+            let l = left.root.as_deref().unwrap();
+
+            if l.left.height() >= l.right.height() {
+                let right = Map::node(key, value, l.right.clone(), right);
+                Map::node(l.key, l.value.clone(), l.left.clone(), right)
+            } else {
+                let lr = l.right.root.as_deref().unwrap();
+                let left = Map::node(l.key, l.value.clone(), l.left.clone(), lr.left.clone());
+                let right = Map::node(key, value, lr.right.clone(), right);
+                Map::node(lr.key, lr.value.clone(), left, right)
+            }
+        } else if right.height() > left.height() + 1 {
+            let r = right.root.as_deref().unwrap();
+
+            if r.right.height() >= r.left.height() {
+                let left = Map::node(key, value, left, r.left.clone());
+                Map::node(r.key, r.value.clone(), left, r.right.clone())
+            } else {
+                let rl = r.left.root.as_deref().unwrap();
+                let left = Map::node(key, value, left, rl.left.clone());
+                let right = Map::node(r.key, r.value.clone(), rl.right.clone(), r.right.clone());
+                Map::node(rl.key, rl.value.clone(), left, right)
+            }
+        } else {
+            Map::node(key, value, left, right)
+        }
+    }
+
+    fn inserted(&self, key: i32, value: Value) -> Map {
+        let node = match self.root.as_deref() {
+            Some(node) => node,
+            None => return Map::node(key, value, Map::default(), Map::default()),
+        };
+
+        let (left, right) = (node.left.clone(), node.right.clone());
+
+        match key.cmp(&node.key) {
+            Ordering::Less => {
+                let left = node.left.inserted(key, value);
+                Map::balanced(node.key, node.value.clone(), left, right)
+            }
+            Ordering::Equal => Map::node(key, value, left, right),
+            Ordering::Greater => {
+                let right = node.right.inserted(key, value);
+                Map::balanced(node.key, node.value.clone(), left, right)
+            }
+        }
+    }
+
+    fn removed(&self, key: i32) -> Map {
+        let node = match self.root.as_deref() {
+            Some(node) => node,
+            None => return Map::default(),
+        };
+
+        let (left, right) = (node.left.clone(), node.right.clone());
+
+        match key.cmp(&node.key) {
+            Ordering::Less => {
+                let left = node.left.removed(key);
+                Map::balanced(node.key, node.value.clone(), left, right)
+            }
+            Ordering::Equal => match right.without_min() {
+                Some((key, value, right)) => Map::balanced(key, value, left, right),
+                None => left,
+            },
+            Ordering::Greater => {
+                let right = node.right.removed(key);
+                Map::balanced(node.key, node.value.clone(), left, right)
+            }
+        }
+    }
+
+    /// Returns the entry with the smallest key, along with the rest of the
+    /// map.
+    fn without_min(&self) -> Option<(i32, Value, Map)> {
+        let node = self.root.as_deref()?;
+
+        match node.left.without_min() {
+            Some((key, value, left)) => {
+                let rest = Map::balanced(node.key, node.value.clone(), left, node.right.clone());
+                Some((key, value, rest))
+            }
+            None => Some((node.key, node.value.clone(), node.right.clone())),
+        }
+    }
+}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Map) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Debug for Map {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
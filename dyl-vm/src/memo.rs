@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use crate::value::Value;
+
+/// The results of the memoized functions, indexed by function address and
+/// by arguments.
+///
+/// The cached values are not roots of the garbage collector: memoized
+/// functions are expected to return integers or booleans only.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct MemoCaches {
+    caches: HashMap<u32, MemoCache>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct MemoCache {
+    results: HashMap<Vec<i32>, Value>,
+    stats: MemoStats,
+}
+
+/// Statistics about the cache of a memoized function.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MemoStats {
+    /// The number of calls whose result was found in the cache.
+    pub hits: u64,
+    /// The number of calls whose result had to be computed.
+    pub misses: u64,
+}
+
+impl MemoCaches {
+    /// Returns the result of a previous call to `function` with the same
+    /// arguments, if any.
+    pub(crate) fn load(&mut self, function: u32, args: &[i32]) -> Option<Value> {
+        let cache = self.caches.entry(function).or_default();
+
+        let value = cache.results.get(args).cloned();
+        match value {
+            Some(_) => cache.stats.hits += 1,
+            None => cache.stats.misses += 1,
+        }
+
+        value
+    }
+
+    pub(crate) fn store(&mut self, function: u32, args: Vec<i32>, value: Value) {
+        self.caches
+            .entry(function)
+            .or_default()
+            .results
+            .insert(args, value);
+    }
+
+    pub(crate) fn stats(&self, function: u32) -> MemoStats {
+        self.caches
+            .get(&function)
+            .map(|cache| cache.stats)
+            .unwrap_or_default()
+    }
+}
@@ -1,15 +1,24 @@
 use std::cmp::Ordering;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 
 use dyl_bytecode::{
     operations::{
-        AddI, Call, CondJmp, FStop, Goto, Mul, Neg, Pop, PopCopy, PushCopy, PushI, ResV, Ret,
+        AddI, Alloc, Assert, AssertEq, AssertEqB, Call, CallIndirect, Cmp, CondJmp, Cons, EmptyMap,
+        FStop, GetField, Goto, Head, HostCall, IsEmpty, Load, MakeClosure, MakeList, MakeRecord,
+        MapContains, MapGet, MapInsert, MapRemove, MemoLoad, MemoStore, Mul, Neg, Panic, Pop,
+        PopCopy, PrintB, PrintI, PrintS, PushCopy, PushFn, PushI, ReadI, Rem, ResV, Ret, Store,
+        Tail,
     },
     Instruction,
 };
 
-use crate::{interpreter::RunningInterpreterState, value::Value};
+use crate::{
+    interpreter::RunningInterpreterState,
+    list::List,
+    map::Map,
+    value::{Closure, Value},
+};
 
 pub(crate) trait Runnable {
     fn run(&self, state: RunningInterpreterState) -> Result<RunStatus>;
@@ -37,6 +46,67 @@ impl Runnable for Instruction {
             Instruction::Neg(op) => op.run(state).context("Failed to run `neg` instruction"),
             Instruction::Mul(op) => op.run(state).context("Failed to run `mul` instruction"),
             Instruction::Pop(op) => op.run(state).context("Failed to run the `pop` instruction"),
+            Instruction::Cmp(op) => op.run(state).context("Failed to run `cmp` instruction"),
+            Instruction::PushFn(op) => op.run(state).context("Failed to run `push_fn` instruction"),
+            Instruction::CallIndirect(op) => op
+                .run(state)
+                .context("Failed to run `call_indirect` instruction"),
+            Instruction::MakeClosure(op) => op
+                .run(state)
+                .context("Failed to run `make_closure` instruction"),
+            Instruction::MakeRecord(op) => op
+                .run(state)
+                .context("Failed to run `make_record` instruction"),
+            Instruction::GetField(op) => op
+                .run(state)
+                .context("Failed to run `get_field` instruction"),
+            Instruction::Panic(op) => op.run(state).context("Failed to run `panic` instruction"),
+            Instruction::Rem(op) => op.run(state).context("Failed to run `rem` instruction"),
+            Instruction::HostCall(op) => op
+                .run(state)
+                .context("Failed to run `host_call` instruction"),
+            Instruction::PrintI(op) => op.run(state).context("Failed to run `print_i` instruction"),
+            Instruction::PrintB(op) => op.run(state).context("Failed to run `print_b` instruction"),
+            Instruction::PrintS(op) => op.run(state).context("Failed to run `print_s` instruction"),
+            Instruction::ReadI(op) => op.run(state).context("Failed to run `read_i` instruction"),
+            Instruction::Alloc(op) => op.run(state).context("Failed to run `alloc` instruction"),
+            Instruction::Load(op) => op.run(state).context("Failed to run `load` instruction"),
+            Instruction::Store(op) => op.run(state).context("Failed to run `store` instruction"),
+            Instruction::MakeList(op) => op
+                .run(state)
+                .context("Failed to run `make_list` instruction"),
+            Instruction::Cons(op) => op.run(state).context("Failed to run `cons` instruction"),
+            Instruction::Head(op) => op.run(state).context("Failed to run `head` instruction"),
+            Instruction::Tail(op) => op.run(state).context("Failed to run `tail` instruction"),
+            Instruction::IsEmpty(op) => op
+                .run(state)
+                .context("Failed to run `is_empty` instruction"),
+            Instruction::EmptyMap(op) => op
+                .run(state)
+                .context("Failed to run `empty_map` instruction"),
+            Instruction::MapInsert(op) => op
+                .run(state)
+                .context("Failed to run `map_insert` instruction"),
+            Instruction::MapGet(op) => op.run(state).context("Failed to run `map_get` instruction"),
+            Instruction::MapRemove(op) => op
+                .run(state)
+                .context("Failed to run `map_remove` instruction"),
+            Instruction::MapContains(op) => op
+                .run(state)
+                .context("Failed to run `map_contains` instruction"),
+            Instruction::Assert(op) => op.run(state).context("Failed to run `assert` instruction"),
+            Instruction::AssertEq(op) => op
+                .run(state)
+                .context("Failed to run `assert_eq` instruction"),
+            Instruction::AssertEqB(op) => op
+                .run(state)
+                .context("Failed to run `assert_eq_b` instruction"),
+            Instruction::MemoLoad(op) => op
+                .run(state)
+                .context("Failed to run `memo_load` instruction"),
+            Instruction::MemoStore(op) => op
+                .run(state)
+                .context("Failed to run `memo_store` instruction"),
         }
     }
 }
@@ -195,6 +265,43 @@ impl Runnable for Mul {
     }
 }
 
+impl Runnable for Rem {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let rhs = state
+            .stack_mut()
+            .pop_integer()
+            .context("Failed to get integer right-hand-side value")?;
+        let lhs = state
+            .stack_mut()
+            .pop_integer()
+            .context("Failed to get integer left-hand-side-value")?;
+
+        let rem = lhs
+            .checked_rem(rhs)
+            .ok_or_else(|| anyhow!("Attempt to compute `{} % {}`", lhs, rhs))?;
+        state.stack_mut().push_integer(rem);
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for Cmp {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let rhs = state
+            .stack_mut()
+            .pop_integer()
+            .context("Failed to get integer right-hand-side value")?;
+        let lhs = state
+            .stack_mut()
+            .pop_integer()
+            .context("Failed to get integer left-hand-side-value")?;
+
+        state.stack_mut().push_integer(lhs.cmp(&rhs) as i32);
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
 impl Runnable for Pop {
     fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
         state.stack_mut().truncate(self.0)?;
@@ -203,9 +310,472 @@ impl Runnable for Pop {
     }
 }
 
+impl Runnable for PushFn {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        state.stack_mut().push_function(self.0);
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for CallIndirect {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let (jump_addr, captures) = state
+            .stack_mut()
+            .pop_callable()
+            .context("Failed to get function to call")?;
+
+        captures
+            .into_iter()
+            .for_each(|v| state.stack_mut().push_value(v));
+
+        let next_addr = state.ip() + 1;
+        state.stack_mut().push_instruction_pointer(next_addr);
+
+        Ok(state.continue_to(jump_addr).into())
+    }
+}
+
+impl Runnable for MakeClosure {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let captures = state
+            .stack_mut()
+            .pop_many(self.captures)
+            .context("Failed to get captured values")?;
+
+        let closure = Closure::new(self.addr, captures);
+        state.stack_mut().push_closure(closure);
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for MakeRecord {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let fields = state
+            .stack_mut()
+            .pop_many(self.0)
+            .context("Failed to get record fields")?;
+
+        state.stack_mut().push_record(fields);
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for GetField {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let record = state.stack_mut().pop_record()?;
+        let field = record
+            .get(self.0 as usize)
+            .ok_or_else(|| anyhow!("Out-of-bound field access"))?
+            .clone();
+
+        state.stack_mut().push_value(field);
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for Panic {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let message = pop_message(&mut state, self.0)?;
+
+        Ok(RunStatus::Panic(message, state))
+    }
+}
+
+/// Pops the bytes of the message of a panic, which are pushed one by one.
+fn pop_message(state: &mut RunningInterpreterState, len: u16) -> Result<String> {
+    let bytes = state
+        .stack_mut()
+        .pop_many(len)
+        .context("Failed to get panic message")?
+        .into_iter()
+        .map(|byte| Ok(Value::try_into_integer(byte)? as u8))
+        .collect::<Result<Vec<_>>>()
+        .context("Failed to decode panic message")?;
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+impl Runnable for HostCall {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let args = state
+            .stack_mut()
+            .pop_many(self.args)
+            .context("Failed to get host function arguments")?;
+
+        Ok(RunStatus::HostCall(self.function, args, state))
+    }
+}
+
+impl Runnable for PrintI {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let n = state.stack_mut().pop_integer()?;
+
+        Ok(RunStatus::Output(n.to_string().into_bytes(), state))
+    }
+}
+
+impl Runnable for PrintB {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let b = state.stack_mut().pop_integer()? != 0;
+
+        Ok(RunStatus::Output(b.to_string().into_bytes(), state))
+    }
+}
+
+impl Runnable for PrintS {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let bytes = state
+            .stack_mut()
+            .pop_many(self.0)
+            .context("Failed to get string")?
+            .into_iter()
+            .map(|byte| Ok(Value::try_into_integer(byte)? as u8))
+            .collect::<Result<Vec<_>>>()
+            .context("Failed to decode string")?;
+
+        Ok(RunStatus::Output(bytes, state))
+    }
+}
+
+impl Runnable for ReadI {
+    fn run(&self, state: RunningInterpreterState) -> Result<RunStatus> {
+        Ok(RunStatus::ReadInt(state))
+    }
+}
+
+impl Runnable for Alloc {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let fields = state
+            .stack_mut()
+            .pop_many(self.0)
+            .context("Failed to get object fields")?;
+
+        Ok(RunStatus::Alloc(fields, state))
+    }
+}
+
+impl Runnable for Load {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let handle = state
+            .stack_mut()
+            .pop_handle()
+            .context("Failed to get object to load from")?;
+
+        Ok(RunStatus::Load(handle, self.0, state))
+    }
+}
+
+impl Runnable for Store {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let value = state
+            .stack_mut()
+            .pop()
+            .context("Failed to get value to store")?;
+        let handle = state
+            .stack_mut()
+            .pop_handle()
+            .context("Failed to get object to store to")?;
+
+        Ok(RunStatus::Store(handle, self.0, value, state))
+    }
+}
+
+impl Runnable for MakeList {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let elements = state
+            .stack_mut()
+            .pop_many(self.0)
+            .context("Failed to get list elements")?;
+
+        state
+            .stack_mut()
+            .push_value(Value::List(List::from_values(elements)));
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for Cons {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let list = state
+            .stack_mut()
+            .pop_list()
+            .context("Failed to get list to prepend to")?;
+        let value = state
+            .stack_mut()
+            .pop()
+            .context("Failed to get value to prepend")?;
+
+        state.stack_mut().push_value(Value::List(list.cons(value)));
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for Head {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let list = state
+            .stack_mut()
+            .pop_list()
+            .context("Failed to get list to get the head of")?;
+
+        state.stack_mut().push_value(list.head()?);
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for Tail {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let list = state
+            .stack_mut()
+            .pop_list()
+            .context("Failed to get list to get the tail of")?;
+
+        state.stack_mut().push_value(Value::List(list.tail()?));
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for IsEmpty {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let list = state
+            .stack_mut()
+            .pop_list()
+            .context("Failed to get list to check")?;
+
+        state.stack_mut().push_integer(list.is_empty() as i32);
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for EmptyMap {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        state.stack_mut().push_value(Value::Map(Map::default()));
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for MapInsert {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let value = state
+            .stack_mut()
+            .pop()
+            .context("Failed to get value to insert")?;
+        let key = state
+            .stack_mut()
+            .pop_integer()
+            .context("Failed to get key to insert")?;
+        let map = state
+            .stack_mut()
+            .pop_map()
+            .context("Failed to get map to insert into")?;
+
+        state
+            .stack_mut()
+            .push_value(Value::Map(map.insert(key, value)));
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for MapGet {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let key = state
+            .stack_mut()
+            .pop_integer()
+            .context("Failed to get key to look up")?;
+        let map = state
+            .stack_mut()
+            .pop_map()
+            .context("Failed to get map to look up")?;
+
+        // The value is wrapped in an `Option`, whose first field is the tag
+        // of its constructor.
+        let fields = match map.get(key) {
+            Some(value) => vec![Value::Integer(1), value],
+            None => vec![Value::Integer(0)],
+        };
+        state.stack_mut().push_record(fields);
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for MapRemove {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let key = state
+            .stack_mut()
+            .pop_integer()
+            .context("Failed to get key to remove")?;
+        let map = state
+            .stack_mut()
+            .pop_map()
+            .context("Failed to get map to remove from")?;
+
+        state.stack_mut().push_value(Value::Map(map.remove(key)));
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for MapContains {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let key = state
+            .stack_mut()
+            .pop_integer()
+            .context("Failed to get key to look up")?;
+        let map = state
+            .stack_mut()
+            .pop_map()
+            .context("Failed to get map to look up")?;
+
+        state.stack_mut().push_integer(map.contains(key) as i32);
+
+        Ok(state.continue_to_next().into())
+    }
+}
+
+impl Runnable for Assert {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        let message = pop_message(&mut state, self.0)?;
+        let condition = state
+            .stack_mut()
+            .pop_integer()
+            .context("Failed to get asserted condition")?;
+
+        if condition == 0 {
+            Ok(RunStatus::Panic(message, state))
+        } else {
+            Ok(state.continue_to_next().into())
+        }
+    }
+}
+
+impl Runnable for AssertEq {
+    fn run(&self, state: RunningInterpreterState) -> Result<RunStatus> {
+        assert_equal(state, self.0, |value| value.to_string())
+    }
+}
+
+impl Runnable for AssertEqB {
+    fn run(&self, state: RunningInterpreterState) -> Result<RunStatus> {
+        assert_equal(state, self.0, |value| (value != 0).to_string())
+    }
+}
+
+/// Pops a message of `len` bytes and two integers, and panics with the
+/// message and both operands, shown by `show`, if they are not equal.
+fn assert_equal(
+    mut state: RunningInterpreterState,
+    len: u16,
+    show: fn(i32) -> String,
+) -> Result<RunStatus> {
+    let message = pop_message(&mut state, len)?;
+    let right = state
+        .stack_mut()
+        .pop_integer()
+        .context("Failed to get right operand")?;
+    let left = state
+        .stack_mut()
+        .pop_integer()
+        .context("Failed to get left operand")?;
+
+    if left == right {
+        Ok(state.continue_to_next().into())
+    } else {
+        let message = format!(
+            "{}: left is `{}`, right is `{}`",
+            message,
+            show(left),
+            show(right)
+        );
+        Ok(RunStatus::Panic(message, state))
+    }
+}
+
+impl Runnable for MemoLoad {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        // The return address sits above the arguments.
+        let args = frame_args(&mut state, self.params, 1)?;
+
+        Ok(RunStatus::MemoLoad(
+            self.function,
+            args,
+            self.hit_addr,
+            state,
+        ))
+    }
+}
+
+impl Runnable for MemoStore {
+    fn run(&self, mut state: RunningInterpreterState) -> Result<RunStatus> {
+        // The result and the return address sit above the arguments.
+        let args = frame_args(&mut state, self.params, 2)?;
+        let value = state
+            .stack_mut()
+            .get_at_offset(0)
+            .context("Failed to get the result")?
+            .clone();
+
+        Ok(RunStatus::MemoStore(self.function, args, value, state))
+    }
+}
+
+/// Returns the integer arguments of the current function, in order, the last
+/// one being `offset` values below the top of the stack.
+fn frame_args(state: &mut RunningInterpreterState, params: u16, offset: u16) -> Result<Vec<i32>> {
+    (0..params)
+        .rev()
+        .map(|idx| {
+            state
+                .stack_mut()
+                .get_at_offset(offset + idx)
+                .cloned()
+                .and_then(Value::try_into_integer)
+                .context("Failed to get an argument")
+        })
+        .collect()
+}
+
 pub(crate) enum RunStatus {
     Continue(RunningInterpreterState),
     Stop(Value),
+    /// The program stopped with a user message. The state is kept so that
+    /// the caller can describe where it happened.
+    Panic(String, RunningInterpreterState),
+    /// The program calls a host function with some arguments. The caller
+    /// pushes the return value and moves to the next instruction.
+    HostCall(u16, Vec<Value>, RunningInterpreterState),
+    /// The program writes some bytes to its output. The caller moves to the
+    /// next instruction once they are written.
+    Output(Vec<u8>, RunningInterpreterState),
+    /// The program reads an integer from its input. The caller pushes it and
+    /// moves to the next instruction.
+    ReadInt(RunningInterpreterState),
+    /// The program allocates an object with some fields. The caller pushes
+    /// its handle and moves to the next instruction.
+    Alloc(Vec<Value>, RunningInterpreterState),
+    /// The program reads a field of an object. The caller pushes its value
+    /// and moves to the next instruction.
+    Load(u32, u16, RunningInterpreterState),
+    /// The program writes a value to a field of an object. The caller moves
+    /// to the next instruction once it is written.
+    Store(u32, u16, Value, RunningInterpreterState),
+    /// The program looks up the result of the function at the first address
+    /// for some arguments. On a hit, the caller pushes it and jumps to the
+    /// second address; otherwise it moves to the next instruction.
+    MemoLoad(u32, Vec<i32>, u32, RunningInterpreterState),
+    /// The program caches the result of the function at the address for some
+    /// arguments. The caller moves to the next instruction once it is stored.
+    MemoStore(u32, Vec<i32>, Value, RunningInterpreterState),
 }
 
 impl From<RunningInterpreterState> for RunStatus {
@@ -1,7 +1,12 @@
-use dyl_bytecode::Instruction;
+use std::{cell::RefCell, io::Write, mem, rc::Rc};
+
+use dyl_bytecode::{symbols::Symbols, Instruction};
 
 use crate::interpreter::Interpreter;
+use crate::list::List;
+use crate::map::Map;
 use crate::value::Value;
+use crate::HostFunctions;
 
 macro_rules! generate_bytecode {
     (@internal($acc:ident, $val:expr) {}) => {};
@@ -71,11 +76,166 @@ macro_rules! generate_bytecode {
         generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
     };
 
+    (@internal($acc:ident, $val:expr) { cmp $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::cmp());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { rem $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::rem());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
     (@internal($acc:ident, $val:expr) { pop $idx:literal $( $tail:tt )* } ) => {
         $acc.push(dyl_bytecode::Instruction::pop($idx));
         generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
     };
 
+    (@internal($acc:ident, $val:expr) { push_fn $label:ident $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::push_fn($label));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { call_indirect $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::call_indirect());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { make_closure $label:ident $captures:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::make_closure($label, $captures));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { make_record $fields:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::make_record($fields));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { get_field $idx:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::get_field($idx));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { panic $len:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::panic($len));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { assert $len:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::assert($len));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { assert_eq $len:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::assert_eq($len));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { assert_eq_b $len:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::assert_eq_b($len));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { memo_load $function:ident $params:literal $label:ident $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::memo_load($function, $params, $label));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { memo_store $function:ident $params:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::memo_store($function, $params));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { host_call $function:literal $args:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::host_call($function, $args));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { print_i $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::print_i());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { print_b $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::print_b());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { print_s $len:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::print_s($len));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { read_i $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::read_i());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { alloc $fields:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::alloc($fields));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { load $idx:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::load($idx));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { store $idx:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::store($idx));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { make_list $len:literal $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::make_list($len));
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { cons $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::cons());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { head $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::head());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { tail $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::tail());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { is_empty $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::is_empty());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { empty_map $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::empty_map());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { map_insert $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::map_insert());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { map_get $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::map_get());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { map_remove $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::map_remove());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
+    (@internal($acc:ident, $val:expr) { map_contains $( $tail:tt )* } ) => {
+        $acc.push(dyl_bytecode::Instruction::map_contains());
+        generate_bytecode! { @internal($acc, $val + 1) { $( $tail )* } }
+    };
+
     ( $( $tail:tt )* ) => {{
         // TODO: figure out a way to initialize all the vector at once, instead
         // of always pushing on it, maybe by keeping a list of all the generated
@@ -336,6 +496,30 @@ test_bytecode_execution! {
     } = Ok(Value::Integer(42)),
 }
 
+test_bytecode_execution! {
+    rem_keeps_the_sign_of_the_dividend :: {
+        push_i -47
+        push_i 5
+        rem
+        f_stop
+    } = Ok(Value::Integer(-2)),
+}
+
+#[test]
+fn rem_by_zero() {
+    let rslt = run_bytecode! {
+        push_i 42
+        push_i 0
+        rem
+        f_stop
+    };
+
+    assert_eq!(
+        rslt.unwrap_err().root_cause().to_string(),
+        "Attempt to compute `42 % 0`"
+    );
+}
+
 test_bytecode_execution! {
     typical_expr_execution :: {
         res_v 1
@@ -346,3 +530,726 @@ test_bytecode_execution! {
         f_stop
     } = Ok(Value::Integer(101)),
 }
+
+test_bytecode_execution! {
+    cmp_does_not_overflow :: {
+        push_i -2147483648
+        push_i 2147483647
+        cmp
+        f_stop
+    } = Ok(Value::Integer(-1)),
+}
+
+test_bytecode_execution! {
+    less_than_with_extreme_values :: {
+            push_i -2147483648
+            push_i 2147483647
+            cmp
+            cond_jmp TRUE FALSE FALSE
+
+        TRUE:
+            push_i 1
+            goto END
+
+        FALSE:
+            push_i 0
+
+        END:
+            f_stop
+    } = Ok(Value::Integer(1)),
+}
+
+test_bytecode_execution! {
+    greater_than_with_extreme_values :: {
+            push_i -2147483648
+            push_i 2147483647
+            cmp
+            cond_jmp FALSE FALSE TRUE
+
+        TRUE:
+            push_i 1
+            goto END
+
+        FALSE:
+            push_i 0
+
+        END:
+            f_stop
+    } = Ok(Value::Integer(0)),
+}
+
+test_bytecode_execution! {
+    indirect_call :: {
+            res_v 1
+            push_i 41
+            push_fn ADD_1
+            call_indirect
+            f_stop
+
+        ADD_1:
+            push_cpy 1
+            push_i 1
+            add_i
+            pop_cpy 3
+            ret 2 0
+    } = Ok(Value::Integer(42)),
+}
+
+test_bytecode_execution! {
+    closure_call :: {
+            res_v 1
+            push_i 40
+            push_i 2
+            make_closure ADD_CAPTURED 1
+            call_indirect
+            f_stop
+
+        ADD_CAPTURED:
+            push_cpy 2
+            push_cpy 2
+            add_i
+            pop_cpy 4
+            ret 3 0
+    } = Ok(Value::Integer(42)),
+}
+
+test_bytecode_execution! {
+    record_field :: {
+            push_i 1
+            push_i 42
+            push_i 3
+            make_record 3
+            get_field 1
+            f_stop
+    } = Ok(Value::Integer(42)),
+}
+
+#[test]
+fn get_field_requires_record() {
+    let rslt = run_bytecode! {
+        push_i 0
+        get_field 0
+    };
+
+    let err = rslt.unwrap_err();
+    assert_eq!(
+        err.root_cause().to_string(),
+        "Expected value of type `record`, found value `0` of type `integer`"
+    );
+}
+
+#[test]
+fn indirect_call_requires_function() {
+    let rslt = run_bytecode! {
+        push_i 0
+        call_indirect
+    };
+
+    let err = rslt.unwrap_err();
+    assert_eq!(
+        err.root_cause().to_string(),
+        "Expected value of type `function`, found value `0` of type `integer`"
+    );
+}
+
+#[test]
+fn panic_message() {
+    let rslt = run_bytecode! {
+        push_i 111
+        push_i 107
+        panic 2
+    };
+
+    let err = rslt.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Program panicked: ok\n    at <unknown at 2>"
+    );
+}
+
+#[test]
+fn panic_backtrace() {
+    let instrs = generate_bytecode! {
+            res_v 1
+            call F
+            f_stop
+
+        F:
+            push_i 33
+            panic 1
+    };
+
+    let mut symbols = Symbols::new();
+    symbols.add_function(0, "main".to_owned());
+    symbols.add_function(3, "f".to_owned());
+
+    let rslt = Interpreter::from_instructions(instrs)
+        .with_symbols(symbols)
+        .run();
+
+    let err = rslt.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Program panicked: !\n    at f\n    at main"
+    );
+}
+
+#[test]
+fn assertions_that_hold() {
+    let rslt = run_bytecode! {
+        push_i 1
+        push_i 33
+        assert 1
+        push_i 42
+        push_i 42
+        push_i 33
+        assert_eq 1
+        push_i 0
+        f_stop
+    };
+
+    assert_eq!(rslt.unwrap(), Value::Integer(0));
+}
+
+#[test]
+fn failed_assertion() {
+    let rslt = run_bytecode! {
+        push_i 0
+        push_i 111
+        push_i 107
+        assert 2
+    };
+
+    let err = rslt.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Program panicked: ok\n    at <unknown at 3>"
+    );
+}
+
+#[test]
+fn failed_equality_assertion() {
+    let rslt = run_bytecode! {
+        push_i 1
+        push_i 2
+        push_i 33
+        assert_eq 1
+    };
+
+    let err = rslt.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Program panicked: !: left is `1`, right is `2`\n    at <unknown at 3>"
+    );
+}
+
+#[test]
+fn failed_boolean_equality_assertion() {
+    let rslt = run_bytecode! {
+        push_i 1
+        push_i 0
+        push_i 33
+        assert_eq_b 1
+    };
+
+    let err = rslt.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Program panicked: !: left is `true`, right is `false`\n    at <unknown at 3>"
+    );
+}
+
+#[test]
+fn memoized_results_are_reused() {
+    let instrs = generate_bytecode! {
+            res_v 1
+            push_i 5
+            call SQUARE
+            res_v 1
+            push_i 5
+            call SQUARE
+            add_i
+            f_stop
+
+        SQUARE:
+            memo_load SQUARE 1 HIT
+            push_cpy 1
+            push_cpy 0
+            mul
+            memo_store SQUARE 1
+        HIT:
+            pop_cpy 3
+            ret 2 0
+    };
+
+    // Caches are identified by function address, not by name.
+    let rslt = Interpreter::from_instructions(instrs.clone()).run();
+    assert_eq!(rslt.unwrap(), Value::Integer(50));
+
+    let mut symbols = Symbols::new();
+    symbols.add_function(0, "main".to_owned());
+    symbols.add_function(8, "square".to_owned());
+
+    let mut interpreter = Interpreter::from_instructions(instrs).with_symbols(symbols);
+
+    assert_eq!(interpreter.run().unwrap(), Value::Integer(50));
+
+    let stats = interpreter.memo_stats("square");
+    assert_eq!(stats.hits, 1);
+    assert_eq!(stats.misses, 1);
+}
+
+fn host_symbols(names: &[&str]) -> Symbols {
+    let mut symbols = Symbols::new();
+    names.iter().for_each(|name| {
+        symbols.add_host_function(name.to_string());
+    });
+
+    symbols
+}
+
+#[test]
+fn host_call() {
+    let instrs = generate_bytecode! {
+        push_i 7
+        push_i 3
+        host_call 1 2
+        f_stop
+    };
+
+    let mut host_functions = HostFunctions::new();
+    host_functions.register("sub", |args| match args.as_slice() {
+        [Value::Integer(lhs), Value::Integer(rhs)] => Ok(Value::Integer(lhs - rhs)),
+        _ => unreachable!(),
+    });
+
+    let rslt = Interpreter::from_instructions(instrs)
+        .with_symbols(host_symbols(&["unused", "sub"]))
+        .with_host_functions(host_functions)
+        .run();
+
+    assert_eq!(rslt.unwrap(), Value::Integer(4));
+}
+
+#[test]
+fn unregistered_host_function() {
+    let instrs = generate_bytecode! {
+        host_call 0 0
+        f_stop
+    };
+
+    let rslt = Interpreter::from_instructions(instrs)
+        .with_symbols(host_symbols(&["now"]))
+        .run();
+
+    assert_eq!(
+        rslt.unwrap_err().to_string(),
+        "Host function `now` is not registered"
+    );
+}
+
+/// An output that can still be read once given to the interpreter.
+#[derive(Clone, Default)]
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl SharedOutput {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn print() {
+    let instrs = generate_bytecode! {
+        push_i -12
+        print_i
+        push_i 32
+        print_s 1
+        push_i 0
+        print_b
+        push_i 104
+        push_i 105
+        push_i 10
+        print_s 3
+        push_i 1
+        print_b
+        push_i 0
+        f_stop
+    };
+
+    let output = SharedOutput::default();
+    let rslt = Interpreter::from_instructions(instrs)
+        .with_output(output.clone())
+        .run();
+
+    assert_eq!(rslt.unwrap(), Value::Integer(0));
+    assert_eq!(output.contents(), "-12 falsehi\ntrue");
+}
+
+#[test]
+fn output_is_kept_on_panic() {
+    let instrs = generate_bytecode! {
+        push_i 1
+        print_i
+        push_i 33
+        panic 1
+    };
+
+    let output = SharedOutput::default();
+    let rslt = Interpreter::from_instructions(instrs)
+        .with_output(output.clone())
+        .run();
+
+    assert!(rslt.is_err());
+    assert_eq!(output.contents(), "1");
+}
+
+#[test]
+fn read_int() {
+    let instrs = generate_bytecode! {
+        read_i
+        read_i
+        neg
+        add_i
+        read_i
+        mul
+        f_stop
+    };
+
+    let input: &[u8] = b"  12 5\n\n-3\n";
+    let rslt = Interpreter::from_instructions(instrs)
+        .with_input(input)
+        .run();
+
+    assert_eq!(rslt.unwrap(), Value::Integer(-21));
+}
+
+#[test]
+fn read_int_fails_at_end_of_input() {
+    let instrs = generate_bytecode! {
+        read_i
+        f_stop
+    };
+
+    let input: &[u8] = b"four";
+    let rslt = Interpreter::from_instructions(instrs.clone())
+        .with_input(input)
+        .run();
+    assert!(rslt.is_err());
+
+    let input: &[u8] = b" \n";
+    let rslt = Interpreter::from_instructions(instrs)
+        .with_input(input)
+        .run();
+    assert!(rslt.is_err());
+}
+
+#[test]
+fn args_are_pushed_before_running() {
+    let instrs = generate_bytecode! {
+        push_cpy 1
+        pop_cpy 2
+        pop 1
+        f_stop
+    };
+
+    let mut symbols = Symbols::new();
+    symbols.set_main_params(2);
+
+    let rslt = Interpreter::from_instructions(instrs.clone())
+        .with_symbols(symbols.clone())
+        .with_args(vec![4, 2])
+        .run();
+    assert_eq!(rslt.unwrap(), Value::Integer(4));
+
+    let rslt = Interpreter::from_instructions(instrs)
+        .with_symbols(symbols)
+        .with_args(vec![4])
+        .run();
+    assert_eq!(
+        rslt.unwrap_err().to_string(),
+        "`main` expects 2 arguments, found 1"
+    );
+}
+
+#[test]
+fn heap_objects() {
+    let instrs = generate_bytecode! {
+        push_i 1
+        push_i 2
+        alloc 2
+        push_cpy 0
+        push_i 40
+        store 0
+        push_cpy 0
+        load 0
+        push_cpy 1
+        load 1
+        add_i
+        pop_cpy 1
+        f_stop
+    };
+
+    let rslt = Interpreter::from_instructions(instrs).run();
+
+    assert_eq!(rslt.unwrap(), Value::Integer(42));
+}
+
+#[test]
+fn unreachable_objects_are_collected() {
+    let instrs = generate_bytecode! {
+        push_i 1
+        alloc 1
+        pop 1
+        push_i 2
+        alloc 1
+        load 0
+        f_stop
+    };
+
+    let mut interpreter = Interpreter::from_instructions(instrs).with_gc_threshold(0);
+    let rslt = interpreter.run();
+
+    assert_eq!(rslt.unwrap(), Value::Integer(2));
+
+    let stats = interpreter.gc_stats();
+    let object_size = mem::size_of::<Value>() as u64;
+    assert_eq!(stats.collections, 2);
+    assert_eq!(stats.bytes_allocated, 2 * object_size);
+    assert_eq!(stats.bytes_freed, object_size);
+}
+
+#[test]
+fn objects_reachable_from_the_stack_are_kept() {
+    // The inner object is only reachable through the outer one, which is
+    // only reachable through a record.
+    let instrs = generate_bytecode! {
+        push_i 42
+        alloc 1
+        alloc 1
+        make_record 1
+        push_i 0
+        alloc 1
+        pop 1
+        push_i 0
+        alloc 1
+        pop 1
+        get_field 0
+        load 0
+        load 0
+        f_stop
+    };
+
+    let mut interpreter = Interpreter::from_instructions(instrs).with_gc_threshold(0);
+    let rslt = interpreter.run();
+
+    assert_eq!(rslt.unwrap(), Value::Integer(42));
+    assert_eq!(interpreter.gc_stats().collections, 4);
+}
+
+#[test]
+fn cycles_are_collected() {
+    let instrs = generate_bytecode! {
+        push_i 0
+        alloc 1
+        push_cpy 0
+        alloc 1
+        store 0
+        push_i 0
+        alloc 0
+        pop 1
+        f_stop
+    };
+
+    let mut interpreter = Interpreter::from_instructions(instrs).with_gc_threshold(0);
+    let rslt = interpreter.run();
+
+    assert_eq!(rslt.unwrap(), Value::Integer(0));
+
+    let object_size = mem::size_of::<Value>() as u64;
+    assert_eq!(interpreter.gc_stats().bytes_freed, 2 * object_size);
+}
+
+#[test]
+fn invalid_handles_are_not_collected() {
+    let instrs = generate_bytecode! {
+        host_call 0 0
+        push_i 0
+        alloc 1
+        pop 1
+        load 0
+        f_stop
+    };
+
+    let mut host_functions = HostFunctions::new();
+    host_functions.register("forge", |_| Ok(Value::Handle(99)));
+
+    let mut interpreter = Interpreter::from_instructions(instrs)
+        .with_symbols(host_symbols(&["forge"]))
+        .with_host_functions(host_functions)
+        .with_gc_threshold(0);
+    let rslt = interpreter.run();
+
+    assert_eq!(
+        rslt.unwrap_err().root_cause().to_string(),
+        "Invalid object handle `99`"
+    );
+    assert_eq!(interpreter.gc_stats().collections, 1);
+}
+
+#[test]
+fn lists() {
+    let instrs = generate_bytecode! {
+        push_i 0
+        push_i 1
+        push_i 2
+        make_list 2
+        cons
+        push_cpy 0
+        tail
+        head
+        push_cpy 1
+        head
+        add_i
+        pop_cpy 1
+        f_stop
+    };
+
+    let rslt = Interpreter::from_instructions(instrs).run();
+
+    assert_eq!(rslt.unwrap(), Value::Integer(1));
+}
+
+#[test]
+fn empty_list_has_no_head() {
+    let rslt = run_bytecode! {
+        make_list 0
+        push_cpy 0
+        is_empty
+        pop 1
+        head
+    };
+
+    let err = rslt.unwrap_err();
+    assert_eq!(
+        err.root_cause().to_string(),
+        "Attempt to get the head of an empty list"
+    );
+}
+
+#[test]
+fn long_lists_are_dropped() {
+    let list = (0..1_000_000).fold(List::default(), |list, i| list.cons(Value::Integer(i)));
+
+    assert_eq!(list.head().unwrap(), Value::Integer(999_999));
+}
+
+#[test]
+fn maps() {
+    let instrs = generate_bytecode! {
+        empty_map
+        push_i 2
+        push_i 20
+        map_insert
+        push_i 1
+        push_i 10
+        map_insert
+        push_cpy 0
+        push_i 2
+        map_remove
+        push_i 2
+        map_contains
+        pop 1
+        push_cpy 0
+        push_i 2
+        map_get
+        get_field 1
+        pop_cpy 1
+        f_stop
+    };
+
+    let rslt = Interpreter::from_instructions(instrs).run();
+
+    assert_eq!(rslt.unwrap(), Value::Integer(20));
+}
+
+#[test]
+fn maps_are_ordered_by_key() {
+    let rslt = run_bytecode! {
+        empty_map
+        push_i 3
+        push_i 30
+        map_insert
+        push_i 1
+        push_i 10
+        map_insert
+        push_i 2
+        push_i 20
+        map_insert
+        f_stop
+    };
+
+    assert_eq!(rslt.unwrap().to_string(), "{1: 10, 2: 20, 3: 30}");
+}
+
+#[test]
+fn missing_keys_are_none() {
+    let rslt = run_bytecode! {
+        empty_map
+        push_i 1
+        map_get
+        get_field 0
+        f_stop
+    };
+
+    assert_eq!(rslt.unwrap(), Value::Integer(0));
+}
+
+#[test]
+fn old_map_versions_are_unaffected() {
+    let mut versions = vec![Map::default()];
+
+    for i in 0..1000 {
+        let key = (i * 7919) % 1000;
+        let map = versions
+            .last()
+            .unwrap()
+            .clone()
+            .insert(key, Value::Integer(i));
+        versions.push(map);
+    }
+
+    for (len, map) in versions.iter().enumerate() {
+        assert_eq!(map.iter().count(), len);
+
+        for i in 0..1000 {
+            let key = (i * 7919) % 1000;
+            let expected = (i < len as i32).then(|| Value::Integer(i));
+            assert_eq!(map.get(key), expected);
+        }
+    }
+
+    let full = versions.last().unwrap().clone();
+    let removed = (0..1000)
+        .step_by(2)
+        .fold(full.clone(), |map, key| map.remove(key));
+
+    assert_eq!(
+        removed.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+        (1..1000).step_by(2).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        full.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+        (0..1000).collect::<Vec<_>>()
+    );
+}
@@ -1,14 +1,25 @@
 use std::{
     error::Error,
     fmt::{self, Display, Formatter},
+    sync::Arc,
 };
 
 use anyhow::{bail, Result};
 
+use crate::{list::List, map::Map};
+
+/// A value manipulated by a program. Booleans are represented as integers.
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) enum Value {
+pub enum Value {
     Integer(i32),
     InstructionPointer(u32),
+    Function(u32),
+    Closure(Arc<Closure>),
+    Record(Arc<Vec<Value>>),
+    /// A reference to an object allocated on the heap.
+    Handle(u32),
+    List(List),
+    Map(Map),
 }
 
 impl Value {
@@ -32,10 +43,69 @@ impl Value {
         }
     }
 
+    pub(crate) fn try_into_record(self) -> Result<Arc<Vec<Value>>> {
+        match self {
+            Value::Record(fields) => Ok(fields),
+            anything => bail!(ValueConversionError {
+                expected_type: Type::Record,
+                found_value: anything,
+            }),
+        }
+    }
+
+    pub(crate) fn try_into_handle(self) -> Result<u32> {
+        match self {
+            Value::Handle(handle) => Ok(handle),
+            anything => bail!(ValueConversionError {
+                expected_type: Type::Handle,
+                found_value: anything,
+            }),
+        }
+    }
+
+    pub(crate) fn try_into_list(self) -> Result<List> {
+        match self {
+            Value::List(list) => Ok(list),
+            anything => bail!(ValueConversionError {
+                expected_type: Type::List,
+                found_value: anything,
+            }),
+        }
+    }
+
+    pub(crate) fn try_into_map(self) -> Result<Map> {
+        match self {
+            Value::Map(map) => Ok(map),
+            anything => bail!(ValueConversionError {
+                expected_type: Type::Map,
+                found_value: anything,
+            }),
+        }
+    }
+
+    /// Returns the address of the code to run and the values to push before
+    /// the return address when calling the value.
+    pub(crate) fn try_into_callable(self) -> Result<(u32, Vec<Value>)> {
+        match self {
+            Value::Function(addr) => Ok((addr, Vec::new())),
+            Value::Closure(closure) => Ok((closure.addr, closure.captures.clone())),
+            anything => bail!(ValueConversionError {
+                expected_type: Type::Function,
+                found_value: anything,
+            }),
+        }
+    }
+
     fn type_(&self) -> Type {
         match self {
             Value::Integer(_) => Type::Integer,
             Value::InstructionPointer(_) => Type::InstructionPointer,
+            Value::Function(_) => Type::Function,
+            Value::Closure(_) => Type::Closure,
+            Value::Record(_) => Type::Record,
+            Value::Handle(_) => Type::Handle,
+            Value::List(_) => Type::List,
+            Value::Map(_) => Type::Map,
         }
     }
 }
@@ -45,14 +115,70 @@ impl Display for Value {
         match self {
             Value::Integer(i) => write!(f, "{}", i),
             Value::InstructionPointer(ip) => write!(f, "*{}*", ip),
+            Value::Function(addr) => write!(f, "fn@{}", addr),
+            Value::Closure(closure) => write!(f, "closure@{}", closure.addr),
+            Value::Record(fields) => {
+                write!(f, "{{")?;
+
+                fields.iter().enumerate().try_for_each(|(idx, field)| {
+                    let separator = if idx == 0 { "" } else { ", " };
+                    write!(f, "{}{}", separator, field)
+                })?;
+
+                write!(f, "}}")
+            }
+            Value::Handle(handle) => write!(f, "obj@{}", handle),
+            Value::List(list) => {
+                write!(f, "[")?;
+
+                list.iter().enumerate().try_for_each(|(idx, value)| {
+                    let separator = if idx == 0 { "" } else { ", " };
+                    write!(f, "{}{}", separator, value)
+                })?;
+
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                write!(f, "{{")?;
+
+                map.iter().enumerate().try_for_each(|(idx, (key, value))| {
+                    let separator = if idx == 0 { "" } else { ", " };
+                    write!(f, "{}{}: {}", separator, key, value)
+                })?;
+
+                write!(f, "}}")
+            }
         }
     }
 }
 
+/// A function along with the values it captured when it was created.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Closure {
+    addr: u32,
+    captures: Vec<Value>,
+}
+
+impl Closure {
+    pub(crate) fn new(addr: u32, captures: Vec<Value>) -> Closure {
+        Closure { addr, captures }
+    }
+
+    pub(crate) fn captures(&self) -> &[Value] {
+        self.captures.as_slice()
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Type {
     Integer,
     InstructionPointer,
+    Function,
+    Closure,
+    Record,
+    Handle,
+    List,
+    Map,
 }
 
 impl Display for Type {
@@ -60,6 +186,12 @@ impl Display for Type {
         match self {
             Type::Integer => write!(f, "integer"),
             Type::InstructionPointer => write!(f, "instruction pointer"),
+            Type::Function => write!(f, "function"),
+            Type::Closure => write!(f, "closure"),
+            Type::Record => write!(f, "record"),
+            Type::Handle => write!(f, "handle"),
+            Type::List => write!(f, "list"),
+            Type::Map => write!(f, "map"),
         }
     }
 }